@@ -0,0 +1,71 @@
+use rsky_lexicon::com::rsky::admin::{CreateLabel, CreateLabelsInput};
+use std::env;
+use std::sync::OnceLock;
+
+/// Applies `val` to any post whose text contains one of `keywords`, ignoring case
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelRule {
+    pub val: String,
+    pub keywords: Vec<String>,
+}
+
+static RULES: OnceLock<Vec<LabelRule>> = OnceLock::new();
+
+/// Parses `LABELER_RULES`, formatted as `val=keyword|keyword;val=keyword`
+pub fn rules_from_env() -> Vec<LabelRule> {
+    env::var("LABELER_RULES")
+        .unwrap_or_default()
+        .split(';')
+        .filter_map(|rule| {
+            let (val, keywords) = rule.split_once('=')?;
+            let keywords = keywords
+                .split('|')
+                .map(|keyword| keyword.trim().to_lowercase())
+                .filter(|keyword| !keyword.is_empty())
+                .collect::<Vec<String>>();
+            match val.trim().is_empty() || keywords.is_empty() {
+                true => None,
+                false => Some(LabelRule {
+                    val: val.trim().to_string(),
+                    keywords,
+                }),
+            }
+        })
+        .collect()
+}
+
+pub fn rules() -> &'static Vec<LabelRule> {
+    RULES.get_or_init(rules_from_env)
+}
+
+pub fn labels_for_text(text: &str) -> Vec<String> {
+    let text = text.to_lowercase();
+    rules()
+        .iter()
+        .filter(|rule| rule.keywords.iter().any(|keyword| text.contains(keyword)))
+        .map(|rule| rule.val.clone())
+        .collect()
+}
+
+/// Sends labels to the labeler's `com.rsky.admin.createLabels`, authenticated as
+/// its admin. `LABELER_ENDPOINT` is the labeling PDS, e.g. `https://pds.example.com`.
+pub async fn create_labels(
+    labels: Vec<CreateLabel>,
+    client: &reqwest::Client,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let endpoint = env::var("LABELER_ENDPOINT").map_err(|_| {
+        "Pass the labeler's url via `LABELER_ENDPOINT` environment variable.".to_string()
+    })?;
+    let password = env::var("LABELER_ADMIN_PASSWORD").map_err(|_| {
+        "Pass the labeler's admin password via `LABELER_ADMIN_PASSWORD` environment variable."
+            .to_string()
+    })?;
+    client
+        .post(format!("{endpoint}/xrpc/com.rsky.admin.createLabels"))
+        .json(&CreateLabelsInput { labels })
+        .basic_auth("admin", Some(password))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
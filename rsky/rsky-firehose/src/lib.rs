@@ -6,4 +6,5 @@ extern crate serde_json;
 
 pub mod car;
 pub mod firehose;
+pub mod labeler;
 pub mod models;
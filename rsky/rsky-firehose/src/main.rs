@@ -7,6 +7,7 @@ use rsky_lexicon::app::bsky::feed::like::Like;
 use rsky_lexicon::app::bsky::feed::Post;
 use rsky_lexicon::app::bsky::graph::follow::Follow;
 use rsky_lexicon::com::atproto::sync::SubscribeRepos;
+use rsky_lexicon::com::rsky::admin::CreateLabel;
 use serde::Deserialize;
 use std::env;
 use std::io::Cursor;
@@ -103,6 +104,7 @@ async fn process(message: Vec<u8>, client: &reqwest::Client) {
             let mut likes_to_create = Vec::new();
             let mut follows_to_delete = Vec::new();
             let mut follows_to_create = Vec::new();
+            let mut labels_to_create = Vec::new();
 
             match body {
                 SubscribeRepos::Commit(commit) => {
@@ -147,6 +149,15 @@ async fn process(message: Vec<u8>, client: &reqwest::Client) {
                                         match serde_cbor::from_reader(record_reader) {
                                             Ok(Lexicon::AppBskyFeedPost(r)) => {
                                                 let post: Post = r;
+                                                for val in rsky_firehose::labeler::labels_for_text(&post.text) {
+                                                    labels_to_create.push(CreateLabel {
+                                                        uri: uri.to_owned(),
+                                                        cid: Some(cid.to_string()),
+                                                        val,
+                                                        neg: None,
+                                                        exp: None,
+                                                    });
+                                                }
                                                 let mut create = rsky_firehose::models::CreateOp {
                                                     uri: uri.to_owned(),
                                                     cid: cid.to_string(),
@@ -267,6 +278,13 @@ async fn process(message: Vec<u8>, client: &reqwest::Client) {
                     Err(error) => eprintln!("Records failed to queue: {error:?}"),
                 };
             }
+            if labels_to_create.len() > 0 {
+                let resp = rsky_firehose::labeler::create_labels(labels_to_create, client).await;
+                match resp {
+                    Ok(()) => (),
+                    Err(error) => eprintln!("Labels failed to create: {error:?}"),
+                };
+            }
         }
         Err(error) => eprintln!(
             "@LOG: Error unwrapping message and header: {}",
@@ -87,6 +87,21 @@ pub struct GetProfilesOutput {
     pub profiles: Vec<ProfileViewDetailed>,
 }
 
+///app.bsky.actor.searchActors
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchActorsOutput {
+    pub cursor: Option<String>,
+    pub actors: Vec<ProfileView>,
+}
+
+///app.bsky.actor.searchActorsTypeahead
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchActorsTypeaheadOutput {
+    pub actors: Vec<ProfileViewBasic>,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RefProfileAssociated {
@@ -119,6 +134,7 @@ pub struct ViewerState {
     pub muted: Option<bool>,
     pub muted_by_list: Option<ListViewBasic>,
     pub blocked_by: Option<bool>,
+    pub blocking: Option<String>,
     pub blocking_by_list: Option<ListViewBasic>,
     pub following: Option<String>,
     pub followed_by: Option<String>,
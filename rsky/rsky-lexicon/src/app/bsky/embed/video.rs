@@ -11,6 +11,7 @@ pub struct Video {
     pub aspect_ratio: Option<AspectRatio>,
 }
 
+/// Closed captions for a video, as a VTT file
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Caption {
     pub lang: String,
@@ -23,8 +24,12 @@ pub struct Caption {
 #[serde(rename_all = "camelCase")]
 pub struct View {
     pub cid: String,
+    /// Fully-qualified URL of the video's HLS playlist. For example, CDN location provided
+    /// by the App View.
     pub playlist: String,
+    /// Fully-qualified URL where a thumbnail of the video can be fetched.
     pub thumbnail: Option<String>,
+    /// Alt text description of the video, for accessibility.
     pub alt: Option<String>,
     pub aspect_ratio: Option<AspectRatio>,
 }
@@ -242,3 +242,13 @@ pub struct GetPostThread {
 pub struct GetPostThreadOutput {
     pub thread: ThreadViewPostEnum,
 }
+
+///app.bsky.feed.searchPosts
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPostsOutput {
+    pub cursor: Option<String>,
+    /// Count of search hits. Optional, may be rounded/truncated, and may not be possible to paginate through all hits.
+    pub hits_total: Option<usize>,
+    pub posts: Vec<PostView>,
+}
@@ -5,3 +5,4 @@ pub mod graph;
 pub mod labeler;
 pub mod notification;
 pub mod richtext;
+pub mod video;
@@ -1,3 +1,7 @@
+use crate::app::bsky::actor::ProfileView;
+use crate::com::atproto::label::Label;
+use serde_json::Value;
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RegisterPushInput {
@@ -6,3 +10,43 @@ pub struct RegisterPushInput {
     pub platform: String,
     pub app_id: String,
 }
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub uri: String,
+    pub cid: String,
+    pub author: ProfileView,
+    /// Expected values are 'like', 'repost', 'follow', 'mention', 'reply' and 'quote'
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason_subject: Option<String>,
+    pub record: Value,
+    pub is_read: bool,
+    pub indexed_at: String,
+    pub labels: Vec<Label>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListNotificationsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub notifications: Vec<Notification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seen_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUnreadCountOutput {
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSeenInput {
+    pub seen_at: String,
+}
@@ -0,0 +1,52 @@
+use crate::com::atproto::repo::Blob;
+
+pub const JOB_STATE_CREATED: &str = "JOB_STATE_CREATED";
+pub const JOB_STATE_PROCESSING: &str = "JOB_STATE_PROCESSING";
+pub const JOB_STATE_COMPLETED: &str = "JOB_STATE_COMPLETED";
+pub const JOB_STATE_FAILED: &str = "JOB_STATE_FAILED";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub job_id: String,
+    pub did: String,
+    /// The state of the video processing job. All values not listed as a known value
+    /// indicate that the job is in process.
+    pub state: String,
+    /// Progress within the current processing state.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<i64>,
+    /// The blob to embed with `app.bsky.embed.video` once the job completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob: Option<Blob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetJobStatusOutput {
+    pub job_status: JobStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadVideoOutput {
+    pub job_status: JobStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUploadLimitsOutput {
+    pub can_upload: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_daily_videos: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_daily_bytes: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
@@ -126,6 +126,8 @@ pub struct GetLogOutput {
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetMessagesOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
     pub messages: Vec<MessageViewEnum>,
 }
 
@@ -54,6 +54,15 @@ pub struct UpdateAccountPasswordInput {
     pub password: String,
 }
 
+/// Administrative action to update an account's signing key in their Did document.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UpdateAccountSigningKeyInput {
+    pub did: String,
+    /// Did-key formatted public key
+    #[serde(rename = "signingKey")]
+    pub signing_key: String,
+}
+
 /// Send email to a user's account email address.
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct SendMailInput {
@@ -73,6 +82,18 @@ pub struct SendMailOutput {
     pub sent: bool,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetAccountInfosOutput {
+    pub infos: Vec<AccountView>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SearchAccountsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub accounts: Vec<AccountView>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct GetInviteCodesOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -4,25 +4,90 @@ use chrono::{DateTime, Utc};
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Label {
     /// The AT Protocol version of the label object.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub ver: Option<u8>,
     /// DID of the actor who created this label.
     pub src: String,
     /// AT URI of the record, repository (account), or other resource that this label applies to.
     pub uri: String,
     /// Optionally, CID specifying the specific version of 'uri' resource this label applies to.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cid: Option<String>,
     /// The short string name of the value or type of this label.
     pub val: String,
     /// If true, this is a negation label, overwriting a previous label.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub neg: Option<bool>,
     /// Timestamp when this label was created.
     pub cts: DateTime<Utc>,
     /// Timestamp at which this label expires (no longer applies).
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub exp: Option<DateTime<Utc>>,
     /// Signature of dag-cbor encoded label.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "sig_bytes")]
     pub sig: Option<Vec<u8>>,
 }
 
+/// Labels are signed over their dag-cbor encoding, where `sig` is a byte string. In json
+/// the signature is represented as `{"$bytes": "<base64>"}`.
+mod sig_bytes {
+    use base64::engine::general_purpose::STANDARD_NO_PAD;
+    use base64::Engine;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct JsonBytes {
+        #[serde(rename = "$bytes")]
+        bytes: String,
+    }
+
+    pub fn serialize<S: Serializer>(
+        sig: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match sig {
+            None => serializer.serialize_none(),
+            Some(sig) if serializer.is_human_readable() => JsonBytes {
+                bytes: STANDARD_NO_PAD.encode(sig),
+            }
+            .serialize(serializer),
+            Some(sig) => serializer.serialize_bytes(sig),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        match deserializer.is_human_readable() {
+            true => match Option::<JsonBytes>::deserialize(deserializer)? {
+                None => Ok(None),
+                Some(JsonBytes { bytes }) => Ok(Some(
+                    STANDARD_NO_PAD
+                        .decode(bytes.trim_end_matches('='))
+                        .map_err(D::Error::custom)?,
+                )),
+            },
+            false => Ok(Option::<serde_bytes::ByteBuf>::deserialize(deserializer)?
+                .map(|sig| sig.into_vec())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct QueryLabelsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub labels: Vec<Label>,
+}
+
+/// `#labels` message of `com.atproto.label.subscribeLabels`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SubscribeLabelsLabels {
+    pub seq: i64,
+    pub labels: Vec<Label>,
+}
+
 /// Metadata tags on an atproto record, published by the author within the record
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct SelfLabels {
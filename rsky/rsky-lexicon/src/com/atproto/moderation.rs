@@ -0,0 +1,38 @@
+use crate::com::atproto::admin::RepoRef;
+use crate::com::atproto::repo::StrongRef;
+use serde::{Deserialize, Serialize};
+
+/// Submit a moderation report regarding an atproto account or record.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CreateReportInput {
+    /// Indicates the broad category of violation the report is for.
+    #[serde(rename = "reasonType")]
+    pub reason_type: String,
+    /// Additional context about the content and violation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub subject: ReportSubject,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CreateReportOutput {
+    pub id: i64,
+    #[serde(rename = "reasonType")]
+    pub reason_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub subject: ReportSubject,
+    #[serde(rename = "reportedBy")]
+    pub reported_by: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "$type")]
+pub enum ReportSubject {
+    #[serde(rename = "com.atproto.admin.defs#repoRef")]
+    RepoRef(RepoRef),
+    #[serde(rename = "com.atproto.repo.strongRef")]
+    StrongRef(StrongRef),
+}
@@ -56,6 +56,9 @@ pub struct CreateSessionInput {
     /// Handle or other identifier supported by the server for the authenticating user.
     pub identifier: String,
     pub password: String,
+    /// Code emailed to the account when email auth factor is enabled.
+    #[serde(rename = "authFactorToken", skip_serializing_if = "Option::is_none")]
+    pub auth_factor_token: Option<String>,
 }
 
 /// Delete an actor's account with a token and password. Can only be called after
@@ -111,6 +114,9 @@ pub struct UpdateEmailInput {
     /// Requires a token from com.atproto.sever.requestEmailUpdate
     /// if the account's email has been confirmed.
     pub token: Option<String>,
+    /// Enables or disables requiring an emailed code to sign in.
+    #[serde(rename = "emailAuthFactor", skip_serializing_if = "Option::is_none")]
+    pub email_auth_factor: Option<bool>,
 }
 
 // Outputs
@@ -164,6 +170,8 @@ pub struct CreateSessionOutput {
     pub email: Option<String>,
     #[serde(rename = "emailConfirmed", skip_serializing_if = "Option::is_none")]
     pub email_confirmed: Option<bool>,
+    #[serde(rename = "emailAuthFactor", skip_serializing_if = "Option::is_none")]
+    pub email_auth_factor: Option<bool>,
 }
 
 /// Get information about the current auth session. Requires auth.
@@ -174,6 +182,8 @@ pub struct GetSessionOutput {
     pub email: Option<String>,
     #[serde(rename = "emailConfirmed", skip_serializing_if = "Option::is_none")]
     pub email_confirmed: Option<bool>,
+    #[serde(rename = "emailAuthFactor", skip_serializing_if = "Option::is_none")]
+    pub email_auth_factor: Option<bool>,
     #[serde(rename = "didDoc", skip_serializing_if = "Option::is_none")]
     pub did_doc: Option<String>,
 }
@@ -0,0 +1,165 @@
+use crate::com::atproto::label::Label;
+use crate::com::atproto::moderation::ReportSubject;
+use serde::{Deserialize, Serialize};
+
+/// Audit a DID's PLC operation log for signature, `prev` chain, rotation key and PDS endpoint
+/// anomalies.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuditPlcLogOutput {
+    pub did: String,
+    /// Number of operations in the log, including nullified ones.
+    #[serde(rename = "opCount")]
+    pub op_count: usize,
+    pub findings: Vec<PlcAuditFinding>,
+}
+
+/// Mark a locally stored moderation report as resolved.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ResolveReportInput {
+    pub id: i64,
+    /// Optional note on how the report was handled.
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ListReportsOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub reports: Vec<ReportView>,
+}
+
+/// Create labels signed by this service's labeler.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CreateLabelsInput {
+    pub labels: Vec<CreateLabel>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CreateLabelsOutput {
+    pub labels: Vec<Label>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetJobStatusesOutput {
+    pub jobs: Vec<JobStatus>,
+}
+
+/// Find, and unless `dryRun` is set, delete blobs that no record references.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CollectBlobGarbageInput {
+    /// Defaults to true: only report what would be deleted.
+    #[serde(rename = "dryRun")]
+    pub dry_run: Option<bool>,
+    /// Blobs and store objects younger than this are left alone.
+    #[serde(rename = "minAgeMs")]
+    pub min_age_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct BlobGcReport {
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    /// Uploaded blobs never referenced by a record, still under their temp key.
+    #[serde(rename = "untetheredTempBlobs")]
+    pub untethered_temp_blobs: Vec<BlobGcItem>,
+    /// Permanent blobs no longer referenced by any record.
+    #[serde(rename = "dereferencedBlobs")]
+    pub dereferenced_blobs: Vec<BlobGcItem>,
+    /// Blob store objects with no blob row.
+    #[serde(rename = "orphanedObjects")]
+    pub orphaned_objects: Vec<BlobGcItem>,
+    /// Resumable uploads that expired before completing, by upload id.
+    #[serde(rename = "expiredUploads")]
+    pub expired_uploads: Vec<BlobGcItem>,
+}
+
+/// Override the storage quotas of an account. Omitted limits fall back to the
+/// server-wide defaults.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SetAccountQuotaInput {
+    pub did: String,
+    #[serde(rename = "maxBlobBytes")]
+    pub max_blob_bytes: Option<i64>,
+    #[serde(rename = "maxRecords")]
+    pub max_records: Option<i64>,
+    #[serde(rename = "maxRepoBytes")]
+    pub max_repo_bytes: Option<i64>,
+}
+
+// Defs
+// ----
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlcAuditFinding {
+    /// CID of the offending operation.
+    pub cid: String,
+    pub kind: String,
+    pub message: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    /// Whether the operation can still be nullified with a higher-priority rotation key.
+    pub recoverable: bool,
+}
+
+/// Moderation report stored by a PDS without a report service.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ReportView {
+    pub id: i64,
+    #[serde(rename = "reasonType")]
+    pub reason_type: String,
+    pub reason: Option<String>,
+    pub subject: ReportSubject,
+    #[serde(rename = "reportedBy")]
+    pub reported_by: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "resolvedAt")]
+    pub resolved_at: Option<String>,
+    #[serde(rename = "resolvedBy")]
+    pub resolved_by: Option<String>,
+    #[serde(rename = "resolutionNote")]
+    pub resolution_note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CreateLabel {
+    /// AT URI of the record, or DID of the account, being labeled.
+    pub uri: String,
+    pub cid: Option<String>,
+    pub val: String,
+    /// Negates a previously created label with the same value.
+    pub neg: Option<bool>,
+    pub exp: Option<String>,
+}
+
+/// State of a background job run by the PDS scheduler.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct JobStatus {
+    pub name: String,
+    #[serde(rename = "intervalMs")]
+    pub interval_ms: u64,
+    pub running: bool,
+    pub runs: u64,
+    #[serde(rename = "lastStartedAt")]
+    pub last_started_at: Option<String>,
+    #[serde(rename = "lastFinishedAt")]
+    pub last_finished_at: Option<String>,
+    /// Items handled by the last successful run, e.g. accounts purged.
+    #[serde(rename = "lastProcessed")]
+    pub last_processed: u64,
+    #[serde(rename = "totalProcessed")]
+    pub total_processed: u64,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BlobGcItem {
+    pub did: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    #[serde(rename = "tempKey", skip_serializing_if = "Option::is_none")]
+    pub temp_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i32>,
+}
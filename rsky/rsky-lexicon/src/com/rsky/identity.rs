@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Nullify a PLC operation on the current account's DID, reverting it to the state before that
+/// operation. Only possible within the PLC recovery window and with a rotation key of higher
+/// priority than the one that signed the operation. Without `operation`, returns the unsigned
+/// recovery operation for the client to sign with its rotation key and send back.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NullifyPlcOperationInput {
+    /// CID of the operation to nullify.
+    pub cid: String,
+    /// The recovery operation, signed by a rotation key for the DID.
+    pub operation: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NullifyPlcOperationOutput {
+    /// The recovery operation: unsigned if none was given, otherwise the one submitted.
+    pub operation: Value,
+}
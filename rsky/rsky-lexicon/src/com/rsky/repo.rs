@@ -0,0 +1,48 @@
+/// A record hosted on the PDS that links to the queried subject
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backlink {
+    pub uri: String,
+    pub cid: String,
+    pub collection: String,
+    // Where the link sits in the record, e.g. `subject.uri` or `reply.parent.uri`
+    pub path: String,
+    pub indexed_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBacklinksOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    pub subject: String,
+    pub backlinks: Vec<Backlink>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUploadInput {
+    // Total size of the blob in bytes
+    pub size: i64,
+    pub mime_type: String,
+    // Sha256 cid of the whole blob, checked once every chunk is in
+    pub cid: String,
+}
+
+/// A resumable upload session. Chunks are appended at `offset`, the number of bytes
+/// received so far.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadStatus {
+    pub upload_id: String,
+    pub size: i64,
+    pub offset: i64,
+    pub max_chunk_size: i64,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteUploadInput {
+    pub upload_id: String,
+}
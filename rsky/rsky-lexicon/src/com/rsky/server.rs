@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// List the active device sessions of the account.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ListSessionsOutput {
+    pub sessions: Vec<Session>,
+}
+
+/// Revoke a device session, along with all of its refresh tokens.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RevokeSessionInput {
+    pub id: String,
+}
+
+/// Storage used by an account and the quotas that apply to it. Limits are absent when
+/// unlimited.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GetAccountUsageOutput {
+    pub did: String,
+    pub usage: AccountUsage,
+    pub limits: AccountLimits,
+}
+
+// Defs
+// ----
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Session {
+    pub id: String,
+    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(rename = "lastRefreshedAt", skip_serializing_if = "Option::is_none")]
+    pub last_refreshed_at: Option<String>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    /// Set when the session was created with an App Password.
+    #[serde(rename = "appPasswordName", skip_serializing_if = "Option::is_none")]
+    pub app_password_name: Option<String>,
+    #[serde(rename = "userAgent", skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AccountUsage {
+    #[serde(rename = "blobBytes")]
+    pub blob_bytes: i64,
+    #[serde(rename = "blobCount")]
+    pub blob_count: i64,
+    #[serde(rename = "recordCount")]
+    pub record_count: i64,
+    #[serde(rename = "repoBytes")]
+    pub repo_bytes: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct AccountLimits {
+    #[serde(rename = "maxBlobBytes", skip_serializing_if = "Option::is_none")]
+    pub max_blob_bytes: Option<i64>,
+    #[serde(rename = "maxRecords", skip_serializing_if = "Option::is_none")]
+    pub max_records: Option<i64>,
+    #[serde(rename = "maxRepoBytes", skip_serializing_if = "Option::is_none")]
+    pub max_repo_bytes: Option<i64>,
+}
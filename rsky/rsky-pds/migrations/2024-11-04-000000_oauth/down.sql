@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.oauth_authorized_client;
+DROP TABLE IF EXISTS pds.oauth_token;
+DROP TABLE IF EXISTS pds.oauth_request;
@@ -0,0 +1,45 @@
+-- Your SQL goes here
+-- Create OAuth Request Table
+-- Pushed authorization requests, later updated with the signed-in account and code
+CREATE TABLE IF NOT EXISTS pds.oauth_request (
+    id character varying PRIMARY KEY,
+    "clientId" character varying NOT NULL,
+    parameters text NOT NULL,
+    "dpopJkt" character varying,
+    did character varying,
+    code character varying,
+    "createdAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL
+);
+CREATE UNIQUE INDEX oauth_request_code_idx
+    ON pds.oauth_request(code);
+
+-- Create OAuth Token Table
+CREATE TABLE IF NOT EXISTS pds.oauth_token (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    "clientId" character varying NOT NULL,
+    scope character varying NOT NULL,
+    "dpopJkt" character varying NOT NULL,
+    "refreshToken" character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL
+);
+CREATE UNIQUE INDEX oauth_token_refresh_token_idx
+    ON pds.oauth_token("refreshToken");
+CREATE INDEX oauth_token_did_idx
+    ON pds.oauth_token(did);
+
+-- Create OAuth Authorized Client Table
+CREATE TABLE IF NOT EXISTS pds.oauth_authorized_client (
+    did character varying NOT NULL,
+    "clientId" character varying NOT NULL,
+    scope character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
+ALTER TABLE ONLY pds.oauth_authorized_client
+    DROP CONSTRAINT IF EXISTS oauth_authorized_client_pkey;
+ALTER TABLE ONLY pds.oauth_authorized_client
+    ADD CONSTRAINT oauth_authorized_client_pkey PRIMARY KEY (did, "clientId");
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE pds.account
+    ADD COLUMN "emailAuthFactor" boolean NOT NULL DEFAULT false;
@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS pds.refresh_token_session_id_idx;
+ALTER TABLE pds.refresh_token
+    DROP COLUMN IF EXISTS "sessionId",
+    DROP COLUMN IF EXISTS "createdAt",
+    DROP COLUMN IF EXISTS "lastRefreshedAt",
+    DROP COLUMN IF EXISTS "userAgent",
+    DROP COLUMN IF EXISTS "ip";
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+ALTER TABLE pds.refresh_token
+    ADD COLUMN "sessionId" character varying,
+    ADD COLUMN "createdAt" character varying,
+    ADD COLUMN "lastRefreshedAt" character varying,
+    ADD COLUMN "userAgent" character varying,
+    ADD COLUMN "ip" character varying;
+UPDATE pds.refresh_token SET "sessionId" = id;
+ALTER TABLE pds.refresh_token
+    ALTER COLUMN "sessionId" SET NOT NULL;
+CREATE INDEX refresh_token_session_id_idx
+    ON pds.refresh_token ("sessionId");
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.moderation_report (
+    id SERIAL PRIMARY KEY,
+    "reasonType" character varying NOT NULL,
+    reason character varying,
+    "subjectDid" character varying NOT NULL,
+    "subjectUri" character varying,
+    "subjectCid" character varying,
+    "reportedBy" character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "resolvedAt" character varying,
+    "resolvedBy" character varying,
+    "resolutionNote" character varying
+);
+CREATE INDEX moderation_report_resolved_at_idx
+    ON pds.moderation_report ("resolvedAt", id);
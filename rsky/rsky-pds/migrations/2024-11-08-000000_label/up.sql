@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.label (
+    seq BIGSERIAL PRIMARY KEY,
+    src character varying NOT NULL,
+    uri character varying NOT NULL,
+    cid character varying,
+    val character varying NOT NULL,
+    neg boolean NOT NULL DEFAULT FALSE,
+    cts character varying NOT NULL,
+    exp character varying,
+    sig bytea NOT NULL
+);
+CREATE INDEX label_uri_idx
+    ON pds.label (uri, seq);
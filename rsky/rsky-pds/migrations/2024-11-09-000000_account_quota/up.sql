@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.account_quota (
+    did character varying PRIMARY KEY,
+    "maxBlobBytes" bigint,
+    "maxRecords" bigint,
+    "maxRepoBytes" bigint,
+    "updatedAt" character varying NOT NULL
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.notification_seen;
+DROP TABLE IF EXISTS pds.notification;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.notification (
+    id BIGSERIAL PRIMARY KEY,
+    did character varying NOT NULL,
+    "recordUri" character varying NOT NULL,
+    "recordCid" character varying NOT NULL,
+    author character varying NOT NULL,
+    reason character varying NOT NULL,
+    "reasonSubject" character varying,
+    "sortAt" character varying NOT NULL
+);
+CREATE INDEX notification_did_idx
+    ON pds.notification (did, id);
+CREATE INDEX notification_record_uri_idx
+    ON pds.notification ("recordUri");
+
+CREATE TABLE IF NOT EXISTS pds.notification_seen (
+    did character varying PRIMARY KEY,
+    "seenAt" character varying NOT NULL
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.search_profile;
+DROP TABLE IF EXISTS pds.search_post;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.search_post (
+    uri character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    text text NOT NULL,
+    langs character varying[] NOT NULL,
+    tags character varying[] NOT NULL,
+    mentions character varying[] NOT NULL,
+    "sortAt" character varying NOT NULL,
+    document tsvector GENERATED ALWAYS AS (to_tsvector('simple', text)) STORED
+);
+CREATE INDEX search_post_document_idx
+    ON pds.search_post USING GIN (document);
+CREATE INDEX search_post_tags_idx
+    ON pds.search_post USING GIN (tags);
+CREATE INDEX search_post_sort_at_idx
+    ON pds.search_post ("sortAt", uri);
+
+CREATE TABLE IF NOT EXISTS pds.search_profile (
+    did character varying PRIMARY KEY,
+    "displayName" character varying,
+    description text,
+    document tsvector GENERATED ALWAYS AS (
+        to_tsvector('simple', coalesce("displayName", '') || ' ' || coalesce(description, ''))
+    ) STORED
+);
+CREATE INDEX search_profile_document_idx
+    ON pds.search_profile USING GIN (document);
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS pds.chat_log;
+DROP TABLE IF EXISTS pds.chat_message_deletion;
+DROP TABLE IF EXISTS pds.chat_message;
+DROP TABLE IF EXISTS pds.chat_member;
+DROP TABLE IF EXISTS pds.chat_convo;
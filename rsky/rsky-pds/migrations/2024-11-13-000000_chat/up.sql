@@ -0,0 +1,49 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.chat_convo (
+    id character varying PRIMARY KEY,
+    rev character varying NOT NULL,
+    "createdAt" character varying NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS pds.chat_member (
+    "convoId" character varying NOT NULL,
+    did character varying NOT NULL,
+    muted boolean NOT NULL DEFAULT false,
+    "lastReadRev" character varying,
+    "leftAt" character varying,
+    CONSTRAINT chat_member_pkey PRIMARY KEY ("convoId", did)
+);
+CREATE INDEX chat_member_did_idx
+    ON pds.chat_member (did);
+
+CREATE TABLE IF NOT EXISTS pds.chat_message (
+    "convoId" character varying NOT NULL,
+    id character varying NOT NULL,
+    rev character varying NOT NULL,
+    sender character varying NOT NULL,
+    text text NOT NULL,
+    facets text,
+    embed text,
+    "sentAt" character varying NOT NULL,
+    CONSTRAINT chat_message_pkey PRIMARY KEY ("convoId", id)
+);
+CREATE INDEX chat_message_rev_idx
+    ON pds.chat_message ("convoId", rev);
+
+CREATE TABLE IF NOT EXISTS pds.chat_message_deletion (
+    did character varying NOT NULL,
+    "convoId" character varying NOT NULL,
+    "messageId" character varying NOT NULL,
+    CONSTRAINT chat_message_deletion_pkey PRIMARY KEY (did, "convoId", "messageId")
+);
+
+CREATE TABLE IF NOT EXISTS pds.chat_log (
+    id BIGSERIAL PRIMARY KEY,
+    did character varying NOT NULL,
+    "convoId" character varying NOT NULL,
+    rev character varying NOT NULL,
+    "logType" character varying NOT NULL,
+    "messageId" character varying
+);
+CREATE INDEX chat_log_did_rev_idx
+    ON pds.chat_log (did, rev);
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.blob_upload (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    "mimeType" character varying NOT NULL,
+    size bigint NOT NULL,
+    received bigint NOT NULL DEFAULT 0,
+    cid character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    "expiresAt" character varying NOT NULL
+);
+CREATE INDEX blob_upload_did_idx
+    ON pds.blob_upload (did);
@@ -0,0 +1,22 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.video_job (
+    id character varying PRIMARY KEY,
+    did character varying NOT NULL,
+    state character varying NOT NULL,
+    progress integer NOT NULL DEFAULT 0,
+    "tempKey" character varying NOT NULL,
+    "mimeType" character varying NOT NULL,
+    size bigint NOT NULL,
+    "blobCid" character varying,
+    width integer,
+    height integer,
+    "durationMs" bigint,
+    error character varying,
+    message character varying,
+    "createdAt" character varying NOT NULL,
+    "updatedAt" character varying NOT NULL
+);
+CREATE INDEX video_job_did_created_at_idx
+    ON pds.video_job (did, "createdAt");
+CREATE INDEX video_job_state_idx
+    ON pds.video_job (state, "createdAt");
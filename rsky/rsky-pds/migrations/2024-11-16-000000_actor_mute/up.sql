@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.actor_mute (
+    did character varying NOT NULL,
+    "subjectDid" character varying NOT NULL,
+    "createdAt" character varying NOT NULL,
+    CONSTRAINT actor_mute_pkey PRIMARY KEY (did, "subjectDid")
+);
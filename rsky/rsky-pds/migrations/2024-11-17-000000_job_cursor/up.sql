@@ -0,0 +1,6 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS pds.job_cursor (
+    name character varying PRIMARY KEY,
+    cursor character varying NOT NULL,
+    "completedAt" character varying
+);
@@ -1,6 +1,7 @@
 use crate::common;
 use crate::common::RFC3339_VARIANT;
 use crate::db::establish_connection;
+use crate::db::pagination::{paginate, KeySetPaginateOpts, TimeKeySet};
 use crate::schema::pds::account::dsl as AccountSchema;
 use crate::schema::pds::account::table as AccountTable;
 use crate::schema::pds::actor::dsl as ActorSchema;
@@ -14,6 +15,7 @@ use diesel::pg::Pg;
 use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use diesel::*;
 use rsky_lexicon::com::atproto::admin::StatusAttr;
+use std::collections::BTreeMap;
 use std::ops::Add;
 use std::time::SystemTime;
 use thiserror::Error;
@@ -192,6 +194,147 @@ pub async fn get_account_by_email(
     Ok(found)
 }
 
+pub async fn get_accounts(
+    dids: &Vec<String>,
+    flags: Option<AvailabilityFlags>,
+) -> Result<BTreeMap<String, ActorAccount>> {
+    if dids.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let conn = &mut establish_connection()?;
+
+    let found = select_account_qb(flags)
+        .select((
+            ActorSchema::did,
+            ActorSchema::handle,
+            ActorSchema::createdAt,
+            ActorSchema::takedownRef,
+            ActorSchema::deactivatedAt,
+            ActorSchema::deleteAfter,
+            AccountSchema::email.nullable(),
+            AccountSchema::emailConfirmedAt.nullable(),
+            AccountSchema::invitesDisabled.nullable(),
+        ))
+        .filter(ActorSchema::did.eq_any(dids))
+        .load::<ActorAccountRow>(conn)?;
+    Ok(found
+        .into_iter()
+        .map(|res| (res.0.clone(), actor_account_from_row(res)))
+        .collect())
+}
+
+pub struct SearchAccountsOpts {
+    /// Prefix of the account email
+    pub email: Option<String>,
+    /// Prefix of the account handle
+    pub handle: Option<String>,
+    pub did: Option<String>,
+    /// Invite code the account signed up with
+    pub invite_code: Option<String>,
+    pub limit: i64,
+    pub cursor: Option<String>,
+}
+
+/// Keyset accounts are searched with, newest first
+pub const SEARCH_ACCOUNTS_KEYSET: TimeKeySet = TimeKeySet {
+    created_at: r#""actor"."createdAt""#,
+    key: r#""actor"."did""#,
+};
+
+pub async fn search_accounts(
+    opts: SearchAccountsOpts,
+    flags: Option<AvailabilityFlags>,
+) -> Result<Vec<ActorAccount>> {
+    use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
+    let conn = &mut establish_connection()?;
+    let SearchAccountsOpts {
+        email,
+        handle,
+        did,
+        invite_code,
+        limit,
+        cursor,
+    } = opts;
+
+    let mut builder = select_account_qb(flags);
+    if let Some(email) = email {
+        builder = builder
+            .filter(AccountSchema::email.like(format!("{}%", escape_like(&email.to_lowercase()))));
+    }
+    if let Some(handle) = handle {
+        builder = builder
+            .filter(ActorSchema::handle.like(format!("{}%", escape_like(&handle.to_lowercase()))));
+    }
+    if let Some(did) = did {
+        builder = builder.filter(ActorSchema::did.eq(did));
+    }
+    if let Some(invite_code) = invite_code {
+        builder = builder.filter(
+            ActorSchema::did.eq_any(
+                InviteCodeUseSchema::invite_code_use
+                    .filter(InviteCodeUseSchema::code.eq(invite_code))
+                    .select(InviteCodeUseSchema::usedBy),
+            ),
+        );
+    }
+    let builder = builder.select((
+        ActorSchema::did,
+        ActorSchema::handle,
+        ActorSchema::createdAt,
+        ActorSchema::takedownRef,
+        ActorSchema::deactivatedAt,
+        ActorSchema::deleteAfter,
+        AccountSchema::email.nullable(),
+        AccountSchema::emailConfirmedAt.nullable(),
+        AccountSchema::invitesDisabled.nullable(),
+    ));
+    let found = paginate(
+        builder,
+        &SEARCH_ACCOUNTS_KEYSET,
+        KeySetPaginateOpts {
+            limit: Some(limit),
+            cursor,
+            direction: None,
+        },
+    )?
+    .load::<ActorAccountRow>(conn)?;
+    Ok(found.into_iter().map(actor_account_from_row).collect())
+}
+
+type ActorAccountRow = (
+    String,
+    Option<String>,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i16>,
+);
+
+fn actor_account_from_row(res: ActorAccountRow) -> ActorAccount {
+    ActorAccount {
+        did: res.0,
+        handle: res.1,
+        created_at: res.2,
+        takedown_ref: res.3,
+        deactivated_at: res.4,
+        delete_after: res.5,
+        email: res.6,
+        email_confirmed_at: res.7,
+        invites_disabled: res.8,
+    }
+}
+
+/// Escapes `LIKE` wildcards so user input only ever matches literally
+pub fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 pub fn register_actor(did: String, handle: String, deactivated: Option<bool>) -> Result<()> {
     let conn = &mut establish_connection()?;
 
@@ -244,9 +387,20 @@ pub fn register_account(did: String, email: String, password: String) -> Result<
 }
 
 pub async fn delete_account(did: &String) -> Result<()> {
+    use crate::schema::pds::account_quota::dsl as AccountQuotaSchema;
+    use crate::schema::pds::actor_mute::dsl as ActorMuteSchema;
+    use crate::schema::pds::app_password::dsl as AppPasswordSchema;
+    use crate::schema::pds::chat_log::dsl as ChatLogSchema;
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+    use crate::schema::pds::chat_message_deletion::dsl as ChatMessageDeletionSchema;
     use crate::schema::pds::email_token::dsl as EmailTokenSchema;
+    use crate::schema::pds::notification::dsl as NotificationSchema;
+    use crate::schema::pds::notification_seen::dsl as NotificationSeenSchema;
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
     use crate::schema::pds::repo_root::dsl as RepoRootSchema;
+    use crate::schema::pds::search_post::dsl as SearchPostSchema;
+    use crate::schema::pds::search_profile::dsl as SearchProfileSchema;
 
     let conn = &mut establish_connection()?;
     delete(RepoRootSchema::repo_root)
@@ -258,6 +412,46 @@ pub async fn delete_account(did: &String) -> Result<()> {
     delete(RefreshTokenSchema::refresh_token)
         .filter(RefreshTokenSchema::did.eq(did))
         .execute(conn)?;
+    delete(AppPasswordSchema::app_password)
+        .filter(AppPasswordSchema::did.eq(did))
+        .execute(conn)?;
+    delete(PushRegistrationSchema::push_registration)
+        .filter(PushRegistrationSchema::did.eq(did))
+        .execute(conn)?;
+    delete(NotificationSchema::notification)
+        .filter(
+            NotificationSchema::did
+                .eq(did)
+                .or(NotificationSchema::author.eq(did)),
+        )
+        .execute(conn)?;
+    delete(NotificationSeenSchema::notification_seen)
+        .filter(NotificationSeenSchema::did.eq(did))
+        .execute(conn)?;
+    delete(ActorMuteSchema::actor_mute)
+        .filter(ActorMuteSchema::did.eq(did))
+        .execute(conn)?;
+    delete(AccountQuotaSchema::account_quota)
+        .filter(AccountQuotaSchema::did.eq(did))
+        .execute(conn)?;
+    delete(SearchPostSchema::search_post)
+        .filter(SearchPostSchema::did.eq(did))
+        .execute(conn)?;
+    delete(SearchProfileSchema::search_profile)
+        .filter(SearchProfileSchema::did.eq(did))
+        .execute(conn)?;
+    // Convos stay readable for the other members, the account just leaves them
+    update(ChatMemberSchema::chat_member)
+        .filter(ChatMemberSchema::did.eq(did))
+        .filter(ChatMemberSchema::leftAt.is_null())
+        .set(ChatMemberSchema::leftAt.eq(common::now()))
+        .execute(conn)?;
+    delete(ChatMessageDeletionSchema::chat_message_deletion)
+        .filter(ChatMessageDeletionSchema::did.eq(did))
+        .execute(conn)?;
+    delete(ChatLogSchema::chat_log)
+        .filter(ChatLogSchema::did.eq(did))
+        .execute(conn)?;
     delete(AccountSchema::account)
         .filter(AccountSchema::did.eq(did))
         .execute(conn)?;
@@ -267,6 +461,29 @@ pub async fn delete_account(did: &String) -> Result<()> {
     Ok(())
 }
 
+/// Deactivated accounts whose `deleteAfter` has passed. `deleteAfter` is client provided
+/// so it's compared as a parsed datetime rather than as a string.
+pub async fn get_accounts_due_for_deletion() -> Result<Vec<String>> {
+    let conn = &mut establish_connection()?;
+    let now = UtcOffset::now();
+
+    let res: Vec<(String, Option<String>)> = ActorSchema::actor
+        .filter(ActorSchema::deactivatedAt.is_not_null())
+        .filter(ActorSchema::deleteAfter.is_not_null())
+        .select((ActorSchema::did, ActorSchema::deleteAfter))
+        .load(conn)?;
+    Ok(res
+        .into_iter()
+        .filter_map(|(did, delete_after)| {
+            let delete_after = DateTime::parse_from_rfc3339(&delete_after?).ok()?;
+            match delete_after <= now {
+                true => Some(did),
+                false => None,
+            }
+        })
+        .collect())
+}
+
 pub async fn update_account_takedown_status(did: &String, takedown: StatusAttr) -> Result<()> {
     let conn = &mut establish_connection()?;
     let takedown_ref: Option<String> = match takedown.applied {
@@ -364,6 +581,27 @@ pub async fn set_email_confirmed_at(did: &String, email_confirmed_at: String) ->
     Ok(())
 }
 
+pub async fn get_email_auth_factor(did: &String) -> Result<bool> {
+    let conn = &mut establish_connection()?;
+
+    let res = AccountSchema::account
+        .filter(AccountSchema::did.eq(did))
+        .select(AccountSchema::emailAuthFactor)
+        .first::<bool>(conn)
+        .optional()?;
+    Ok(res.unwrap_or(false))
+}
+
+pub async fn set_email_auth_factor(did: &String, email_auth_factor: bool) -> Result<()> {
+    let conn = &mut establish_connection()?;
+
+    update(AccountSchema::account)
+        .filter(AccountSchema::did.eq(did))
+        .set(AccountSchema::emailAuthFactor.eq(email_auth_factor))
+        .execute(conn)?;
+    Ok(())
+}
+
 pub async fn get_account_admin_status(did: &String) -> Result<Option<GetAccountAdminStatusOutput>> {
     let conn = &mut establish_connection()?;
 
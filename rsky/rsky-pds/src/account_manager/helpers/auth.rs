@@ -18,6 +18,8 @@ pub struct CreateTokensOpts {
     pub scope: Option<AuthScope>,
     pub jti: Option<String>,
     pub expires_in: Option<Duration>,
+    /// Session the access token belongs to, so it stops being accepted once the session is revoked
+    pub session_id: Option<String>,
 }
 
 pub struct RefreshGracePeriodOpts {
@@ -26,6 +28,15 @@ pub struct RefreshGracePeriodOpts {
     pub next_id: String,
 }
 
+/// Device a session was created from. Carried over to every refresh token of the session.
+pub struct RefreshTokenSession {
+    pub session_id: String,
+    pub created_at: Option<String>,
+    pub last_refreshed_at: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
 pub struct AuthToken {
     pub scope: AuthScope,
     pub sub: String,
@@ -66,6 +77,8 @@ pub struct ServiceJwtParams {
 #[derive(Serialize, Deserialize)]
 pub struct CustomClaimObj {
     pub scope: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 #[derive(Error, Debug)]
@@ -82,6 +95,7 @@ pub fn create_tokens(opts: CreateTokensOpts) -> Result<(String, String)> {
         scope,
         jti,
         expires_in,
+        session_id,
     } = opts;
     let access_jwt = create_access_token(CreateTokensOpts {
         did: did.clone(),
@@ -90,6 +104,7 @@ pub fn create_tokens(opts: CreateTokensOpts) -> Result<(String, String)> {
         scope,
         expires_in,
         jti: None,
+        session_id,
     })?;
     let refresh_jwt = create_refresh_token(CreateTokensOpts {
         did,
@@ -98,6 +113,7 @@ pub fn create_tokens(opts: CreateTokensOpts) -> Result<(String, String)> {
         jti,
         expires_in,
         scope: None,
+        session_id: None,
     })?;
     Ok((access_jwt, refresh_jwt))
 }
@@ -109,6 +125,7 @@ pub fn create_access_token(opts: CreateTokensOpts) -> Result<String> {
         service_did,
         scope,
         expires_in,
+        session_id,
         ..
     } = opts;
     let scope = scope.unwrap_or_else(|| AuthScope::Access);
@@ -116,6 +133,7 @@ pub fn create_access_token(opts: CreateTokensOpts) -> Result<String> {
     let claims = Claims::with_custom_claims(
         CustomClaimObj {
             scope: scope.as_str().to_owned(),
+            sid: session_id,
         },
         expires_in,
     )
@@ -141,6 +159,7 @@ pub fn create_refresh_token(opts: CreateTokensOpts) -> Result<String> {
     let claims = Claims::with_custom_claims(
         CustomClaimObj {
             scope: AuthScope::Refresh.as_str().to_owned(),
+            sid: None,
         },
         expires_in,
     )
@@ -218,6 +237,7 @@ pub fn decode_refresh_token(jwt: String, jwt_key: Keypair) -> Result<RefreshToke
 pub async fn store_refresh_token(
     payload: RefreshToken,
     app_password_name: Option<String>,
+    session: RefreshTokenSession,
 ) -> Result<()> {
     use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
     let conn = &mut establish_connection()?;
@@ -230,6 +250,11 @@ pub async fn store_refresh_token(
             RefreshTokenSchema::did.eq(payload.sub),
             RefreshTokenSchema::appPasswordName.eq(app_password_name),
             RefreshTokenSchema::expiresAt.eq(format!("{}", exp.format(RFC3339_VARIANT))),
+            RefreshTokenSchema::sessionId.eq(session.session_id),
+            RefreshTokenSchema::createdAt.eq(session.created_at),
+            RefreshTokenSchema::lastRefreshedAt.eq(session.last_refreshed_at),
+            RefreshTokenSchema::userAgent.eq(session.user_agent),
+            RefreshTokenSchema::ip.eq(session.ip),
         ))
         .on_conflict_do_nothing() // E.g. when re-granting during a refresh grace period
         .execute(conn)?;
@@ -258,6 +283,47 @@ pub async fn revoke_refresh_tokens_by_did(did: &String) -> Result<bool> {
     Ok(deleted_rows.len() > 0)
 }
 
+/// Revokes every refresh token of a session, including the one still in its grace period
+pub async fn revoke_session(did: &String, session_id: &String) -> Result<bool> {
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let deleted_rows = delete(RefreshTokenSchema::refresh_token)
+        .filter(RefreshTokenSchema::did.eq(did))
+        .filter(RefreshTokenSchema::sessionId.eq(session_id))
+        .get_results::<models::RefreshToken>(conn)?;
+
+    Ok(deleted_rows.len() > 0)
+}
+
+/// Whether any refresh token of the session is still stored, i.e. it was not revoked or logged out
+pub async fn session_exists(did: &String, session_id: &String) -> Result<bool> {
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let found = RefreshTokenSchema::refresh_token
+        .filter(RefreshTokenSchema::did.eq(did))
+        .filter(RefreshTokenSchema::sessionId.eq(session_id))
+        .select(RefreshTokenSchema::id)
+        .first::<String>(conn)
+        .optional()?;
+    Ok(found.is_some())
+}
+
+/// Active sessions of an account: the latest, not yet rotated, refresh token of each session
+pub async fn list_sessions(did: &String, now: String) -> Result<Vec<models::RefreshToken>> {
+    use crate::schema::pds::refresh_token::dsl as RefreshTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    Ok(RefreshTokenSchema::refresh_token
+        .filter(RefreshTokenSchema::did.eq(did))
+        .filter(RefreshTokenSchema::nextId.is_null())
+        .filter(RefreshTokenSchema::expiresAt.gt(now))
+        .order(RefreshTokenSchema::createdAt.desc())
+        .select(models::RefreshToken::as_select())
+        .get_results(conn)?)
+}
+
 pub async fn revoke_app_password_refresh_token(
     did: &String,
     app_pass_name: &String,
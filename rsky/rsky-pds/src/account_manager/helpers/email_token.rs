@@ -6,6 +6,15 @@ use crate::models::models::EmailTokenPurpose;
 use crate::models::EmailToken;
 use anyhow::{bail, Result};
 use diesel::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EmailTokenError {
+    #[error("Token is invalid")]
+    Invalid,
+    #[error("Token is expired")]
+    Expired,
+}
 
 pub async fn create_email_token(did: &String, purpose: EmailTokenPurpose) -> Result<String> {
     use crate::schema::pds::email_token::dsl as EmailTokenSchema;
@@ -51,11 +60,11 @@ pub async fn assert_valid_token(
         let requested_at = from_str_to_utc(&res.requested_at);
         let expired = !less_than_ago_ms(requested_at, expiration_len);
         if expired {
-            bail!("Token is expired")
+            bail!(EmailTokenError::Expired)
         }
         Ok(())
     } else {
-        bail!("Token is invalid")
+        bail!(EmailTokenError::Invalid)
     }
 }
 
@@ -78,11 +87,11 @@ pub async fn assert_valid_token_and_find_did(
         let requested_at = from_str_to_utc(&res.requested_at);
         let expired = !less_than_ago_ms(requested_at, expiration_len);
         if expired {
-            bail!("Token is expired")
+            bail!(EmailTokenError::Expired)
         }
         Ok(res.did)
     } else {
-        bail!("Token is invalid")
+        bail!(EmailTokenError::Invalid)
     }
 }
 
@@ -167,6 +167,38 @@ pub async fn get_account_invite_codes(did: &String) -> Result<Vec<CodeDetail>> {
         .collect::<Vec<CodeDetail>>())
 }
 
+pub async fn get_accounts_invite_codes(
+    dids: &Vec<String>,
+) -> Result<BTreeMap<String, Vec<CodeDetail>>> {
+    use crate::schema::pds::invite_code::dsl as InviteCodeSchema;
+    let mut res: BTreeMap<String, Vec<CodeDetail>> =
+        dids.iter().map(|did| (did.clone(), Vec::new())).collect();
+    if dids.is_empty() {
+        return Ok(res);
+    }
+    let conn = &mut establish_connection()?;
+
+    let codes_res: Vec<models::InviteCode> = InviteCodeSchema::invite_code
+        .filter(InviteCodeSchema::forAccount.eq_any(dids))
+        .select(models::InviteCode::as_select())
+        .get_results(conn)?;
+    let codes: Vec<String> = codes_res.iter().map(|row| row.code.clone()).collect();
+    let mut uses = get_invite_codes_uses(codes).await?;
+    for row in codes_res {
+        let code = CodeDetail {
+            code: row.code.clone(),
+            available: row.available_uses,
+            disabled: row.disabled == 1,
+            for_account: row.for_account.clone(),
+            created_by: row.created_by,
+            created_at: row.created_at,
+            uses: mem::take(uses.get_mut(&row.code).unwrap_or(&mut Vec::new())),
+        };
+        res.entry(row.for_account).or_default().push(code);
+    }
+    Ok(res)
+}
+
 pub async fn get_invite_codes_uses(codes: Vec<String>) -> Result<BTreeMap<String, Vec<CodeUse>>> {
     use crate::schema::pds::invite_code_use::dsl as InviteCodeUseSchema;
     let conn = &mut establish_connection()?;
@@ -1,8 +1,8 @@
 use crate::account_manager::helpers::account::{
-    AccountStatus, ActorAccount, AvailabilityFlags, GetAccountAdminStatusOutput,
+    AccountStatus, ActorAccount, AvailabilityFlags, GetAccountAdminStatusOutput, SearchAccountsOpts,
 };
 use crate::account_manager::helpers::auth::{
-    AuthHelperError, CreateTokensOpts, RefreshGracePeriodOpts,
+    AuthHelperError, CreateTokensOpts, RefreshGracePeriodOpts, RefreshTokenSession,
 };
 use crate::account_manager::helpers::invite::{CodeDetail, CodeUse};
 use crate::account_manager::helpers::password::UpdateUserPasswordOpts;
@@ -11,13 +11,17 @@ use crate::auth_verifier::AuthScope;
 use crate::common;
 use crate::common::time::{from_micros_to_str, from_str_to_micros, HOUR};
 use crate::common::RFC3339_VARIANT;
+use crate::models;
 use crate::models::models::EmailTokenPurpose;
+use crate::repo::quota;
 use anyhow::Result;
 use chrono::offset::Utc as UtcOffset;
 use chrono::DateTime;
 use futures::try_join;
 use helpers::{account, auth, email_token, invite, password};
 use libipld::Cid;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
 use rsky_lexicon::com::atproto::admin::StatusAttr;
 use rsky_lexicon::com::atproto::server::{AccountCodes, CreateAppPasswordOutput};
 use secp256k1::{Keypair, Secp256k1, SecretKey};
@@ -52,6 +56,28 @@ pub struct UpdateAccountPasswordOpts {
     pub password: String,
 }
 
+/// Client details recorded with a new session so users can recognize their devices
+#[derive(Default)]
+pub struct SessionClient {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SessionClient {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(SessionClient {
+            user_agent: req
+                .headers()
+                .get_one("User-Agent")
+                .map(|user_agent| user_agent.to_string()),
+            ip: req.client_ip().map(|ip| ip.to_string()),
+        })
+    }
+}
+
 pub struct UpdateEmailOpts {
     pub did: String,
     pub email: String,
@@ -80,6 +106,20 @@ impl AccountManager {
         account::get_account_by_email(email, flags).await
     }
 
+    pub async fn get_accounts(
+        dids: &Vec<String>,
+        flags: Option<AvailabilityFlags>,
+    ) -> Result<BTreeMap<String, ActorAccount>> {
+        account::get_accounts(dids, flags).await
+    }
+
+    pub async fn search_accounts(
+        opts: SearchAccountsOpts,
+        flags: Option<AvailabilityFlags>,
+    ) -> Result<Vec<ActorAccount>> {
+        account::search_accounts(opts, flags).await
+    }
+
     pub async fn is_account_activated(did: &String) -> Result<bool> {
         let account = Self::get_account(
             did,
@@ -127,6 +167,7 @@ impl AccountManager {
         let secret_key =
             SecretKey::from_slice(&hex::decode(private_key.as_bytes()).unwrap()).unwrap();
         let jwt_key = Keypair::from_secret_key(&secp, &secret_key);
+        let session_id = auth::get_refresh_token_id();
         let (access_jwt, refresh_jwt) = auth::create_tokens(auth::CreateTokensOpts {
             did: did.clone(),
             jwt_key,
@@ -134,6 +175,7 @@ impl AccountManager {
             scope: Some(AuthScope::Access),
             jti: None,
             expires_in: None,
+            session_id: Some(session_id.clone()),
         })?;
         let refresh_payload = auth::decode_refresh_token(refresh_jwt.clone(), jwt_key)?;
         let now = common::now();
@@ -146,7 +188,18 @@ impl AccountManager {
             account::register_account(did.clone(), email, password_encrypted)?;
         }
         invite::record_invite_use(did.clone(), invite_code, now)?;
-        auth::store_refresh_token(refresh_payload, None).await?;
+        auth::store_refresh_token(
+            refresh_payload,
+            None,
+            RefreshTokenSession {
+                session_id,
+                created_at: Some(now),
+                last_refreshed_at: None,
+                user_agent: None,
+                ip: None,
+            },
+        )
+        .await?;
         repo::update_root(did, repo_cid, repo_rev)?;
         Ok((access_jwt, refresh_jwt))
     }
@@ -162,7 +215,9 @@ impl AccountManager {
     }
 
     pub async fn delete_account(did: &String) -> Result<()> {
-        account::delete_account(did).await
+        account::delete_account(did).await?;
+        quota::forget_usage(did);
+        Ok(())
     }
 
     pub async fn takedown_account(did: &String, takedown: StatusAttr) -> Result<()> {
@@ -182,6 +237,10 @@ impl AccountManager {
         account::deactivate_account(did, delete_after).await
     }
 
+    pub async fn get_accounts_due_for_deletion() -> Result<Vec<String>> {
+        account::get_accounts_due_for_deletion().await
+    }
+
     pub async fn activate_account(did: &String) -> Result<()> {
         account::activate_account(did).await
     }
@@ -207,6 +266,7 @@ impl AccountManager {
     pub async fn create_session(
         did: String,
         app_password_name: Option<String>,
+        client: SessionClient,
     ) -> Result<(String, String)> {
         let secp = Secp256k1::new();
         let private_key = env::var("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX").unwrap();
@@ -218,6 +278,7 @@ impl AccountManager {
         } else {
             AuthScope::AppPass
         };
+        let session_id = auth::get_refresh_token_id();
         let (access_jwt, refresh_jwt) = auth::create_tokens(CreateTokensOpts {
             did,
             jwt_key,
@@ -225,9 +286,21 @@ impl AccountManager {
             scope: Some(scope),
             jti: None,
             expires_in: None,
+            session_id: Some(session_id.clone()),
         })?;
         let refresh_payload = auth::decode_refresh_token(refresh_jwt.clone(), jwt_key)?;
-        auth::store_refresh_token(refresh_payload, app_password_name).await?;
+        auth::store_refresh_token(
+            refresh_payload,
+            app_password_name,
+            RefreshTokenSession {
+                session_id,
+                created_at: Some(common::now()),
+                last_refreshed_at: None,
+                user_agent: client.user_agent,
+                ip: client.ip,
+            },
+        )
+        .await?;
         Ok((access_jwt, refresh_jwt))
     }
 
@@ -282,6 +355,7 @@ impl AccountManager {
                 }),
                 jti: Some(next_id.clone()),
                 expires_in: None,
+                session_id: Some(token.session_id.clone()),
             })?;
             let refresh_payload = auth::decode_refresh_token(refresh_jwt.clone(), jwt_key)?;
             match try_join!(
@@ -290,7 +364,17 @@ impl AccountManager {
                     expires_at: from_micros_to_str(expires_at),
                     next_id
                 }),
-                auth::store_refresh_token(refresh_payload, token.app_password_name)
+                auth::store_refresh_token(
+                    refresh_payload,
+                    token.app_password_name,
+                    RefreshTokenSession {
+                        session_id: token.session_id,
+                        created_at: token.created_at,
+                        last_refreshed_at: Some(from_micros_to_str(dt.timestamp_micros())),
+                        user_agent: token.user_agent,
+                        ip: token.ip,
+                    }
+                )
             ) {
                 Ok(_) => Ok(Some((access_jwt, refresh_jwt))),
                 Err(e) => match e.downcast_ref() {
@@ -308,6 +392,22 @@ impl AccountManager {
     pub async fn revoke_refresh_token(id: String) -> Result<bool> {
         auth::revoke_refresh_token(id).await
     }
+
+    pub async fn list_sessions(did: &String) -> Result<Vec<models::RefreshToken>> {
+        auth::list_sessions(did, common::now()).await
+    }
+
+    pub async fn revoke_session(did: &String, session_id: &String) -> Result<bool> {
+        auth::revoke_session(did, session_id).await
+    }
+
+    pub async fn session_exists(did: &String, session_id: &String) -> Result<bool> {
+        auth::session_exists(did, session_id).await
+    }
+
+    pub async fn revoke_sessions(did: &String) -> Result<bool> {
+        auth::revoke_refresh_tokens_by_did(did).await
+    }
     // Invites
     // ----------
 
@@ -332,6 +432,12 @@ impl AccountManager {
         invite::get_account_invite_codes(did).await
     }
 
+    pub async fn get_accounts_invite_codes(
+        dids: &Vec<String>,
+    ) -> Result<BTreeMap<String, Vec<CodeDetail>>> {
+        invite::get_accounts_invite_codes(dids).await
+    }
+
     pub async fn get_invited_by_for_accounts(
         dids: Vec<&String>,
     ) -> Result<BTreeMap<String, CodeDetail>> {
@@ -443,6 +549,18 @@ impl AccountManager {
     pub async fn create_email_token(did: &String, purpose: EmailTokenPurpose) -> Result<String> {
         email_token::create_email_token(did, purpose).await
     }
+
+    pub async fn delete_email_token(did: &String, purpose: EmailTokenPurpose) -> Result<()> {
+        email_token::delete_email_token(did, purpose).await
+    }
+
+    pub async fn get_email_auth_factor(did: &String) -> Result<bool> {
+        account::get_email_auth_factor(did).await
+    }
+
+    pub async fn set_email_auth_factor(did: &String, email_auth_factor: bool) -> Result<()> {
+        account::set_email_auth_factor(did, email_auth_factor).await
+    }
 }
 
 pub mod helpers;
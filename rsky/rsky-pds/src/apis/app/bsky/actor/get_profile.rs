@@ -1,5 +1,6 @@
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
+use crate::local_appview::{LocalAppView, LocalAppViewError, UseLocalAppView, UseUpstreamAppView};
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::read_after_write::types::LocalRecords;
 use crate::read_after_write::util::{handle_read_after_write, ReadAfterWriteResponse};
@@ -49,6 +50,7 @@ pub async fn inner_get_profile(
 /// but contains relevant metadata with auth.
 #[rocket::get("/xrpc/app.bsky.actor.getProfile?<actor>")]
 pub async fn get_profile(
+    _upstream: UseUpstreamAppView,
     // Handle or DID of account to fetch profile of.
     actor: String,
     auth: AccessStandard,
@@ -98,3 +100,42 @@ pub fn get_profile_munge(
         }
     }
 }
+
+/// Builds a profile with follower, follow and post counts from records on this PDS when
+/// running without an appview
+#[rocket::get("/xrpc/app.bsky.actor.getProfile?<actor>", rank = 1)]
+pub async fn local_get_profile(
+    _local: UseLocalAppView,
+    actor: String,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<ProfileViewDetailed>, status::Custom<Json<ErrorMessageResponse>>> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    let res = match app_view.profile_detailed(&actor).await {
+        Ok(Some(profile)) => Ok(profile),
+        Ok(None) => Err(LocalAppViewError::ProfileNotFound.into()),
+        Err(error) => Err(error),
+    };
+    match res {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<LocalAppViewError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
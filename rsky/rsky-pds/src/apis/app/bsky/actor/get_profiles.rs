@@ -1,5 +1,6 @@
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
+use crate::local_appview::{LocalAppView, UseLocalAppView, UseUpstreamAppView};
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::read_after_write::types::LocalRecords;
 use crate::read_after_write::util::{handle_read_after_write, ReadAfterWriteResponse};
@@ -42,6 +43,7 @@ pub async fn inner_get_profiles(
 /// Get detailed profile views of multiple actors.
 #[rocket::get("/xrpc/app.bsky.actor.getProfiles?<actors>")]
 pub async fn get_profiles(
+    _upstream: UseUpstreamAppView,
     actors: Vec<String>,
     auth: AccessStandard,
     res: HandlerPipeThrough,
@@ -98,3 +100,39 @@ pub fn get_profiles_munge(
         }
     }
 }
+
+/// Builds profiles from records on this PDS when running without an appview. Actors
+/// that aren't hosted here are left out.
+#[rocket::get("/xrpc/app.bsky.actor.getProfiles?<actors>", rank = 1)]
+pub async fn local_get_profiles(
+    _local: UseLocalAppView,
+    actors: Vec<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<GetProfilesOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    let mut profiles = Vec::new();
+    for actor in actors {
+        match app_view.profile_detailed(&actor).await {
+            Ok(Some(profile)) => profiles.push(profile),
+            Ok(None) => (),
+            Err(error) => {
+                eprintln!("@LOG: ERROR: {error}");
+                let internal_error = ErrorMessageResponse {
+                    code: Some(ErrorCode::InternalServerError),
+                    message: Some(error.to_string()),
+                };
+                return Err(status::Custom(
+                    Status::InternalServerError,
+                    Json(internal_error),
+                ));
+            }
+        }
+    }
+    Ok(Json(GetProfilesOutput { profiles }))
+}
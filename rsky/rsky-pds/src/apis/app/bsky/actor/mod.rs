@@ -2,3 +2,5 @@ pub mod get_preferences;
 pub mod get_profile;
 pub mod get_profiles;
 pub mod put_preferences;
+pub mod search_actors;
+pub mod search_actors_typeahead;
@@ -0,0 +1,94 @@
+use crate::auth_verifier::AccessStandard;
+use crate::local_appview::{LocalAppView, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::search::{search_actors as search_local_actors, SearchError};
+use crate::SharedLocalViewer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::actor::SearchActorsOutput;
+
+pub async fn inner_search_actors(
+    q: String,
+    limit: i64,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<SearchActorsOutput> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    let (dids, cursor) = search_local_actors(q, limit, cursor).await?;
+    let mut actors = Vec::new();
+    for did in dids {
+        if let Some(actor) = app_view.profile_view(&did).await? {
+            actors.push(actor);
+        }
+    }
+    Ok(SearchActorsOutput { cursor, actors })
+}
+
+/// Find actors (profiles) matching search criteria. Served from the local search index,
+/// covering accounts on this PDS, when running without an appview.
+#[rocket::get(
+    "/xrpc/app.bsky.actor.searchActors?<q>&<term>&<limit>&<cursor>",
+    rank = 1
+)]
+pub async fn search_actors(
+    _local: UseLocalAppView,
+    q: Option<String>,
+    term: Option<String>, // DEPRECATED: use 'q' instead.
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<SearchActorsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some("invalid limit".to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    let Some(q) = q.or(term) else {
+        return Ok(Json(SearchActorsOutput {
+            cursor: None,
+            actors: vec![],
+        }));
+    };
+    match inner_search_actors(
+        q,
+        limit.unwrap_or(25) as i64,
+        cursor,
+        auth,
+        s3_config,
+        state_local_viewer,
+    )
+    .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<SearchError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
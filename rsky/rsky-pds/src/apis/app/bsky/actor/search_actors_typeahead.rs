@@ -0,0 +1,84 @@
+use crate::auth_verifier::AccessStandard;
+use crate::local_appview::{LocalAppView, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::search::search_actors_typeahead as search_local_actors_typeahead;
+use crate::SharedLocalViewer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::actor::SearchActorsTypeaheadOutput;
+
+pub async fn inner_search_actors_typeahead(
+    q: String,
+    limit: i64,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<SearchActorsTypeaheadOutput> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    let mut actors = Vec::new();
+    for did in search_local_actors_typeahead(q, limit).await? {
+        if let Some(actor) = app_view.profile_basic(&did).await? {
+            actors.push(actor);
+        }
+    }
+    Ok(SearchActorsTypeaheadOutput { actors })
+}
+
+/// Find actor suggestions for a prefix search term. Served from the local search index,
+/// covering accounts on this PDS, when running without an appview.
+#[rocket::get(
+    "/xrpc/app.bsky.actor.searchActorsTypeahead?<q>&<term>&<limit>",
+    rank = 1
+)]
+pub async fn search_actors_typeahead(
+    _local: UseLocalAppView,
+    q: Option<String>,
+    term: Option<String>, // DEPRECATED: use 'q' instead.
+    limit: Option<u8>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<SearchActorsTypeaheadOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some("invalid limit".to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    let Some(q) = q.or(term) else {
+        return Ok(Json(SearchActorsTypeaheadOutput { actors: vec![] }));
+    };
+    match inner_search_actors_typeahead(
+        q,
+        limit.unwrap_or(10) as i64,
+        auth,
+        s3_config,
+        state_local_viewer,
+    )
+    .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
+use crate::local_appview::{LocalAppView, LocalAppViewError, UseLocalAppView, UseUpstreamAppView};
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::read_after_write::types::LocalRecords;
 use crate::read_after_write::util::{handle_read_after_write, ReadAfterWriteResponse};
@@ -49,6 +50,7 @@ pub async fn inner_get_actor_likes(
 /// Get a list of posts liked by an actor. Does not require auth.
 #[rocket::get("/xrpc/app.bsky.feed.getActorLikes?<actor>&<limit>&<cursor>")]
 pub async fn get_actor_likes(
+    _upstream: UseUpstreamAppView,
     actor: String,
     limit: Option<u8>,
     cursor: Option<String>,
@@ -168,3 +170,50 @@ pub fn get_author_munge(
         }
     }
 }
+
+/// Lists the requester's own likes from records on this PDS when running without an appview
+#[rocket::get("/xrpc/app.bsky.feed.getActorLikes?<actor>&<limit>&<cursor>", rank = 1)]
+pub async fn local_get_actor_likes(
+    _local: UseLocalAppView,
+    actor: String,
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<AuthorFeed>, status::Custom<Json<ErrorMessageResponse>>> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some("invalid limit".to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    match app_view
+        .actor_likes(&actor, limit.unwrap_or(50) as i64, cursor)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<LocalAppViewError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
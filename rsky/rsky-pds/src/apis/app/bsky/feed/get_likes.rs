@@ -0,0 +1,56 @@
+use crate::auth_verifier::AccessStandard;
+use crate::local_appview::{LocalAppView, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::SharedLocalViewer;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::feed::GetLikesOutput;
+
+/// Get like records which reference a subject (by AT-URI and CID). Only served in local
+/// AppView mode; otherwise the request is proxied to the appview.
+#[rocket::get("/xrpc/app.bsky.feed.getLikes?<uri>&<cid>&<limit>&<cursor>", rank = 1)]
+pub async fn get_likes(
+    _local: UseLocalAppView,
+    uri: String,
+    cid: Option<String>,
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<GetLikesOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some("invalid limit".to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    match app_view
+        .likes(uri, cid, limit.unwrap_or(50) as i64, cursor)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
+        }
+    }
+}
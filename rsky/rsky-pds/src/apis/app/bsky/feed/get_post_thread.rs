@@ -1,5 +1,6 @@
 use crate::auth_verifier::AccessStandard;
 use crate::config::ServerConfig;
+use crate::local_appview::{LocalAppView, LocalAppViewError, UseLocalAppView, UseUpstreamAppView};
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::read_after_write::types::{LocalRecords, RecordDescript};
 use crate::read_after_write::util::{
@@ -121,6 +122,7 @@ pub async fn inner_get_post_thread(
 #[allow(unused_variables)]
 #[rocket::get("/xrpc/app.bsky.feed.getPostThread?<uri>&<depth>&<parentHeight>")]
 pub async fn get_post_thread(
+    _upstream: UseUpstreamAppView,
     uri: String,               // Reference (AT-URI) to post record.
     depth: Option<u16>,        // How many levels of reply depth should be included in response.
     parentHeight: Option<u16>, // How many levels of parent (and grandparent, etc.) post to include.
@@ -509,3 +511,51 @@ pub fn get_highest_parent(thread: &ThreadViewPost) -> Option<String> {
         },
     }
 }
+
+/// Builds a post thread from records on this PDS when running without an appview
+#[allow(non_snake_case)]
+#[rocket::get(
+    "/xrpc/app.bsky.feed.getPostThread?<uri>&<depth>&<parentHeight>",
+    rank = 1
+)]
+pub async fn local_get_post_thread(
+    _local: UseLocalAppView,
+    uri: String,
+    depth: Option<u16>,
+    parentHeight: Option<u16>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<GetPostThreadOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let depth = depth.unwrap_or(6);
+    let parentHeight = parentHeight.unwrap_or(80);
+    if depth > 1000 || parentHeight > 1000 {
+        let bad_request = ErrorMessageResponse {
+            code: Some(ErrorCode::BadRequest),
+            message: Some("invalid depth or parentHeight. Maximum is 1000.".to_string()),
+        };
+        return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+    }
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    match app_view.post_thread(&uri, depth, parentHeight).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<LocalAppViewError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
@@ -1,5 +1,7 @@
 pub mod get_actor_likes;
 pub mod get_author_feed;
 pub mod get_feed;
+pub mod get_likes;
 pub mod get_post_thread;
 pub mod get_timeline;
+pub mod search_posts;
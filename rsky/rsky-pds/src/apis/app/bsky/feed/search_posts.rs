@@ -0,0 +1,98 @@
+use crate::auth_verifier::AccessStandard;
+use crate::local_appview::{LocalAppView, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::search::{search_posts as search_local_posts, SearchError, SearchPostsOpts};
+use crate::SharedLocalViewer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::feed::SearchPostsOutput;
+
+pub async fn inner_search_posts(
+    opts: SearchPostsOpts,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<SearchPostsOutput> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, requester);
+    let (uris, cursor) = search_local_posts(opts).await?;
+    let mut posts = Vec::new();
+    for uri in uris {
+        if let Some(post) = app_view.post_view(&uri).await? {
+            posts.push(post);
+        }
+    }
+    Ok(SearchPostsOutput {
+        cursor,
+        hits_total: None,
+        posts,
+    })
+}
+
+/// Find posts matching search criteria, returning views of those posts. Served from the
+/// local search index when running without an appview; results are always sorted by
+/// recency since there's no engagement data to rank `top` by.
+#[rocket::get(
+    "/xrpc/app.bsky.feed.searchPosts?<q>&<since>&<until>&<mentions>&<author>&<lang>&<tag>&<limit>&<cursor>",
+    rank = 1
+)]
+pub async fn search_posts(
+    _local: UseLocalAppView,
+    q: String,
+    since: Option<String>,
+    until: Option<String>,
+    mentions: Option<String>,
+    author: Option<String>,
+    lang: Option<String>,
+    tag: Vec<String>,
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<SearchPostsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some("invalid limit".to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    let opts = SearchPostsOpts {
+        q,
+        author,
+        mentions,
+        lang,
+        since,
+        until,
+        tags: tag,
+        limit: limit.unwrap_or(25) as i64,
+        cursor,
+    };
+    match inner_search_posts(opts, auth, s3_config, state_local_viewer).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<SearchError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
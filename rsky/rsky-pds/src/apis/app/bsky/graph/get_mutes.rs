@@ -0,0 +1,83 @@
+use crate::auth_verifier::AccessStandard;
+use crate::graph;
+use crate::local_appview::{LocalAppView, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::SharedLocalViewer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::actor::ProfileView;
+use rsky_lexicon::app::bsky::graph::GetMutesOutput;
+
+async fn inner_get_mutes(
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<GetMutesOutput> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did)
+        .unwrap_or_default();
+    let (dids, cursor) = graph::list_mutes(&requester, limit.unwrap_or(50) as i64, cursor)?;
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester));
+    let mut mutes = Vec::new();
+    for did in dids {
+        // Accounts hosted elsewhere only have their did to show
+        let mute = match app_view.profile_view(&did).await? {
+            Some(profile) => profile,
+            None => ProfileView {
+                did: did.clone(),
+                handle: did,
+                display_name: None,
+                description: None,
+                avatar: None,
+                labels: vec![],
+                indexed_at: None,
+            },
+        };
+        mutes.push(mute);
+    }
+    Ok(GetMutesOutput { cursor, mutes })
+}
+
+/// Enumerates accounts that the requesting account currently has muted. Only served in
+/// local AppView mode.
+#[rocket::get("/xrpc/app.bsky.graph.getMutes?<limit>&<cursor>", rank = 1)]
+pub async fn get_mutes(
+    _local: UseLocalAppView,
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<Json<GetMutesOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some("invalid limit".to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    match inner_get_mutes(limit, cursor, auth, s3_config, state_local_viewer).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
+        }
+    }
+}
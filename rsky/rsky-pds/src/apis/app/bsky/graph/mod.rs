@@ -0,0 +1,14 @@
+use crate::local_appview::LocalAppView;
+use anyhow::Result;
+
+pub mod get_mutes;
+pub mod mute_actor;
+pub mod unmute_actor;
+
+/// Mutes can target accounts anywhere by did, or accounts on this PDS by handle
+async fn resolve_mute_subject(app_view: &LocalAppView<'_>, actor: &String) -> Result<String> {
+    match actor.starts_with("did:") {
+        true => Ok(actor.clone()),
+        false => app_view.resolve_actor(actor).await,
+    }
+}
@@ -0,0 +1,61 @@
+use crate::apis::app::bsky::graph::resolve_mute_subject;
+use crate::auth_verifier::AccessStandard;
+use crate::graph;
+use crate::local_appview::{LocalAppView, LocalAppViewError, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::SharedLocalViewer;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::graph::MuteActorInput;
+
+async fn inner_mute_actor(
+    body: Json<MuteActorInput>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<()> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did)
+        .unwrap_or_default();
+    let app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    let subject = resolve_mute_subject(&app_view, &body.actor).await?;
+    if subject == requester {
+        bail!("Cannot mute oneself")
+    }
+    graph::mute_actor(&requester, &subject)
+}
+
+/// Creates a mute relationship for the specified account. Mutes are private and only
+/// kept on this PDS. Only served in local AppView mode.
+#[rocket::post("/xrpc/app.bsky.graph.muteActor", format = "json", data = "<body>")]
+pub async fn mute_actor(
+    _local: UseLocalAppView,
+    body: Json<MuteActorInput>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_mute_actor(body, auth, s3_config, state_local_viewer).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<LocalAppViewError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use crate::apis::app::bsky::graph::resolve_mute_subject;
+use crate::auth_verifier::AccessStandard;
+use crate::graph;
+use crate::local_appview::{LocalAppView, LocalAppViewError, UseLocalAppView};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::SharedLocalViewer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::graph::UnmuteActorInput;
+
+async fn inner_unmute_actor(
+    body: Json<UnmuteActorInput>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<()> {
+    let requester = auth
+        .access
+        .credentials
+        .and_then(|credentials| credentials.did)
+        .unwrap_or_default();
+    let app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    let subject = resolve_mute_subject(&app_view, &body.actor).await?;
+    graph::unmute_actor(&requester, &subject)
+}
+
+/// Unmutes the specified account. Only served in local AppView mode.
+#[rocket::post("/xrpc/app.bsky.graph.unmuteActor", format = "json", data = "<body>")]
+pub async fn unmute_actor(
+    _local: UseLocalAppView,
+    body: Json<UnmuteActorInput>,
+    auth: AccessStandard,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_unmute_actor(body, auth, s3_config, state_local_viewer).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            let (status, code) = match error.downcast_ref::<LocalAppViewError>() {
+                Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+                None => {
+                    eprintln!("@LOG: ERROR: {error}");
+                    (Status::InternalServerError, ErrorCode::InternalServerError)
+                }
+            };
+            let error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(status, Json(error)))
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod actor;
 pub mod feed;
+pub mod graph;
 pub mod notification;
 pub mod util;
+pub mod video;
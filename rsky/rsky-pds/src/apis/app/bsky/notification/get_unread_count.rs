@@ -0,0 +1,43 @@
+use crate::auth_verifier::AccessStandard;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::notification::{self, LocalNotifications};
+use anyhow::Result;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::app::bsky::notification::GetUnreadCountOutput;
+
+async fn inner_get_unread_count(auth: AccessStandard) -> Result<GetUnreadCountOutput> {
+    let did: String = match auth.access.credentials {
+        None => "".to_string(),
+        Some(credentials) => credentials.did.unwrap_or("".to_string()),
+    };
+    let count = notification::get_unread_count(&did).await?;
+    Ok(GetUnreadCountOutput { count })
+}
+
+/// Count the requesting account's notifications indexed since it last updated its
+/// seen time.
+#[rocket::get("/xrpc/app.bsky.notification.getUnreadCount?<seenAt>&<priority>")]
+#[allow(non_snake_case, unused_variables)]
+pub async fn get_unread_count(
+    _local: LocalNotifications,
+    seenAt: Option<String>,
+    priority: Option<bool>,
+    auth: AccessStandard,
+) -> Result<Json<GetUnreadCountOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_get_unread_count(auth).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
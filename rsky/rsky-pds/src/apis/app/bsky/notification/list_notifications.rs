@@ -0,0 +1,64 @@
+use crate::auth_verifier::AccessStandard;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::notification::{self, ListNotificationsOpts, LocalNotifications};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::app::bsky::notification::ListNotificationsOutput;
+
+async fn inner_list_notifications(
+    auth: AccessStandard,
+    limit: u16,
+    cursor: Option<String>,
+    reasons: Vec<String>,
+) -> Result<ListNotificationsOutput> {
+    if limit > 100 {
+        bail!("Error: limit can not be greater than 100")
+    }
+    let did: String = match auth.access.credentials {
+        None => "".to_string(),
+        Some(credentials) => credentials.did.unwrap_or("".to_string()),
+    };
+    notification::list_notifications(
+        &did,
+        ListNotificationsOpts {
+            limit: limit as i64,
+            cursor,
+            reasons,
+        },
+    )
+    .await
+}
+
+/// Enumerate notifications for the requesting account, served from the local
+/// notification index when there's no appview to forward to.
+#[rocket::get(
+    "/xrpc/app.bsky.notification.listNotifications?<limit>&<cursor>&<reasons>&<seenAt>&<priority>"
+)]
+#[allow(non_snake_case, unused_variables)]
+pub async fn list_notifications(
+    _local: LocalNotifications,
+    limit: Option<u16>,
+    cursor: Option<String>,
+    reasons: Vec<String>,
+    seenAt: Option<String>,
+    priority: Option<bool>,
+    auth: AccessStandard,
+) -> Result<Json<ListNotificationsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let limit = limit.unwrap_or(50);
+    match inner_list_notifications(auth, limit, cursor, reasons).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -1 +1,4 @@
+pub mod get_unread_count;
+pub mod list_notifications;
 pub mod register_push;
+pub mod update_seen;
@@ -3,6 +3,7 @@ use crate::auth_verifier::AccessStandardSignupQueued;
 use crate::common::get_notif_endpoint;
 use crate::config::ServerConfig;
 use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::push::{self, WebPushSubscription, UNIFIED_PUSH_PLATFORM};
 use crate::repo::types::Ids;
 use crate::{context, SharedIdResolver, APP_USER_AGENT};
 use anyhow::{anyhow, bail, Result};
@@ -101,6 +102,19 @@ pub async fn inner_register_push(
     Ok(())
 }
 
+/// Stores the registration locally when `PDS_PUSH_LOCAL` is enabled, so notifications
+/// are dispatched by this PDS, otherwise forwards it to the notification service
+pub async fn inner_register_push_local(
+    body: Json<RegisterPushInput>,
+    auth: AccessStandardSignupQueued,
+) -> Result<()> {
+    let did: String = match auth.access.credentials {
+        None => "".to_string(),
+        Some(credentials) => credentials.did.unwrap_or("".to_string()),
+    };
+    push::register(&did, body.into_inner()).await
+}
+
 #[rocket::post(
     "/xrpc/app.bsky.notification.registerPush",
     format = "json",
@@ -112,36 +126,50 @@ pub async fn register_push(
     cfg: &State<ServerConfig>,
     id_resolver: &State<SharedIdResolver>,
 ) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
-    if !vec!["ios", "android", "web"].contains(&body.platform.as_str()) {
+    let mut platforms = vec!["ios", "android", "web"];
+    if cfg.push.local {
+        platforms.push(UNIFIED_PUSH_PLATFORM);
+    }
+    if !platforms.contains(&body.platform.as_str()) {
         let bad_request = ErrorMessageResponse {
             code: Some(ErrorCode::BadRequest),
             message: Some("invalid platform".to_string()),
         };
         return Err(status::Custom(Status::BadRequest, Json(bad_request)));
     }
-    match &cfg.bsky_app_view {
-        None => {
+    if body.platform == UNIFIED_PUSH_PLATFORM {
+        if let Err(error) = WebPushSubscription::parse(&body.token) {
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+        }
+    }
+    let res = match (cfg.push.local, &cfg.bsky_app_view) {
+        (true, _) => inner_register_push_local(body, auth).await,
+        (false, None) => {
             let not_found = ErrorMessageResponse {
                 code: Some(ErrorCode::NotFound),
                 message: Some("not found".to_string()),
             };
             return Err(status::Custom(Status::NotFound, Json(not_found)));
         }
-        Some(bsky_app_view) => {
-            match inner_register_push(body, auth, cfg, bsky_app_view.url.clone(), id_resolver).await
-            {
-                Ok(_) => Ok(()),
-                Err(error) => {
-                    let internal_error = ErrorMessageResponse {
-                        code: Some(ErrorCode::InternalServerError),
-                        message: Some(error.to_string()),
-                    };
-                    return Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ));
-                }
-            }
+        (false, Some(bsky_app_view)) => {
+            inner_register_push(body, auth, cfg, bsky_app_view.url.clone(), id_resolver).await
+        }
+    };
+    match res {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
         }
     }
 }
@@ -0,0 +1,43 @@
+use crate::auth_verifier::AccessStandard;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::notification::{self, LocalNotifications};
+use anyhow::Result;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::app::bsky::notification::UpdateSeenInput;
+
+async fn inner_update_seen(body: Json<UpdateSeenInput>, auth: AccessStandard) -> Result<()> {
+    let did: String = match auth.access.credentials {
+        None => "".to_string(),
+        Some(credentials) => credentials.did.unwrap_or("".to_string()),
+    };
+    notification::update_seen(&did, &body.seen_at).await
+}
+
+/// Notify server that the requesting account has seen notifications.
+#[rocket::post(
+    "/xrpc/app.bsky.notification.updateSeen",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn update_seen(
+    _local: LocalNotifications,
+    body: Json<UpdateSeenInput>,
+    auth: AccessStandard,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_update_seen(body, auth).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::apis::app::bsky::video::video_error_response;
+use crate::auth_verifier::AccessStandard;
+use crate::models::ErrorMessageResponse;
+use crate::video;
+use crate::video::LocalVideo;
+use anyhow::Result;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::app::bsky::video::GetJobStatusOutput;
+
+async fn inner_get_job_status(job_id: String, auth: AccessStandard) -> Result<GetJobStatusOutput> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let job = video::get_job(&job_id, &did)?;
+    Ok(GetJobStatusOutput {
+        job_status: video::job_status(job)?,
+    })
+}
+
+/// Get status details for a video processing job.
+#[rocket::get("/xrpc/app.bsky.video.getJobStatus?<jobId>")]
+#[allow(non_snake_case)]
+pub async fn get_job_status(
+    _local: LocalVideo,
+    jobId: String,
+    auth: AccessStandard,
+) -> Result<Json<GetJobStatusOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_get_job_status(jobId, auth).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(video_error_response(error)),
+    }
+}
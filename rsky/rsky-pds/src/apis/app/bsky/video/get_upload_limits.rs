@@ -0,0 +1,26 @@
+use crate::apis::app::bsky::video::video_error_response;
+use crate::auth_verifier::AccessStandard;
+use crate::models::ErrorMessageResponse;
+use crate::video;
+use crate::video::LocalVideo;
+use anyhow::Result;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::app::bsky::video::GetUploadLimitsOutput;
+
+async fn inner_get_upload_limits(auth: AccessStandard) -> Result<GetUploadLimitsOutput> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    video::get_upload_limits(&did)
+}
+
+/// Get video upload limits for the authenticated user.
+#[rocket::get("/xrpc/app.bsky.video.getUploadLimits")]
+pub async fn get_upload_limits(
+    _local: LocalVideo,
+    auth: AccessStandard,
+) -> Result<Json<GetUploadLimitsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_get_upload_limits(auth).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(video_error_response(error)),
+    }
+}
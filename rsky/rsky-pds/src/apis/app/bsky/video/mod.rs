@@ -0,0 +1,25 @@
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::video::VideoError;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+
+pub mod get_job_status;
+pub mod get_upload_limits;
+pub mod upload_video;
+
+pub fn video_error_response(error: anyhow::Error) -> status::Custom<Json<ErrorMessageResponse>> {
+    eprintln!("@LOG: ERROR: {error}");
+    let (status, code) = match error.downcast_ref::<VideoError>() {
+        Some(VideoError::JobNotFound(_)) => (Status::NotFound, ErrorCode::NotFound),
+        Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+        None => (Status::InternalServerError, ErrorCode::InternalServerError),
+    };
+    status::Custom(
+        status,
+        Json(ErrorMessageResponse {
+            code: Some(code),
+            message: Some(error.to_string()),
+        }),
+    )
+}
@@ -0,0 +1,70 @@
+use crate::apis::app::bsky::video::video_error_response;
+use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::common::ContentType;
+use crate::config::ServerConfig;
+use crate::image;
+use crate::models::ErrorMessageResponse;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::video;
+use crate::video::{LocalVideo, VideoError};
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::data::{Data, ToByteUnit};
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::video::UploadVideoOutput;
+
+async fn inner_upload_video(
+    body: Data<'_>,
+    content_type: ContentType,
+    auth: AccessStandardIncludeChecks,
+    cfg: &State<ServerConfig>,
+    s3_config: &State<SdkConfig>,
+) -> Result<UploadVideoOutput> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    let max = cfg.video.max_size;
+    let bytes = body.open(max.bytes()).into_bytes().await?;
+    if !bytes.is_complete() {
+        bail!(VideoError::TooLarge { max })
+    }
+    let bytes = bytes.into_inner();
+    // The sniffed type wins over what the client says, minus any parameters
+    let mime_type = match image::mime_type_from_bytes(bytes.clone()).await? {
+        Some(sniffed) => sniffed,
+        None => content_type
+            .name
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    };
+    let size = bytes.len() as i64;
+    video::check_upload(&did, &mime_type, size)?;
+
+    let temp_key = S3BlobStore::new(did.clone(), s3_config)
+        .put_temp(bytes)
+        .await?;
+    let job = video::create_job(&did, temp_key, mime_type, size)?;
+    Ok(UploadVideoOutput {
+        job_status: video::job_status(job)?,
+    })
+}
+
+/// Upload a video to be processed then stored on the PDS. Poll
+/// app.bsky.video.getJobStatus for the blob to embed with app.bsky.embed.video.
+#[rocket::post("/xrpc/app.bsky.video.uploadVideo", data = "<body>")]
+pub async fn upload_video(
+    _local: LocalVideo,
+    body: Data<'_>,
+    content_type: ContentType,
+    auth: AccessStandardIncludeChecks,
+    cfg: &State<ServerConfig>,
+    s3_config: &State<SdkConfig>,
+) -> Result<Json<UploadVideoOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_upload_video(body, content_type, auth, cfg, s3_config).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(video_error_response(error)),
+    }
+}
@@ -0,0 +1,360 @@
+//! `chat.bsky.convo` served from this PDS when it runs its own chat service. Messages for
+//! members hosted elsewhere are delivered to their PDS over iroh.
+use crate::auth_verifier::AccessPrivileged;
+use crate::chat;
+use crate::chat::delivery::{deliver, ChatEvent};
+use crate::chat::{ChatError, LocalChat};
+use crate::local_appview::LocalAppView;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::{SharedIroh, SharedLocalViewer};
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::chat::bsky::convo::{
+    DeleteMessageForSelfInput, DeletedMessageView, GetConvoOutput, GetLogOutput, GetMessagesOutput,
+    LeaveConvoInput, LeaveConvoOutput, ListConvosOutput, MessageView, MuteConvoInput,
+    MuteConvoOutput, SendMessageBatchInput, SendMessageBatchOutput, SendMessageInput,
+    UnmuteConvoInput, UnmuteConvoOutput, UpdateReadInput, UpdateReadOutput,
+};
+
+const MAX_BATCH_ITEMS: usize = 100;
+
+type ChatResult<T> = Result<Json<T>, status::Custom<Json<ErrorMessageResponse>>>;
+
+fn error_response(error: anyhow::Error) -> status::Custom<Json<ErrorMessageResponse>> {
+    let (status, code) = match error.downcast_ref::<ChatError>() {
+        Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+        None => {
+            eprintln!("@LOG: ERROR: {error}");
+            (Status::InternalServerError, ErrorCode::InternalServerError)
+        }
+    };
+    let error = ErrorMessageResponse {
+        code: Some(code),
+        message: Some(error.to_string()),
+    };
+    status::Custom(status, Json(error))
+}
+
+fn bad_request(message: &str) -> status::Custom<Json<ErrorMessageResponse>> {
+    let bad_request = ErrorMessageResponse {
+        code: Some(ErrorCode::BadRequest),
+        message: Some(message.to_string()),
+    };
+    status::Custom(Status::BadRequest, Json(bad_request))
+}
+
+fn requester(auth: AccessPrivileged) -> String {
+    auth.access.credentials.unwrap().did.unwrap()
+}
+
+#[allow(non_snake_case)]
+#[rocket::get("/xrpc/chat.bsky.convo.getConvo?<convoId>", rank = 1)]
+pub async fn get_convo(
+    _local: LocalChat,
+    convoId: String,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> ChatResult<GetConvoOutput> {
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match chat::convo_view(&mut app_view, &requester, &convoId).await {
+        Ok(convo) => Ok(Json(GetConvoOutput { convo })),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+pub async fn inner_get_convo_for_members(
+    app_view: &mut LocalAppView<'_>,
+    requester: String,
+    members: Vec<String>,
+) -> Result<GetConvoOutput> {
+    let convo_id = chat::get_or_create_convo(&requester, members).await?;
+    let convo = chat::convo_view(app_view, &requester, &convo_id).await?;
+    Ok(GetConvoOutput { convo })
+}
+
+#[rocket::get("/xrpc/chat.bsky.convo.getConvoForMembers?<members>", rank = 1)]
+pub async fn get_convo_for_members(
+    _local: LocalChat,
+    members: Vec<String>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> ChatResult<GetConvoOutput> {
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match inner_get_convo_for_members(&mut app_view, requester, members).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[rocket::get("/xrpc/chat.bsky.convo.listConvos?<limit>&<cursor>", rank = 1)]
+pub async fn list_convos(
+    _local: LocalChat,
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> ChatResult<ListConvosOutput> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            return Err(bad_request("invalid limit"));
+        }
+    }
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match chat::list_convos(
+        &mut app_view,
+        &requester,
+        limit.unwrap_or(50) as i64,
+        cursor,
+    )
+    .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[allow(non_snake_case)]
+#[rocket::get(
+    "/xrpc/chat.bsky.convo.getMessages?<convoId>&<limit>&<cursor>",
+    rank = 1
+)]
+pub async fn get_messages(
+    _local: LocalChat,
+    convoId: String,
+    limit: Option<u8>,
+    cursor: Option<String>,
+    auth: AccessPrivileged,
+) -> ChatResult<GetMessagesOutput> {
+    if let Some(limit) = limit {
+        if limit > 100 || limit < 1 {
+            return Err(bad_request("invalid limit"));
+        }
+    }
+    let requester = requester(auth);
+    match chat::get_messages(&requester, &convoId, limit.unwrap_or(50) as i64, cursor).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.sendMessage",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn send_message(
+    _local: LocalChat,
+    body: Json<SendMessageInput>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+    iroh: &State<SharedIroh>,
+) -> ChatResult<MessageView> {
+    let requester = requester(auth);
+    let SendMessageInput { convo_id, message } = body.into_inner();
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match chat::send_message(&mut app_view, &requester, &convo_id, message).await {
+        Ok(message) => {
+            deliver(
+                iroh.endpoint.clone(),
+                convo_id,
+                ChatEvent::Message {
+                    message: message.clone(),
+                },
+            );
+            Ok(Json(message))
+        }
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.sendMessageBatch",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn send_message_batch(
+    _local: LocalChat,
+    body: Json<SendMessageBatchInput>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+    iroh: &State<SharedIroh>,
+) -> ChatResult<SendMessageBatchOutput> {
+    let SendMessageBatchInput { items } = body.into_inner();
+    if items.is_empty() || items.len() > MAX_BATCH_ITEMS {
+        return Err(bad_request("invalid number of items"));
+    }
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    let mut sent = Vec::new();
+    for item in items {
+        match chat::send_message(&mut app_view, &requester, &item.convo_id, item.message).await {
+            Ok(message) => {
+                deliver(
+                    iroh.endpoint.clone(),
+                    item.convo_id,
+                    ChatEvent::Message {
+                        message: message.clone(),
+                    },
+                );
+                sent.push(message);
+            }
+            Err(error) => return Err(error_response(error)),
+        }
+    }
+    Ok(Json(SendMessageBatchOutput { items: sent }))
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.deleteMessageForSelf",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn delete_message_for_self(
+    _local: LocalChat,
+    body: Json<DeleteMessageForSelfInput>,
+    auth: AccessPrivileged,
+) -> ChatResult<DeletedMessageView> {
+    let requester = requester(auth);
+    match chat::delete_message_for_self(&requester, &body.convo_id, &body.message_id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.leaveConvo",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn leave_convo(
+    _local: LocalChat,
+    body: Json<LeaveConvoInput>,
+    auth: AccessPrivileged,
+    iroh: &State<SharedIroh>,
+) -> ChatResult<LeaveConvoOutput> {
+    let requester = requester(auth);
+    match chat::leave_convo(&requester, &body.convo_id) {
+        Ok(res) => {
+            deliver(
+                iroh.endpoint.clone(),
+                res.convo_id.clone(),
+                ChatEvent::Leave { did: requester },
+            );
+            Ok(Json(res))
+        }
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+pub async fn inner_set_muted(
+    app_view: &mut LocalAppView<'_>,
+    requester: String,
+    convo_id: String,
+    muted: bool,
+) -> Result<MuteConvoOutput> {
+    chat::set_muted(&requester, &convo_id, muted)?;
+    let convo = chat::convo_view(app_view, &requester, &convo_id).await?;
+    Ok(MuteConvoOutput { convo })
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.muteConvo",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn mute_convo(
+    _local: LocalChat,
+    body: Json<MuteConvoInput>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> ChatResult<MuteConvoOutput> {
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match inner_set_muted(&mut app_view, requester, body.into_inner().convo_id, true).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.unmuteConvo",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn unmute_convo(
+    _local: LocalChat,
+    body: Json<UnmuteConvoInput>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> ChatResult<UnmuteConvoOutput> {
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match inner_set_muted(&mut app_view, requester, body.into_inner().convo_id, false).await {
+        Ok(res) => Ok(Json(UnmuteConvoOutput { convo: res.convo })),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+pub async fn inner_update_read(
+    app_view: &mut LocalAppView<'_>,
+    requester: String,
+    body: UpdateReadInput,
+) -> Result<UpdateReadOutput> {
+    chat::update_read(&requester, &body.convo_id, body.message_id)?;
+    let convo = chat::convo_view(app_view, &requester, &body.convo_id).await?;
+    Ok(UpdateReadOutput { convo })
+}
+
+#[rocket::post(
+    "/xrpc/chat.bsky.convo.updateRead",
+    format = "json",
+    data = "<body>",
+    rank = 1
+)]
+pub async fn update_read(
+    _local: LocalChat,
+    body: Json<UpdateReadInput>,
+    auth: AccessPrivileged,
+    s3_config: &State<SdkConfig>,
+    state_local_viewer: &State<SharedLocalViewer>,
+) -> ChatResult<UpdateReadOutput> {
+    let requester = requester(auth);
+    let mut app_view = LocalAppView::new(s3_config, state_local_viewer, Some(requester.clone()));
+    match inner_update_read(&mut app_view, requester, body.into_inner()).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
+
+#[rocket::get("/xrpc/chat.bsky.convo.getLog?<cursor>", rank = 1)]
+pub async fn get_log(
+    _local: LocalChat,
+    cursor: Option<String>,
+    auth: AccessPrivileged,
+) -> ChatResult<GetLogOutput> {
+    let requester = requester(auth);
+    match chat::get_log(&requester, cursor) {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(error_response(error)),
+    }
+}
@@ -13,6 +13,8 @@ use rsky_lexicon::chat::bsky::convo::{
     UnmuteConvoInput, UnmuteConvoOutput, UpdateReadInput, UpdateReadOutput,
 };
 
+pub mod convo;
+
 #[rocket::post("/xrpc/chat.bsky.actor.deleteAccount")]
 pub async fn delete_account(
     auth: AccessPrivileged,
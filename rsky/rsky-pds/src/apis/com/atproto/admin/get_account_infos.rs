@@ -0,0 +1,84 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::auth_verifier::Moderator;
+use crate::common::env::env_str;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::INVALID_HANDLE;
+use anyhow::Result;
+use futures::try_join;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::atproto::admin::{AccountView, GetAccountInfosOutput};
+
+/// Builds admin views for `dids`, in the same order, skipping accounts that don't exist
+pub async fn get_account_views(dids: Vec<String>) -> Result<Vec<AccountView>> {
+    let (mut accounts, mut invites, invited_by) = try_join!(
+        AccountManager::get_accounts(
+            &dids,
+            Some(AvailabilityFlags {
+                include_deactivated: Some(true),
+                include_taken_down: Some(true)
+            })
+        ),
+        AccountManager::get_accounts_invite_codes(&dids),
+        AccountManager::get_invited_by_for_accounts(dids.iter().collect())
+    )?;
+    let manages_own_invites = env_str("PDS_ENTRYWAY_URL").is_none();
+    Ok(dids
+        .iter()
+        .filter_map(|did| {
+            let account = accounts.remove(did)?;
+            Some(AccountView {
+                did: account.did,
+                handle: account.handle.unwrap_or(INVALID_HANDLE.to_string()),
+                email: account.email,
+                indexed_at: account.created_at,
+                email_confirmed_at: account.email_confirmed_at,
+                invited_by: match invited_by.get(did) {
+                    Some(code_detail) if manages_own_invites => Some(code_detail.clone()),
+                    _ => None,
+                },
+                invites: if manages_own_invites {
+                    Some(invites.remove(did).unwrap_or_default())
+                } else {
+                    None
+                },
+                invites_disabled: if manages_own_invites {
+                    Some(account.invites_disabled == Some(1))
+                } else {
+                    None
+                },
+                related_records: None,
+                invite_note: None,
+            })
+        })
+        .collect())
+}
+
+async fn inner_get_account_infos(dids: Vec<String>) -> Result<GetAccountInfosOutput> {
+    Ok(GetAccountInfosOutput {
+        infos: get_account_views(dids).await?,
+    })
+}
+
+#[rocket::get("/xrpc/com.atproto.admin.getAccountInfos?<dids>")]
+pub async fn get_account_infos(
+    dids: Vec<String>,
+    _auth: Moderator,
+) -> Result<Json<GetAccountInfosOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_get_account_infos(dids).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
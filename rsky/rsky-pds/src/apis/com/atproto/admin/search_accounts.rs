@@ -0,0 +1,85 @@
+use crate::account_manager::helpers::account::{
+    AvailabilityFlags, SearchAccountsOpts, SEARCH_ACCOUNTS_KEYSET,
+};
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::admin::get_account_infos::get_account_views;
+use crate::auth_verifier::Moderator;
+use crate::db::pagination::{KeySet, TimeKeyResult};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::atproto::admin::SearchAccountsOutput;
+
+async fn inner_search_accounts(
+    email: Option<String>,
+    handle: Option<String>,
+    did: Option<String>,
+    invite_code: Option<String>,
+    limit: u16,
+    cursor: Option<String>,
+) -> Result<SearchAccountsOutput> {
+    if limit > 100 {
+        bail!("Error: limit can not be greater than 100")
+    }
+    let accounts = AccountManager::search_accounts(
+        SearchAccountsOpts {
+            email,
+            handle,
+            did,
+            invite_code,
+            limit: limit as i64,
+            cursor,
+        },
+        Some(AvailabilityFlags {
+            include_deactivated: Some(true),
+            include_taken_down: Some(true),
+        }),
+    )
+    .await?;
+    let time_did_results = accounts
+        .iter()
+        .map(|account| TimeKeyResult {
+            created_at: account.created_at.clone(),
+            key: account.did.clone(),
+        })
+        .collect::<Vec<TimeKeyResult>>();
+    let dids = accounts.into_iter().map(|account| account.did).collect();
+
+    Ok(SearchAccountsOutput {
+        cursor: SEARCH_ACCOUNTS_KEYSET.pack_from_result(&time_did_results)?,
+        accounts: get_account_views(dids).await?,
+    })
+}
+
+/// Search accounts by email prefix, handle prefix, DID or the invite code they signed up with.
+#[allow(non_snake_case)]
+#[rocket::get(
+    "/xrpc/com.atproto.admin.searchAccounts?<email>&<handle>&<did>&<inviteCode>&<limit>&<cursor>"
+)]
+pub async fn search_accounts(
+    email: Option<String>,
+    handle: Option<String>,
+    did: Option<String>,
+    inviteCode: Option<String>,
+    limit: Option<u16>,
+    cursor: Option<String>,
+    _auth: Moderator,
+) -> Result<Json<SearchAccountsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let limit = limit.unwrap_or(50);
+    match inner_search_accounts(email, handle, did, inviteCode, limit, cursor).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
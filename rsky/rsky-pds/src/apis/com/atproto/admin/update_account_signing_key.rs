@@ -0,0 +1,99 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::{encode_did_key, get_keys_from_private_key_str};
+use crate::auth_verifier::AdminToken;
+use crate::config::ServerConfig;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::{plc, SharedSequencer};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::admin::UpdateAccountSigningKeyInput;
+use std::env;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum UpdateSigningKeyError {
+    #[error("Signing keys can only be updated for did:plc accounts")]
+    NotPlc,
+    #[error("Account not found: {0}")]
+    AccountNotFound(String),
+    #[error("Signing key must be the key this PDS signs repo commits with: {0}")]
+    UnexpectedKey(String),
+}
+
+async fn inner_update_account_signing_key(
+    body: Json<UpdateAccountSigningKeyInput>,
+    sequencer: &State<SharedSequencer>,
+    cfg: &State<ServerConfig>,
+) -> Result<()> {
+    let UpdateAccountSigningKeyInput { did, signing_key } = body.into_inner();
+    if !did.starts_with("did:plc:") {
+        bail!(UpdateSigningKeyError::NotPlc)
+    }
+    let account = AccountManager::get_account(
+        &did,
+        Some(AvailabilityFlags {
+            include_deactivated: Some(true),
+            include_taken_down: Some(true),
+        }),
+    )
+    .await?;
+    if account.is_none() {
+        bail!(UpdateSigningKeyError::AccountNotFound(did))
+    }
+    // Every commit is signed with the PDS's repo signing key, so publishing any other key
+    // would make the account's repo fail verification
+    let repo_private_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX")?;
+    let (_, repo_public_key) = get_keys_from_private_key_str(repo_private_key)?;
+    let repo_signing_key = encode_did_key(&repo_public_key);
+    if signing_key != repo_signing_key {
+        bail!(UpdateSigningKeyError::UnexpectedKey(repo_signing_key))
+    }
+    let plc_client = plc::Client::new(cfg.identity.plc_url.clone());
+    let private_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX")?;
+    let (rotation_key, _) = get_keys_from_private_key_str(private_key)?;
+    plc_client
+        .update_atproto_key(&did, &rotation_key, &signing_key)
+        .await?;
+
+    let mut lock = sequencer.sequencer.write().await;
+    lock.sequence_identity_evt(did, None).await?;
+    Ok(())
+}
+
+#[rocket::post(
+    "/xrpc/com.atproto.admin.updateAccountSigningKey",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn update_account_signing_key(
+    body: Json<UpdateAccountSigningKeyInput>,
+    sequencer: &State<SharedSequencer>,
+    cfg: &State<ServerConfig>,
+    _auth: AdminToken,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_update_account_signing_key(body, sequencer, cfg).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            if error.downcast_ref::<UpdateSigningKeyError>().is_some() {
+                let bad_request = ErrorMessageResponse {
+                    code: Some(ErrorCode::BadRequest),
+                    message: Some(error.to_string()),
+                };
+                return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+            }
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
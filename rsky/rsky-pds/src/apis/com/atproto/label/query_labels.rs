@@ -0,0 +1,55 @@
+use crate::labeler::{self, QueryLabelsOpts};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::atproto::label::QueryLabelsOutput;
+
+async fn inner_query_labels(
+    uri_patterns: Vec<String>,
+    sources: Vec<String>,
+    limit: u16,
+    cursor: Option<String>,
+) -> Result<QueryLabelsOutput> {
+    if limit > 250 {
+        bail!("Error: limit can not be greater than 250")
+    }
+    if uri_patterns.is_empty() {
+        bail!("Error: at least one uri pattern is required")
+    }
+    labeler::query_labels(QueryLabelsOpts {
+        uri_patterns,
+        sources,
+        limit: limit as i64,
+        cursor,
+    })
+    .await
+}
+
+/// Find labels relevant to the provided AT-URI patterns. Public endpoint for moderation
+/// services, though may return different or additional results with auth.
+#[rocket::get("/xrpc/com.atproto.label.queryLabels?<uriPatterns>&<sources>&<limit>&<cursor>")]
+#[allow(non_snake_case)]
+pub async fn query_labels(
+    uriPatterns: Vec<String>,
+    sources: Vec<String>,
+    limit: Option<u16>,
+    cursor: Option<String>,
+) -> Result<Json<QueryLabelsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let limit = limit.unwrap_or(50);
+    match inner_query_labels(uriPatterns, sources, limit, cursor).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::labeler;
+use crate::xrpc_server::stream::frames::{ErrorFrame, Frame, MessageFrame, MessageFrameOpts};
+use crate::xrpc_server::stream::types::ErrorFrameBody;
+use futures::{pin_mut, StreamExt};
+use rocket::tokio::select;
+use rocket::Shutdown;
+use rsky_lexicon::com::atproto::label::SubscribeLabelsLabels;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval, Duration as TokioDuration};
+use ws::Message;
+
+const BATCH_LIMIT: i64 = 500;
+
+/// Subscribe to stream of labels (and negations). Public endpoint implemented by mod
+/// services. Uses same sequencing scheme as repo event stream.
+#[rocket::get("/xrpc/com.atproto.label.subscribeLabels?<cursor>")]
+pub async fn subscribe_labels(
+    cursor: Option<i64>,
+    mut shutdown: Shutdown,
+    ws: ws::WebSocket,
+) -> ws::Stream!['static] {
+    ws::Stream! { ws =>
+        // Subscribed first so no label created while the stream starts is missed
+        let mut created = labeler::subscribe_created();
+        let curr = match labeler::curr_seq().await {
+            Ok(curr) => curr.unwrap_or(0),
+            Err(err) => {
+                let error_frame = ErrorFrame::new(ErrorFrameBody {
+                    error: "CurrError".to_string(),
+                    message: Some(err.to_string()),
+                });
+                yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                return;
+            }
+        };
+        // Without a cursor only labels created from now on are streamed
+        let mut last_seq = match cursor {
+            None => curr,
+            Some(cursor) if cursor > curr => {
+                let error_frame = ErrorFrame::new(ErrorFrameBody {
+                    error: "FutureCursor".to_string(),
+                    message: Some("Cursor in the future.".to_string()),
+                });
+                yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                return;
+            },
+            Some(cursor) => cursor,
+        };
+
+        pin_mut!(ws);
+
+        let mut ping_interval = interval(TokioDuration::from_secs(30));
+        // Catches up from the cursor first, then again every time labels are created
+        let mut behind = true;
+
+        loop {
+            while behind {
+                let rows = match labeler::labels_after(last_seq, BATCH_LIMIT).await {
+                    Ok(rows) => rows,
+                    Err(err) => {
+                        let error_frame = ErrorFrame::new(ErrorFrameBody {
+                            error: "EventStreamError".to_string(),
+                            message: Some(err.to_string()),
+                        });
+                        yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                        return;
+                    }
+                };
+                behind = rows.len() as i64 == BATCH_LIMIT;
+                for row in rows {
+                    last_seq = row.seq;
+                    let labels_evt = SubscribeLabelsLabels {
+                        seq: row.seq,
+                        labels: vec![labeler::row_to_label(row)],
+                    };
+                    let message_frame = MessageFrame::new(labels_evt, Some(MessageFrameOpts { r#type: Some("#labels".to_string()) }));
+                    let binary = match message_frame.to_bytes() {
+                        Ok(binary) => binary,
+                        Err(_) => {
+                            let error_frame = ErrorFrame::new(ErrorFrameBody {
+                                error: "SerializationError".to_string(),
+                                message: Some("Failed to serialize event to message frame.".to_string()),
+                            });
+                            yield Message::Binary(error_frame.to_bytes().expect("couldn't translate error to binary."));
+                            return;
+                        }
+                    };
+                    yield Message::Binary(binary);
+                }
+            }
+            select! {
+                seq = created.recv() => {
+                    match seq {
+                        Ok(seq) => behind = seq > last_seq,
+                        // Missed some wake-ups, the query picks up whatever was created
+                        Err(RecvError::Lagged(_)) => behind = true,
+                        Err(RecvError::Closed) => break,
+                    }
+                },
+                message = ws.next() => {
+                    match message {
+                        Some(Ok(ws::Message::Close(_))) => break,
+                        Some(Ok(ws::Message::Ping(payload))) => {
+                            yield ws::Message::Pong(payload);
+                        },
+                        Some(Ok(_)) => (),
+                        Some(Err(err)) => {
+                            eprintln!("@LOG: ERROR: subscribeLabels websocket error: {err}");
+                            break;
+                        },
+                        None => break,
+                    }
+                },
+                _ = ping_interval.tick() => {
+                    yield ws::Message::Ping(vec![]);
+                },
+                _ = &mut shutdown => break
+            }
+        }
+    }
+}
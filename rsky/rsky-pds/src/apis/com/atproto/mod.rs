@@ -1,5 +1,7 @@
 pub mod admin;
 pub mod identity;
+pub mod label;
+pub mod moderation;
 pub mod repo;
 pub mod server;
 pub mod sync;
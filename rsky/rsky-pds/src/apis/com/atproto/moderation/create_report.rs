@@ -0,0 +1,70 @@
+use crate::auth_verifier::AccessStandard;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::moderation;
+use crate::pipethrough::{pipethrough_procedure, ProxyRequest};
+use crate::read_after_write::util::ReadAfterWriteResponse;
+use crate::xrpc_server::types::HandlerPipeThrough;
+use anyhow::{anyhow, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::atproto::moderation::{CreateReportInput, CreateReportOutput};
+
+async fn inner_create_report(
+    body: Json<CreateReportInput>,
+    auth: AccessStandard,
+    req: ProxyRequest<'_>,
+) -> Result<HandlerPipeThrough> {
+    let requester = match auth.access.credentials {
+        Some(credentials) => credentials.did,
+        None => None,
+    }
+    .ok_or_else(|| anyhow!("Requester did not found"))?;
+
+    // Reports go to the report service (or a labeler picked with atproto-proxy) when
+    // there is one, otherwise they're kept for the PDS admin to review.
+    if req.cfg.report_service.is_some() || req.headers.contains_key("atproto-proxy") {
+        return pipethrough_procedure(&req, Some(requester), Some(body.into_inner())).await;
+    }
+    let report = moderation::create_report(body.into_inner(), requester).await?;
+    let output = CreateReportOutput {
+        id: report.id,
+        reason_type: report.reason_type,
+        reason: report.reason,
+        subject: report.subject,
+        reported_by: report.reported_by,
+        created_at: report.created_at,
+    };
+    Ok(HandlerPipeThrough {
+        encoding: "application/json".to_string(),
+        buffer: serde_json::to_vec(&output)?,
+        headers: None,
+    })
+}
+
+#[rocket::post(
+    "/xrpc/com.atproto.moderation.createReport",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn create_report(
+    body: Json<CreateReportInput>,
+    auth: AccessStandard,
+    req: ProxyRequest<'_>,
+) -> Result<ReadAfterWriteResponse<CreateReportOutput>, status::Custom<Json<ErrorMessageResponse>>>
+{
+    match inner_create_report(body, auth, req).await {
+        Ok(res) => Ok(ReadAfterWriteResponse::HandlerPipeThrough(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
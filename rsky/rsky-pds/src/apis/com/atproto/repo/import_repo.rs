@@ -1,4 +1,161 @@
-#[rocket::post("/xrpc/com.atproto.repo.importRepo")]
-pub async fn import_repo() {
-    unimplemented!()
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::repo::write_error_response;
+use crate::auth_verifier::AccessFull;
+use crate::car::read_car_with_root;
+use crate::config::ServerConfig;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::mst::util::leaves_from_blocks;
+use crate::repo::mst::MST;
+use crate::repo::parse::get_and_parse_record;
+use crate::repo::types::{PreparedWrite, RecordPath};
+use crate::repo::util::parse_data_key;
+use crate::repo::{
+    decode_commit, prepare_create, prepare_delete, prepare_update, ActorStore, PrepareCreateOpts,
+    PrepareDeleteOpts, PrepareUpdateOpts,
+};
+use crate::SharedSequencer;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use lexicon_cid::Cid;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ImportRepoError {
+    #[error("Service is not accepting repo imports")]
+    NotAccepting,
+    #[error("Repo is over the import size limit")]
+    TooLarge,
+    #[error("Repo CAR is missing its commit block")]
+    MissingCommit,
+    #[error("Repo belongs to {0}, not the authenticated account")]
+    WrongRepo(String),
+}
+
+async fn inner_import_repo(
+    body: Data<'_>,
+    auth: AccessFull,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
+) -> Result<()> {
+    if !cfg.service.accepting_imports {
+        bail!(ImportRepoError::NotAccepting)
+    }
+    let requester = auth.access.credentials.unwrap().did.unwrap();
+    let car = body.open(100.mebibytes()).into_bytes().await?;
+    if !car.is_complete() {
+        bail!(ImportRepoError::TooLarge)
+    }
+    let (root, blocks) = read_car_with_root(car.into_inner()).await?;
+    let commit = match blocks.get(root) {
+        Some(bytes) => decode_commit(root, bytes.clone())?,
+        None => bail!(ImportRepoError::MissingCommit),
+    };
+    if commit.did != requester {
+        bail!(ImportRepoError::WrongRepo(commit.did))
+    }
+    let incoming = leaves_from_blocks(&blocks, commit.data)?;
+
+    let mut actor_store = ActorStore::new(
+        requester.clone(),
+        S3BlobStore::new(requester.clone(), s3_config),
+    );
+    let current_root = actor_store.storage.get_root_detailed().await?;
+    let current_commit = decode_commit(
+        current_root.cid,
+        actor_store.storage.get_bytes(&current_root.cid)?,
+    )?;
+    let mut current: BTreeMap<String, Cid> =
+        MST::load(actor_store.storage.clone(), current_commit.data, None)?
+            .leaves()?
+            .into_iter()
+            .map(|leaf| (leaf.key, leaf.value))
+            .collect();
+
+    // Diff the imported repo against the current one, so importing again is a no-op
+    let mut writes: Vec<PreparedWrite> = Vec::new();
+    for leaf in incoming {
+        let existing = current.remove(&leaf.key);
+        if existing == Some(leaf.value) {
+            continue;
+        }
+        let RecordPath { collection, rkey } = parse_data_key(&leaf.key)?;
+        let record = get_and_parse_record(&blocks, leaf.value)?.record;
+        writes.push(match existing {
+            None => PreparedWrite::Create(
+                prepare_create(PrepareCreateOpts {
+                    did: requester.clone(),
+                    collection,
+                    rkey: Some(rkey),
+                    swap_cid: None,
+                    record,
+                    validate: Some(false),
+                })
+                .await?,
+            ),
+            Some(_) => PreparedWrite::Update(
+                prepare_update(PrepareUpdateOpts {
+                    did: requester.clone(),
+                    collection,
+                    rkey,
+                    swap_cid: None,
+                    record,
+                    validate: Some(false),
+                })
+                .await?,
+            ),
+        });
+    }
+    for key in current.into_keys() {
+        let RecordPath { collection, rkey } = parse_data_key(&key)?;
+        writes.push(PreparedWrite::Delete(prepare_delete(PrepareDeleteOpts {
+            did: requester.clone(),
+            collection,
+            rkey,
+            swap_cid: None,
+        })));
+    }
+    if writes.is_empty() {
+        return Ok(());
+    }
+
+    let commit = actor_store.process_import(writes.clone()).await?;
+
+    let mut lock = sequencer.sequencer.write().await;
+    lock.sequence_commit(requester.clone(), commit.clone(), writes)
+        .await?;
+    AccountManager::update_repo_root(requester, commit.cid, commit.rev)?;
+    Ok(())
+}
+
+/// Import a repo in the form of a CAR file, e.g. when restoring an account archive into a
+/// fresh account. Records are re-committed with this PDS's signing key; blobs they reference
+/// are listed by listMissingBlobs until uploaded. Requires auth.
+#[rocket::post("/xrpc/com.atproto.repo.importRepo", data = "<body>")]
+pub async fn import_repo(
+    body: Data<'_>,
+    auth: AccessFull,
+    sequencer: &State<SharedSequencer>,
+    s3_config: &State<SdkConfig>,
+    cfg: &State<ServerConfig>,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_import_repo(body, auth, sequencer, s3_config, cfg).await {
+        Ok(()) => Ok(()),
+        Err(error) if error.downcast_ref::<ImportRepoError>().is_some() => {
+            eprintln!("@LOG: ERROR: {error}");
+            let bad_request = ErrorMessageResponse {
+                code: Some(ErrorCode::BadRequest),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(Status::BadRequest, Json(bad_request)))
+        }
+        Err(error) => Err(write_error_response(error)),
+    }
 }
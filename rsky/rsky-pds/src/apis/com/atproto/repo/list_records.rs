@@ -1,6 +1,8 @@
 use crate::account_manager::AccountManager;
+use crate::db::pagination::KeySet;
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::record::LIST_RECORDS_KEYSET;
 use crate::repo::ActorStore;
 use anyhow::{bail, Result};
 use aws_config::SdkConfig;
@@ -57,21 +59,16 @@ async fn inner_list_records(
             })
             .collect::<Result<Vec<Record>>>()?;
 
-        let last_record = records.last();
         // @TODO: Use ATUri
-        let cursor: Option<String>;
-        if let Some(last_record) = last_record {
-            let last_uri = last_record.clone().uri;
-            let last_uri_without_prefix = last_uri.replace("at://", "");
+        let last_rkey: Option<String> = records.last().and_then(|last_record| {
+            let last_uri_without_prefix = last_record.uri.replace("at://", "");
             let parts = last_uri_without_prefix.split("/").collect::<Vec<&str>>();
-            if let (Some(_), Some(_), Some(uri_rkey)) = (parts.get(0), parts.get(1), parts.get(2)) {
-                cursor = Some(uri_rkey.to_string());
-            } else {
-                cursor = None;
+            match (parts.get(0), parts.get(1), parts.get(2)) {
+                (Some(_), Some(_), Some(uri_rkey)) => Some(uri_rkey.to_string()),
+                _ => None,
             }
-        } else {
-            cursor = None;
-        }
+        });
+        let cursor = LIST_RECORDS_KEYSET.pack_from_result(last_rkey.as_slice())?;
         Ok(ListRecordsOutput { records, cursor })
     } else {
         bail!("Could not find repo: {repo}")
@@ -1,6 +1,12 @@
 use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
 use crate::account_manager::AccountManager;
+use crate::image::ImageError;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::quota::QuotaError;
 use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
 
 pub async fn assert_repo_availability(
     did: &String,
@@ -31,6 +37,26 @@ pub async fn assert_repo_availability(
     }
 }
 
+/// Over-quota writes and oversized images are the client's to fix and answer a 400, anything
+/// else is a server failure
+pub fn write_error_response(error: anyhow::Error) -> status::Custom<Json<ErrorMessageResponse>> {
+    eprintln!("@LOG: ERROR: {error}");
+    let (status, code) = if error.downcast_ref::<QuotaError>().is_some()
+        || error.downcast_ref::<ImageError>().is_some()
+    {
+        (Status::BadRequest, ErrorCode::BadRequest)
+    } else {
+        (Status::InternalServerError, ErrorCode::InternalServerError)
+    };
+    status::Custom(
+        status,
+        Json(ErrorMessageResponse {
+            code: Some(code),
+            message: Some(error.to_string()),
+        }),
+    )
+}
+
 pub mod apply_writes;
 pub mod create_record;
 pub mod delete_record;
@@ -1,7 +1,8 @@
 use crate::account_manager::helpers::account::AvailabilityFlags;
 use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::repo::write_error_response;
 use crate::auth_verifier::AccessStandardIncludeChecks;
-use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::models::ErrorMessageResponse;
 use crate::repo::aws::s3::S3BlobStore;
 use crate::repo::types::{CommitData, PreparedWrite};
 use crate::repo::{
@@ -11,7 +12,6 @@ use crate::SharedSequencer;
 use anyhow::{bail, Result};
 use aws_config::SdkConfig;
 use libipld::Cid;
-use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -130,16 +130,6 @@ pub async fn put_record(
     println!("@LOG: debug put_record {body:#?}");
     match inner_put_record(body, auth, sequencer, s3_config).await {
         Ok(res) => Ok(Json(res)),
-        Err(error) => {
-            eprintln!("@LOG: ERROR: {error}");
-            let internal_error = ErrorMessageResponse {
-                code: Some(ErrorCode::InternalServerError),
-                message: Some(error.to_string()),
-            };
-            return Err(status::Custom(
-                Status::InternalServerError,
-                Json(internal_error),
-            ));
-        }
+        Err(error) => Err(write_error_response(error)),
     }
 }
@@ -1,13 +1,15 @@
+use crate::apis::com::atproto::repo::write_error_response;
 use crate::auth_verifier::AccessStandardIncludeChecks;
 use crate::common::ContentType;
-use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::models::ErrorMessageResponse;
 use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::blob::BlobMetadata;
+use crate::repo::quota;
 use crate::repo::types::{BlobConstraint, PreparedBlobRef};
 use crate::repo::ActorStore;
 use anyhow::Result;
 use aws_config::SdkConfig;
 use rocket::data::Data;
-use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::Json;
 use rocket::State;
@@ -30,6 +32,24 @@ async fn inner_upload_blob(
         .blob
         .upload_blob_and_get_metadata(content_type.name, blob)
         .await?;
+    track_uploaded_blob(&actor_store, &requester, metadata).await
+}
+
+/// Checks a blob stored under a temp key against the account's quota and tracks it,
+/// making it permanent if an associated record is already indexed
+pub async fn track_uploaded_blob(
+    actor_store: &ActorStore,
+    requester: &String,
+    metadata: BlobMetadata,
+) -> Result<BlobOutput> {
+    if let Err(error) = quota::check_blob_upload(requester, metadata.size).await {
+        actor_store
+            .blob
+            .blobstore
+            .delete_temp(metadata.temp_key)
+            .await?;
+        return Err(error);
+    }
     let blobref = actor_store.blob.track_untethered_blob(metadata).await?;
 
     // make the blob permanent if an associated record is already indexed
@@ -73,16 +93,6 @@ pub async fn upload_blob(
 ) -> Result<Json<BlobOutput>, status::Custom<Json<ErrorMessageResponse>>> {
     match inner_upload_blob(auth, blob, content_type, s3_config).await {
         Ok(res) => Ok(Json(res)),
-        Err(error) => {
-            eprintln!("{error:?}");
-            let internal_error = ErrorMessageResponse {
-                code: Some(ErrorCode::InternalServerError),
-                message: Some(error.to_string()),
-            };
-            return Err(status::Custom(
-                Status::InternalServerError,
-                Json(internal_error),
-            ));
-        }
+        Err(error) => Err(write_error_response(error)),
     }
 }
@@ -1,70 +1,149 @@
-use crate::account_manager::helpers::account::AvailabilityFlags;
-use crate::account_manager::AccountManager;
+use crate::account_manager::helpers::account::{ActorAccount, AvailabilityFlags};
+use crate::account_manager::helpers::email_token::EmailTokenError;
+use crate::account_manager::helpers::password::hash_app_password;
+use crate::account_manager::{AccountManager, SessionClient};
+use crate::common::time::from_millis_to_str;
+use crate::mailer;
+use crate::mailer::{LoginLockoutParams, TokenParam};
+use crate::models::models::EmailTokenPurpose;
 use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::rate_limiter::{LoginRateLimit, RateLimit, RateLimitExceeded};
 use crate::INVALID_HANDLE;
 use anyhow::bail;
 use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::Json;
 use rsky_lexicon::com::atproto::server::{CreateSessionInput, CreateSessionOutput};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("A sign in code has been sent to your email address")]
+struct AuthFactorTokenRequired;
+
+#[derive(Error, Debug)]
+#[error("Invalid identifier or password")]
+pub struct InvalidLogin;
+
+/// Checks a sign in password against the login limits and lockout. Unknown accounts get the
+/// same delays and hashing work as known ones, so the response time doesn't give away
+/// whether an account exists. Returns the name of the app password used, if any.
+pub async fn verify_login(
+    rate_limit: &RateLimit<'_>,
+    identifier: &String,
+    user: Option<&ActorAccount>,
+    password: &String,
+    allow_app_passwords: bool,
+) -> Result<Option<String>, anyhow::Error> {
+    rate_limit.consume(
+        &format!("login-identifier:{identifier}"),
+        &rate_limit.cfg().login_per_identifier,
+    )?;
+    let Some(user) = user else {
+        let key = format!("unknown:{identifier}");
+        tokio::time::sleep(rate_limit.failure_delay(&key)).await;
+        let _ = hash_app_password(identifier, password).await;
+        rate_limit.record_failure(&key);
+        bail!(InvalidLogin)
+    };
+    rate_limit.check_lockout(&user.did)?;
+    tokio::time::sleep(rate_limit.failure_delay(&user.did)).await;
+
+    if AccountManager::verify_account_password(&user.did, password).await? {
+        return Ok(None);
+    }
+    if allow_app_passwords {
+        let app_password_name = AccountManager::verify_app_password(&user.did, password).await?;
+        if app_password_name.is_some() {
+            return Ok(app_password_name);
+        }
+    }
+    record_login_failure(rate_limit, user).await;
+    bail!(InvalidLogin)
+}
+
+/// Counts a failed sign in and emails the account if that locked it out
+pub async fn record_login_failure(rate_limit: &RateLimit<'_>, user: &ActorAccount) {
+    if let Some(locked_until) = rate_limit.record_failure(&user.did) {
+        if let Some(email) = &user.email {
+            let params = LoginLockoutParams {
+                identifier: user.handle.clone().unwrap_or(email.clone()),
+                locked_until: from_millis_to_str(locked_until as i64),
+            };
+            if let Err(error) = mailer::send_login_lockout(email.clone(), params).await {
+                eprintln!("@LOG: ERROR: failed to send lockout alert: {error}");
+            }
+        }
+    }
+}
 
 async fn inner_create_session(
     body: Json<CreateSessionInput>,
+    rate_limit: &RateLimit<'_>,
+    client: SessionClient,
 ) -> Result<CreateSessionOutput, anyhow::Error> {
     let CreateSessionInput {
         password,
         identifier,
+        auth_factor_token,
     } = body.into_inner();
     let identifier = identifier.to_lowercase();
 
+    let flags = Some(AvailabilityFlags {
+        include_deactivated: Some(true),
+        include_taken_down: Some(true),
+    });
     let user = match identifier.contains("@") {
-        true => {
-            AccountManager::get_account_by_email(
-                &identifier,
-                Some(AvailabilityFlags {
-                    include_deactivated: Some(true),
-                    include_taken_down: Some(true),
-                }),
-            )
-            .await
-        }
-        false => {
-            AccountManager::get_account(
-                &identifier,
-                Some(AvailabilityFlags {
-                    include_deactivated: Some(true),
-                    include_taken_down: Some(true),
-                }),
-            )
-            .await
-        }
+        true => AccountManager::get_account_by_email(&identifier, flags).await?,
+        false => AccountManager::get_account(&identifier, flags).await?,
     };
-    if let Ok(Some(user)) = user {
-        let mut app_password_name: Option<String> = None;
-        let valid_account_pass =
-            AccountManager::verify_account_password(&user.did, &password).await?;
-        if !valid_account_pass {
-            app_password_name = AccountManager::verify_app_password(&user.did, &password).await?;
-            if app_password_name.is_none() {
-                bail!("Invalid identifier or password")
-            }
-        }
+    let app_password_name =
+        verify_login(rate_limit, &identifier, user.as_ref(), &password, true).await?;
+    if let Some(user) = user {
         if user.takedown_ref.is_some() {
             bail!("Account has been taken down")
         }
+        // App passwords are meant for clients that can't prompt for a code
+        let email_auth_factor = AccountManager::get_email_auth_factor(&user.did).await?;
+        if let (true, None, Some(email)) = (email_auth_factor, &app_password_name, &user.email) {
+            match auth_factor_token {
+                None => {
+                    let token =
+                        AccountManager::create_email_token(&user.did, EmailTokenPurpose::SignIn)
+                            .await?;
+                    mailer::send_sign_in_token(email.clone(), TokenParam { token }).await?;
+                    bail!(AuthFactorTokenRequired)
+                }
+                Some(token) => {
+                    if let Err(error) = AccountManager::assert_valid_email_token(
+                        &user.did,
+                        EmailTokenPurpose::SignIn,
+                        &token,
+                    )
+                    .await
+                    {
+                        record_login_failure(rate_limit, &user).await;
+                        return Err(error);
+                    }
+                    AccountManager::delete_email_token(&user.did, EmailTokenPurpose::SignIn)
+                        .await?;
+                }
+            }
+        }
+        rate_limit.record_success(&user.did);
         let (access_jwt, refresh_jwt) =
-            AccountManager::create_session(user.did.clone(), app_password_name).await?;
+            AccountManager::create_session(user.did.clone(), app_password_name, client).await?;
         Ok(CreateSessionOutput {
             did: user.did,
             did_doc: None,
             handle: user.handle.unwrap_or(INVALID_HANDLE.to_string()),
             email: user.email,
             email_confirmed: Some(user.email_confirmed_at.is_some()),
+            email_auth_factor: Some(email_auth_factor),
             access_jwt,
             refresh_jwt,
         })
     } else {
-        bail!("Invalid identifier or password")
+        bail!(InvalidLogin)
     }
 }
 
@@ -75,11 +154,52 @@ async fn inner_create_session(
 )]
 pub async fn create_session(
     body: Json<CreateSessionInput>,
+    rate_limit: LoginRateLimit<'_>,
+    client: SessionClient,
 ) -> Result<Json<CreateSessionOutput>, status::Custom<Json<ErrorMessageResponse>>> {
-    // @TODO: Add rate limiting
-
-    match inner_create_session(body).await {
+    match inner_create_session(body, &rate_limit.0, client).await {
         Ok(res) => Ok(Json(res)),
+        Err(error) if error.is::<RateLimitExceeded>() => {
+            let rate_limit_error = ErrorMessageResponse {
+                code: Some(ErrorCode::RateLimitExceeded),
+                message: Some(error.downcast::<RateLimitExceeded>().unwrap().message),
+            };
+            return Err(status::Custom(
+                Status::TooManyRequests,
+                Json(rate_limit_error),
+            ));
+        }
+        Err(error) if error.is::<AuthFactorTokenRequired>() => {
+            let auth_factor_error = ErrorMessageResponse {
+                code: Some(ErrorCode::AuthFactorTokenRequired),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::Unauthorized,
+                Json(auth_factor_error),
+            ));
+        }
+        Err(error) if error.is::<InvalidLogin>() => {
+            let invalid_login_error = ErrorMessageResponse {
+                code: Some(ErrorCode::AuthenticationRequired),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::Unauthorized,
+                Json(invalid_login_error),
+            ));
+        }
+        Err(error) if error.is::<EmailTokenError>() => {
+            let code = match error.downcast_ref::<EmailTokenError>() {
+                Some(EmailTokenError::Expired) => ErrorCode::ExpiredToken,
+                _ => ErrorCode::InvalidToken,
+            };
+            let token_error = ErrorMessageResponse {
+                code: Some(code),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(Status::Unauthorized, Json(token_error)));
+        }
         Err(error) => {
             eprintln!("{error:?}");
             let internal_error = ErrorMessageResponse {
@@ -12,13 +12,17 @@ pub async fn get_session(
     auth: AccessStandard,
 ) -> Result<Json<GetSessionOutput>, status::Custom<Json<ErrorMessageResponse>>> {
     let did = auth.access.credentials.unwrap().did.unwrap();
-    match AccountManager::get_account(&did, None).await {
-        Ok(Some(user)) => Ok(Json(GetSessionOutput {
+    match (
+        AccountManager::get_account(&did, None).await,
+        AccountManager::get_email_auth_factor(&did).await,
+    ) {
+        (Ok(Some(user)), Ok(email_auth_factor)) => Ok(Json(GetSessionOutput {
             handle: user.handle.unwrap_or(INVALID_HANDLE.to_string()),
             did: user.did,
             email: user.email,
             did_doc: None,
             email_confirmed: Some(user.email_confirmed_at.is_some()),
+            email_auth_factor: Some(email_auth_factor),
         })),
         _ => {
             let internal_error = ErrorMessageResponse {
@@ -5,13 +5,18 @@ use crate::mailer;
 use crate::mailer::TokenParam;
 use crate::models::models::EmailTokenPurpose;
 use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::rate_limiter::{EmailRateLimit, RateLimit, RateLimitExceeded};
 use anyhow::{bail, Result};
 use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::Json;
 
-async fn inner_request_email_confirmation(auth: AccessStandardIncludeChecks) -> Result<()> {
+async fn inner_request_email_confirmation(
+    auth: AccessStandardIncludeChecks,
+    rate_limit: &RateLimit<'_>,
+) -> Result<()> {
     let did = auth.access.credentials.unwrap().did.unwrap();
+    rate_limit.consume(&format!("email-account:{did}"), &rate_limit.cfg().email)?;
     let account = AccountManager::get_account(
         &did,
         Some(AvailabilityFlags {
@@ -37,9 +42,20 @@ async fn inner_request_email_confirmation(auth: AccessStandardIncludeChecks) ->
 #[rocket::post("/xrpc/com.atproto.server.requestEmailConfirmation")]
 pub async fn request_email_confirmation(
     auth: AccessStandardIncludeChecks,
+    rate_limit: EmailRateLimit<'_>,
 ) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
-    match inner_request_email_confirmation(auth).await {
+    match inner_request_email_confirmation(auth, &rate_limit.0).await {
         Ok(_) => Ok(()),
+        Err(error) if error.is::<RateLimitExceeded>() => {
+            let rate_limit_error = ErrorMessageResponse {
+                code: Some(ErrorCode::RateLimitExceeded),
+                message: Some(error.downcast::<RateLimitExceeded>().unwrap().message),
+            };
+            return Err(status::Custom(
+                Status::TooManyRequests,
+                Json(rate_limit_error),
+            ));
+        }
         Err(error) => {
             eprintln!("@LOG: ERROR: {error}");
             let internal_error = ErrorMessageResponse {
@@ -5,15 +5,20 @@ use crate::mailer;
 use crate::mailer::IdentifierAndTokenParams;
 use crate::models::models::EmailTokenPurpose;
 use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::rate_limiter::{EmailRateLimit, RateLimit, RateLimitExceeded};
 use anyhow::{bail, Result};
 use rocket::http::Status;
 use rocket::response::status;
 use rocket::serde::json::Json;
 use rsky_lexicon::com::atproto::server::RequestPasswordResetInput;
 
-async fn inner_request_password_reset(body: Json<RequestPasswordResetInput>) -> Result<()> {
+async fn inner_request_password_reset(
+    body: Json<RequestPasswordResetInput>,
+    rate_limit: &RateLimit<'_>,
+) -> Result<()> {
     let RequestPasswordResetInput { email } = body.into_inner();
     let email = email.to_lowercase();
+    rate_limit.consume(&format!("email-account:{email}"), &rate_limit.cfg().email)?;
 
     let account = AccountManager::get_account_by_email(
         &email,
@@ -54,9 +59,20 @@ async fn inner_request_password_reset(body: Json<RequestPasswordResetInput>) ->
 pub async fn request_password_reset(
     body: Json<RequestPasswordResetInput>,
     _auth: AccessStandardIncludeChecks,
+    rate_limit: EmailRateLimit<'_>,
 ) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
-    match inner_request_password_reset(body).await {
+    match inner_request_password_reset(body, &rate_limit.0).await {
         Ok(_) => Ok(()),
+        Err(error) if error.is::<RateLimitExceeded>() => {
+            let rate_limit_error = ErrorMessageResponse {
+                code: Some(ErrorCode::RateLimitExceeded),
+                message: Some(error.downcast::<RateLimitExceeded>().unwrap().message),
+            };
+            return Err(status::Custom(
+                Status::TooManyRequests,
+                Json(rate_limit_error),
+            ));
+        }
         Err(error) => {
             eprintln!("@LOG: ERROR: {error}");
             let internal_error = ErrorMessageResponse {
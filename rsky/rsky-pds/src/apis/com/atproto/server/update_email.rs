@@ -11,7 +11,11 @@ use rsky_lexicon::com::atproto::server::UpdateEmailInput;
 
 async fn inner_update_email(body: Json<UpdateEmailInput>, auth: AccessFull) -> Result<()> {
     let did = auth.access.credentials.unwrap().did.unwrap();
-    let UpdateEmailInput { email, token } = body.into_inner();
+    let UpdateEmailInput {
+        email,
+        token,
+        email_auth_factor,
+    } = body.into_inner();
     if !mailchecker::is_valid(&email) {
         bail!("This email address is not supported, please use a different email.")
     }
@@ -38,6 +42,19 @@ async fn inner_update_email(body: Json<UpdateEmailInput>, auth: AccessFull) -> R
                 bail!("Confirmation token required")
             }
         }
+        let email_changed = account.email != Some(email.to_lowercase());
+        if email_auth_factor == Some(true)
+            && (email_changed || account.email_confirmed_at.is_none())
+        {
+            bail!("Email must be confirmed before enabling email auth factor")
+        }
+        if let Some(email_auth_factor) = email_auth_factor {
+            AccountManager::set_email_auth_factor(&did, email_auth_factor).await?;
+        }
+        if !email_changed {
+            AccountManager::delete_email_token(&did, EmailTokenPurpose::UpdateEmail).await?;
+            return Ok(());
+        }
         match AccountManager::update_email(UpdateEmailOpts { did, email }).await {
             Ok(_) => Ok(()),
             Err(e) => match e.downcast_ref() {
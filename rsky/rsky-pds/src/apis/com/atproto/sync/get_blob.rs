@@ -3,28 +3,163 @@ use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::error::BlobError;
 use crate::repo::ActorStore;
 use anyhow::Result;
 use aws_config::SdkConfig;
 use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::primitives::AggregatedBytes;
 use libipld::Cid;
-use rocket::http::{Header, Status};
-use rocket::response::status;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, status, Responder, Response};
 use rocket::serde::json::Json;
-use rocket::{Responder, State};
+use rocket::{Request, State};
+use std::io::Cursor;
 use std::str::FromStr;
 
-#[derive(Responder)]
-#[response(status = 200)]
-pub struct BlobResponder(Vec<u8>, Header<'static>, Header<'static>, Header<'static>);
+/// The conditional and range headers of a blob request
+pub struct BlobRequestHeaders {
+    pub range: Option<String>,
+    pub if_none_match: Option<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BlobRequestHeaders {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(BlobRequestHeaders {
+            range: req.headers().get_one("Range").map(|h| h.to_string()),
+            if_none_match: req
+                .headers()
+                .get_one("If-None-Match")
+                .map(|h| h.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RangeRequest {
+    // Inclusive, like the Content-Range header
+    Satisfiable { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+/// Parses a single byte range (`a-b`, `a-` or `-n`) against a blob of `size` bytes. Anything
+/// else, including multiple ranges, is ignored and the whole blob is served.
+pub fn parse_range(header: &str, size: u64) -> Option<RangeRequest> {
+    let spec = header.trim().strip_prefix("bytes=")?.trim();
+    if spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+    let (start, end) = match (first.is_empty(), last.is_empty()) {
+        // Suffix range: the last n bytes
+        (true, false) => {
+            let n = u64::from_str(last).ok()?;
+            if n == 0 || size == 0 {
+                return Some(RangeRequest::Unsatisfiable);
+            }
+            (size.saturating_sub(n), size - 1)
+        }
+        (false, true) => (u64::from_str(first).ok()?, size.saturating_sub(1)),
+        (false, false) => {
+            let (start, end) = (u64::from_str(first).ok()?, u64::from_str(last).ok()?);
+            if end < start {
+                return None;
+            }
+            (start, end.min(size.saturating_sub(1)))
+        }
+        (true, true) => return None,
+    };
+    if start >= size {
+        return Some(RangeRequest::Unsatisfiable);
+    }
+    Some(RangeRequest::Satisfiable { start, end })
+}
+
+pub enum BlobResponder {
+    Full {
+        bytes: Vec<u8>,
+        mime_type: String,
+        etag: String,
+    },
+    Partial {
+        bytes: Vec<u8>,
+        mime_type: String,
+        etag: String,
+        content_range: String,
+    },
+    NotModified {
+        etag: String,
+    },
+    Unsatisfiable {
+        etag: String,
+        size: u64,
+    },
+}
+
+impl<'r> Responder<'r, 'static> for BlobResponder {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut res = Response::build();
+        let etag = match &self {
+            BlobResponder::Full { etag, .. }
+            | BlobResponder::Partial { etag, .. }
+            | BlobResponder::NotModified { etag }
+            | BlobResponder::Unsatisfiable { etag, .. } => etag.clone(),
+        };
+        res.raw_header("etag", etag)
+            // Blobs are content addressed, so a cid's bytes never change
+            .raw_header("cache-control", "public, max-age=31536000, immutable")
+            .raw_header("accept-ranges", "bytes")
+            .raw_header("content-security-policy", "default-src 'none'; sandbox");
+        match self {
+            BlobResponder::Full {
+                bytes, mime_type, ..
+            } => {
+                res.status(Status::Ok)
+                    .raw_header("content-type", mime_type)
+                    .sized_body(bytes.len(), Cursor::new(bytes));
+            }
+            BlobResponder::Partial {
+                bytes,
+                mime_type,
+                content_range,
+                ..
+            } => {
+                res.status(Status::PartialContent)
+                    .raw_header("content-type", mime_type)
+                    .raw_header("content-range", content_range)
+                    .sized_body(bytes.len(), Cursor::new(bytes));
+            }
+            BlobResponder::NotModified { .. } => {
+                res.status(Status::NotModified);
+            }
+            BlobResponder::Unsatisfiable { size, .. } => {
+                res.status(Status::RangeNotSatisfiable)
+                    .raw_header("content-range", format!("bytes */{size}"));
+            }
+        }
+        res.ok()
+    }
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag == etag || tag.strip_prefix("W/") == Some(etag))
+}
 
 async fn inner_get_blob(
     did: String,
     cid: String,
     s3_config: &State<SdkConfig>,
     auth: OptionalAccessOrAdminToken,
-) -> Result<(Vec<u8>, Option<String>)> {
+    headers: BlobRequestHeaders,
+) -> Result<BlobResponder> {
     let is_user_or_admin = if let Some(access) = auth.access {
         auth_verifier::is_user_or_admin(access, &did)
     } else {
@@ -35,56 +170,159 @@ async fn inner_get_blob(
     let cid = Cid::from_str(&cid)?;
     let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config));
 
-    let found = actor_store.blob.get_blob(cid).await?;
+    // Checked before anything else so taken down and temp blobs are a 404 either way
+    let metadata = actor_store.blob.get_blob_metadata(cid).await?;
+    let etag = format!("\"{cid}\"");
+    if let Some(if_none_match) = &headers.if_none_match {
+        if etag_matches(if_none_match, &etag) {
+            return Ok(BlobResponder::NotModified { etag });
+        }
+    }
+
+    let size = metadata.size as u64;
+    let range = headers
+        .range
+        .as_deref()
+        .and_then(|header| parse_range(header, size));
+    let (start, end) = match range {
+        None => {
+            let found = actor_store.blob.get_blob(cid).await?;
+            let buf: AggregatedBytes = found.stream.collect().await?;
+            return Ok(BlobResponder::Full {
+                bytes: buf.to_vec(),
+                mime_type: found
+                    .mime_type
+                    .unwrap_or("application/octet-stream".to_string()),
+                etag,
+            });
+        }
+        Some(RangeRequest::Unsatisfiable) => {
+            return Ok(BlobResponder::Unsatisfiable { etag, size });
+        }
+        Some(RangeRequest::Satisfiable { start, end }) => (start, end),
+    };
+    let found = actor_store
+        .blob
+        .get_blob_range(cid, Some((start, end)))
+        .await?;
     let buf: AggregatedBytes = found.stream.collect().await?;
-    Ok((buf.to_vec(), found.mime_type))
+    Ok(BlobResponder::Partial {
+        bytes: buf.to_vec(),
+        mime_type: found
+            .mime_type
+            .unwrap_or("application/octet-stream".to_string()),
+        etag,
+        content_range: format!("bytes {start}-{end}/{size}"),
+    })
 }
 
-/// Get a blob associated with a given account. Returns the full blob as originally uploaded.
-/// Does not require auth; implemented by PDS.
+/// Get a blob associated with a given account. Returns the full blob as originally uploaded,
+/// or a single byte range of it. Does not require auth; implemented by PDS.
 #[rocket::get("/xrpc/com.atproto.sync.getBlob?<did>&<cid>")]
 pub async fn get_blob(
     did: String,
     cid: String,
     s3_config: &State<SdkConfig>,
     auth: OptionalAccessOrAdminToken,
+    headers: BlobRequestHeaders,
 ) -> Result<BlobResponder, status::Custom<Json<ErrorMessageResponse>>> {
-    match inner_get_blob(did, cid, s3_config, auth).await {
-        Ok(res) => {
-            let (bytes, mime_type) = res;
-            Ok(BlobResponder(
-                bytes.clone(),
-                Header::new("content-length", bytes.len().to_string()),
-                Header::new(
-                    "content-type",
-                    mime_type.unwrap_or("application/octet-stream".to_string()),
-                ),
-                Header::new("content-security-policy", "default-src 'none'; sandbox"),
-            ))
-        }
+    match inner_get_blob(did, cid, s3_config, auth, headers).await {
+        Ok(res) => Ok(res),
         Err(error) => {
-            return match error.downcast_ref() {
-                Some(GetObjectError::NoSuchKey(_)) => {
-                    eprintln!("Error: {}", error);
-                    let internal_error = ErrorMessageResponse {
-                        code: Some(ErrorCode::NotFound),
-                        message: Some("cannot find blob".to_owned()),
-                    };
-                    Err(status::Custom(Status::NotFound, Json(internal_error)))
-                }
-                _ => {
-                    eprintln!("Error: {}", error);
-                    let internal_error = ErrorMessageResponse {
-                        code: Some(ErrorCode::InternalServerError),
-                        message: Some(error.to_string()),
-                    };
-                    Err(status::Custom(
-                        Status::InternalServerError,
-                        Json(internal_error),
-                    ))
-                }
+            eprintln!("Error: {}", error);
+            let not_found = error.downcast_ref::<BlobError>().is_some()
+                || matches!(error.downcast_ref(), Some(GetObjectError::NoSuchKey(_)));
+            if not_found {
+                let not_found = ErrorMessageResponse {
+                    code: Some(ErrorCode::NotFound),
+                    message: Some("cannot find blob".to_owned()),
+                };
+                return Err(status::Custom(Status::NotFound, Json(not_found)));
+            }
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
             };
-            // @TODO: Need to update error handling to return 404 if we have it but it's in tmp
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfiable(start: u64, end: u64) -> Option<RangeRequest> {
+        Some(RangeRequest::Satisfiable { start, end })
+    }
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-99", 1000), satisfiable(0, 99));
+        assert_eq!(parse_range(" bytes= 10 - 19 ", 1000), satisfiable(10, 19));
+        assert_eq!(parse_range("bytes=500-", 1000), satisfiable(500, 999));
+        // The end is clamped to the last byte
+        assert_eq!(parse_range("bytes=900-5000", 1000), satisfiable(900, 999));
+    }
+
+    #[test]
+    fn parses_suffix_ranges() {
+        assert_eq!(parse_range("bytes=-100", 1000), satisfiable(900, 999));
+        // Asking for more than there is serves the whole blob
+        assert_eq!(parse_range("bytes=-5000", 1000), satisfiable(0, 999));
+        assert_eq!(
+            parse_range("bytes=-0", 1000),
+            Some(RangeRequest::Unsatisfiable)
+        );
+        assert_eq!(
+            parse_range("bytes=-10", 0),
+            Some(RangeRequest::Unsatisfiable)
+        );
+    }
+
+    #[test]
+    fn flags_unsatisfiable_ranges() {
+        assert_eq!(
+            parse_range("bytes=1000-", 1000),
+            Some(RangeRequest::Unsatisfiable)
+        );
+        assert_eq!(
+            parse_range("bytes=1000-1999", 1000),
+            Some(RangeRequest::Unsatisfiable)
+        );
+        assert_eq!(
+            parse_range("bytes=0-", 0),
+            Some(RangeRequest::Unsatisfiable)
+        );
+    }
+
+    #[test]
+    fn ignores_unsupported_ranges() {
+        for header in [
+            "bytes=0-1,5-6",
+            "bytes=20-10",
+            "bytes=-",
+            "bytes=a-b",
+            "bytes=0",
+            "items=0-10",
+            "bytes=--1",
+        ] {
+            assert_eq!(parse_range(header, 1000), None, "{header}");
+        }
+    }
+
+    #[test]
+    fn matches_etags() {
+        let etag = "\"bafkreiabc\"";
+        assert!(etag_matches("\"bafkreiabc\"", etag));
+        assert!(etag_matches("W/\"bafkreiabc\"", etag));
+        assert!(etag_matches("\"other\", \"bafkreiabc\"", etag));
+        assert!(etag_matches("*", etag));
+        assert!(!etag_matches("\"other\"", etag));
+        assert!(!etag_matches("bafkreiabc", etag));
+        assert!(!etag_matches("", etag));
+    }
+}
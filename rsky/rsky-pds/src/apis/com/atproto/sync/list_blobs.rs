@@ -1,9 +1,10 @@
 use crate::apis::com::atproto::repo::assert_repo_availability;
 use crate::auth_verifier;
 use crate::auth_verifier::OptionalAccessOrAdminToken;
+use crate::db::pagination::KeySet;
 use crate::models::{ErrorCode, ErrorMessageResponse};
 use crate::repo::aws::s3::S3BlobStore;
-use crate::repo::blob::ListBlobsOpts;
+use crate::repo::blob::{ListBlobsOpts, LIST_BLOBS_KEYSET};
 use crate::repo::ActorStore;
 use anyhow::Result;
 use aws_config::SdkConfig;
@@ -38,12 +39,8 @@ async fn inner_list_blobs(
         })
         .await?;
 
-    let last_blob: Option<String> = match blob_cids.last() {
-        None => None,
-        Some(last) => Some(last.clone()),
-    };
     Ok(ListBlobsOutput {
-        cursor: last_blob,
+        cursor: LIST_BLOBS_KEYSET.pack_from_result(&blob_cids)?,
         cids: blob_cids,
     })
 }
@@ -1 +1,2 @@
 pub mod atproto;
+pub mod rsky;
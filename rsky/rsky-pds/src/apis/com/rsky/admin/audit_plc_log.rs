@@ -0,0 +1,71 @@
+use crate::apis::com::atproto::server::{encode_did_key, get_keys_from_private_key_str};
+use crate::auth_verifier::Moderator;
+use crate::config::ServerConfig;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::plc;
+use crate::plc::audit::{audit_log, AuditOpts};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::admin::{AuditPlcLogOutput, PlcAuditFinding};
+use std::env;
+
+async fn inner_audit_plc_log(did: String, cfg: &State<ServerConfig>) -> Result<AuditPlcLogOutput> {
+    if !did.starts_with("did:plc:") {
+        bail!("Can only audit did:plc identities")
+    }
+    let plc_client = plc::Client::new(cfg.identity.plc_url.clone());
+    let log = plc_client.get_audit_log(&did).await?;
+
+    let private_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX")?;
+    let (_, plc_rotation_key) = get_keys_from_private_key_str(private_key)?;
+    let mut trusted_rotation_keys = vec![encode_did_key(&plc_rotation_key)];
+    if let Some(recovery_did_key) = &cfg.identity.recovery_did_key {
+        trusted_rotation_keys.push(recovery_did_key.clone());
+    }
+    let findings = audit_log(
+        &log,
+        &AuditOpts {
+            trusted_rotation_keys,
+            pds_endpoint: cfg.service.public_url.clone(),
+        },
+    )?;
+    Ok(AuditPlcLogOutput {
+        did,
+        op_count: log.len(),
+        findings: findings
+            .into_iter()
+            .map(|finding| PlcAuditFinding {
+                cid: finding.cid,
+                kind: finding.kind.as_str().to_string(),
+                message: finding.message,
+                created_at: finding.created_at,
+                recoverable: finding.recoverable,
+            })
+            .collect(),
+    })
+}
+
+#[rocket::get("/xrpc/com.rsky.admin.auditPlcLog?<did>")]
+pub async fn audit_plc_log(
+    did: String,
+    cfg: &State<ServerConfig>,
+    _auth: Moderator,
+) -> Result<Json<AuditPlcLogOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_audit_plc_log(did, cfg).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
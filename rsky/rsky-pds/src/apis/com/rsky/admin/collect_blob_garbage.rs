@@ -0,0 +1,59 @@
+use crate::auth_verifier::AdminToken;
+use crate::config::ServerConfig;
+use crate::jobs::blob_gc::{collect_blob_garbage as collect, BlobGcOpts};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::admin::{BlobGcReport, CollectBlobGarbageInput};
+
+async fn inner_collect_blob_garbage(
+    body: Json<CollectBlobGarbageInput>,
+    cfg: &State<ServerConfig>,
+    s3_config: &State<SdkConfig>,
+) -> Result<BlobGcReport> {
+    let CollectBlobGarbageInput {
+        dry_run,
+        min_age_ms,
+    } = body.into_inner();
+    collect(
+        s3_config,
+        BlobGcOpts {
+            dry_run: dry_run.unwrap_or(true),
+            min_age_ms: min_age_ms.unwrap_or(cfg.jobs.blob_gc_min_age_ms),
+        },
+    )
+    .await
+}
+
+/// Run the blob garbage collector now. Reports what would be deleted unless `dryRun`
+/// is explicitly false.
+#[rocket::post(
+    "/xrpc/com.rsky.admin.collectBlobGarbage",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn collect_blob_garbage(
+    body: Json<CollectBlobGarbageInput>,
+    cfg: &State<ServerConfig>,
+    s3_config: &State<SdkConfig>,
+    _auth: AdminToken,
+) -> Result<Json<BlobGcReport>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_collect_blob_garbage(body, cfg, s3_config).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
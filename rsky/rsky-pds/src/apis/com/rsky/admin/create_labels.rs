@@ -0,0 +1,53 @@
+use crate::auth_verifier::AdminToken;
+use crate::config::ServerConfig;
+use crate::labeler;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::admin::{CreateLabelsInput, CreateLabelsOutput};
+
+async fn inner_create_labels(
+    body: Json<CreateLabelsInput>,
+    cfg: &State<ServerConfig>,
+) -> Result<CreateLabelsOutput> {
+    let CreateLabelsInput { labels } = body.into_inner();
+    let labeler_cfg = match &cfg.labeler {
+        Some(labeler_cfg) => labeler_cfg,
+        None => bail!("Labeler is not configured"),
+    };
+    if labels.is_empty() {
+        bail!("At least one label is required")
+    }
+    if labels.len() > 100 {
+        bail!("Can not create more than 100 labels at once")
+    }
+    Ok(CreateLabelsOutput {
+        labels: labeler::create_labels(labeler_cfg.did.clone(), labels).await?,
+    })
+}
+
+/// Sign labels with this service's labeler key and publish them to `subscribeLabels`.
+#[rocket::post("/xrpc/com.rsky.admin.createLabels", format = "json", data = "<body>")]
+pub async fn create_labels(
+    body: Json<CreateLabelsInput>,
+    cfg: &State<ServerConfig>,
+    _auth: AdminToken,
+) -> Result<Json<CreateLabelsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_create_labels(body, cfg).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use crate::auth_verifier::Moderator;
+use crate::jobs::JobScheduler;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::admin::GetJobStatusesOutput;
+
+/// Status of the PDS's background jobs, such as purging accounts past their `deleteAfter`.
+#[rocket::get("/xrpc/com.rsky.admin.getJobStatuses")]
+pub async fn get_job_statuses(
+    scheduler: &State<JobScheduler>,
+    _auth: Moderator,
+) -> Json<GetJobStatusesOutput> {
+    Json(GetJobStatusesOutput {
+        jobs: scheduler.statuses().await,
+    })
+}
@@ -0,0 +1,57 @@
+use crate::auth_verifier::Moderator;
+use crate::db::pagination::KeySet;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::moderation::{list_reports as list_local_reports, ListReportsOpts, ReportIdKeySet};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::admin::ListReportsOutput;
+
+async fn inner_list_reports(
+    resolved: Option<bool>,
+    subject: Option<String>,
+    limit: u16,
+    cursor: Option<String>,
+) -> Result<ListReportsOutput> {
+    if limit > 100 {
+        bail!("Error: limit can not be greater than 100")
+    }
+    let reports = list_local_reports(ListReportsOpts {
+        resolved,
+        subject,
+        limit: limit as i64,
+        cursor,
+    })
+    .await?;
+    Ok(ListReportsOutput {
+        cursor: ReportIdKeySet {}.pack_from_result(&reports)?,
+        reports,
+    })
+}
+
+/// List moderation reports stored on this PDS, for instances without a report service.
+#[rocket::get("/xrpc/com.rsky.admin.listReports?<resolved>&<subject>&<limit>&<cursor>")]
+pub async fn list_reports(
+    resolved: Option<bool>,
+    subject: Option<String>,
+    limit: Option<u16>,
+    cursor: Option<String>,
+    _auth: Moderator,
+) -> Result<Json<ListReportsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let limit = limit.unwrap_or(50);
+    match inner_list_reports(resolved, subject, limit, cursor).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
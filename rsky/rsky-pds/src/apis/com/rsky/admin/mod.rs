@@ -0,0 +1,7 @@
+pub mod audit_plc_log;
+pub mod collect_blob_garbage;
+pub mod create_labels;
+pub mod get_job_statuses;
+pub mod list_reports;
+pub mod resolve_report;
+pub mod set_account_quota;
@@ -0,0 +1,43 @@
+use crate::auth_verifier::Moderator;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::moderation;
+use anyhow::Result;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::admin::{ReportView, ResolveReportInput};
+
+async fn inner_resolve_report(
+    body: Json<ResolveReportInput>,
+    auth: Moderator,
+) -> Result<ReportView> {
+    let ResolveReportInput { id, note } = body.into_inner();
+    // Mod services authenticate with their own did, the admin password has none
+    let resolved_by = match auth.access.credentials {
+        Some(credentials) => credentials.did,
+        None => None,
+    }
+    .unwrap_or("admin".to_string());
+    moderation::resolve_report(id, resolved_by, note).await
+}
+
+#[rocket::post("/xrpc/com.rsky.admin.resolveReport", format = "json", data = "<body>")]
+pub async fn resolve_report(
+    body: Json<ResolveReportInput>,
+    auth: Moderator,
+) -> Result<Json<ReportView>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_resolve_report(body, auth).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::get_account_usage::get_account_usage_output;
+use crate::auth_verifier::AdminToken;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::quota;
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::admin::SetAccountQuotaInput;
+use rsky_lexicon::com::rsky::server::{AccountLimits, GetAccountUsageOutput};
+
+async fn inner_set_account_quota(
+    body: Json<SetAccountQuotaInput>,
+) -> Result<GetAccountUsageOutput> {
+    let SetAccountQuotaInput {
+        did,
+        max_blob_bytes,
+        max_records,
+        max_repo_bytes,
+    } = body.into_inner();
+    let account = AccountManager::get_account(
+        &did,
+        Some(AvailabilityFlags {
+            include_taken_down: Some(true),
+            include_deactivated: Some(true),
+        }),
+    )
+    .await?;
+    if account.is_none() {
+        bail!("Account not found")
+    }
+    if [max_blob_bytes, max_records, max_repo_bytes]
+        .iter()
+        .any(|limit| limit.is_some_and(|limit| limit < 0))
+    {
+        bail!("Quotas can not be negative")
+    }
+    quota::set_limits(
+        &did,
+        AccountLimits {
+            max_blob_bytes,
+            max_records,
+            max_repo_bytes,
+        },
+    )
+    .await?;
+    get_account_usage_output(did).await
+}
+
+/// Override an account's storage quotas and return its resulting usage and limits.
+#[rocket::post(
+    "/xrpc/com.rsky.admin.setAccountQuota",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn set_account_quota(
+    body: Json<SetAccountQuotaInput>,
+    _auth: AdminToken,
+) -> Result<Json<GetAccountUsageOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_set_account_quota(body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1 @@
+pub mod nullify_plc_operation;
@@ -0,0 +1,67 @@
+use crate::auth_verifier::AccessFull;
+use crate::config::ServerConfig;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::plc::types::Operation;
+use crate::{plc, SharedSequencer};
+use anyhow::Result;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::identity::{NullifyPlcOperationInput, NullifyPlcOperationOutput};
+
+async fn inner_nullify_plc_operation(
+    body: Json<NullifyPlcOperationInput>,
+    sequencer: &State<SharedSequencer>,
+    cfg: &State<ServerConfig>,
+    auth: AccessFull,
+) -> Result<NullifyPlcOperationOutput> {
+    let NullifyPlcOperationInput { cid, operation } = body.into_inner();
+    let requester = auth.access.credentials.unwrap().did.unwrap();
+    let plc_client = plc::Client::new(cfg.identity.plc_url.clone());
+
+    // The rotation key never leaves the client: first hand back the op to sign, then
+    // submit it once signed
+    let Some(operation) = operation else {
+        let op = plc_client.create_nullify_op(&requester, &cid).await?;
+        return Ok(NullifyPlcOperationOutput {
+            operation: serde_json::to_value(op)?,
+        });
+    };
+    let op: Operation = serde_json::from_value(operation.clone())?;
+    plc_client.nullify_op(&requester, &cid, op).await?;
+
+    let mut lock = sequencer.sequencer.write().await;
+    match lock.sequence_identity_evt(requester.clone(), None).await {
+        Ok(_) => (),
+        Err(error) => eprintln!("Error: {}; DID: {}", error.to_string(), &requester),
+    };
+    Ok(NullifyPlcOperationOutput { operation })
+}
+
+#[rocket::post(
+    "/xrpc/com.rsky.identity.nullifyPlcOperation",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn nullify_plc_operation(
+    body: Json<NullifyPlcOperationInput>,
+    sequencer: &State<SharedSequencer>,
+    cfg: &State<ServerConfig>,
+    auth: AccessFull,
+) -> Result<Json<NullifyPlcOperationOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_nullify_plc_operation(body, sequencer, cfg, auth).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
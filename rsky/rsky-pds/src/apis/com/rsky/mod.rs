@@ -0,0 +1,4 @@
+pub mod admin;
+pub mod identity;
+pub mod repo;
+pub mod server;
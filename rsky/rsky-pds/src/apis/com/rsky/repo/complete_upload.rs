@@ -0,0 +1,44 @@
+use crate::apis::com::atproto::repo::upload_blob::track_uploaded_blob;
+use crate::apis::com::rsky::repo::upload_error_response;
+use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::models::ErrorMessageResponse;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::ActorStore;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::atproto::repo::BlobOutput;
+use rsky_lexicon::com::rsky::repo::CompleteUploadInput;
+
+async fn inner_complete_upload(
+    body: Json<CompleteUploadInput>,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<BlobOutput> {
+    let requester = auth.access.credentials.unwrap().did.unwrap();
+    let actor_store = ActorStore::new(
+        requester.clone(),
+        S3BlobStore::new(requester.clone(), s3_config),
+    );
+    let metadata = actor_store
+        .blob
+        .complete_upload(&body.into_inner().upload_id)
+        .await?;
+    track_uploaded_blob(&actor_store, &requester, metadata).await
+}
+
+/// Finishes a resumable upload once every chunk is in. Returns the same blob ref as
+/// com.atproto.repo.uploadBlob, to be used in a record.
+#[rocket::post("/xrpc/com.rsky.repo.completeUpload", format = "json", data = "<body>")]
+pub async fn complete_upload(
+    body: Json<CompleteUploadInput>,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<Json<BlobOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_complete_upload(body, auth, s3_config).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(upload_error_response(error)),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::apis::com::rsky::repo::{upload_error_response, upload_status};
+use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::models::ErrorMessageResponse;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::ActorStore;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::repo::{CreateUploadInput, UploadStatus};
+
+async fn inner_create_upload(
+    body: Json<CreateUploadInput>,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<UploadStatus> {
+    let CreateUploadInput {
+        size,
+        mime_type,
+        cid,
+    } = body.into_inner();
+    let requester = auth.access.credentials.unwrap().did.unwrap();
+
+    let actor_store = ActorStore::new(
+        requester.clone(),
+        S3BlobStore::new(requester.clone(), s3_config),
+    );
+    let upload = actor_store.blob.create_upload(size, mime_type, cid).await?;
+    Ok(upload_status(upload))
+}
+
+/// Starts a resumable blob upload, for blobs too big or connections too flaky for
+/// com.atproto.repo.uploadBlob. Send the chunks to com.rsky.repo.uploadChunk, then finish
+/// with com.rsky.repo.completeUpload. The completed blob must match `cid`.
+#[rocket::post("/xrpc/com.rsky.repo.createUpload", format = "json", data = "<body>")]
+pub async fn create_upload(
+    body: Json<CreateUploadInput>,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<Json<UploadStatus>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_create_upload(body, auth, s3_config).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(upload_error_response(error)),
+    }
+}
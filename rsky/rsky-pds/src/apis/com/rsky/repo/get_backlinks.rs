@@ -0,0 +1,73 @@
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::record::{backlinks_cursor, list_backlinks, ListBacklinksOpts};
+use anyhow::Result;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::repo::{Backlink, GetBacklinksOutput};
+
+async fn inner_get_backlinks(opts: ListBacklinksOpts) -> Result<GetBacklinksOutput> {
+    let subject = opts.subject.clone();
+    let records = list_backlinks(opts).await?;
+    let cursor = backlinks_cursor(&records)?;
+    let backlinks = records
+        .into_iter()
+        .map(|(record, path)| Backlink {
+            uri: record.uri,
+            cid: record.cid,
+            collection: record.collection,
+            path,
+            indexed_at: record.indexed_at,
+        })
+        .collect::<Vec<Backlink>>();
+    Ok(GetBacklinksOutput {
+        cursor,
+        subject,
+        backlinks,
+    })
+}
+
+/// Records hosted on this PDS that link to a subject: likes, reposts, replies and quotes
+/// of a post, follows, blocks and list items of an account. Filter by the collection of
+/// the linking record and by the path of the link in it, e.g. `subject.uri`,
+/// `reply.parent.uri`, `reply.root.uri`, `embed.record.uri` or `subject`.
+#[rocket::get("/xrpc/com.rsky.repo.getBacklinks?<subject>&<collection>&<path>&<limit>&<cursor>")]
+pub async fn get_backlinks(
+    // An at-uri or did
+    subject: String,
+    collection: Option<String>,
+    path: Option<String>,
+    limit: Option<u8>,
+    cursor: Option<String>,
+) -> Result<Json<GetBacklinksOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let limit = limit.unwrap_or(50);
+    if limit > 100 || limit < 1 || !(subject.starts_with("at://") || subject.starts_with("did:")) {
+        let bad_request = ErrorMessageResponse {
+            code: Some(ErrorCode::BadRequest),
+            message: Some("subject must be an at-uri or did, limit between 1 and 100".to_string()),
+        };
+        return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+    }
+    match inner_get_backlinks(ListBacklinksOpts {
+        subject,
+        collection,
+        path,
+        limit: limit as i64,
+        cursor,
+    })
+    .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
+        }
+    }
+}
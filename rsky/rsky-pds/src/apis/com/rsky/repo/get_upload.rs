@@ -0,0 +1,39 @@
+use crate::apis::com::rsky::repo::{upload_error_response, upload_status};
+use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::models::ErrorMessageResponse;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::ActorStore;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::repo::UploadStatus;
+
+async fn inner_get_upload(
+    upload_id: String,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<UploadStatus> {
+    let requester = auth.access.credentials.unwrap().did.unwrap();
+    let actor_store = ActorStore::new(
+        requester.clone(),
+        S3BlobStore::new(requester.clone(), s3_config),
+    );
+    let upload = actor_store.blob.get_upload(&upload_id).await?;
+    Ok(upload_status(upload))
+}
+
+/// The offset a resumable upload is at, where a client picks up after losing its connection
+#[rocket::get("/xrpc/com.rsky.repo.getUpload?<uploadId>")]
+#[allow(non_snake_case)]
+pub async fn get_upload(
+    uploadId: String,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<Json<UploadStatus>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_get_upload(uploadId, auth, s3_config).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(upload_error_response(error)),
+    }
+}
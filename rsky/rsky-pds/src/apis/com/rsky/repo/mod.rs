@@ -0,0 +1,50 @@
+use crate::config::SERVER_CONFIG;
+use crate::image::ImageError;
+use crate::models::models;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::blob::upload::UploadError;
+use crate::repo::quota::QuotaError;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::repo::UploadStatus;
+
+pub mod complete_upload;
+pub mod create_upload;
+pub mod get_backlinks;
+pub mod get_upload;
+pub mod upload_chunk;
+
+pub fn upload_status(upload: models::BlobUpload) -> UploadStatus {
+    UploadStatus {
+        upload_id: upload.id,
+        size: upload.size,
+        offset: upload.received,
+        max_chunk_size: SERVER_CONFIG.uploads.max_chunk_size as i64,
+        expires_at: upload.expires_at,
+    }
+}
+
+/// Resumable upload failures the client can act on: a missing upload is a 404, a chunk
+/// at the wrong offset a 409 and the rest a 400
+pub fn upload_error_response(error: anyhow::Error) -> status::Custom<Json<ErrorMessageResponse>> {
+    eprintln!("@LOG: ERROR: {error}");
+    let (status, code) = match error.downcast_ref::<UploadError>() {
+        Some(UploadError::NotFound(_)) => (Status::NotFound, ErrorCode::NotFound),
+        Some(UploadError::OffsetMismatch { .. }) => (Status::Conflict, ErrorCode::Conflict),
+        Some(_) => (Status::BadRequest, ErrorCode::BadRequest),
+        None if error.downcast_ref::<QuotaError>().is_some()
+            || error.downcast_ref::<ImageError>().is_some() =>
+        {
+            (Status::BadRequest, ErrorCode::BadRequest)
+        }
+        None => (Status::InternalServerError, ErrorCode::InternalServerError),
+    };
+    status::Custom(
+        status,
+        Json(ErrorMessageResponse {
+            code: Some(code),
+            message: Some(error.to_string()),
+        }),
+    )
+}
@@ -0,0 +1,59 @@
+use crate::apis::com::rsky::repo::{upload_error_response, upload_status};
+use crate::auth_verifier::AccessStandardIncludeChecks;
+use crate::config::SERVER_CONFIG;
+use crate::models::ErrorMessageResponse;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::blob::upload::UploadError;
+use crate::repo::ActorStore;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use rocket::data::{Data, ToByteUnit};
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::com::rsky::repo::UploadStatus;
+
+async fn inner_upload_chunk(
+    upload_id: String,
+    offset: i64,
+    chunk: Data<'_>,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<UploadStatus> {
+    let requester = auth.access.credentials.unwrap().did.unwrap();
+    let max = SERVER_CONFIG.uploads.max_chunk_size;
+    let chunk = chunk.open(max.bytes()).into_bytes().await?;
+    if !chunk.is_complete() {
+        bail!(UploadError::ChunkTooLarge { max })
+    }
+
+    let actor_store = ActorStore::new(
+        requester.clone(),
+        S3BlobStore::new(requester.clone(), s3_config),
+    );
+    let upload = actor_store
+        .blob
+        .append_upload_chunk(&upload_id, offset, chunk.into_inner())
+        .await?;
+    Ok(upload_status(upload))
+}
+
+/// Appends the request body to a resumable upload. `offset` has to be the upload's current
+/// offset; otherwise this fails with a 409 and the client should check com.rsky.repo.getUpload.
+#[rocket::post(
+    "/xrpc/com.rsky.repo.uploadChunk?<uploadId>&<offset>",
+    data = "<chunk>"
+)]
+#[allow(non_snake_case)]
+pub async fn upload_chunk(
+    uploadId: String,
+    offset: i64,
+    chunk: Data<'_>,
+    auth: AccessStandardIncludeChecks,
+    s3_config: &State<SdkConfig>,
+) -> Result<Json<UploadStatus>, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_upload_chunk(uploadId, offset, chunk, auth, s3_config).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(upload_error_response(error)),
+    }
+}
@@ -0,0 +1,148 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::safe_resolve_did_doc;
+use crate::archive::{
+    blob_path, tar_end, tar_entry, ArchiveBlob, ArchiveManifest, ARCHIVE_VERSION, MANIFEST_PATH,
+    PREFERENCES_PATH, REPO_PATH,
+};
+use crate::auth_verifier::AccessFull;
+use crate::common;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::ActorStore;
+use crate::SharedIdResolver;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use lexicon_cid::Cid;
+use rocket::http::{ContentType, Status};
+use rocket::response::status;
+use rocket::response::stream::ByteStream;
+use rocket::serde::json::Json;
+use rocket::State;
+use rsky_lexicon::app::bsky::actor::GetPreferencesOutput;
+use std::str::FromStr;
+
+/// Everything in the archive except the blobs, which are streamed one at a time
+struct PreparedExport {
+    head: Vec<u8>,
+    blobs: Vec<ArchiveBlob>,
+}
+
+async fn prepare_export(
+    did: &String,
+    auth: AccessFull,
+    s3_config: &State<SdkConfig>,
+    id_resolver: &State<SharedIdResolver>,
+) -> Result<PreparedExport> {
+    let account = AccountManager::get_account(
+        did,
+        Some(AvailabilityFlags {
+            include_deactivated: Some(true),
+            include_taken_down: None,
+        }),
+    )
+    .await?;
+    let Some(account) = account else {
+        bail!("Could not find account: `{did}`")
+    };
+    let did_doc = safe_resolve_did_doc(id_resolver, did, None).await?;
+
+    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config));
+    let rev = actor_store.storage.get_root_detailed().await?.rev;
+    let car = actor_store.storage.get_car_stream(None).await?;
+    let preferences = actor_store
+        .pref
+        .get_preferences(
+            Some("app.bsky".to_string()),
+            auth.access.credentials.unwrap().scope.unwrap(),
+        )
+        .await?;
+
+    let mut blobs: Vec<ArchiveBlob> = Vec::new();
+    for cid in actor_store.blob.list_record_blob_cids().await? {
+        // Blobs referenced by records but never uploaded aren't in the archive
+        match actor_store
+            .blob
+            .get_blob_metadata(Cid::from_str(&cid)?)
+            .await
+        {
+            Ok(metadata) => blobs.push(ArchiveBlob {
+                cid,
+                mime_type: metadata
+                    .mime_type
+                    .unwrap_or("application/octet-stream".to_string()),
+                size: metadata.size,
+            }),
+            Err(_) => continue,
+        }
+    }
+
+    let manifest = ArchiveManifest {
+        version: ARCHIVE_VERSION,
+        did: did.clone(),
+        handle: account.handle,
+        did_doc,
+        rev,
+        exported_at: common::now(),
+        blobs: blobs.clone(),
+    };
+    let mut head = tar_entry(MANIFEST_PATH, &serde_json::to_vec_pretty(&manifest)?)?;
+    head.append(&mut tar_entry(REPO_PATH, &car)?);
+    head.append(&mut tar_entry(
+        PREFERENCES_PATH,
+        &serde_json::to_vec_pretty(&GetPreferencesOutput { preferences })?,
+    )?);
+    Ok(PreparedExport { head, blobs })
+}
+
+async fn read_blob(did: &String, cid: &String, s3_config: &SdkConfig) -> Result<Vec<u8>> {
+    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config));
+    let found = actor_store.blob.get_blob(Cid::from_str(cid)?).await?;
+    Ok(found.stream.collect().await?.to_vec())
+}
+
+/// Download a full archive of the account as a tar: manifest.json (DID doc, handle and blob
+/// list), repo.car, preferences.json and every blob under blobs/. If a blob can't be read the
+/// tar is cut short, leaving fewer blobs than the manifest lists. Requires auth.
+#[rocket::get("/xrpc/com.rsky.server.exportAccount")]
+pub async fn export_account(
+    auth: AccessFull,
+    s3_config: &State<SdkConfig>,
+    id_resolver: &State<SharedIdResolver>,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), status::Custom<Json<ErrorMessageResponse>>> {
+    let did = auth.access.credentials.clone().unwrap().did.unwrap();
+    match prepare_export(&did, auth, s3_config, id_resolver).await {
+        Ok(PreparedExport { head, blobs }) => {
+            let s3_config = s3_config.inner().clone();
+            let stream = ByteStream! {
+                yield head;
+                for blob in blobs {
+                    let entry = match read_blob(&did, &blob.cid, &s3_config).await {
+                        Ok(bytes) => tar_entry(&blob_path(&blob.cid), &bytes),
+                        Err(error) => Err(error),
+                    };
+                    match entry {
+                        Ok(entry) => yield entry,
+                        Err(error) => {
+                            eprintln!("@LOG: ERROR: export of {did} failed at blob {}: {error}", blob.cid);
+                            return;
+                        }
+                    }
+                }
+                yield tar_end();
+            };
+            Ok((ContentType::new("application", "x-tar"), stream))
+        }
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
+        }
+    }
+}
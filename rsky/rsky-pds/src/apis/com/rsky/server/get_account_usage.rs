@@ -0,0 +1,38 @@
+use crate::auth_verifier::AccessStandard;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::quota;
+use anyhow::Result;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::server::GetAccountUsageOutput;
+
+pub async fn get_account_usage_output(did: String) -> Result<GetAccountUsageOutput> {
+    let (usage, limits) = (
+        quota::get_usage(&did).await?,
+        quota::get_limits(&did).await?,
+    );
+    Ok(GetAccountUsageOutput { did, usage, limits })
+}
+
+/// Storage used by the requesting account and its quotas.
+#[rocket::get("/xrpc/com.rsky.server.getAccountUsage")]
+pub async fn get_account_usage(
+    auth: AccessStandard,
+) -> Result<Json<GetAccountUsageOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match get_account_usage_output(did).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::account_manager::AccountManager;
+use crate::auth_verifier::AccessFull;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::server::{ListSessionsOutput, Session};
+
+#[rocket::get("/xrpc/com.rsky.server.listSessions")]
+pub async fn list_sessions(
+    auth: AccessFull,
+) -> Result<Json<ListSessionsOutput>, status::Custom<Json<ErrorMessageResponse>>> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match AccountManager::list_sessions(&did).await {
+        Ok(sessions) => {
+            let sessions: Vec<Session> = sessions
+                .into_iter()
+                .map(|session| Session {
+                    id: session.session_id,
+                    created_at: session.created_at,
+                    last_refreshed_at: session.last_refreshed_at,
+                    expires_at: session.expires_at,
+                    app_password_name: session.app_password_name,
+                    user_agent: session.user_agent,
+                    ip: session.ip,
+                })
+                .collect();
+            Ok(Json(ListSessionsOutput { sessions }))
+        }
+        Err(error) => {
+            eprintln!("Internal Error: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some("Internal error".to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod export_account;
+pub mod get_account_usage;
+pub mod list_sessions;
+pub mod revoke_session;
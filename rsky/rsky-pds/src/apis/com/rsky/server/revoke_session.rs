@@ -0,0 +1,64 @@
+use crate::account_manager::AccountManager;
+use crate::auth_verifier::AccessFull;
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use anyhow::{bail, Result};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rsky_lexicon::com::rsky::server::RevokeSessionInput;
+
+async fn inner_revoke_session(body: Json<RevokeSessionInput>, auth: AccessFull) -> Result<()> {
+    let RevokeSessionInput { id } = body.into_inner();
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match AccountManager::revoke_session(&did, &id).await? {
+        true => Ok(()),
+        false => bail!("Session not found"),
+    }
+}
+
+#[rocket::post(
+    "/xrpc/com.rsky.server.revokeSession",
+    format = "json",
+    data = "<body>"
+)]
+pub async fn revoke_session(
+    body: Json<RevokeSessionInput>,
+    auth: AccessFull,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_revoke_session(body, auth).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
+
+/// Revokes every session of the account, including the caller's own.
+#[rocket::post("/xrpc/com.rsky.server.revokeAllSessions")]
+pub async fn revoke_all_sessions(
+    auth: AccessFull,
+) -> Result<(), status::Custom<Json<ErrorMessageResponse>>> {
+    let did = auth.access.credentials.unwrap().did.unwrap();
+    match AccountManager::revoke_sessions(&did).await {
+        Ok(_) => Ok(()),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            return Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ));
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Account archives: a tar of the repo CAR, every blob referenced by the repo's records, the
+// account's preferences and a manifest with the DID doc and handle. Served by
+// com.rsky.server.exportAccount and restored into a fresh account by the rsky-pds-archive CLI.
+
+use anyhow::Result;
+use rsky_identity::types::DidDocument;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const ARCHIVE_VERSION: u8 = 1;
+pub const MANIFEST_PATH: &str = "manifest.json";
+pub const REPO_PATH: &str = "repo.car";
+pub const PREFERENCES_PATH: &str = "preferences.json";
+pub const BLOBS_DIR: &str = "blobs/";
+
+const TAR_BLOCK_SIZE: usize = 512;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub version: u8,
+    pub did: String,
+    pub handle: Option<String>,
+    pub did_doc: Option<DidDocument>,
+    // Rev of the repo commit in repo.car
+    pub rev: String,
+    pub exported_at: String,
+    pub blobs: Vec<ArchiveBlob>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveBlob {
+    pub cid: String,
+    pub mime_type: String,
+    pub size: i32,
+}
+
+pub fn blob_path(cid: &String) -> String {
+    format!("{BLOBS_DIR}{cid}")
+}
+
+/// A file in the archive: its tar header then its contents, padded to the tar block size.
+/// Entries are built one at a time so blobs can be streamed without buffering the archive.
+pub fn tar_entry(path: &str, bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut header = tar::Header::new_ustar();
+    header.set_path(path)?;
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+    header.set_cksum();
+
+    let padding = (TAR_BLOCK_SIZE - bytes.len() % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+    let mut entry = Vec::with_capacity(TAR_BLOCK_SIZE + bytes.len() + padding);
+    entry.extend_from_slice(header.as_bytes());
+    entry.extend_from_slice(bytes);
+    entry.resize(entry.len() + padding, 0);
+    Ok(entry)
+}
+
+/// Two empty blocks mark the end of a tar archive. A failed export is cut short without them;
+/// compare the blobs in the archive against the manifest to be sure it is complete.
+pub fn tar_end() -> Vec<u8> {
+    vec![0; TAR_BLOCK_SIZE * 2]
+}
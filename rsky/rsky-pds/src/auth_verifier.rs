@@ -3,6 +3,10 @@ use crate::account_manager::helpers::auth::CustomClaimObj;
 use crate::account_manager::AccountManager;
 use crate::common::env::env_str;
 use crate::common::get_verification_material;
+use crate::config::ServerConfig;
+use crate::oauth;
+use crate::oauth::dpop::{DpopManager, UseDpopNonce};
+use crate::oauth::OAuthError;
 use crate::xrpc_server::auth::{verify_jwt as verify_service_jwt_server, ServiceJwtPayload};
 use crate::SharedIdResolver;
 use anyhow::{bail, Result};
@@ -104,6 +108,7 @@ pub struct ValidateAccessTokenOpts {
 pub struct VerifiedServiceJwt {
     pub aud: String,
     pub iss: String,
+    pub lxm: Option<String>,
 }
 
 pub struct BasicAuth {
@@ -119,6 +124,8 @@ pub struct JwtPayload {
     pub exp: Option<Duration>,
     pub iat: Option<Duration>,
     pub jti: Option<String>,
+    /// Session an access token was issued for
+    pub sid: Option<String>,
 }
 
 #[derive(Error, Debug)]
@@ -137,6 +144,8 @@ pub enum AuthError {
     AccountTakedown(String),
     #[error("AccountDeactivated: `{0}`")]
     AccountDeactivated(String),
+    #[error("InvalidDpopToken: `{0}`")]
+    InvalidDpopToken(String),
 }
 
 // verifier guards
@@ -215,6 +224,10 @@ pub async fn access_check<'r>(
                 Status::BadRequest,
                 AuthError::AccountTakedown(error.to_string()),
             )),
+            Some(AuthError::InvalidDpopToken(error)) => Outcome::Error((
+                Status::Unauthorized,
+                AuthError::InvalidDpopToken(error.to_string()),
+            )),
             _ => Outcome::Error((Status::BadRequest, AuthError::BadJwt(error.to_string()))),
         },
     }
@@ -616,7 +629,7 @@ impl<'r> FromRequest<'r> for OptionalAccessOrAdminToken {
     type Error = AuthError;
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        if is_bearer_token(req) {
+        if is_bearer_token(req) || is_dpop_token(req) {
             match AccessFull::from_request(req).await {
                 Outcome::Success(output) => Outcome::Success(OptionalAccessOrAdminToken {
                     access: Some(output.access),
@@ -674,6 +687,9 @@ pub async fn validate_bearer_token<'r>(
     scopes: Vec<AuthScope>,
     verify_options: Option<VerificationOptions>,
 ) -> Result<ValidatedBearer> {
+    if is_dpop_token(request) {
+        return validate_dpop_token(request, scopes).await;
+    }
     let token = bearer_token_from_req(request)?;
     if let Some(token) = token {
         let secp = Secp256k1::new();
@@ -683,9 +699,19 @@ pub async fn validate_bearer_token<'r>(
         let jwt_key = Keypair::from_secret_key(&secp, &secret_key);
         let payload = verify_jwt(token.clone(), jwt_key, verify_options).await?;
         let JwtPayload {
-            sub, aud, scope, ..
+            sub,
+            aud,
+            scope,
+            sid,
+            ..
         } = payload.clone();
         let sub = sub.unwrap();
+        // Access tokens outlive a revoked session unless checked against it
+        if let Some(sid) = sid {
+            if !AccountManager::session_exists(&sub, &sid).await? {
+                bail!("Token has been revoked")
+            }
+        }
         let aud = aud.unwrap();
         if !sub.starts_with("did:") {
             bail!("Malformed token")
@@ -716,7 +742,69 @@ pub async fn validate_bearer_token<'r>(
     }
 }
 
-// @TODO: Implement DPop/OAuth
+/// Validates an OAuth access token presented with the `DPoP` scheme, along with the
+/// DPoP proof that binds it to the client's key.
+pub async fn validate_dpop_token<'r>(
+    request: &'r Request<'_>,
+    scopes: Vec<AuthScope>,
+) -> Result<ValidatedBearer> {
+    let invalid = |message: String| anyhow::Error::new(AuthError::InvalidDpopToken(message));
+
+    let token = match dpop_token_from_req(request) {
+        Some(token) => token,
+        None => bail!("AuthMissing"),
+    };
+    let proof = match request.headers().get_one("DPoP") {
+        Some(proof) => proof,
+        None => return Err(invalid("DPoP proof required".to_string())),
+    };
+    let cfg = match request.guard::<&State<ServerConfig>>().await {
+        Outcome::Success(cfg) => cfg,
+        _ => bail!("Server config unavailable"),
+    };
+    let dpop_manager = match request.guard::<&State<DpopManager>>().await {
+        Outcome::Success(dpop_manager) => dpop_manager,
+        _ => bail!("DPoP manager unavailable"),
+    };
+    let access_token = oauth::verify_access_token(&token)
+        .await
+        .map_err(|error| invalid(error.to_string()))?;
+    let htu = format!("{}{}", cfg.service.public_url, request.uri().path());
+    let verified =
+        match dpop_manager.verify_proof(proof, request.method().as_str(), &htu, Some(&token)) {
+            Ok(verified) => verified,
+            Err(error) => {
+                if let Some(OAuthError::UseDpopNonce(_)) = error.downcast_ref() {
+                    request.local_cache(|| UseDpopNonce(true));
+                }
+                return Err(invalid(error.to_string()));
+            }
+        };
+    if verified.jkt != access_token.jkt {
+        return Err(invalid("DPoP key does not match token binding".to_string()));
+    }
+    let scope = oauth::scope_to_auth_scope(&access_token.scope)
+        .map_err(|error| invalid(error.to_string()))?;
+    if scopes.len() > 0 && !scopes.contains(&scope) {
+        bail!("Bad token scope")
+    }
+    Ok(ValidatedBearer {
+        did: access_token.did.clone(),
+        scope: scope.clone(),
+        audience: Some(cfg.service.did.clone()),
+        token,
+        payload: JwtPayload {
+            scope,
+            sub: Some(access_token.did),
+            aud: None,
+            exp: None,
+            iat: None,
+            jti: Some(access_token.token_id),
+            sid: None,
+        },
+    })
+}
+
 pub async fn validate_access_token<'r>(
     request: &'r Request<'_>,
     scopes: Vec<AuthScope>,
@@ -789,6 +877,18 @@ pub async fn verify_service_jwt<'r>(
     request: &'r Request<'_>,
     id_resolver: &State<SharedIdResolver>,
     opts: ServiceJwtOpts,
+) -> Result<VerifiedServiceJwt> {
+    match bearer_token_from_req(request)? {
+        None => bail!("MissingJwt: missing jwt"),
+        Some(jwt_str) => verify_service_jwt_str(jwt_str, id_resolver, opts).await,
+    }
+}
+
+/// Verifies a service jwt received outside of an http request, e.g. over iroh
+pub async fn verify_service_jwt_str(
+    jwt_str: String,
+    id_resolver: &SharedIdResolver,
+    opts: ServiceJwtOpts,
 ) -> Result<VerifiedServiceJwt> {
     let get_signing_key = |iss: String, force_refresh: bool| -> Result<String> {
         match &opts.iss {
@@ -796,9 +896,10 @@ pub async fn verify_service_jwt<'r>(
             _ => (),
         }
         let parts = iss.split("#").collect::<Vec<&str>>();
-        if let (Some(did), Some(service_id)) = (parts.get(0), parts.get(1)) {
-            let (did, service_id) = (did.to_string(), *service_id);
-            let key_id = if service_id == "atproto_labeler" {
+        // Users sign as their bare did, services as `did#service_id`
+        if let Some(did) = parts.get(0) {
+            let (did, service_id) = (did.to_string(), parts.get(1).copied());
+            let key_id = if service_id == Some("atproto_labeler") {
                 "atproto_label"
             } else {
                 "atproto"
@@ -822,17 +923,13 @@ pub async fn verify_service_jwt<'r>(
         }
     };
 
-    match bearer_token_from_req(request)? {
-        None => bail!("MissingJwt: missing jwt"),
-        Some(jwt_str) => {
-            let payload: ServiceJwtPayload =
-                verify_service_jwt_server(jwt_str, opts.aud, get_signing_key).await?;
-            Ok(VerifiedServiceJwt {
-                iss: payload.iss,
-                aud: payload.aud,
-            })
-        }
-    }
+    let payload: ServiceJwtPayload =
+        verify_service_jwt_server(jwt_str, opts.aud, get_signing_key).await?;
+    Ok(VerifiedServiceJwt {
+        iss: payload.iss,
+        aud: payload.aud,
+        lxm: payload.lxm,
+    })
 }
 
 pub fn is_user_or_admin(auth: AccessOutput, did: &String) -> bool {
@@ -848,6 +945,7 @@ pub fn is_user_or_admin(auth: AccessOutput, did: &String) -> bool {
 
 const BEARER: &str = "Bearer ";
 const BASIC: &str = "Basic ";
+const DPOP: &str = "DPoP ";
 
 pub fn is_bearer_token(request: &Request) -> bool {
     match request.headers().get_one("Authorization") {
@@ -856,6 +954,13 @@ pub fn is_bearer_token(request: &Request) -> bool {
     }
 }
 
+pub fn is_dpop_token(request: &Request) -> bool {
+    match request.headers().get_one("Authorization") {
+        None => false,
+        Some(auth_header) => auth_header.starts_with(DPOP),
+    }
+}
+
 pub fn is_basic_token(request: &Request) -> bool {
     match request.headers().get_one("Authorization") {
         None => false,
@@ -874,6 +979,13 @@ pub fn bearer_token_from_req(request: &Request) -> Result<Option<String>> {
     }
 }
 
+pub fn dpop_token_from_req(request: &Request) -> Option<String> {
+    match request.headers().get_one("authorization") {
+        Some(header) if header.starts_with(DPOP) => Some(header[DPOP.len()..].to_string()),
+        _ => None,
+    }
+}
+
 pub async fn verify_jwt(
     jwt: String,
     jwt_key: Keypair,
@@ -890,6 +1002,7 @@ pub async fn verify_jwt(
         exp: claims.expires_at,
         iat: claims.issued_at,
         jti: claims.jwt_id,
+        sid: claims.custom.sid,
     })
 }
 
@@ -0,0 +1,243 @@
+// Exports an account from a PDS as an archive (see com.rsky.server.exportAccount) and
+// restores one into a fresh account with the same DID.
+//
+//   rsky-pds-archive export <pds url> <handle or did> <archive.tar>
+//   rsky-pds-archive restore <pds url> <handle or did> <archive.tar>
+//
+// The account password is read from RSKY_ARCHIVE_PASSWORD.
+
+#[macro_use]
+extern crate serde_derive;
+
+use anyhow::{bail, Result};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use rsky_lexicon::com::atproto::repo::ListMissingBlobsOutput;
+use rsky_lexicon::com::atproto::server::CreateSessionInput;
+use rsky_pds::archive::{
+    blob_path, ArchiveManifest, BLOBS_DIR, MANIFEST_PATH, PREFERENCES_PATH, REPO_PATH,
+};
+use rsky_pds::APP_USER_AGENT;
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+use std::time::Duration;
+
+const USAGE: &str =
+    "usage: rsky-pds-archive <export|restore> <pds url> <handle or did> <archive.tar>
+the account password is read from RSKY_ARCHIVE_PASSWORD";
+
+// Just the fields we need, since servers differ on the rest (e.g. didDoc)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    access_jwt: String,
+    did: String,
+}
+
+fn create_session(client: &Client, pds: &str, identifier: &str) -> Result<Session> {
+    let Ok(password) = env::var("RSKY_ARCHIVE_PASSWORD") else {
+        bail!("RSKY_ARCHIVE_PASSWORD is not set")
+    };
+    let session = client
+        .post(format!("{pds}/xrpc/com.atproto.server.createSession"))
+        .json(&CreateSessionInput {
+            identifier: identifier.to_string(),
+            password,
+            auth_factor_token: None,
+        })
+        .send()?
+        .error_for_status()?
+        .json::<Session>()?;
+    Ok(session)
+}
+
+fn export(client: &Client, pds: &str, identifier: &str, path: &str) -> Result<()> {
+    let session = create_session(client, pds, identifier)?;
+    let mut res = client
+        .get(format!("{pds}/xrpc/com.rsky.server.exportAccount"))
+        .bearer_auth(&session.access_jwt)
+        .send()?
+        .error_for_status()?;
+    let mut file = File::create(path)?;
+    let written = io::copy(&mut res, &mut file)?;
+
+    // The server stops at the first blob it fails to read, so check against the manifest
+    let mut manifest: Option<ArchiveManifest> = None;
+    let mut blobs = 0;
+    for entry in tar::Archive::new(File::open(path)?).entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().to_string();
+        if entry_path == MANIFEST_PATH {
+            let mut bytes: Vec<u8> = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            manifest = Some(serde_json::from_slice(&bytes)?);
+        } else if entry_path.starts_with(BLOBS_DIR) {
+            blobs += 1;
+        }
+    }
+    let Some(manifest) = manifest else {
+        bail!("Export is missing {MANIFEST_PATH}")
+    };
+    if blobs != manifest.blobs.len() {
+        bail!(
+            "Export is incomplete: {blobs} of {} blobs",
+            manifest.blobs.len()
+        )
+    }
+    println!(
+        "Exported {} to {path}: {written} bytes, {blobs} blobs",
+        session.did
+    );
+    Ok(())
+}
+
+fn post_bytes(
+    client: &Client,
+    pds: &str,
+    session: &Session,
+    nsid: &str,
+    content_type: &str,
+    bytes: Vec<u8>,
+) -> Result<()> {
+    client
+        .post(format!("{pds}/xrpc/{nsid}"))
+        .bearer_auth(&session.access_jwt)
+        .header(CONTENT_TYPE, content_type)
+        .body(bytes)
+        .send()?
+        .error_for_status()?;
+    Ok(())
+}
+
+fn count_missing_blobs(client: &Client, pds: &str, session: &Session) -> Result<usize> {
+    let mut missing = 0;
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut req = client
+            .get(format!("{pds}/xrpc/com.atproto.repo.listMissingBlobs"))
+            .bearer_auth(&session.access_jwt)
+            .query(&[("limit", "1000")]);
+        if let Some(cursor) = &cursor {
+            req = req.query(&[("cursor", cursor)]);
+        }
+        let res = req
+            .send()?
+            .error_for_status()?
+            .json::<ListMissingBlobsOutput>()?;
+        missing += res.blobs.len();
+        match res.cursor {
+            Some(next) if !res.blobs.is_empty() => cursor = Some(next),
+            _ => return Ok(missing),
+        }
+    }
+}
+
+fn restore(client: &Client, pds: &str, identifier: &str, path: &str) -> Result<()> {
+    let session = create_session(client, pds, identifier)?;
+    let mut archive = tar::Archive::new(File::open(path)?);
+    let mut manifest: Option<ArchiveManifest> = None;
+    let mut mime_types: HashMap<String, String> = HashMap::new();
+    let mut uploaded = 0;
+
+    // Entries come in the order the export writes them: manifest, repo, preferences, blobs
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().to_string();
+        let mut bytes: Vec<u8> = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+
+        if entry_path == MANIFEST_PATH {
+            let parsed: ArchiveManifest = serde_json::from_slice(&bytes)?;
+            if parsed.did != session.did {
+                bail!(
+                    "Archive is for {}, but {identifier} is {}",
+                    parsed.did,
+                    session.did
+                )
+            }
+            for blob in &parsed.blobs {
+                mime_types.insert(blob_path(&blob.cid), blob.mime_type.clone());
+            }
+            manifest = Some(parsed);
+            continue;
+        }
+        if manifest.is_none() {
+            bail!("Archive doesn't start with {MANIFEST_PATH}")
+        }
+        if entry_path == REPO_PATH {
+            post_bytes(
+                client,
+                pds,
+                &session,
+                "com.atproto.repo.importRepo",
+                "application/vnd.ipld.car",
+                bytes,
+            )?;
+            println!("Imported repo");
+        } else if entry_path == PREFERENCES_PATH {
+            post_bytes(
+                client,
+                pds,
+                &session,
+                "app.bsky.actor.putPreferences",
+                "application/json",
+                bytes,
+            )?;
+            println!("Restored preferences");
+        } else if let Some(mime_type) = mime_types.get(&entry_path) {
+            post_bytes(
+                client,
+                pds,
+                &session,
+                "com.atproto.repo.uploadBlob",
+                mime_type,
+                bytes,
+            )?;
+            uploaded += 1;
+        } else {
+            eprintln!("Skipping unexpected archive entry {entry_path}");
+        }
+    }
+
+    let missing = count_missing_blobs(client, pds, &session)?;
+    println!(
+        "Restored {} into {pds}: uploaded {uploaded} blobs, {missing} still missing",
+        session.did
+    );
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 5 {
+        eprintln!("{USAGE}");
+        process::exit(2);
+    }
+    let (command, pds, identifier, path) = (
+        args[1].as_str(),
+        args[2].trim_end_matches('/'),
+        args[3].as_str(),
+        args[4].as_str(),
+    );
+    let client = Client::builder()
+        .user_agent(APP_USER_AGENT)
+        // Archives and big blobs can take a while
+        .timeout(Duration::from_secs(60 * 60))
+        .build()
+        .expect("failed to build http client");
+    let result = match command {
+        "export" => export(&client, pds, identifier, path),
+        "restore" => restore(&client, pds, identifier, path),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+    if let Err(error) = result {
+        eprintln!("@LOG: ERROR: {error}");
+        process::exit(1);
+    }
+}
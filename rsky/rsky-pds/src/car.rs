@@ -1,8 +1,9 @@
 use crate::repo::block_map::BlockMap;
 use crate::repo::types::CidAndBytes;
-use crate::vendored::iroh_car::{CarHeader, CarWriter};
-use anyhow::Result;
+use crate::vendored::iroh_car::{CarHeader, CarReader, CarWriter};
+use anyhow::{bail, Result};
 use lexicon_cid::Cid;
+use libipld::{Block, DefaultParams};
 
 pub async fn read_car_bytes(root: Option<&Cid>, blocks: BlockMap) -> Result<Vec<u8>> {
     let roots = match root {
@@ -18,3 +19,19 @@ pub async fn read_car_bytes(root: Option<&Cid>, blocks: BlockMap) -> Result<Vec<
     }
     Ok(car_writer.finish().await?)
 }
+
+/// Reads a CAR with a single root into a BlockMap, checking every block against its cid
+pub async fn read_car_with_root(bytes: Vec<u8>) -> Result<(Cid, BlockMap)> {
+    let mut car_reader = CarReader::new(bytes.as_slice()).await?;
+    let root = match car_reader.header().roots() {
+        [root] => *root,
+        _ => bail!("Expected one root"),
+    };
+    let mut blocks = BlockMap::new();
+    while let Some((cid, bytes)) = car_reader.next_block().await? {
+        // Block::new fails if the bytes don't hash to the cid
+        let block = Block::<DefaultParams>::new(cid, bytes)?;
+        blocks.set(cid, block.data().to_vec());
+    }
+    Ok((root, blocks))
+}
@@ -0,0 +1,322 @@
+use crate::account_manager::helpers::auth::{create_service_jwt, ServiceJwtParams};
+use crate::auth_verifier::{verify_service_jwt_str, ServiceJwtOpts};
+use crate::chat::{
+    assert_can_message_local_members, convo_id, embed_view, ensure_convo, get_convo, get_member,
+    get_members, is_local, rejoin, store_leave, store_message, validate_message_text, ID_RESOLVER,
+};
+use crate::common::get_pds_endpoint;
+use crate::local_appview::LocalAppView;
+use crate::{SharedLocalViewer, APP_USER_AGENT};
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use iroh::net::endpoint::Connecting;
+use iroh::net::{Endpoint, NodeId};
+use iroh::node::ProtocolHandler;
+use rsky_lexicon::app::bsky::embed::record::{View, ViewUnion};
+use rsky_lexicon::chat::bsky::convo::MessageView;
+use secp256k1::SecretKey;
+use std::env;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// ALPN of the protocol PDSes use to hand chat events to each other
+pub const CHAT_ALPN: &[u8] = b"rsky/chat/0";
+
+const MAX_DELIVERY_SIZE: usize = 1024 * 1024;
+const MAX_RESPONSE_SIZE: usize = 64 * 1024;
+
+#[derive(Error, Debug)]
+pub enum ChatDeliveryError {
+    #[error("No PDS in did doc for {0}")]
+    NoPds(String),
+    #[error("Could not resolve {0}")]
+    DidNotFound(String),
+    #[error("Sender is not authorized for this convo")]
+    Unauthorized,
+    #[error("Recipient is not hosted on this PDS")]
+    UnknownRecipient,
+    #[error("Delivery rejected: {0}")]
+    Rejected(String),
+}
+
+/// Served at `/.well-known/iroh-node` so other PDSes can dial this one
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IrohNode {
+    pub node_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum ChatEvent {
+    Message { message: MessageView },
+    Leave { did: String },
+}
+
+impl ChatEvent {
+    pub fn sender(&self) -> &String {
+        match self {
+            ChatEvent::Message { message } => &message.sender,
+            ChatEvent::Leave { did } => did,
+        }
+    }
+
+    fn lxm(&self) -> &'static str {
+        match self {
+            ChatEvent::Message { .. } => "chat.bsky.convo.sendMessage",
+            ChatEvent::Leave { .. } => "chat.bsky.convo.leaveConvo",
+        }
+    }
+}
+
+/// A chat event for one member, authorized by a service jwt from the sender's did to the
+/// recipient's did
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatDelivery {
+    pub token: String,
+    pub recipient: String,
+    pub convo_id: String,
+    pub members: Vec<String>,
+    pub event: ChatEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatDeliveryResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Accepts chat deliveries from other PDSes over iroh. Embeds in delivered messages are
+/// rendered again from this PDS's own view of the record.
+pub struct ChatProtocol {
+    pub s3_config: SdkConfig,
+    pub local_viewer: SharedLocalViewer,
+}
+
+impl fmt::Debug for ChatProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChatProtocol")
+    }
+}
+
+impl ProtocolHandler for ChatProtocol {
+    fn accept(
+        self: Arc<Self>,
+        conn: Connecting,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>> {
+        Box::pin(async move {
+            let connection = conn.await?;
+            let (mut send, mut recv) = connection.accept_bi().await?;
+            let bytes = recv.read_to_end(MAX_DELIVERY_SIZE).await?;
+            let response = match self.receive(serde_json::from_slice(&bytes)?).await {
+                Ok(()) => ChatDeliveryResponse { error: None },
+                Err(error) => {
+                    eprintln!("@LOG: ERROR: rejected chat delivery: {error}");
+                    ChatDeliveryResponse {
+                        error: Some(error.to_string()),
+                    }
+                }
+            };
+            send.write_all(&serde_json::to_vec(&response)?).await?;
+            send.finish()?;
+            // Wait for the sender to read the response and close
+            connection.closed().await;
+            Ok(())
+        })
+    }
+}
+
+impl ChatProtocol {
+    async fn receive(&self, delivery: ChatDelivery) -> Result<()> {
+        let ChatDelivery {
+            token,
+            recipient,
+            convo_id: delivered_convo_id,
+            members,
+            event,
+        } = delivery;
+        if !is_local(&recipient).await? {
+            bail!(ChatDeliveryError::UnknownRecipient);
+        }
+        let jwt = verify_service_jwt_str(
+            token,
+            &ID_RESOLVER,
+            ServiceJwtOpts {
+                aud: Some(recipient.clone()),
+                iss: None,
+            },
+        )
+        .await?;
+        let sender = event.sender().clone();
+        if jwt.iss != sender
+            || jwt.lxm.as_deref() != Some(event.lxm())
+            || !members.contains(&sender)
+            || !members.contains(&recipient)
+            || convo_id(&members) != delivered_convo_id
+        {
+            bail!(ChatDeliveryError::Unauthorized);
+        }
+        // Membership is what this PDS has recorded, not what the sender claims
+        let existing = get_convo(&delivered_convo_id)?.is_some();
+        let member = match existing {
+            true => get_member(&sender, &delivered_convo_id)?,
+            false => None,
+        };
+        match event {
+            ChatEvent::Message { mut message } => {
+                validate_message_text(&message.text)?;
+                match member {
+                    Some(member) if member.left_at.is_none() => {
+                        assert_can_message_local_members(&sender, &members, false).await?;
+                    }
+                    Some(_) => {
+                        assert_can_message_local_members(&sender, &members, true).await?;
+                        rejoin(&sender, &delivered_convo_id)?;
+                    }
+                    None if !existing => {
+                        // Everyone hosted here is checked, not only the addressed recipient,
+                        // since the convo and message reach all of them
+                        assert_can_message_local_members(&sender, &members, true).await?;
+                        ensure_convo(members).await?;
+                    }
+                    None => bail!(ChatDeliveryError::Unauthorized),
+                }
+                message.embed = match message.embed {
+                    Some(embed) => Some(self.embed_view(&recipient, embed_uri(&embed)).await?),
+                    None => None,
+                };
+                store_message(&delivered_convo_id, message).await?;
+            }
+            ChatEvent::Leave { did } => {
+                if member.is_some_and(|member| member.left_at.is_none()) {
+                    store_leave(&did, &delivered_convo_id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn embed_view(&self, recipient: &String, uri: &String) -> Result<View> {
+        let mut app_view =
+            LocalAppView::new(&self.s3_config, &self.local_viewer, Some(recipient.clone()));
+        embed_view(&mut app_view, uri).await
+    }
+}
+
+fn embed_uri(embed: &View) -> &String {
+    match &embed.record {
+        ViewUnion::ViewRecord(view) => &view.uri,
+        ViewUnion::ViewNotFound(view) => &view.uri,
+        ViewUnion::ViewBlocked(view) => &view.uri,
+        ViewUnion::GeneratorView(view) => &view.uri,
+        ViewUnion::ListView(view) => &view.uri,
+        ViewUnion::LabelerView(view) => &view.uri,
+        ViewUnion::StarterPackViewBasic(view) => &view.uri,
+    }
+}
+
+/// Looks up the iroh node of the PDS hosting `did`
+async fn resolve_node_id(did: &String) -> Result<NodeId> {
+    let doc = {
+        let mut lock = ID_RESOLVER.id_resolver.write().await;
+        lock.did.resolve(did.clone(), None).await?
+    };
+    let Some(doc) = doc else {
+        bail!(ChatDeliveryError::DidNotFound(did.clone()));
+    };
+    let Some(pds) = get_pds_endpoint(doc) else {
+        bail!(ChatDeliveryError::NoPds(did.clone()));
+    };
+    let client = reqwest::Client::builder()
+        .user_agent(APP_USER_AGENT)
+        .build()?;
+    let node = client
+        .get(format!("{pds}/.well-known/iroh-node"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<IrohNode>()
+        .await?;
+    Ok(NodeId::from_str(&node.node_id)?)
+}
+
+async fn deliver_to(
+    endpoint: &Endpoint,
+    recipient: &String,
+    convo_id: &String,
+    members: &Vec<String>,
+    event: &ChatEvent,
+) -> Result<()> {
+    let node_id = resolve_node_id(recipient).await?;
+    // We just use the repo signing key
+    let private_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX")?;
+    let keypair = SecretKey::from_slice(&hex::decode(private_key.as_bytes())?)?;
+    let token = create_service_jwt(ServiceJwtParams {
+        iss: event.sender().clone(),
+        aud: recipient.clone(),
+        exp: None,
+        lxm: Some(event.lxm().to_string()),
+        jti: None,
+        keypair,
+    })
+    .await?;
+    let delivery = ChatDelivery {
+        token,
+        recipient: recipient.clone(),
+        convo_id: convo_id.clone(),
+        members: members.clone(),
+        event: event.clone(),
+    };
+
+    let connection = endpoint.connect(node_id, CHAT_ALPN).await?;
+    let (mut send, mut recv) = connection.open_bi().await?;
+    send.write_all(&serde_json::to_vec(&delivery)?).await?;
+    send.finish()?;
+    let response: ChatDeliveryResponse =
+        serde_json::from_slice(&recv.read_to_end(MAX_RESPONSE_SIZE).await?)?;
+    connection.close(0u32.into(), b"done");
+    match response.error {
+        Some(error) => bail!(ChatDeliveryError::Rejected(error)),
+        None => Ok(()),
+    }
+}
+
+async fn deliver_to_remote_members(
+    endpoint: &Endpoint,
+    convo_id: &String,
+    event: &ChatEvent,
+) -> Result<()> {
+    let members = get_members(convo_id)?;
+    let dids = members
+        .iter()
+        .map(|member| member.did.clone())
+        .collect::<Vec<String>>();
+    for member in members {
+        if member.left_at.is_some() || is_local(&member.did).await? {
+            continue;
+        }
+        if let Err(error) = deliver_to(endpoint, &member.did, convo_id, &dids, event).await {
+            eprintln!(
+                "@LOG: ERROR: failed to deliver chat event to {}: {error}",
+                member.did
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Hands a chat event to the PDS of every member not hosted here, in the background
+pub fn deliver(endpoint: Endpoint, convo_id: String, event: ChatEvent) {
+    tokio::spawn(async move {
+        if let Err(error) = deliver_to_remote_members(&endpoint, &convo_id, &event).await {
+            eprintln!("@LOG: ERROR: chat delivery failed: {error}");
+        }
+    });
+}
@@ -0,0 +1,936 @@
+use crate::account_manager::AccountManager;
+use crate::common::tid::Ticker;
+use crate::common::time::from_str_to_utc;
+use crate::common::{get_handle, now, RFC3339_VARIANT};
+use crate::config::ServerConfig;
+use crate::db::establish_connection;
+use crate::graph;
+use crate::local_appview::LocalAppView;
+use crate::models::models;
+use crate::repo::record::RecordReader;
+use crate::repo::types::Ids;
+use crate::{SharedIdResolver, INVALID_HANDLE};
+use anyhow::Result;
+use diesel::dsl::{exists, not};
+use diesel::*;
+use lazy_static::lazy_static;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rsky_identity::types::{DidCache, IdentityResolverOpts};
+use rsky_identity::IdResolver;
+use rsky_lexicon::app::bsky::actor::AssociatedChatAllowIncoming;
+use rsky_lexicon::app::bsky::embed::record::{View, ViewNotFound, ViewRecord, ViewUnion};
+use rsky_lexicon::chat::bsky::actor::ProfileViewBasic;
+use rsky_lexicon::chat::bsky::convo::{
+    ConvoView, DeletedMessageView, GetLogOutput, GetMessagesOutput, LeaveConvoOutput,
+    ListConvosOutput, LogBeginConvo, LogCreateMessage, LogDeleteMessage, LogEnum, LogLeaveConvo,
+    MessageInput, MessageView, MessageViewEnum, MessageViewSender,
+};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::sync::Mutex;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+pub mod delivery;
+
+const MAX_MEMBERS: usize = 10;
+const MAX_TEXT_BYTES: usize = 10000;
+const MAX_TEXT_CHARS: usize = 1000;
+const LOG_LIMIT: i64 = 100;
+
+const LOG_BEGIN_CONVO: &str = "beginConvo";
+const LOG_LEAVE_CONVO: &str = "leaveConvo";
+const LOG_CREATE_MESSAGE: &str = "createMessage";
+const LOG_DELETE_MESSAGE: &str = "deleteMessage";
+
+lazy_static! {
+    // Revs order convos, messages and log entries on this PDS
+    static ref TICKER: Mutex<Ticker> = Mutex::new(Ticker::new());
+    // Member dids are resolved outside of a request, e.g. when delivering over iroh
+    pub static ref ID_RESOLVER: SharedIdResolver = SharedIdResolver {
+        id_resolver: RwLock::new(IdResolver::new(IdentityResolverOpts {
+            timeout: None,
+            plc_url: Some(env::var("PLC_SERVER").unwrap_or("plc.directory".to_owned())),
+            did_cache: Some(DidCache::new(None, None)),
+            backup_nameservers: None,
+        })),
+    };
+}
+
+#[derive(Error, Debug)]
+pub enum ChatError {
+    #[error("Convo not found")]
+    ConvoNotFound,
+    #[error("Message not found")]
+    MessageNotFound,
+    #[error("A convo needs between 2 and {MAX_MEMBERS} members, including the requester")]
+    InvalidMembers,
+    #[error("Message text must not be empty or longer than {MAX_TEXT_CHARS} characters")]
+    InvalidMessage,
+    #[error("Recipient has disabled incoming messages")]
+    MessagingDisabled,
+    #[error("Block between recipient and sender")]
+    Blocked,
+}
+
+/// The `chat.bsky.actor.declaration` record, of which we only need `allowIncoming`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Declaration {
+    allow_incoming: Option<AssociatedChatAllowIncoming>,
+}
+
+/// Matches when `chat.bsky.convo` is served from this PDS instead of a chat service
+pub struct LocalChat;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LocalChat {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.rocket().state::<ServerConfig>() {
+            Some(cfg) if cfg.chat.local => Outcome::Success(LocalChat),
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
+
+pub fn next_rev() -> String {
+    let mut ticker = TICKER.lock().unwrap();
+    ticker.next(None).to_string()
+}
+
+fn normalize_members(mut members: Vec<String>) -> Result<Vec<String>> {
+    members.sort();
+    members.dedup();
+    if members.len() < 2 || members.len() > MAX_MEMBERS {
+        return Err(ChatError::InvalidMembers.into());
+    }
+    Ok(members)
+}
+
+/// Convos are keyed by their member set, so every PDS hosting a member derives the same id
+pub fn convo_id(members: &Vec<String>) -> String {
+    let mut members = members.clone();
+    members.sort();
+    members.dedup();
+    let hash = Sha256::digest(members.join(",").as_bytes());
+    hex::encode(hash.as_slice())[..32].to_string()
+}
+
+pub async fn is_local(did: &String) -> Result<bool> {
+    Ok(AccountManager::get_account(did, None).await?.is_some())
+}
+
+pub fn validate_message_text(text: &String) -> Result<()> {
+    if text.trim().is_empty()
+        || text.len() > MAX_TEXT_BYTES
+        || text.chars().count() > MAX_TEXT_CHARS
+    {
+        return Err(ChatError::InvalidMessage.into());
+    }
+    Ok(())
+}
+
+/// Who may message `did`, from their declaration. Accounts without one only hear from
+/// accounts they follow, as on the chat service.
+async fn get_allow_incoming(did: &String) -> Result<AssociatedChatAllowIncoming> {
+    let uri = format!("at://{did}/{}/self", Ids::ChatBskyActorDeclaration.as_str());
+    let mut reader = RecordReader::new(did.clone());
+    let declaration = match reader.get_record(&uri, None, None).await? {
+        Some(record) => {
+            serde_json::from_value::<Declaration>(serde_json::to_value(record.value)?).ok()
+        }
+        None => None,
+    };
+    Ok(declaration
+        .and_then(|declaration| declaration.allow_incoming)
+        .unwrap_or(AssociatedChatAllowIncoming::Following))
+}
+
+/// Where a member hosted here stands towards a sender
+#[derive(Debug, Clone, PartialEq)]
+struct MemberStanding {
+    blocked: bool,
+    /// Only looked up when starting or rejoining a convo
+    allow_incoming: Option<AssociatedChatAllowIncoming>,
+    follows_sender: bool,
+}
+
+impl MemberStanding {
+    /// Blocks apply to every message; `allowIncoming` only to starting or rejoining a convo
+    fn check(&self) -> Result<(), ChatError> {
+        if self.blocked {
+            return Err(ChatError::Blocked);
+        }
+        match self.allow_incoming {
+            None | Some(AssociatedChatAllowIncoming::All) => Ok(()),
+            Some(AssociatedChatAllowIncoming::Following) if self.follows_sender => Ok(()),
+            Some(_) => Err(ChatError::MessagingDisabled),
+        }
+    }
+}
+
+async fn get_standing(sender: &String, member: &String, new_convo: bool) -> Result<MemberStanding> {
+    let blocked = graph::is_blocked_between(sender, member)?;
+    let allow_incoming = match new_convo && !blocked {
+        true => Some(get_allow_incoming(member).await?),
+        false => None,
+    };
+    let follows_sender = match allow_incoming {
+        Some(AssociatedChatAllowIncoming::Following) => graph::is_following(member, sender)?,
+        _ => false,
+    };
+    Ok(MemberStanding {
+        blocked,
+        allow_incoming,
+        follows_sender,
+    })
+}
+
+/// Every member must accept the sender, not just the one a convo was started with
+fn check_standings(standings: &[MemberStanding]) -> Result<(), ChatError> {
+    standings.iter().try_for_each(MemberStanding::check)
+}
+
+/// Checks `sender` against every other member of the convo hosted here
+pub(crate) async fn assert_can_message_local_members(
+    sender: &String,
+    members: &Vec<String>,
+    new_convo: bool,
+) -> Result<()> {
+    let mut standings = Vec::new();
+    for member in members {
+        if member != sender && is_local(member).await? {
+            standings.push(get_standing(sender, member, new_convo).await?);
+        }
+    }
+    Ok(check_standings(&standings)?)
+}
+
+fn insert_log(
+    did: &String,
+    convo_id: &String,
+    rev: &String,
+    log_type: &str,
+    message_id: Option<&String>,
+) -> Result<()> {
+    use crate::schema::pds::chat_log::dsl as ChatLogSchema;
+    let conn = &mut establish_connection()?;
+
+    insert_into(ChatLogSchema::chat_log)
+        .values((
+            ChatLogSchema::did.eq(did),
+            ChatLogSchema::convoId.eq(convo_id),
+            ChatLogSchema::rev.eq(rev),
+            ChatLogSchema::logType.eq(log_type),
+            ChatLogSchema::messageId.eq(message_id),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Writes a log entry for each member of the convo hosted on this PDS
+async fn log_for_local_members(
+    convo_id: &String,
+    rev: &String,
+    log_type: &str,
+    message_id: Option<&String>,
+) -> Result<()> {
+    for member in get_members(convo_id)? {
+        if member.left_at.is_none() && is_local(&member.did).await? {
+            insert_log(&member.did, convo_id, rev, log_type, message_id)?;
+        }
+    }
+    Ok(())
+}
+
+fn bump_convo(convo_id: &String, rev: &String) -> Result<()> {
+    use crate::schema::pds::chat_convo::dsl as ChatConvoSchema;
+    let conn = &mut establish_connection()?;
+
+    update(ChatConvoSchema::chat_convo)
+        .filter(ChatConvoSchema::id.eq(convo_id))
+        .set(ChatConvoSchema::rev.eq(rev))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_convo(convo_id: &String) -> Result<Option<models::ChatConvo>> {
+    use crate::schema::pds::chat_convo::dsl as ChatConvoSchema;
+    let conn = &mut establish_connection()?;
+
+    let convo = ChatConvoSchema::chat_convo
+        .filter(ChatConvoSchema::id.eq(convo_id))
+        .select(models::ChatConvo::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(convo)
+}
+
+pub fn get_members(convo_id: &String) -> Result<Vec<models::ChatMember>> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+    let conn = &mut establish_connection()?;
+
+    let members = ChatMemberSchema::chat_member
+        .filter(ChatMemberSchema::convoId.eq(convo_id))
+        .select(models::ChatMember::as_select())
+        .order(ChatMemberSchema::did.asc())
+        .load(conn)?;
+    Ok(members)
+}
+
+/// The requester's membership of a convo they haven't left
+fn get_membership(requester: &String, convo_id: &String) -> Result<models::ChatMember> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+    let conn = &mut establish_connection()?;
+
+    let member = ChatMemberSchema::chat_member
+        .filter(ChatMemberSchema::convoId.eq(convo_id))
+        .filter(ChatMemberSchema::did.eq(requester))
+        .filter(ChatMemberSchema::leftAt.is_null())
+        .select(models::ChatMember::as_select())
+        .first(conn)
+        .optional()?;
+    match member {
+        Some(member) => Ok(member),
+        None => Err(ChatError::ConvoNotFound.into()),
+    }
+}
+
+/// Creates the convo for a member set if it doesn't exist yet, starting it for the
+/// members hosted here
+pub async fn ensure_convo(members: Vec<String>) -> Result<String> {
+    use crate::schema::pds::chat_convo::dsl as ChatConvoSchema;
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+
+    let members = normalize_members(members)?;
+    let id = convo_id(&members);
+    let rev = next_rev();
+    {
+        let conn = &mut establish_connection()?;
+        insert_into(ChatConvoSchema::chat_convo)
+            .values((
+                ChatConvoSchema::id.eq(&id),
+                ChatConvoSchema::rev.eq(&rev),
+                ChatConvoSchema::createdAt.eq(now()),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    for member in members {
+        let inserted = {
+            let conn = &mut establish_connection()?;
+            insert_into(ChatMemberSchema::chat_member)
+                .values((
+                    ChatMemberSchema::convoId.eq(&id),
+                    ChatMemberSchema::did.eq(&member),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?
+        };
+        if inserted > 0 && is_local(&member).await? {
+            insert_log(&member, &id, &rev, LOG_BEGIN_CONVO, None)?;
+        }
+    }
+    Ok(id)
+}
+
+/// A member's row in a convo, whether or not they've left
+pub fn get_member(did: &String, convo_id: &String) -> Result<Option<models::ChatMember>> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+    let conn = &mut establish_connection()?;
+
+    let member = ChatMemberSchema::chat_member
+        .filter(ChatMemberSchema::convoId.eq(convo_id))
+        .filter(ChatMemberSchema::did.eq(did))
+        .select(models::ChatMember::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(member)
+}
+
+/// Brings back a member who had left the convo
+pub fn rejoin(did: &String, convo_id: &String) -> Result<()> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+
+    let rejoined = {
+        let conn = &mut establish_connection()?;
+        update(ChatMemberSchema::chat_member)
+            .filter(ChatMemberSchema::convoId.eq(convo_id))
+            .filter(ChatMemberSchema::did.eq(did))
+            .filter(ChatMemberSchema::leftAt.is_not_null())
+            .set(ChatMemberSchema::leftAt.eq(None::<String>))
+            .execute(conn)?
+    };
+    if rejoined > 0 {
+        insert_log(did, convo_id, &next_rev(), LOG_BEGIN_CONVO, None)?;
+    }
+    Ok(())
+}
+
+/// `getConvoForMembers`: finds or creates the convo, rejoining it if the requester had left
+pub async fn get_or_create_convo(requester: &String, mut members: Vec<String>) -> Result<String> {
+    members.push(requester.clone());
+    let members = normalize_members(members)?;
+    let new_convo = get_convo(&convo_id(&members))?.is_none();
+    assert_can_message_local_members(requester, &members, new_convo).await?;
+    let id = ensure_convo(members).await?;
+    rejoin(requester, &id)?;
+    Ok(id)
+}
+
+fn get_message(convo_id: &String, message_id: &String) -> Result<Option<models::ChatMessage>> {
+    use crate::schema::pds::chat_message::dsl as ChatMessageSchema;
+    let conn = &mut establish_connection()?;
+
+    let message = ChatMessageSchema::chat_message
+        .filter(ChatMessageSchema::convoId.eq(convo_id))
+        .filter(ChatMessageSchema::id.eq(message_id))
+        .select(models::ChatMessage::as_select())
+        .first(conn)
+        .optional()?;
+    Ok(message)
+}
+
+fn is_deleted_for(did: &String, message: &models::ChatMessage) -> Result<bool> {
+    use crate::schema::pds::chat_message_deletion::dsl as ChatMessageDeletionSchema;
+    let conn = &mut establish_connection()?;
+
+    let deleted = select(exists(
+        ChatMessageDeletionSchema::chat_message_deletion
+            .filter(ChatMessageDeletionSchema::did.eq(did))
+            .filter(ChatMessageDeletionSchema::convoId.eq(&message.convo_id))
+            .filter(ChatMessageDeletionSchema::messageId.eq(&message.id)),
+    ))
+    .get_result(conn)?;
+    Ok(deleted)
+}
+
+fn message_view(message: models::ChatMessage) -> Result<MessageView> {
+    Ok(MessageView {
+        facets: match message.facets {
+            Some(facets) => Some(serde_json::from_str(&facets)?),
+            None => None,
+        },
+        embed: match message.embed {
+            Some(embed) => Some(serde_json::from_str(&embed)?),
+            None => None,
+        },
+        sent_at: from_str_to_utc(&message.sent_at),
+        id: message.id,
+        rev: message.rev,
+        text: message.text,
+        sender: message.sender,
+    })
+}
+
+fn deleted_message_view(message: models::ChatMessage) -> DeletedMessageView {
+    DeletedMessageView {
+        sent_at: from_str_to_utc(&message.sent_at),
+        id: message.id,
+        rev: message.rev,
+        sender: MessageViewSender {
+            did: message.sender,
+        },
+    }
+}
+
+/// Messages the requester deleted for themselves are shown as deleted
+fn message_view_for(requester: &String, message: models::ChatMessage) -> Result<MessageViewEnum> {
+    match is_deleted_for(requester, &message)? {
+        true => Ok(MessageViewEnum::DeletedMessageView(deleted_message_view(
+            message,
+        ))),
+        false => Ok(MessageViewEnum::MessageView(message_view(message)?)),
+    }
+}
+
+fn unread_count(member: &models::ChatMember) -> Result<u64> {
+    use crate::schema::pds::chat_message::dsl as ChatMessageSchema;
+    use crate::schema::pds::chat_message_deletion::dsl as ChatMessageDeletionSchema;
+    let conn = &mut establish_connection()?;
+
+    let deleted = ChatMessageDeletionSchema::chat_message_deletion
+        .filter(ChatMessageDeletionSchema::did.eq(&member.did))
+        .filter(ChatMessageDeletionSchema::convoId.eq(&member.convo_id))
+        .select(ChatMessageDeletionSchema::messageId);
+    let mut builder = ChatMessageSchema::chat_message
+        .filter(ChatMessageSchema::convoId.eq(&member.convo_id))
+        .filter(ChatMessageSchema::sender.ne(&member.did))
+        .filter(not(ChatMessageSchema::id.eq_any(deleted)))
+        .into_boxed();
+    if let Some(last_read_rev) = &member.last_read_rev {
+        builder = builder.filter(ChatMessageSchema::rev.gt(last_read_rev));
+    }
+    let count: i64 = builder.count().get_result(conn)?;
+    Ok(count as u64)
+}
+
+/// Members hosted here get their local profile, others just the handle from their did doc
+async fn member_view(app_view: &mut LocalAppView<'_>, did: &String) -> Result<ProfileViewBasic> {
+    if let Some(profile) = app_view.profile_basic(did).await? {
+        return Ok(ProfileViewBasic {
+            did: profile.did,
+            handle: profile.handle,
+            display_name: profile.display_name,
+            avatar: profile.avatar,
+            associated: profile.associated,
+            viewer: profile.viewer,
+            labels: profile.labels,
+            chat_disabled: None,
+        });
+    }
+    let doc = {
+        let mut lock = ID_RESOLVER.id_resolver.write().await;
+        lock.did.resolve(did.clone(), None).await
+    };
+    let handle = match doc {
+        Ok(Some(doc)) => get_handle(&doc),
+        Ok(None) => None,
+        Err(error) => {
+            eprintln!("@LOG: ERROR: failed to resolve chat member {did}: {error}");
+            None
+        }
+    };
+    Ok(ProfileViewBasic {
+        did: did.clone(),
+        handle: handle.unwrap_or(INVALID_HANDLE.to_string()),
+        display_name: None,
+        avatar: None,
+        associated: None,
+        viewer: None,
+        labels: None,
+        chat_disabled: None,
+    })
+}
+
+async fn build_convo_view(
+    app_view: &mut LocalAppView<'_>,
+    member: models::ChatMember,
+) -> Result<ConvoView> {
+    use crate::schema::pds::chat_message::dsl as ChatMessageSchema;
+
+    let Some(convo) = get_convo(&member.convo_id)? else {
+        return Err(ChatError::ConvoNotFound.into());
+    };
+    let mut members = Vec::new();
+    for convo_member in get_members(&convo.id)? {
+        members.push(member_view(app_view, &convo_member.did).await?);
+    }
+    let last_message = {
+        let conn = &mut establish_connection()?;
+        ChatMessageSchema::chat_message
+            .filter(ChatMessageSchema::convoId.eq(&convo.id))
+            .select(models::ChatMessage::as_select())
+            .order(ChatMessageSchema::rev.desc())
+            .first(conn)
+            .optional()?
+    };
+    Ok(ConvoView {
+        id: convo.id,
+        rev: convo.rev,
+        members,
+        last_message: match last_message {
+            Some(message) => Some(message_view_for(&member.did, message)?),
+            None => None,
+        },
+        muted: member.muted,
+        unread_count: unread_count(&member)?,
+    })
+}
+
+pub async fn convo_view(
+    app_view: &mut LocalAppView<'_>,
+    requester: &String,
+    convo_id: &String,
+) -> Result<ConvoView> {
+    let member = get_membership(requester, convo_id)?;
+    build_convo_view(app_view, member).await
+}
+
+pub async fn list_convos(
+    app_view: &mut LocalAppView<'_>,
+    requester: &String,
+    limit: i64,
+    cursor: Option<String>,
+) -> Result<ListConvosOutput> {
+    use crate::schema::pds::chat_convo::dsl as ChatConvoSchema;
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+
+    let members: Vec<(models::ChatMember, String)> = {
+        let conn = &mut establish_connection()?;
+        let mut builder = ChatMemberSchema::chat_member
+            .inner_join(
+                ChatConvoSchema::chat_convo.on(ChatConvoSchema::id.eq(ChatMemberSchema::convoId)),
+            )
+            .filter(ChatMemberSchema::did.eq(requester))
+            .filter(ChatMemberSchema::leftAt.is_null())
+            .select((models::ChatMember::as_select(), ChatConvoSchema::rev))
+            .order(ChatConvoSchema::rev.desc())
+            .limit(limit)
+            .into_boxed();
+        if let Some(cursor) = cursor {
+            builder = builder.filter(ChatConvoSchema::rev.lt(cursor));
+        }
+        builder.load(conn)?
+    };
+    let cursor = match members.len() as i64 == limit {
+        true => members.last().map(|(_, rev)| rev.clone()),
+        false => None,
+    };
+    let mut convos = Vec::new();
+    for (member, _) in members {
+        convos.push(build_convo_view(app_view, member).await?);
+    }
+    Ok(ListConvosOutput { cursor, convos })
+}
+
+pub async fn get_messages(
+    requester: &String,
+    convo_id: &String,
+    limit: i64,
+    cursor: Option<String>,
+) -> Result<GetMessagesOutput> {
+    use crate::schema::pds::chat_message::dsl as ChatMessageSchema;
+
+    get_membership(requester, convo_id)?;
+    let messages: Vec<models::ChatMessage> = {
+        let conn = &mut establish_connection()?;
+        let mut builder = ChatMessageSchema::chat_message
+            .filter(ChatMessageSchema::convoId.eq(convo_id))
+            .select(models::ChatMessage::as_select())
+            .order(ChatMessageSchema::rev.desc())
+            .limit(limit)
+            .into_boxed();
+        if let Some(cursor) = cursor {
+            builder = builder.filter(ChatMessageSchema::rev.lt(cursor));
+        }
+        builder.load(conn)?
+    };
+    let cursor = match messages.len() as i64 == limit {
+        true => messages.last().map(|message| message.rev.clone()),
+        false => None,
+    };
+    let messages = messages
+        .into_iter()
+        .map(|message| message_view_for(requester, message))
+        .collect::<Result<Vec<MessageViewEnum>>>()?;
+    Ok(GetMessagesOutput { cursor, messages })
+}
+
+/// Embedded posts are rendered by each member's PDS, from its own view of the record
+pub async fn embed_view(app_view: &mut LocalAppView<'_>, uri: &String) -> Result<View> {
+    let record = match app_view.post_view(uri).await? {
+        Some(post) => ViewUnion::ViewRecord(ViewRecord {
+            uri: post.uri,
+            cid: post.cid,
+            author: post.author,
+            value: post.record,
+            labels: post.labels,
+            reply_count: post.reply_count,
+            repost_count: post.repost_count,
+            like_count: post.like_count,
+            embeds: post.embed.map(|embed| vec![embed]),
+            indexed_at: post.indexed_at,
+        }),
+        None => ViewUnion::ViewNotFound(ViewNotFound {
+            uri: uri.clone(),
+            not_found: true,
+        }),
+    };
+    Ok(View { record })
+}
+
+/// Stores a message under a rev from this PDS and logs it for the local members.
+/// Redelivered messages are ignored.
+pub async fn store_message(convo_id: &String, mut message: MessageView) -> Result<MessageView> {
+    use crate::schema::pds::chat_message::dsl as ChatMessageSchema;
+
+    if let Some(existing) = get_message(convo_id, &message.id)? {
+        return message_view(existing);
+    }
+    message.rev = next_rev();
+    {
+        let conn = &mut establish_connection()?;
+        insert_into(ChatMessageSchema::chat_message)
+            .values(models::ChatMessage {
+                convo_id: convo_id.clone(),
+                id: message.id.clone(),
+                rev: message.rev.clone(),
+                sender: message.sender.clone(),
+                text: message.text.clone(),
+                facets: match &message.facets {
+                    Some(facets) => Some(serde_json::to_string(facets)?),
+                    None => None,
+                },
+                embed: match &message.embed {
+                    Some(embed) => Some(serde_json::to_string(embed)?),
+                    None => None,
+                },
+                sent_at: format!("{}", message.sent_at.format(RFC3339_VARIANT)),
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    bump_convo(convo_id, &message.rev)?;
+    log_for_local_members(
+        convo_id,
+        &message.rev,
+        LOG_CREATE_MESSAGE,
+        Some(&message.id),
+    )
+    .await?;
+    Ok(message)
+}
+
+pub async fn send_message(
+    app_view: &mut LocalAppView<'_>,
+    requester: &String,
+    convo_id: &String,
+    input: MessageInput,
+) -> Result<MessageView> {
+    validate_message_text(&input.text)?;
+    get_membership(requester, convo_id)?;
+    let members = get_members(convo_id)?
+        .into_iter()
+        .filter(|member| member.left_at.is_none())
+        .map(|member| member.did)
+        .collect::<Vec<String>>();
+    assert_can_message_local_members(requester, &members, false).await?;
+    let embed = match input.embed {
+        Some(embed) => Some(embed_view(app_view, &embed.record.uri).await?),
+        None => None,
+    };
+    let id = next_rev();
+    let message = MessageView {
+        id: id.clone(),
+        rev: id,
+        text: input.text,
+        facets: input.facets,
+        embed,
+        sender: requester.clone(),
+        sent_at: from_str_to_utc(&now()),
+    };
+    store_message(convo_id, message).await
+}
+
+pub async fn delete_message_for_self(
+    requester: &String,
+    convo_id: &String,
+    message_id: &String,
+) -> Result<DeletedMessageView> {
+    use crate::schema::pds::chat_message_deletion::dsl as ChatMessageDeletionSchema;
+
+    get_membership(requester, convo_id)?;
+    let Some(message) = get_message(convo_id, message_id)? else {
+        return Err(ChatError::MessageNotFound.into());
+    };
+    {
+        let conn = &mut establish_connection()?;
+        insert_into(ChatMessageDeletionSchema::chat_message_deletion)
+            .values((
+                ChatMessageDeletionSchema::did.eq(requester),
+                ChatMessageDeletionSchema::convoId.eq(convo_id),
+                ChatMessageDeletionSchema::messageId.eq(message_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    insert_log(
+        requester,
+        convo_id,
+        &next_rev(),
+        LOG_DELETE_MESSAGE,
+        Some(message_id),
+    )?;
+    Ok(deleted_message_view(message))
+}
+
+/// Marks a member as having left. Used for the requester and for leaves delivered from
+/// other PDSes.
+pub fn store_leave(did: &String, convo_id: &String) -> Result<String> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+
+    let rev = next_rev();
+    let left = {
+        let conn = &mut establish_connection()?;
+        update(ChatMemberSchema::chat_member)
+            .filter(ChatMemberSchema::convoId.eq(convo_id))
+            .filter(ChatMemberSchema::did.eq(did))
+            .filter(ChatMemberSchema::leftAt.is_null())
+            .set(ChatMemberSchema::leftAt.eq(now()))
+            .execute(conn)?
+    };
+    if left > 0 {
+        bump_convo(convo_id, &rev)?;
+        insert_log(did, convo_id, &rev, LOG_LEAVE_CONVO, None)?;
+    }
+    Ok(rev)
+}
+
+pub fn leave_convo(requester: &String, convo_id: &String) -> Result<LeaveConvoOutput> {
+    get_membership(requester, convo_id)?;
+    let rev = store_leave(requester, convo_id)?;
+    Ok(LeaveConvoOutput {
+        convo_id: convo_id.clone(),
+        rev,
+    })
+}
+
+pub fn set_muted(requester: &String, convo_id: &String, muted: bool) -> Result<()> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+
+    get_membership(requester, convo_id)?;
+    let conn = &mut establish_connection()?;
+    update(ChatMemberSchema::chat_member)
+        .filter(ChatMemberSchema::convoId.eq(convo_id))
+        .filter(ChatMemberSchema::did.eq(requester))
+        .set(ChatMemberSchema::muted.eq(muted))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Marks the convo read up to `message_id`, or up to its latest message
+pub fn update_read(
+    requester: &String,
+    convo_id: &String,
+    message_id: Option<String>,
+) -> Result<()> {
+    use crate::schema::pds::chat_member::dsl as ChatMemberSchema;
+
+    get_membership(requester, convo_id)?;
+    let last_read_rev = match message_id {
+        Some(message_id) => match get_message(convo_id, &message_id)? {
+            Some(message) => message.rev,
+            None => return Err(ChatError::MessageNotFound.into()),
+        },
+        None => match get_convo(convo_id)? {
+            Some(convo) => convo.rev,
+            None => return Err(ChatError::ConvoNotFound.into()),
+        },
+    };
+    let conn = &mut establish_connection()?;
+    update(ChatMemberSchema::chat_member)
+        .filter(ChatMemberSchema::convoId.eq(convo_id))
+        .filter(ChatMemberSchema::did.eq(requester))
+        .set(ChatMemberSchema::lastReadRev.eq(last_read_rev))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn get_log(requester: &String, cursor: Option<String>) -> Result<GetLogOutput> {
+    use crate::schema::pds::chat_log::dsl as ChatLogSchema;
+
+    let entries: Vec<models::ChatLog> = {
+        let conn = &mut establish_connection()?;
+        let mut builder = ChatLogSchema::chat_log
+            .filter(ChatLogSchema::did.eq(requester))
+            .select(models::ChatLog::as_select())
+            .order((ChatLogSchema::rev.asc(), ChatLogSchema::id.asc()))
+            .limit(LOG_LIMIT)
+            .into_boxed();
+        if let Some(ref cursor) = cursor {
+            builder = builder.filter(ChatLogSchema::rev.gt(cursor));
+        }
+        builder.load(conn)?
+    };
+    let cursor = match entries.last() {
+        Some(entry) => Some(entry.rev.clone()),
+        None => cursor,
+    };
+    let mut logs = Vec::new();
+    for entry in entries {
+        let message = match &entry.message_id {
+            Some(message_id) => get_message(&entry.convo_id, message_id)?,
+            None => None,
+        };
+        let log = match (entry.log_type.as_str(), message) {
+            (LOG_BEGIN_CONVO, _) => LogEnum::LogBeginConvo(LogBeginConvo {
+                rev: entry.rev,
+                convo_id: entry.convo_id,
+            }),
+            (LOG_LEAVE_CONVO, _) => LogEnum::LogLeaveConvo(LogLeaveConvo {
+                rev: entry.rev,
+                convo_id: entry.convo_id,
+            }),
+            (LOG_CREATE_MESSAGE, Some(message)) => LogEnum::LogCreateMessage(LogCreateMessage {
+                rev: entry.rev,
+                convo_id: entry.convo_id,
+                message: message_view_for(requester, message)?,
+            }),
+            (LOG_DELETE_MESSAGE, Some(message)) => LogEnum::LogDeleteMessage(LogDeleteMessage {
+                rev: entry.rev,
+                convo_id: entry.convo_id,
+                message: MessageViewEnum::DeletedMessageView(deleted_message_view(message)),
+            }),
+            _ => continue,
+        };
+        logs.push(log);
+    }
+    Ok(GetLogOutput { cursor, logs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn standing(allow_incoming: AssociatedChatAllowIncoming) -> MemberStanding {
+        MemberStanding {
+            blocked: false,
+            allow_incoming: Some(allow_incoming),
+            follows_sender: false,
+        }
+    }
+
+    #[test]
+    fn allows_new_convo_when_every_member_accepts() {
+        let mut follower = standing(AssociatedChatAllowIncoming::Following);
+        follower.follows_sender = true;
+        let standings = vec![standing(AssociatedChatAllowIncoming::All), follower];
+        assert!(check_standings(&standings).is_ok());
+    }
+
+    #[test]
+    fn rejects_new_convo_when_another_member_disallows_incoming() {
+        // The delivery was addressed to the first member, who accepts anyone
+        let standings = vec![
+            standing(AssociatedChatAllowIncoming::All),
+            standing(AssociatedChatAllowIncoming::None),
+        ];
+        assert!(matches!(
+            check_standings(&standings),
+            Err(ChatError::MessagingDisabled)
+        ));
+        let standings = vec![
+            standing(AssociatedChatAllowIncoming::All),
+            standing(AssociatedChatAllowIncoming::Following),
+        ];
+        assert!(matches!(
+            check_standings(&standings),
+            Err(ChatError::MessagingDisabled)
+        ));
+    }
+
+    #[test]
+    fn rejects_any_message_when_another_member_blocks() {
+        let standings = vec![
+            MemberStanding {
+                blocked: false,
+                allow_incoming: None,
+                follows_sender: false,
+            },
+            MemberStanding {
+                blocked: true,
+                allow_incoming: None,
+                follows_sender: false,
+            },
+        ];
+        assert!(matches!(
+            check_standings(&standings),
+            Err(ChatError::Blocked)
+        ));
+    }
+}
@@ -150,6 +150,16 @@ pub fn get_notif_endpoint(doc: DidDocument) -> Option<String> {
     )
 }
 
+pub fn get_pds_endpoint(doc: DidDocument) -> Option<String> {
+    get_service_endpoint(
+        doc,
+        GetServiceEndpointOpts {
+            id: "#atproto_pds".to_string(),
+            r#type: Some("AtprotoPersonalDataServer".to_string()),
+        },
+    )
+}
+
 pub fn get_service_endpoint(doc: DidDocument, opts: GetServiceEndpointOpts) -> Option<String> {
     println!(
         "@LOG: common::get_service_endpoint() doc: {:?}; opts: {:?}",
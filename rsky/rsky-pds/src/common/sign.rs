@@ -6,15 +6,7 @@ use serde_json::Value as JsonValue;
 use sha2::{Digest, Sha256};
 
 pub fn atproto_sign<T: Serialize>(obj: &T, key: &SecretKey) -> Result<[u8; 64]> {
-    // Encode object to json before dag-cbor because serde_ipld_dagcbor doesn't properly
-    // sort by keys
-    let json = serde_json::to_string(obj).unwrap();
-    // Deserialize to IndexMap with preserve key order enabled. serde_ipld_dagcbor does not sort nested
-    // objects properly by keys
-    let map_unsigned: IndexMap<String, JsonValue> = serde_json::from_str(&json).unwrap();
-    let unsigned_bytes = serde_ipld_dagcbor::to_vec(&map_unsigned).unwrap();
-    // Hash dag_cbor to sha256
-    let hash = Sha256::digest(&*unsigned_bytes);
+    let hash = atproto_signing_hash(obj)?;
     // Sign sha256 hash using private key
     let message = Message::from_digest_slice(hash.as_ref()).unwrap();
     let mut sig = key.sign_ecdsa(message);
@@ -25,6 +17,20 @@ pub fn atproto_sign<T: Serialize>(obj: &T, key: &SecretKey) -> Result<[u8; 64]>
     Ok(normalized_compact_sig)
 }
 
+/// Sha256 of the dag-cbor encoding that atproto_sign() signs over. Useful for verifying
+/// signatures produced by atproto_sign() or any other atproto implementation.
+pub fn atproto_signing_hash<T: Serialize>(obj: &T) -> Result<Vec<u8>> {
+    // Encode object to json before dag-cbor because serde_ipld_dagcbor doesn't properly
+    // sort by keys
+    let json = serde_json::to_string(obj)?;
+    // Deserialize to IndexMap with preserve key order enabled. serde_ipld_dagcbor does not sort nested
+    // objects properly by keys
+    let map_unsigned: IndexMap<String, JsonValue> = serde_json::from_str(&json)?;
+    let unsigned_bytes = serde_ipld_dagcbor::to_vec(&map_unsigned)?;
+    // Hash dag_cbor to sha256
+    Ok(Sha256::digest(&*unsigned_bytes).to_vec())
+}
+
 pub fn sign_without_indexmap<T: Serialize>(obj: &T, key: &SecretKey) -> Result<[u8; 64]> {
     let unsigned_bytes = serde_ipld_dagcbor::to_vec(&obj)?;
     // Hash dag_cbor to sha256
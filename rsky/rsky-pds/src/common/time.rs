@@ -53,3 +53,12 @@ pub fn from_millis_to_utc(millis: i64) -> DateTime<UtcOffset> {
 pub fn from_millis_to_str(millis: i64) -> String {
     format!("{}", from_millis_to_utc(millis).format(RFC3339_VARIANT))
 }
+
+/// Any RFC 3339 timestamp, e.g. a record's `createdAt`, in the format we store
+pub fn normalize_datetime(str: &String) -> Option<String> {
+    let datetime = DateTime::parse_from_rfc3339(str).ok()?;
+    Some(format!(
+        "{}",
+        datetime.with_timezone(&Utc).format(RFC3339_VARIANT)
+    ))
+}
@@ -1,9 +1,16 @@
 use crate::common::env::{env_bool, env_int, env_list, env_str};
-use crate::common::time::{DAY, HOUR, SECOND};
+use crate::common::time::{DAY, HOUR, MINUTE, SECOND};
 use crate::context;
 use anyhow::{bail, Result};
+use lazy_static::lazy_static;
 use reqwest::header::HeaderMap;
 
+lazy_static! {
+    /// Parsed from the environment once. Rocket manages a clone for routes; code that runs
+    /// outside of a request handler reads this one.
+    pub static ref SERVER_CONFIG: ServerConfig = env_to_cfg();
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ServerConfig {
     pub service: CoreConfig,
@@ -14,6 +21,17 @@ pub struct ServerConfig {
     pub invites: InvitesConfig,
     pub identity: IdentityConfig,
     pub crawlers: Vec<String>,
+    pub rate_limits: RateLimitsConfig,
+    pub labeler: Option<LabelerConfig>,
+    pub jobs: JobsConfig,
+    pub quotas: QuotaConfig,
+    pub push: PushConfig,
+    pub notifications: NotificationsConfig,
+    pub local_appview: LocalAppViewConfig,
+    pub chat: ChatConfig,
+    pub images: ImagesConfig,
+    pub uploads: UploadsConfig,
+    pub video: VideoConfig,
 }
 
 /// BksyAppViewConfig, ModServiceConfig, ReportServiceConfig, etc.
@@ -22,6 +40,7 @@ pub struct ServiceConfig {
     pub url: String,
     pub did: String,
     pub cdn_url_pattern: Option<String>, // for BksyAppViewConfig, otherwise None
+    pub video_cdn_url_pattern: Option<String>, // for BksyAppViewConfig, otherwise None
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -49,6 +68,137 @@ pub struct InvitesConfig {
     pub epoch: Option<usize>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitsConfig {
+    pub enabled: bool,
+    pub bypass_ips: Vec<String>,
+    /// createSession attempts per client IP
+    pub login_per_ip: RateLimitConfig,
+    /// createSession attempts per identifier, regardless of IP
+    pub login_per_identifier: RateLimitConfig,
+    /// requestPasswordReset & requestEmailConfirmation, per client IP and per account
+    pub email: RateLimitConfig,
+    /// Consecutive failed logins before an account is temporarily locked
+    pub lockout_threshold: usize,
+    pub lockout_duration_ms: u64,
+    /// Delay added after each failed login, doubling up to `max_failure_delay_ms`
+    pub failure_delay_ms: u64,
+    pub max_failure_delay_ms: u64,
+}
+
+/// Default per-account storage quotas, `None` meaning unlimited. Admins can override
+/// them per account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaConfig {
+    pub max_blob_bytes: Option<i64>,
+    pub max_records: Option<i64>,
+    pub max_repo_bytes: Option<i64>,
+}
+
+/// Push registrations kept by the PDS itself rather than the appview's notification service
+#[derive(Debug, Clone, PartialEq)]
+pub struct PushConfig {
+    pub local: bool,
+    /// Receives a notification payload, including the registration's token, for every
+    /// ios, android and web registration. UnifiedPush registrations are encrypted and
+    /// posted to their own Web Push endpoint.
+    pub webhook_url: Option<String>,
+}
+
+/// Notifications indexed from records on this PDS, served in place of the appview's
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationsConfig {
+    pub local: bool,
+}
+
+/// `app.bsky.*` views built from the records stored on this PDS rather than proxied
+/// to the appview
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalAppViewConfig {
+    pub enabled: bool,
+}
+
+/// `chat.bsky.convo` served from this PDS rather than proxied to a chat service
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatConfig {
+    pub local: bool,
+}
+
+/// Processing of uploaded images and the local image endpoint under `/img/`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagesConfig {
+    /// Strip EXIF, XMP and text metadata (GPS location included) from uploaded JPEGs and
+    /// PNGs, keeping only the orientation. Off by default since the stored bytes, and so
+    /// the blob's cid, then differ from what the client sent.
+    pub strip_metadata: bool,
+    /// Uploaded images wider or taller than this are rejected
+    pub max_dimension: Option<u32>,
+    /// Image urls in views when the appview has no CDN; `{}`s are the preset, did and cid.
+    /// Defaults to this PDS's `/img/` endpoint.
+    pub cdn_url_pattern: Option<String>,
+}
+
+/// Resumable uploads through `com.rsky.repo.createUpload` and `uploadChunk`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadsConfig {
+    /// Largest blob that can be uploaded in chunks
+    pub max_size: u64,
+    pub max_chunk_size: u64,
+    /// How long an upload can take before it and its chunks are collected
+    pub expiry_ms: u64,
+    /// Uploads an account can have in progress at once
+    pub max_open_per_account: u64,
+}
+
+/// `app.bsky.video` served from this PDS. Videos aren't transcoded, so only containers
+/// and codecs that play as is are accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoConfig {
+    pub local: bool,
+    pub max_size: u64,
+    pub max_duration_ms: u64,
+    /// Videos wider or taller than this are rejected
+    pub max_dimension: u32,
+    pub daily_videos: i64,
+    pub daily_bytes: i64,
+    pub mime_types: Vec<String>,
+    /// Sample entry types of the video track, e.g. `avc1` for H.264
+    pub codecs: Vec<String>,
+    /// How often the queue of uploaded videos is checked
+    pub process_interval_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobsConfig {
+    pub enabled: bool,
+    /// How often accounts past their `deleteAfter` are purged
+    pub account_purge_interval_ms: u64,
+    pub blob_gc_interval_ms: u64,
+    /// Unreferenced blobs and store objects younger than this are never collected, so
+    /// uploads have time to be referenced by a record
+    pub blob_gc_min_age_ms: u64,
+    /// Only log what the scheduled blob GC would delete. On unless `PDS_BLOB_GC_DRY_RUN` is
+    /// set to false, so nothing is deleted before an operator has reviewed a report.
+    pub blob_gc_dry_run: bool,
+    /// How often posts and profiles missing from the search index are looked for
+    pub search_backfill_interval_ms: u64,
+    /// How often the one-off reindex of reference backlinks is retried until it finishes
+    pub backlink_reindex_interval_ms: u64,
+}
+
+/// Enabled when `PDS_LABELER_SIGNING_KEY_K256_PRIVATE_KEY_HEX` is set
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelerConfig {
+    /// `src` of the labels this service signs
+    pub did: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    pub points: usize,
+    pub duration_ms: u64,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CoreConfig {
     pub port: usize,
@@ -115,6 +265,7 @@ pub fn env_to_cfg() -> ServerConfig {
                 "if bsky appview service url is configured, must configure its did as well.",
             ),
             cdn_url_pattern: env_str("PDS_BSKY_APP_VIEW_CDN_URL_PATTERN"),
+            video_cdn_url_pattern: env_str("PDS_BSKY_APP_VIEW_VIDEO_CDN_URL_PATTERN"),
         }),
     };
     let mod_service_cfg: Option<ServiceConfig> = match env_str("PDS_MOD_SERVICE_URL") {
@@ -124,6 +275,7 @@ pub fn env_to_cfg() -> ServerConfig {
             did: env_str("PDS_MOD_SERVICE_DID")
                 .expect("if mod service url is configured, must configure its did as well."),
             cdn_url_pattern: None,
+            video_cdn_url_pattern: None,
         }),
     };
     let mut report_service_cfg: Option<ServiceConfig> = match env_str("PDS_REPORT_SERVICE_URL") {
@@ -133,6 +285,7 @@ pub fn env_to_cfg() -> ServerConfig {
             did: env_str("PDS_REPORT_SERVICE_DID")
                 .expect("if mod service url is configured, must configure its did as well."),
             cdn_url_pattern: None,
+            video_cdn_url_pattern: None,
         }),
     };
 
@@ -159,6 +312,118 @@ pub fn env_to_cfg() -> ServerConfig {
         },
     };
     let crawlers_cfg = env_list("PDS_CRAWLERS");
+    let rate_limits_cfg = RateLimitsConfig {
+        enabled: env_bool("PDS_RATE_LIMITS_ENABLED").unwrap_or(true),
+        bypass_ips: env_list("PDS_RATE_LIMIT_BYPASS_IPS"),
+        login_per_ip: RateLimitConfig {
+            points: env_int("PDS_LOGIN_RATE_LIMIT_IP_POINTS").unwrap_or(30),
+            duration_ms: env_int("PDS_LOGIN_RATE_LIMIT_IP_DURATION_MS")
+                .unwrap_or(5 * MINUTE as usize) as u64,
+        },
+        login_per_identifier: RateLimitConfig {
+            points: env_int("PDS_LOGIN_RATE_LIMIT_IDENTIFIER_POINTS").unwrap_or(10),
+            duration_ms: env_int("PDS_LOGIN_RATE_LIMIT_IDENTIFIER_DURATION_MS")
+                .unwrap_or(5 * MINUTE as usize) as u64,
+        },
+        email: RateLimitConfig {
+            points: env_int("PDS_EMAIL_RATE_LIMIT_POINTS").unwrap_or(15),
+            duration_ms: env_int("PDS_EMAIL_RATE_LIMIT_DURATION_MS").unwrap_or(HOUR as usize)
+                as u64,
+        },
+        lockout_threshold: env_int("PDS_LOGIN_LOCKOUT_THRESHOLD").unwrap_or(10),
+        lockout_duration_ms: env_int("PDS_LOGIN_LOCKOUT_DURATION_MS")
+            .unwrap_or(15 * MINUTE as usize) as u64,
+        failure_delay_ms: env_int("PDS_LOGIN_FAILURE_DELAY_MS").unwrap_or(250) as u64,
+        max_failure_delay_ms: env_int("PDS_LOGIN_MAX_FAILURE_DELAY_MS")
+            .unwrap_or(4 * SECOND as usize) as u64,
+    };
+
+    let labeler_cfg: Option<LabelerConfig> =
+        match env_str("PDS_LABELER_SIGNING_KEY_K256_PRIVATE_KEY_HEX") {
+            None => None,
+            Some(_) => Some(LabelerConfig {
+                did: env_str("PDS_LABELER_DID").unwrap_or(service_cfg.did.clone()),
+            }),
+        };
+
+    let jobs_cfg = JobsConfig {
+        enabled: env_bool("PDS_JOBS_ENABLED").unwrap_or(true),
+        account_purge_interval_ms: env_int("PDS_ACCOUNT_PURGE_INTERVAL_MS").unwrap_or(HOUR as usize)
+            as u64,
+        blob_gc_interval_ms: env_int("PDS_BLOB_GC_INTERVAL_MS").unwrap_or(DAY as usize) as u64,
+        blob_gc_min_age_ms: env_int("PDS_BLOB_GC_MIN_AGE_MS").unwrap_or(DAY as usize) as u64,
+        blob_gc_dry_run: env_bool("PDS_BLOB_GC_DRY_RUN").unwrap_or(true),
+        search_backfill_interval_ms: env_int("PDS_SEARCH_BACKFILL_INTERVAL_MS")
+            .unwrap_or(DAY as usize) as u64,
+        backlink_reindex_interval_ms: env_int("PDS_BACKLINK_REINDEX_INTERVAL_MS")
+            .unwrap_or(HOUR as usize) as u64,
+    };
+
+    let quotas_cfg = QuotaConfig {
+        max_blob_bytes: env_int("PDS_QUOTA_MAX_BLOB_BYTES").map(|max| max as i64),
+        max_records: env_int("PDS_QUOTA_MAX_RECORDS").map(|max| max as i64),
+        max_repo_bytes: env_int("PDS_QUOTA_MAX_REPO_BYTES").map(|max| max as i64),
+    };
+
+    let push_cfg = PushConfig {
+        local: env_bool("PDS_PUSH_LOCAL").unwrap_or(bsky_app_view_cfg.is_none()),
+        webhook_url: env_str("PDS_PUSH_WEBHOOK_URL"),
+    };
+
+    let notifications_cfg = NotificationsConfig {
+        local: env_bool("PDS_NOTIFICATIONS_LOCAL").unwrap_or(bsky_app_view_cfg.is_none()),
+    };
+
+    let local_appview_cfg = LocalAppViewConfig {
+        enabled: env_bool("PDS_LOCAL_APPVIEW").unwrap_or(bsky_app_view_cfg.is_none()),
+    };
+
+    let chat_cfg = ChatConfig {
+        local: env_bool("PDS_CHAT_LOCAL").unwrap_or(bsky_app_view_cfg.is_none()),
+    };
+
+    let images_cfg = ImagesConfig {
+        strip_metadata: env_bool("PDS_IMAGE_STRIP_METADATA").unwrap_or(false),
+        max_dimension: env_int("PDS_IMAGE_MAX_DIMENSION").map(|max| max as u32),
+        cdn_url_pattern: match env_bool("PDS_IMAGE_LOCAL_CDN").unwrap_or(true) {
+            false => None,
+            true => Some(env_str("PDS_IMAGE_CDN_URL_PATTERN").unwrap_or(format!(
+                "{}/img/{{}}/plain/{{}}/{{}}@jpeg",
+                service_cfg.public_url
+            ))),
+        },
+    };
+
+    let uploads_cfg = UploadsConfig {
+        max_size: env_int("PDS_UPLOAD_MAX_SIZE").unwrap_or(100 * 1024 * 1024) as u64,
+        max_chunk_size: env_int("PDS_UPLOAD_MAX_CHUNK_SIZE").unwrap_or(8 * 1024 * 1024) as u64,
+        expiry_ms: env_int("PDS_UPLOAD_EXPIRY_MS").unwrap_or(DAY as usize) as u64,
+        max_open_per_account: env_int("PDS_UPLOAD_MAX_OPEN_PER_ACCOUNT").unwrap_or(5) as u64,
+    };
+
+    let video_cfg = VideoConfig {
+        local: env_bool("PDS_VIDEO_LOCAL").unwrap_or(true),
+        max_size: env_int("PDS_VIDEO_MAX_SIZE").unwrap_or(100 * 1024 * 1024) as u64,
+        max_duration_ms: env_int("PDS_VIDEO_MAX_DURATION_MS").unwrap_or(3 * MINUTE as usize) as u64,
+        max_dimension: env_int("PDS_VIDEO_MAX_DIMENSION").unwrap_or(4096) as u32,
+        daily_videos: env_int("PDS_VIDEO_DAILY_LIMIT").unwrap_or(25) as i64,
+        daily_bytes: env_int("PDS_VIDEO_DAILY_BYTES").unwrap_or(10 * 1024 * 1024 * 1024) as i64,
+        mime_types: match env_list("PDS_VIDEO_MIME_TYPES") {
+            mime_types if mime_types.is_empty() => {
+                vec!["video/mp4".to_string(), "video/quicktime".to_string()]
+            }
+            mime_types => mime_types,
+        },
+        codecs: match env_list("PDS_VIDEO_CODECS") {
+            codecs if codecs.is_empty() => ["avc1", "avc3", "hvc1", "hev1", "av01", "vp09"]
+                .iter()
+                .map(|codec| codec.to_string())
+                .collect(),
+            codecs => codecs,
+        },
+        process_interval_ms: env_int("PDS_VIDEO_PROCESS_INTERVAL_MS").unwrap_or(2 * SECOND as usize)
+            as u64,
+    };
 
     ServerConfig {
         service: service_cfg,
@@ -169,6 +434,17 @@ pub fn env_to_cfg() -> ServerConfig {
         invites: invites_cfg,
         crawlers: crawlers_cfg,
         identity: identity_cfg,
+        rate_limits: rate_limits_cfg,
+        labeler: labeler_cfg,
+        jobs: jobs_cfg,
+        quotas: quotas_cfg,
+        push: push_cfg,
+        notifications: notifications_cfg,
+        local_appview: local_appview_cfg,
+        chat: chat_cfg,
+        images: images_cfg,
+        uploads: uploads_cfg,
+        video: video_cfg,
     }
 }
 
@@ -0,0 +1,246 @@
+use crate::common::time::from_str_to_millis;
+use crate::common::RFC3339_VARIANT;
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_builder::BoxedSelectStatement;
+use diesel::sql_types::{Bool, Text};
+
+/// The parts of a keyset cursor. `secondary` is only set for two-part keysets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cursor {
+    pub primary: String,
+    pub secondary: Option<String>,
+}
+
+pub struct KeySetPaginateOpts {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub direction: Option<String>,
+}
+
+/// A KeySet sets-up the interface and partial implementation of a keyset-paginated cursor
+/// with one or two parts. There are three types involved:
+///  - Result: a raw result (i.e. a row from the db) containing data that will make-up a cursor.
+///    - E.g. { createdAt: '2022-01-01T12:00:00Z', cid: 'bafyx' }
+///  - LabeledResult: a Result processed such that the "primary" and "secondary" parts of the cursor are labeled.
+///    - E.g. { primary: '2022-01-01T12:00:00Z', secondary: 'bafyx' }
+///  - Cursor: the string parts that make-up the packed/string cursor.
+///    - E.g. packed cursor '1641038400000::bafyx' in parts { primary: '1641038400000', secondary: 'bafyx' }
+///
+/// These types relate as such. Implementers define the relations marked with a *:
+///   Result -*-> LabeledResult <-*-> Cursor <--> packed/string cursor
+///                     ↳ SQL Condition
+pub trait KeySet {
+    type Result;
+
+    /// SQL expression the primary part of the cursor is compared against, e.g. `"actor"."createdAt"`
+    fn primary(&self) -> String;
+
+    /// SQL expression for the secondary part of the cursor, for keysets whose primary
+    /// part isn't unique
+    fn secondary(&self) -> Option<String> {
+        None
+    }
+
+    /// Postgres type labeled primary values are cast to before comparison
+    fn primary_type(&self) -> &'static str {
+        "varchar"
+    }
+
+    fn label_result(&self, result: &Self::Result) -> Result<Cursor>;
+
+    fn labeled_result_to_cursor(&self, labeled: Cursor) -> Result<Cursor> {
+        Ok(labeled)
+    }
+
+    fn cursor_to_labeled_result(&self, cursor: Cursor) -> Result<Cursor> {
+        Ok(cursor)
+    }
+
+    fn pack_from_result(&self, results: &[Self::Result]) -> Result<Option<String>> {
+        match results.last() {
+            None => Ok(None),
+            Some(result) => self.pack(Some(self.label_result(result)?)),
+        }
+    }
+
+    fn pack(&self, labeled: Option<Cursor>) -> Result<Option<String>> {
+        match labeled {
+            None => Ok(None),
+            Some(labeled) => {
+                let cursor = self.labeled_result_to_cursor(labeled)?;
+                Ok(self.pack_cursor(Some(cursor)))
+            }
+        }
+    }
+
+    fn unpack(&self, cursor_str: Option<String>) -> Result<Option<Cursor>> {
+        match self.unpack_cursor(cursor_str)? {
+            None => Ok(None),
+            Some(cursor) => Ok(Some(self.cursor_to_labeled_result(cursor)?)),
+        }
+    }
+
+    fn pack_cursor(&self, cursor: Option<Cursor>) -> Option<String> {
+        match cursor {
+            None => None,
+            Some(Cursor {
+                primary,
+                secondary: None,
+            }) => Some(primary),
+            Some(Cursor {
+                primary,
+                secondary: Some(secondary),
+            }) => Some(format!("{primary}::{secondary}")),
+        }
+    }
+
+    fn unpack_cursor(&self, cursor_str: Option<String>) -> Result<Option<Cursor>> {
+        match cursor_str {
+            None => Ok(None),
+            Some(cursor_str) if self.secondary().is_none() => Ok(Some(Cursor {
+                primary: cursor_str,
+                secondary: None,
+            })),
+            Some(cursor_str) => {
+                let result = cursor_str.split("::").collect::<Vec<&str>>();
+                match (result.get(0), result.get(1), result.get(2)) {
+                    (Some(primary), Some(secondary), None) => Ok(Some(Cursor {
+                        primary: primary.to_string(),
+                        secondary: Some(secondary.to_string()),
+                    })),
+                    _ => bail!("Malformed cursor"),
+                }
+            }
+        }
+    }
+}
+
+/// Applies keyset ordering, the cursor condition and the limit to a query. Results
+/// are ordered descending unless `direction` is `asc`.
+pub fn paginate<'a, ST, QS, K: KeySet>(
+    mut builder: BoxedSelectStatement<'a, ST, QS, Pg>,
+    keyset: &K,
+    opts: KeySetPaginateOpts,
+) -> Result<BoxedSelectStatement<'a, ST, QS, Pg>> {
+    let KeySetPaginateOpts {
+        limit,
+        cursor,
+        direction,
+    } = opts;
+    let (order, op) = match direction.as_deref() {
+        None | Some("desc") => ("DESC", "<"),
+        Some("asc") => ("ASC", ">"),
+        Some(direction) => bail!("Unknown sort direction: {direction}"),
+    };
+    let primary = keyset.primary();
+    let secondary = keyset.secondary();
+
+    builder = match &secondary {
+        None => builder.order(sql::<Text>(&format!("{primary} {order}"))),
+        Some(secondary) => builder.order(sql::<Text>(&format!(
+            "{primary} {order}, {secondary} {order}"
+        ))),
+    };
+
+    if let Some(labeled) = keyset.unpack(cursor)? {
+        let primary_type = keyset.primary_type();
+        builder = match (&secondary, labeled.secondary) {
+            (None, _) => builder.filter(
+                sql::<Bool>(&format!("({primary} {op} CAST("))
+                    .bind::<Text, _>(labeled.primary)
+                    .sql(&format!(" AS {primary_type}))")),
+            ),
+            (Some(secondary), Some(labeled_secondary)) => builder.filter(
+                sql::<Bool>(&format!("(({primary}, {secondary}) {op} (CAST("))
+                    .bind::<Text, _>(labeled.primary)
+                    .sql(&format!(" AS {primary_type}), "))
+                    .bind::<Text, _>(labeled_secondary)
+                    .sql("))"),
+            ),
+            (Some(_), None) => bail!("Malformed cursor"),
+        };
+    }
+
+    if let Some(limit) = limit {
+        builder = builder.limit(limit);
+    }
+    Ok(builder)
+}
+
+/// Keyset over a `createdAt` timestamp, tie-broken by a unique key. Cursors carry the
+/// timestamp in millis, e.g. `1641038400000::did:plc:abc`.
+pub struct TimeKeySet {
+    pub created_at: &'static str,
+    pub key: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimeKeyResult {
+    pub created_at: String,
+    pub key: String,
+}
+
+impl KeySet for TimeKeySet {
+    type Result = TimeKeyResult;
+
+    fn primary(&self) -> String {
+        self.created_at.to_string()
+    }
+
+    fn secondary(&self) -> Option<String> {
+        Some(self.key.to_string())
+    }
+
+    fn label_result(&self, result: &TimeKeyResult) -> Result<Cursor> {
+        Ok(Cursor {
+            primary: result.created_at.clone(),
+            secondary: Some(result.key.clone()),
+        })
+    }
+
+    fn labeled_result_to_cursor(&self, labeled: Cursor) -> Result<Cursor> {
+        Ok(Cursor {
+            primary: from_str_to_millis(&labeled.primary)?.to_string(),
+            secondary: labeled.secondary,
+        })
+    }
+
+    fn cursor_to_labeled_result(&self, cursor: Cursor) -> Result<Cursor> {
+        let millis = cursor
+            .primary
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Malformed cursor"))?;
+        // Out of range timestamps parse fine but aren't dates
+        let primary_date = DateTime::<Utc>::from_timestamp_millis(millis)
+            .ok_or_else(|| anyhow!("Malformed cursor"))?;
+        Ok(Cursor {
+            primary: format!("{}", primary_date.format(RFC3339_VARIANT)),
+            secondary: cursor.secondary,
+        })
+    }
+}
+
+/// Keyset over a single unique text column, e.g. a blob cid or a record key within
+/// a collection. The cursor is the key itself.
+pub struct StringKeySet {
+    pub key: &'static str,
+}
+
+impl KeySet for StringKeySet {
+    type Result = String;
+
+    fn primary(&self) -> String {
+        self.key.to_string()
+    }
+
+    fn label_result(&self, result: &String) -> Result<Cursor> {
+        Ok(Cursor {
+            primary: result.clone(),
+            secondary: None,
+        })
+    }
+}
@@ -0,0 +1,146 @@
+// Relationships between accounts as seen from this PDS. Blocks are public
+// `app.bsky.graph.block` records, found through the backlink index, so only blocks made
+// by accounts hosted here are known. Mutes are private and are kept in `actor_mute`,
+// like the appview keeps them out of the repo.
+
+use crate::common::now;
+use crate::db::establish_connection;
+use crate::repo::types::Ids;
+use anyhow::Result;
+use diesel::*;
+
+/// Uri of `did`'s record in `collection` whose `subject` is `subject`
+fn find_subject_record(did: &String, collection: &str, subject: &String) -> Result<Option<String>> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::did.eq(did))
+        .filter(RecordSchema::collection.eq(collection))
+        .filter(BacklinkSchema::path.eq("subject"))
+        .filter(BacklinkSchema::linkTo.eq(subject))
+        .select(RecordSchema::uri)
+        .first::<String>(conn)
+        .optional()?;
+    Ok(res)
+}
+
+/// Uri of `did`'s block of `subject`
+pub fn get_block(did: &String, subject: &String) -> Result<Option<String>> {
+    find_subject_record(did, Ids::AppBskyGraphBlock.as_str(), subject)
+}
+
+/// Whether either account blocks the other
+pub fn is_blocked_between(did: &String, other: &String) -> Result<bool> {
+    Ok(get_block(did, other)?.is_some() || get_block(other, did)?.is_some())
+}
+
+pub fn is_following(did: &String, subject: &String) -> Result<bool> {
+    Ok(find_subject_record(did, Ids::AppBskyGraphFollow.as_str(), subject)?.is_some())
+}
+
+pub fn is_muted(did: &String, subject: &String) -> Result<bool> {
+    use crate::schema::pds::actor_mute::dsl as ActorMuteSchema;
+    let conn = &mut establish_connection()?;
+
+    let muted = select(dsl::exists(
+        ActorMuteSchema::actor_mute
+            .filter(ActorMuteSchema::did.eq(did))
+            .filter(ActorMuteSchema::subjectDid.eq(subject)),
+    ))
+    .get_result(conn)?;
+    Ok(muted)
+}
+
+pub fn mute_actor(did: &String, subject: &String) -> Result<()> {
+    use crate::schema::pds::actor_mute::dsl as ActorMuteSchema;
+    let conn = &mut establish_connection()?;
+
+    insert_into(ActorMuteSchema::actor_mute)
+        .values((
+            ActorMuteSchema::did.eq(did),
+            ActorMuteSchema::subjectDid.eq(subject),
+            ActorMuteSchema::createdAt.eq(now()),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn unmute_actor(did: &String, subject: &String) -> Result<()> {
+    use crate::schema::pds::actor_mute::dsl as ActorMuteSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(ActorMuteSchema::actor_mute)
+        .filter(ActorMuteSchema::did.eq(did))
+        .filter(ActorMuteSchema::subjectDid.eq(subject))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Accounts muted by `did`, most recently muted first, with the cursor for the next page
+pub fn list_mutes(
+    did: &String,
+    limit: i64,
+    cursor: Option<String>,
+) -> Result<(Vec<String>, Option<String>)> {
+    use crate::schema::pds::actor_mute::dsl as ActorMuteSchema;
+    let conn = &mut establish_connection()?;
+
+    let mut builder = ActorMuteSchema::actor_mute
+        .filter(ActorMuteSchema::did.eq(did))
+        .select((ActorMuteSchema::subjectDid, ActorMuteSchema::createdAt))
+        .order((
+            ActorMuteSchema::createdAt.desc(),
+            ActorMuteSchema::subjectDid.desc(),
+        ))
+        .limit(limit)
+        .into_boxed();
+    if let Some(cursor) = cursor {
+        builder = builder.filter(ActorMuteSchema::createdAt.lt(cursor));
+    }
+    let mutes: Vec<(String, String)> = builder.load(conn)?;
+    let cursor = match mutes.len() as i64 == limit {
+        true => mutes.last().map(|(_, created_at)| created_at.clone()),
+        false => None,
+    };
+    Ok((
+        mutes.into_iter().map(|(subject, _)| subject).collect(),
+        cursor,
+    ))
+}
+
+/// Accounts whose content `did` shouldn't see: those they block or mute, and those
+/// blocking them
+pub fn get_hidden_actors(did: &String) -> Result<Vec<String>> {
+    use crate::schema::pds::actor_mute::dsl as ActorMuteSchema;
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let mut hidden: Vec<String> = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::did.eq(did))
+        .filter(RecordSchema::collection.eq(Ids::AppBskyGraphBlock.as_str()))
+        .filter(BacklinkSchema::path.eq("subject"))
+        .select(BacklinkSchema::linkTo)
+        .load(conn)?;
+    let blocked_by: Vec<String> = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::collection.eq(Ids::AppBskyGraphBlock.as_str()))
+        .filter(BacklinkSchema::path.eq("subject"))
+        .filter(BacklinkSchema::linkTo.eq(did))
+        .select(RecordSchema::did)
+        .load(conn)?;
+    let muted: Vec<String> = ActorMuteSchema::actor_mute
+        .filter(ActorMuteSchema::did.eq(did))
+        .select(ActorMuteSchema::subjectDid)
+        .load(conn)?;
+    hidden.extend(blocked_by);
+    hidden.extend(muted);
+    hidden.sort();
+    hidden.dedup();
+    Ok(hidden)
+}
@@ -0,0 +1,99 @@
+use crate::apis::com::atproto::repo::assert_repo_availability;
+use crate::image::{render_variant, ImageError, ImagePreset};
+use crate::models::{ErrorCode, ErrorMessageResponse};
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::error::BlobError;
+use crate::repo::ActorStore;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use lexicon_cid::Cid;
+use rocket::http::{Header, Status};
+use rocket::response::status;
+use rocket::serde::json::Json;
+use rocket::{Responder, State};
+use std::str::FromStr;
+
+#[derive(Responder)]
+#[response(status = 200, content_type = "image/jpeg")]
+pub struct ImageResponder(Vec<u8>, Header<'static>, Header<'static>);
+
+async fn inner_get_image(
+    preset: String,
+    did: String,
+    file: String,
+    s3_config: &State<SdkConfig>,
+) -> Result<Vec<u8>> {
+    let preset = ImagePreset::from_str(&preset)?;
+    let cid = Cid::from_str(file.strip_suffix("@jpeg").unwrap_or(&file))?;
+    let _ = assert_repo_availability(&did, false).await?;
+
+    let actor_store = ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), s3_config));
+    // Checked every time, so variants of taken down blobs aren't served
+    let metadata = actor_store.blob.get_blob_metadata(cid).await?;
+    if !metadata.mime_type.unwrap_or_default().starts_with("image/") {
+        bail!(BlobError::BlobNotFoundError)
+    }
+    let blobstore = &actor_store.blob.blobstore;
+    if let Some(variant) = blobstore.get_variant_bytes(cid, preset.as_str()).await? {
+        return Ok(variant);
+    }
+    let bytes = actor_store
+        .blob
+        .get_blob(cid)
+        .await?
+        .stream
+        .collect()
+        .await?
+        .to_vec();
+    let variant = tokio::task::spawn_blocking(move || render_variant(&bytes, preset)).await??;
+    blobstore
+        .put_variant(cid, preset.as_str(), variant.clone())
+        .await?;
+    Ok(variant)
+}
+
+/// Resized variants of an account's images, laid out like the appview CDN so views can
+/// point here when there's no appview CDN: avatar, avatar_thumbnail, banner,
+/// feed_thumbnail and feed_fullsize. Always a JPEG; the `@jpeg` suffix is optional.
+/// Each variant is rendered once and kept in the blob store.
+#[rocket::get("/img/<preset>/plain/<did>/<file>")]
+pub async fn get_image(
+    preset: String,
+    did: String,
+    file: String,
+    s3_config: &State<SdkConfig>,
+) -> Result<ImageResponder, status::Custom<Json<ErrorMessageResponse>>> {
+    match inner_get_image(preset, did, file, s3_config).await {
+        Ok(bytes) => Ok(ImageResponder(
+            bytes,
+            // Variants are addressed by the cid of their source, so they never change
+            Header::new("cache-control", "public, max-age=31536000, immutable"),
+            Header::new("content-security-policy", "default-src 'none'; sandbox"),
+        )),
+        Err(error) => {
+            eprintln!("@LOG: ERROR: {error}");
+            if error.downcast_ref::<ImageError>().is_some() {
+                let bad_request = ErrorMessageResponse {
+                    code: Some(ErrorCode::BadRequest),
+                    message: Some(error.to_string()),
+                };
+                return Err(status::Custom(Status::BadRequest, Json(bad_request)));
+            }
+            if error.downcast_ref::<BlobError>().is_some() {
+                let not_found = ErrorMessageResponse {
+                    code: Some(ErrorCode::NotFound),
+                    message: Some(error.to_string()),
+                };
+                return Err(status::Custom(Status::NotFound, Json(not_found)));
+            }
+            let internal_error = ErrorMessageResponse {
+                code: Some(ErrorCode::InternalServerError),
+                message: Some(error.to_string()),
+            };
+            Err(status::Custom(
+                Status::InternalServerError,
+                Json(internal_error),
+            ))
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// Lossless removal of EXIF, XMP and other embedded metadata from JPEGs and PNGs. Only the
+// metadata segments/chunks are dropped; the image data is copied as is.
+
+use anyhow::{bail, Result};
+
+const JPEG_SOI: [u8; 2] = [0xFF, 0xD8];
+const JPEG_APP0: u8 = 0xE0;
+const JPEG_APP1: u8 = 0xE1;
+const JPEG_APP13: u8 = 0xED;
+const JPEG_SOS: u8 = 0xDA;
+const JPEG_EOI: u8 = 0xD9;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_EXTENSION_HEADER: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+// IPTC, which can hold a location as well
+const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+const EXIF_ORIENTATION_TAG: u16 = 0x0112;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 4] = [b"eXIf", b"tEXt", b"zTXt", b"iTXt"];
+
+/// Returns the image without its metadata, or None if it isn't a JPEG or PNG
+pub fn strip_metadata(bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+    if bytes.starts_with(&JPEG_SOI) {
+        Ok(Some(strip_jpeg(bytes)?))
+    } else if bytes.starts_with(&PNG_SIGNATURE) {
+        Ok(Some(strip_png(bytes)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The EXIF orientation of a JPEG (1-8), if it has one
+pub fn jpeg_orientation(bytes: &[u8]) -> Option<u16> {
+    if !bytes.starts_with(&JPEG_SOI) {
+        return None;
+    }
+    let mut i = 2;
+    while let Some((marker, segment_len)) = jpeg_segment_at(bytes, i) {
+        if marker == JPEG_SOS || marker == JPEG_EOI {
+            return None;
+        }
+        let payload = &bytes[i + 4..i + segment_len];
+        if marker == JPEG_APP1 && payload.starts_with(EXIF_HEADER) {
+            return exif_orientation(&payload[EXIF_HEADER.len()..]);
+        }
+        i += segment_len;
+    }
+    None
+}
+
+/// Marker and total length (marker included) of the JPEG segment at `i`
+fn jpeg_segment_at(bytes: &[u8], i: usize) -> Option<(u8, usize)> {
+    if bytes.get(i)? != &0xFF {
+        return None;
+    }
+    let marker = *bytes.get(i + 1)?;
+    if marker == JPEG_SOS || marker == JPEG_EOI {
+        return Some((marker, 2));
+    }
+    let len = u16::from_be_bytes([*bytes.get(i + 2)?, *bytes.get(i + 3)?]) as usize;
+    if len < 2 || i + 2 + len > bytes.len() {
+        return None;
+    }
+    Some((marker, 2 + len))
+}
+
+fn strip_jpeg(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut out: Vec<u8> = JPEG_SOI.to_vec();
+    let mut orientation: Option<u16> = None;
+    // The orientation goes back in after any JFIF header, where EXIF would normally be
+    let mut exif_at: Option<usize> = None;
+    let mut i = 2;
+    loop {
+        // Markers may be preceded by fill bytes
+        while bytes.get(i) == Some(&0xFF) && bytes.get(i + 1) == Some(&0xFF) {
+            i += 1;
+        }
+        let Some((marker, segment_len)) = jpeg_segment_at(bytes, i) else {
+            bail!("Malformed JPEG segment at byte {i}")
+        };
+        if marker == JPEG_SOS || marker == JPEG_EOI {
+            let exif_at = exif_at.unwrap_or(out.len());
+            // Everything from the start of scan on is image data
+            out.extend_from_slice(&bytes[i..]);
+            if let Some(orientation) = orientation.filter(|orientation| *orientation != 1) {
+                out.splice(exif_at..exif_at, orientation_segment(orientation));
+            }
+            return Ok(out);
+        }
+        let segment = &bytes[i..i + segment_len];
+        let payload = &segment[4..];
+        match marker {
+            JPEG_APP1 if payload.starts_with(EXIF_HEADER) => {
+                orientation = exif_orientation(&payload[EXIF_HEADER.len()..]);
+            }
+            JPEG_APP1 if is_xmp(payload) => (),
+            JPEG_APP13 if payload.starts_with(PHOTOSHOP_HEADER) => (),
+            _ => {
+                if marker != JPEG_APP0 && exif_at.is_none() {
+                    exif_at = Some(out.len());
+                }
+                out.extend_from_slice(segment);
+            }
+        }
+        i += segment_len;
+    }
+}
+
+fn is_xmp(payload: &[u8]) -> bool {
+    payload.starts_with(XMP_HEADER) || payload.starts_with(XMP_EXTENSION_HEADER)
+}
+
+/// Reads the orientation tag from IFD0 of an EXIF (TIFF) block
+fn exif_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(0..2)? {
+        b"MM" => true,
+        b"II" => false,
+        _ => return None,
+    };
+    let read_u16 = |at: usize| -> Option<u16> {
+        let b = tiff.get(at..at + 2)?;
+        Some(match big_endian {
+            true => u16::from_be_bytes([b[0], b[1]]),
+            false => u16::from_le_bytes([b[0], b[1]]),
+        })
+    };
+    let read_u32 = |at: usize| -> Option<u32> {
+        let b = tiff.get(at..at + 4)?;
+        Some(match big_endian {
+            true => u32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            false => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        })
+    };
+    let ifd = read_u32(4)? as usize;
+    let entries = read_u16(ifd)? as usize;
+    (0..entries)
+        .map(|n| ifd + 2 + n * 12)
+        .find(|entry| read_u16(*entry) == Some(EXIF_ORIENTATION_TAG))
+        .and_then(|entry| read_u16(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+/// An APP1 segment with an EXIF block holding nothing but the orientation
+fn orientation_segment(orientation: u16) -> Vec<u8> {
+    let mut payload = EXIF_HEADER.to_vec();
+    // Big endian TIFF header, IFD0 right after it
+    payload.extend_from_slice(b"MM\0\x2a\0\0\0\x08");
+    payload.extend_from_slice(&1u16.to_be_bytes());
+    // Tag, type (SHORT), count, value padded to 4 bytes
+    payload.extend_from_slice(&EXIF_ORIENTATION_TAG.to_be_bytes());
+    payload.extend_from_slice(&3u16.to_be_bytes());
+    payload.extend_from_slice(&1u32.to_be_bytes());
+    payload.extend_from_slice(&orientation.to_be_bytes());
+    payload.extend_from_slice(&[0, 0]);
+    // No next IFD
+    payload.extend_from_slice(&0u32.to_be_bytes());
+
+    let mut segment = vec![0xFF, JPEG_APP1];
+    segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    segment.append(&mut payload);
+    segment
+}
+
+fn strip_png(bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut out: Vec<u8> = PNG_SIGNATURE.to_vec();
+    let mut i = PNG_SIGNATURE.len();
+    while i < bytes.len() {
+        let Some(len) = bytes.get(i..i + 4) else {
+            bail!("Malformed PNG chunk at byte {i}")
+        };
+        // Length, type, data and crc
+        let chunk_len = 12 + u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        let Some(chunk) = bytes.get(i..i + chunk_len) else {
+            bail!("Malformed PNG chunk at byte {i}")
+        };
+        let chunk_type = &chunk[4..8];
+        if !PNG_METADATA_CHUNKS
+            .iter()
+            .any(|metadata| chunk_type == metadata.as_slice())
+        {
+            out.extend_from_slice(chunk);
+        }
+        i += chunk_len;
+        if chunk_type == b"IEND" {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(payload);
+        segment
+    }
+
+    fn little_endian_exif(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"II\x2a\0\x08\0\0\0".to_vec();
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        // An unrelated tag ahead of the orientation
+        tiff.extend_from_slice(&0x010Fu16.to_le_bytes());
+        tiff.extend_from_slice(&[2, 0, 4, 0, 0, 0, b'A', b'B', b'C', 0]);
+        tiff.extend_from_slice(&EXIF_ORIENTATION_TAG.to_le_bytes());
+        tiff.extend_from_slice(&[3, 0, 1, 0, 0, 0]);
+        tiff.extend_from_slice(&orientation.to_le_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        tiff
+    }
+
+    fn jpeg(orientation: u16) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let jfif = jpeg_segment(JPEG_APP0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        let exif = jpeg_segment(
+            JPEG_APP1,
+            &[EXIF_HEADER, little_endian_exif(orientation).as_slice()].concat(),
+        );
+        let xmp = jpeg_segment(JPEG_APP1, &[XMP_HEADER, b"<x:xmpmeta/>"].concat());
+        let iptc = jpeg_segment(JPEG_APP13, &[PHOTOSHOP_HEADER, b"8BIM"].concat());
+        let dqt = jpeg_segment(0xDB, &[0; 65]);
+        let scan = [
+            &[0xFF, JPEG_SOS][..],
+            &[0, 8, 1, 2, 3, 4, 5, 6, 0x12, 0x34, 0xFF, JPEG_EOI],
+        ]
+        .concat();
+        let bytes = [
+            JPEG_SOI.to_vec(),
+            jfif.clone(),
+            exif,
+            xmp,
+            iptc,
+            dqt.clone(),
+            scan.clone(),
+        ]
+        .concat();
+        let kept = [JPEG_SOI.to_vec(), jfif].concat();
+        (bytes, kept, [dqt, scan].concat())
+    }
+
+    #[test]
+    fn strips_jpeg_metadata_but_keeps_orientation() -> Result<()> {
+        let (bytes, head, tail) = jpeg(6);
+        let stripped = strip_jpeg(&bytes)?;
+        assert_eq!(stripped, [head, orientation_segment(6), tail].concat());
+        assert_eq!(jpeg_orientation(&stripped), Some(6));
+        Ok(())
+    }
+
+    #[test]
+    fn drops_upright_orientation() -> Result<()> {
+        let (bytes, head, tail) = jpeg(1);
+        assert_eq!(jpeg_orientation(&bytes), Some(1));
+        assert_eq!(strip_jpeg(&bytes)?, [head, tail].concat());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_jpeg_segments() {
+        let (mut bytes, head, _) = jpeg(6);
+        // Claim the EXIF segment runs past the end of the file
+        let at = head.len() + 2;
+        bytes[at..at + 2].copy_from_slice(&u16::MAX.to_be_bytes());
+        assert!(strip_jpeg(&bytes).is_err());
+        assert!(strip_jpeg(&JPEG_SOI).is_err());
+    }
+
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        // The crc isn't checked
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    #[test]
+    fn strips_png_metadata_chunks() -> Result<()> {
+        let ihdr = png_chunk(b"IHDR", &[0, 0, 0, 1, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+        let idat = png_chunk(b"IDAT", &[0x78, 0x9c, 0x63, 0, 0, 0, 1, 0, 1]);
+        let iend = png_chunk(b"IEND", &[]);
+        let bytes = [
+            PNG_SIGNATURE.to_vec(),
+            ihdr.clone(),
+            png_chunk(b"tEXt", b"Comment\0hello"),
+            png_chunk(b"eXIf", &little_endian_exif(6)),
+            idat.clone(),
+            png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x/>"),
+            iend.clone(),
+            // Trailing bytes after IEND are dropped
+            b"junk".to_vec(),
+        ]
+        .concat();
+        assert_eq!(
+            strip_png(&bytes)?,
+            [PNG_SIGNATURE.to_vec(), ihdr, idat, iend].concat()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_truncated_png_chunks() {
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&png_chunk(b"IHDR", &[0; 13])[..10]);
+        assert!(strip_png(&bytes).is_err());
+        bytes.truncate(PNG_SIGNATURE.len() + 2);
+        assert!(strip_png(&bytes).is_err());
+    }
+
+    #[test]
+    fn reads_exif_orientation() {
+        assert_eq!(exif_orientation(&little_endian_exif(8)), Some(8));
+        // The orientation-only block written back into stripped JPEGs is big endian
+        let segment = orientation_segment(3);
+        assert_eq!(exif_orientation(&segment[4 + EXIF_HEADER.len()..]), Some(3));
+    }
+
+    #[test]
+    fn ignores_invalid_exif_orientation() {
+        assert_eq!(exif_orientation(&little_endian_exif(0)), None);
+        assert_eq!(exif_orientation(&little_endian_exif(9)), None);
+        let tiff = little_endian_exif(6);
+        assert_eq!(exif_orientation(&tiff[..tiff.len() - 10]), None);
+        assert_eq!(exif_orientation(&[b"XX", &tiff[2..]].concat()), None);
+        // IFD offset past the end of the block
+        let mut tiff = tiff;
+        tiff[4..8].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(exif_orientation(&tiff), None);
+    }
+}
@@ -1,7 +1,26 @@
-use anyhow::Result;
+use crate::config::SERVER_CONFIG;
+use anyhow::{bail, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
 use image::io::Reader as ImageReader;
-use image::{guess_format, GenericImageView};
+use image::{guess_format, DynamicImage};
 use std::io::Cursor;
+use std::str::FromStr;
+use thiserror::Error;
+
+const VARIANT_JPEG_QUALITY: u8 = 80;
+/// Images are only decoded below this many pixels, whatever the upload policy allowed
+const MAX_VARIANT_PIXELS: u64 = 50_000_000;
+
+#[derive(Error, Debug)]
+pub enum ImageError {
+    #[error("Image is {width}x{height}, images are limited to {max}x{max}")]
+    TooLarge { width: u32, height: u32, max: u32 },
+    #[error("Unknown image preset: {0}")]
+    UnknownPreset(String),
+    #[error("Image is {width}x{height}, too many pixels to render")]
+    TooManyPixels { width: u32, height: u32 },
+}
 
 pub struct ImageInfo {
     pub height: u32,
@@ -10,6 +29,54 @@ pub struct ImageInfo {
     pub mime: String,
 }
 
+/// Variants served by the local image endpoint, named after the appview CDN's
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImagePreset {
+    Avatar,
+    AvatarThumbnail,
+    Banner,
+    FeedThumbnail,
+    FeedFullsize,
+}
+
+impl ImagePreset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImagePreset::Avatar => "avatar",
+            ImagePreset::AvatarThumbnail => "avatar_thumbnail",
+            ImagePreset::Banner => "banner",
+            ImagePreset::FeedThumbnail => "feed_thumbnail",
+            ImagePreset::FeedFullsize => "feed_fullsize",
+        }
+    }
+
+    /// Bounding box, and whether the image is cropped to fill it (otherwise it fits inside)
+    fn size(&self) -> (u32, u32, bool) {
+        match self {
+            ImagePreset::Avatar => (1000, 1000, true),
+            ImagePreset::AvatarThumbnail => (128, 128, true),
+            ImagePreset::Banner => (3000, 1000, true),
+            ImagePreset::FeedThumbnail => (1000, 1000, false),
+            ImagePreset::FeedFullsize => (2000, 2000, false),
+        }
+    }
+}
+
+impl FromStr for ImagePreset {
+    type Err = ImageError;
+
+    fn from_str(preset: &str) -> Result<Self, Self::Err> {
+        match preset {
+            "avatar" => Ok(ImagePreset::Avatar),
+            "avatar_thumbnail" => Ok(ImagePreset::AvatarThumbnail),
+            "banner" => Ok(ImagePreset::Banner),
+            "feed_thumbnail" => Ok(ImagePreset::FeedThumbnail),
+            "feed_fullsize" => Ok(ImagePreset::FeedFullsize),
+            _ => Err(ImageError::UnknownPreset(preset.to_string())),
+        }
+    }
+}
+
 pub async fn mime_type_from_bytes(bytes: Vec<u8>) -> Result<Option<String>> {
     match infer::get(bytes.as_slice()) {
         Some(kind) => Ok(Some(kind.mime_type().to_string())),
@@ -17,12 +84,12 @@ pub async fn mime_type_from_bytes(bytes: Vec<u8>) -> Result<Option<String>> {
     }
 }
 
+/// Dimensions are read from the image header, without decoding it
 pub async fn maybe_get_info(bytes: Vec<u8>) -> Result<Option<ImageInfo>> {
     let process_image = || -> Result<Option<ImageInfo>> {
-        let img = ImageReader::new(Cursor::new(bytes.clone()))
+        let (width, height) = ImageReader::new(Cursor::new(bytes.as_slice()))
             .with_guessed_format()?
-            .decode()?;
-        let (width, height) = img.dimensions();
+            .into_dimensions()?;
         let mime = guess_format(bytes.as_slice())?.to_mime_type().to_string();
         let size: Option<u32> = None;
         Ok(Some(ImageInfo {
@@ -38,3 +105,82 @@ pub async fn maybe_get_info(bytes: Vec<u8>) -> Result<Option<ImageInfo>> {
         Err(_) => Ok(None),
     };
 }
+
+/// Applies the configured upload policy to a blob: images over the max dimension are
+/// rejected and, if enabled, metadata is stripped. Anything that isn't an image passes
+/// through untouched.
+pub async fn process_upload(bytes: Vec<u8>) -> Result<Vec<u8>> {
+    let Some(info) = maybe_get_info(bytes.clone()).await? else {
+        return Ok(bytes);
+    };
+    if let Some(max) = SERVER_CONFIG.images.max_dimension {
+        if info.width > max || info.height > max {
+            bail!(ImageError::TooLarge {
+                width: info.width,
+                height: info.height,
+                max
+            })
+        }
+    }
+    if SERVER_CONFIG.images.strip_metadata {
+        if let Some(stripped) = metadata::strip_metadata(&bytes)? {
+            return Ok(stripped);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Renders a preset of an image as a JPEG, upright according to its EXIF orientation.
+/// Images are never upscaled.
+pub fn render_variant(bytes: &[u8], preset: ImagePreset) -> Result<Vec<u8>> {
+    // Checked from the header, so a decompression bomb is never decoded
+    let (width, height) = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    if width as u64 * height as u64 > MAX_VARIANT_PIXELS {
+        bail!(ImageError::TooManyPixels { width, height })
+    }
+    let img = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()?
+        .decode()?;
+    let img = match metadata::jpeg_orientation(bytes) {
+        Some(2) => img.fliph(),
+        Some(3) => img.rotate180(),
+        Some(4) => img.flipv(),
+        Some(5) => img.rotate90().fliph(),
+        Some(6) => img.rotate90(),
+        Some(7) => img.rotate270().fliph(),
+        Some(8) => img.rotate270(),
+        _ => img,
+    };
+    let (width, height, fill) = preset.size();
+    let img = if fill {
+        // Crop to the preset's aspect ratio at no more than the image's own resolution
+        let scale = f64::min(
+            1.0,
+            f64::min(
+                img.width() as f64 / width as f64,
+                img.height() as f64 / height as f64,
+            ),
+        );
+        img.resize_to_fill(
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+            FilterType::Lanczos3,
+        )
+    } else if img.width() > width || img.height() > height {
+        img.resize(width, height, FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    let mut out: Vec<u8> = Vec::new();
+    DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(JpegEncoder::new_with_quality(
+        &mut out,
+        VARIANT_JPEG_QUALITY,
+    ))?;
+    Ok(out)
+}
+
+pub mod cdn;
+pub mod metadata;
@@ -0,0 +1,37 @@
+use crate::jobs::Job;
+use crate::search;
+use anyhow::Result;
+use std::time::Duration;
+
+const BATCH_SIZE: i64 = 500;
+
+/// Indexes posts and profiles the search index is missing, e.g. ones written before it
+/// existed. Once everything is indexed, a run is a single query that finds nothing.
+pub struct BackfillSearchJob {
+    pub interval: Duration,
+}
+
+#[rocket::async_trait]
+impl Job for BackfillSearchJob {
+    fn name(&self) -> &'static str {
+        "backfill_search"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn run(&self) -> Result<u64> {
+        let mut processed = 0;
+        let mut cursor = String::new();
+        loop {
+            let (indexed, next_cursor) = search::backfill(&cursor, BATCH_SIZE).await?;
+            processed += indexed;
+            match next_cursor {
+                Some(next_cursor) => cursor = next_cursor,
+                None => break,
+            }
+        }
+        Ok(processed)
+    }
+}
@@ -0,0 +1,271 @@
+use crate::common::time::from_str_to_millis;
+use crate::db::establish_connection;
+use crate::jobs::Job;
+use crate::models::models;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::blob::upload::list_expired_uploads;
+use crate::repo::blob::BlobReader;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use diesel::dsl::exists;
+use diesel::*;
+use lexicon_cid::Cid;
+use rsky_lexicon::app::bsky::video::{JOB_STATE_CREATED, JOB_STATE_PROCESSING};
+use rsky_lexicon::com::rsky::admin::{BlobGcItem, BlobGcReport};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+pub struct BlobGcOpts {
+    pub dry_run: bool,
+    pub min_age_ms: u64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in millis since UNIX epoch")
+        .as_millis() as i64
+}
+
+fn blob_item(blob: &models::Blob) -> BlobGcItem {
+    BlobGcItem {
+        did: blob.did.clone(),
+        cid: Some(blob.cid.clone()),
+        temp_key: blob.temp_key.clone(),
+        size: Some(blob.size),
+    }
+}
+
+/// Every did that may own blobs, including deleted accounts whose blob rows remain
+fn get_blob_owners() -> Result<BTreeSet<String>> {
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    let conn = &mut establish_connection()?;
+
+    let mut dids: BTreeSet<String> = BlobSchema::blob
+        .select(BlobSchema::did)
+        .distinct()
+        .load::<String>(conn)?
+        .into_iter()
+        .collect();
+    dids.extend(
+        ActorSchema::actor
+            .select(ActorSchema::did)
+            .load::<String>(conn)?,
+    );
+    Ok(dids)
+}
+
+/// Deletes the blob row unless a record started referencing it since it was scanned.
+/// Returns whether the row was deleted, in which case the store object can go too.
+fn delete_unreferenced_blob_row(blob: &models::Blob) -> Result<bool> {
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+    let conn = &mut establish_connection()?;
+
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        // Locking the row waits out a write that is making the blob permanent and referencing
+        // it, so the reference check below sees that write's `record_blob` row
+        let temp_key = BlobSchema::blob
+            .filter(BlobSchema::did.eq(&blob.did))
+            .filter(BlobSchema::cid.eq(&blob.cid))
+            .select(BlobSchema::tempKey)
+            .for_update()
+            .first::<Option<String>>(conn)
+            .optional()?;
+        // A temp blob that was made permanent meanwhile is about to be referenced
+        if temp_key.as_ref() != Some(&blob.temp_key) {
+            return Ok(false);
+        }
+        let referenced = select(exists(
+            RecordBlobSchema::record_blob
+                .filter(RecordBlobSchema::did.eq(&blob.did))
+                .filter(RecordBlobSchema::blobCid.eq(&blob.cid)),
+        ))
+        .get_result::<bool>(conn)?;
+        if referenced {
+            return Ok(false);
+        }
+        delete(BlobSchema::blob)
+            .filter(BlobSchema::did.eq(&blob.did))
+            .filter(BlobSchema::cid.eq(&blob.cid))
+            .execute(conn)?;
+        Ok(true)
+    })
+}
+
+async fn collect_for_did(
+    did: String,
+    s3_config: &SdkConfig,
+    opts: &BlobGcOpts,
+    report: &mut BlobGcReport,
+) -> Result<()> {
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let blobstore = S3BlobStore::new(did.clone(), s3_config);
+    let cutoff = now_ms() - opts.min_age_ms as i64;
+    let is_old = |created_at: &String| {
+        from_str_to_millis(created_at).is_ok_and(|created_at| created_at < cutoff)
+    };
+
+    let (blobs, referenced, queued_videos) = {
+        let conn = &mut establish_connection()?;
+        let blobs: Vec<models::Blob> = BlobSchema::blob
+            .filter(BlobSchema::did.eq(&did))
+            .select(models::Blob::as_select())
+            .load(conn)?;
+        let referenced: BTreeSet<String> = RecordBlobSchema::record_blob
+            .filter(RecordBlobSchema::did.eq(&did))
+            .select(RecordBlobSchema::blobCid)
+            .load::<String>(conn)?
+            .into_iter()
+            .collect();
+        // Uploaded videos wait under a temp key, with no blob row, until they're processed
+        let queued_videos: Vec<String> = VideoJobSchema::video_job
+            .filter(VideoJobSchema::did.eq(&did))
+            .filter(VideoJobSchema::state.eq_any(vec![JOB_STATE_CREATED, JOB_STATE_PROCESSING]))
+            .select(VideoJobSchema::tempKey)
+            .load(conn)?;
+        (blobs, referenced, queued_videos)
+    };
+
+    for blob in blobs.iter() {
+        if referenced.contains(&blob.cid) || !is_old(&blob.created_at) {
+            continue;
+        }
+        match &blob.temp_key {
+            Some(temp_key) => {
+                report.untethered_temp_blobs.push(blob_item(blob));
+                if !opts.dry_run && delete_unreferenced_blob_row(blob)? {
+                    if blobstore.has_temp(temp_key.clone()).await? {
+                        blobstore.delete_temp(temp_key.clone()).await?;
+                    }
+                }
+            }
+            // Taken down blobs are kept in quarantine for the moderators
+            None if blob.takedown_ref.is_some() => (),
+            None => {
+                report.dereferenced_blobs.push(blob_item(blob));
+                if !opts.dry_run && delete_unreferenced_blob_row(blob)? {
+                    blobstore.delete(blob.cid.clone()).await?;
+                }
+            }
+        }
+    }
+
+    let known_cids: BTreeSet<&String> = blobs.iter().map(|blob| &blob.cid).collect();
+    let known_temp_keys: BTreeSet<&String> = blobs
+        .iter()
+        .filter_map(|blob| blob.temp_key.as_ref())
+        .chain(queued_videos.iter())
+        .collect();
+    let is_old_object = |last_modified_ms: Option<i64>| {
+        last_modified_ms.is_some_and(|last_modified_ms| last_modified_ms < cutoff)
+    };
+
+    let mut orphaned_cids = Vec::new();
+    for object in blobstore.list_stored().await? {
+        if known_cids.contains(&object.key) || !is_old_object(object.last_modified_ms) {
+            continue;
+        }
+        report.orphaned_objects.push(BlobGcItem {
+            did: did.clone(),
+            cid: Some(object.key.clone()),
+            temp_key: None,
+            size: None,
+        });
+        if let Ok(cid) = Cid::from_str(&object.key) {
+            orphaned_cids.push(cid);
+        }
+    }
+    for object in blobstore.list_temp().await? {
+        if known_temp_keys.contains(&object.key) || !is_old_object(object.last_modified_ms) {
+            continue;
+        }
+        report.orphaned_objects.push(BlobGcItem {
+            did: did.clone(),
+            cid: None,
+            temp_key: Some(object.key.clone()),
+            size: None,
+        });
+        if !opts.dry_run {
+            blobstore.delete_temp(object.key).await?;
+        }
+    }
+    if !opts.dry_run {
+        for chunk in orphaned_cids.chunks(500) {
+            blobstore.delete_many(chunk.to_vec()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Scans the blob table and the blob store for blobs no record references: uploads
+/// that were never tethered to a record, blobs whose records were deleted, and store
+/// objects left behind when deleting a blob failed midway. Resumable uploads that
+/// expired before completing are deleted along with their chunks.
+pub async fn collect_blob_garbage(s3_config: &SdkConfig, opts: BlobGcOpts) -> Result<BlobGcReport> {
+    let mut report = BlobGcReport {
+        dry_run: opts.dry_run,
+        ..Default::default()
+    };
+    for did in get_blob_owners()? {
+        collect_for_did(did, s3_config, &opts, &mut report).await?;
+    }
+    for upload in list_expired_uploads()? {
+        report.expired_uploads.push(BlobGcItem {
+            did: upload.did.clone(),
+            cid: upload.cid.clone(),
+            temp_key: Some(upload.id.clone()),
+            size: Some(upload.received as i32),
+        });
+        if !opts.dry_run {
+            let blob = BlobReader::new(S3BlobStore::new(upload.did.clone(), s3_config));
+            blob.discard_upload(&upload.id).await?;
+        }
+    }
+    Ok(report)
+}
+
+pub struct BlobGcJob {
+    pub interval: Duration,
+    pub s3_config: SdkConfig,
+    pub dry_run: bool,
+    pub min_age_ms: u64,
+}
+
+#[rocket::async_trait]
+impl Job for BlobGcJob {
+    fn name(&self) -> &'static str {
+        "blob_gc"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn run(&self) -> Result<u64> {
+        let report = collect_blob_garbage(
+            &self.s3_config,
+            BlobGcOpts {
+                dry_run: self.dry_run,
+                min_age_ms: self.min_age_ms,
+            },
+        )
+        .await?;
+        println!(
+            "@LOG: blob gc (dry run: {}): {} untethered temp blobs, {} dereferenced blobs, {} orphaned objects, {} expired uploads",
+            report.dry_run,
+            report.untethered_temp_blobs.len(),
+            report.dereferenced_blobs.len(),
+            report.orphaned_objects.len(),
+            report.expired_uploads.len()
+        );
+        Ok((report.untethered_temp_blobs.len()
+            + report.dereferenced_blobs.len()
+            + report.orphaned_objects.len()
+            + report.expired_uploads.len()) as u64)
+    }
+}
@@ -0,0 +1,130 @@
+use crate::common;
+use crate::db::establish_connection;
+use anyhow::Result;
+use diesel::*;
+use rsky_lexicon::com::rsky::admin::JobStatus;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::time::{interval, MissedTickBehavior};
+
+pub mod backfill_search;
+pub mod blob_gc;
+pub mod process_videos;
+pub mod purge_accounts;
+pub mod reindex_backlinks;
+
+/// Work the PDS runs periodically in the background
+#[rocket::async_trait]
+pub trait Job: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn interval(&self) -> Duration;
+
+    /// Runs the job once, returning how many items were processed
+    async fn run(&self) -> Result<u64>;
+}
+
+/// Runs each scheduled job on its own interval and keeps track of how the runs went
+/// so admins can inspect them through `com.rsky.admin.getJobStatuses`.
+#[derive(Clone, Default)]
+pub struct JobScheduler {
+    statuses: Arc<RwLock<BTreeMap<String, JobStatus>>>,
+}
+
+impl JobScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn schedule(&self, job: Arc<dyn Job>) {
+        let name = job.name().to_string();
+        self.statuses.write().await.insert(
+            name.clone(),
+            JobStatus {
+                name: name.clone(),
+                interval_ms: job.interval().as_millis() as u64,
+                running: false,
+                runs: 0,
+                last_started_at: None,
+                last_finished_at: None,
+                last_processed: 0,
+                total_processed: 0,
+                last_error: None,
+            },
+        );
+
+        let statuses = self.statuses.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(job.interval());
+            // A slow run shouldn't be followed by a burst of catch-up runs
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                if let Some(status) = statuses.write().await.get_mut(&name) {
+                    status.running = true;
+                    status.last_started_at = Some(common::now());
+                }
+                let res = job.run().await;
+                if let Err(ref error) = res {
+                    eprintln!("@LOG: ERROR: job {name} failed: {error}");
+                }
+                if let Some(status) = statuses.write().await.get_mut(&name) {
+                    status.running = false;
+                    status.runs += 1;
+                    status.last_finished_at = Some(common::now());
+                    match res {
+                        Ok(processed) => {
+                            status.last_processed = processed;
+                            status.total_processed += processed;
+                            status.last_error = None;
+                        }
+                        Err(error) => status.last_error = Some(error.to_string()),
+                    }
+                }
+            }
+        });
+    }
+
+    pub async fn statuses(&self) -> Vec<JobStatus> {
+        self.statuses.read().await.values().cloned().collect()
+    }
+}
+
+/// Where a one-off job that works through a table got to, and whether it has finished
+pub fn get_cursor(name: &str) -> Result<Option<(String, Option<String>)>> {
+    use crate::schema::pds::job_cursor::dsl as JobCursorSchema;
+    let conn = &mut establish_connection()?;
+
+    let cursor = JobCursorSchema::job_cursor
+        .filter(JobCursorSchema::name.eq(name))
+        .select((JobCursorSchema::cursor, JobCursorSchema::completedAt))
+        .first(conn)
+        .optional()?;
+    Ok(cursor)
+}
+
+pub fn set_cursor(name: &str, cursor: &String, completed: bool) -> Result<()> {
+    use crate::schema::pds::job_cursor::dsl as JobCursorSchema;
+    let conn = &mut establish_connection()?;
+
+    let completed_at = match completed {
+        true => Some(common::now()),
+        false => None,
+    };
+    insert_into(JobCursorSchema::job_cursor)
+        .values((
+            JobCursorSchema::name.eq(name),
+            JobCursorSchema::cursor.eq(cursor),
+            JobCursorSchema::completedAt.eq(&completed_at),
+        ))
+        .on_conflict(JobCursorSchema::name)
+        .do_update()
+        .set((
+            JobCursorSchema::cursor.eq(cursor),
+            JobCursorSchema::completedAt.eq(&completed_at),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use crate::jobs::Job;
+use crate::video;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use std::time::Duration;
+
+/// Works through the queue of uploaded videos, see `app.bsky.video.uploadVideo`
+pub struct ProcessVideosJob {
+    pub interval: Duration,
+    pub s3_config: SdkConfig,
+}
+
+#[rocket::async_trait]
+impl Job for ProcessVideosJob {
+    fn name(&self) -> &'static str {
+        "process_videos"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn run(&self) -> Result<u64> {
+        let mut processed = 0;
+        while let Some(job) = video::claim_next_job()? {
+            video::process_job(job, &self.s3_config).await?;
+            processed += 1;
+        }
+        Ok(processed)
+    }
+}
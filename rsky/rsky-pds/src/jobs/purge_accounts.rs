@@ -0,0 +1,66 @@
+use crate::account_manager::helpers::account::AccountStatus;
+use crate::account_manager::AccountManager;
+use crate::jobs::Job;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::ActorStore;
+use crate::sequencer;
+use crate::SharedSequencer;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use std::time::Duration;
+
+/// Deletes accounts that were deactivated with a `deleteAfter` that has now passed
+pub struct PurgeAccountsJob {
+    pub interval: Duration,
+    pub s3_config: SdkConfig,
+    pub sequencer: SharedSequencer,
+}
+
+impl PurgeAccountsJob {
+    async fn purge_account(&self, did: &String) -> Result<()> {
+        let mut actor_store =
+            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), &self.s3_config));
+        actor_store.destroy().await?;
+        AccountManager::delete_account(did).await?;
+
+        let mut lock = self.sequencer.sequencer.write().await;
+        let tombstone_seq = lock.sequence_tombstone(did.clone()).await?;
+        let account_seq = lock
+            .sequence_account_evt(did.clone(), AccountStatus::Deleted)
+            .await?;
+        sequencer::delete_all_for_user(did, Some(vec![account_seq, tombstone_seq])).await?;
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl Job for PurgeAccountsJob {
+    fn name(&self) -> &'static str {
+        "purge_accounts"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn run(&self) -> Result<u64> {
+        let dids = AccountManager::get_accounts_due_for_deletion().await?;
+        let mut purged = 0;
+        let mut failures = Vec::new();
+        // One failing account shouldn't hold up the rest
+        for did in dids {
+            match self.purge_account(&did).await {
+                Ok(()) => purged += 1,
+                Err(error) => failures.push(format!("{did}: {error}")),
+            }
+        }
+        if !failures.is_empty() {
+            bail!(
+                "Purged {purged} accounts, failed to purge {}: {}",
+                failures.len(),
+                failures.join("; ")
+            )
+        }
+        Ok(purged)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::jobs::{get_cursor, set_cursor, Job};
+use crate::repo::record::reindex_reference_backlinks;
+use anyhow::Result;
+use std::time::Duration;
+
+const BATCH_SIZE: i64 = 500;
+
+/// Adds the reply root, quote and list item backlinks to records indexed before they were
+/// tracked. Works through every post and list item once, saving its place after each batch
+/// so a restart picks up where it stopped, and does nothing once finished.
+pub struct ReindexBacklinksJob {
+    pub interval: Duration,
+}
+
+#[rocket::async_trait]
+impl Job for ReindexBacklinksJob {
+    fn name(&self) -> &'static str {
+        "reindex_backlinks"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn run(&self) -> Result<u64> {
+        let mut cursor = match get_cursor(self.name())? {
+            Some((_, Some(_completed_at))) => return Ok(0),
+            Some((cursor, None)) => cursor,
+            None => String::new(),
+        };
+        let mut processed = 0;
+        loop {
+            let (count, next_cursor) = reindex_reference_backlinks(&cursor, BATCH_SIZE).await?;
+            processed += count;
+            match next_cursor {
+                Some(next_cursor) => {
+                    cursor = next_cursor;
+                    set_cursor(self.name(), &cursor, false)?;
+                }
+                None => {
+                    set_cursor(self.name(), &cursor, true)?;
+                    break;
+                }
+            }
+        }
+        Ok(processed)
+    }
+}
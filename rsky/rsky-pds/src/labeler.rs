@@ -0,0 +1,243 @@
+use crate::account_manager::helpers::account::escape_like;
+use crate::common;
+use crate::common::sign::atproto_sign;
+use crate::common::time::from_str_to_utc;
+use crate::common::RFC3339_VARIANT;
+use crate::db::establish_connection;
+use crate::db::pagination::{paginate, Cursor, KeySet, KeySetPaginateOpts};
+use crate::models::models;
+use anyhow::{anyhow, bail, Result};
+use chrono::DateTime;
+use diesel::pg::Pg;
+use diesel::sql_types::Bool;
+use diesel::*;
+use lazy_static::lazy_static;
+use rsky_lexicon::com::atproto::label::{Label, QueryLabelsOutput};
+use rsky_lexicon::com::rsky::admin::CreateLabel;
+use secp256k1::SecretKey;
+use std::env;
+use tokio::sync::broadcast;
+
+const MAX_LABEL_VAL_LENGTH: usize = 128;
+
+lazy_static! {
+    // Carries the highest seq of every batch of created labels to subscribeLabels streams
+    static ref LABELS_CREATED: broadcast::Sender<i64> = broadcast::channel(64).0;
+}
+
+/// Wakes up whenever labels are created
+pub fn subscribe_created() -> broadcast::Receiver<i64> {
+    LABELS_CREATED.subscribe()
+}
+
+/// Labels are served in the order they were created, by seq
+pub struct LabelSeqKeySet {}
+
+impl KeySet for LabelSeqKeySet {
+    type Result = models::Label;
+
+    fn primary(&self) -> String {
+        r#""label"."seq""#.to_string()
+    }
+
+    fn primary_type(&self) -> &'static str {
+        "bigint"
+    }
+
+    fn label_result(&self, result: &models::Label) -> Result<Cursor> {
+        Ok(Cursor {
+            primary: result.seq.to_string(),
+            secondary: None,
+        })
+    }
+
+    fn cursor_to_labeled_result(&self, cursor: Cursor) -> Result<Cursor> {
+        cursor
+            .primary
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Malformed cursor"))?;
+        Ok(cursor)
+    }
+}
+
+pub struct QueryLabelsOpts {
+    /// Exact AT URIs or DIDs, or prefixes ending in `*`
+    pub uri_patterns: Vec<String>,
+    pub sources: Vec<String>,
+    pub limit: i64,
+    pub cursor: Option<String>,
+}
+
+fn signing_key() -> Result<SecretKey> {
+    match env::var("PDS_LABELER_SIGNING_KEY_K256_PRIVATE_KEY_HEX") {
+        Ok(private_key) => Ok(SecretKey::from_slice(&hex::decode(
+            private_key.as_bytes(),
+        )?)?),
+        Err(_) => bail!("Labeler is not configured"),
+    }
+}
+
+/// Rebuilds the signed label. Optional fields are only set when they were part of
+/// the signed bytes so consumers can re-encode the label to verify `sig`.
+pub fn row_to_label(row: models::Label) -> Label {
+    Label {
+        ver: Some(1),
+        src: row.src,
+        uri: row.uri,
+        cid: row.cid,
+        val: row.val,
+        neg: match row.neg {
+            true => Some(true),
+            false => None,
+        },
+        cts: from_str_to_utc(&row.cts),
+        exp: row.exp.map(|exp| from_str_to_utc(&exp)),
+        sig: Some(row.sig),
+    }
+}
+
+fn validate_label(label: &CreateLabel) -> Result<Option<String>> {
+    if !label.uri.starts_with("at://") && !label.uri.starts_with("did:") {
+        bail!("Label uri must be an AT URI or a DID: {}", label.uri)
+    }
+    if label.cid.is_some() && !label.uri.starts_with("at://") {
+        bail!("Only record labels can reference a cid")
+    }
+    if label.val.is_empty() || label.val.len() > MAX_LABEL_VAL_LENGTH {
+        bail!("Invalid label value: {}", label.val)
+    }
+    match &label.exp {
+        None => Ok(None),
+        Some(exp) => {
+            let exp = DateTime::parse_from_rfc3339(exp)
+                .map_err(|_| anyhow!("Invalid label expiration: {exp}"))?;
+            Ok(Some(format!("{}", exp.naive_utc().format(RFC3339_VARIANT))))
+        }
+    }
+}
+
+/// Signs and stores labels from `src`, the labeler's did. Each stored label gets the
+/// next seq and is picked up by `subscribeLabels` subscribers.
+pub async fn create_labels(src: String, labels: Vec<CreateLabel>) -> Result<Vec<Label>> {
+    use crate::schema::pds::label::dsl as LabelSchema;
+    let key = signing_key()?;
+    let cts = common::now();
+
+    let mut rows = Vec::with_capacity(labels.len());
+    for label in labels {
+        let exp = validate_label(&label)?;
+        let CreateLabel {
+            uri, cid, val, neg, ..
+        } = label;
+        let row = models::Label {
+            seq: 0,
+            src: src.clone(),
+            uri,
+            cid,
+            val,
+            neg: neg.unwrap_or(false),
+            cts: cts.clone(),
+            exp,
+            sig: Vec::new(),
+        };
+        let sig = atproto_sign(
+            &Label {
+                sig: None,
+                ..row_to_label(row.clone())
+            },
+            &key,
+        )?;
+        rows.push((
+            LabelSchema::src.eq(row.src),
+            LabelSchema::uri.eq(row.uri),
+            LabelSchema::cid.eq(row.cid),
+            LabelSchema::val.eq(row.val),
+            LabelSchema::neg.eq(row.neg),
+            LabelSchema::cts.eq(row.cts),
+            LabelSchema::exp.eq(row.exp),
+            LabelSchema::sig.eq(sig.to_vec()),
+        ));
+    }
+
+    let conn = &mut establish_connection()?;
+    let created: Vec<models::Label> = insert_into(LabelSchema::label)
+        .values(&rows)
+        .returning(models::Label::as_returning())
+        .get_results(conn)?;
+    if let Some(seq) = created.iter().map(|row| row.seq).max() {
+        // Fails only when nobody is subscribed
+        let _ = LABELS_CREATED.send(seq);
+    }
+    Ok(created.into_iter().map(row_to_label).collect())
+}
+
+pub async fn query_labels(opts: QueryLabelsOpts) -> Result<QueryLabelsOutput> {
+    use crate::schema::pds::label::dsl as LabelSchema;
+    let conn = &mut establish_connection()?;
+    let QueryLabelsOpts {
+        uri_patterns,
+        sources,
+        limit,
+        cursor,
+    } = opts;
+
+    let mut builder = LabelSchema::label
+        .select(models::Label::as_select())
+        .into_boxed();
+    if !sources.is_empty() {
+        builder = builder.filter(LabelSchema::src.eq_any(sources));
+    }
+    // A lone `*` matches every label
+    if !uri_patterns.iter().any(|pattern| pattern == "*") {
+        let (prefixes, exact): (Vec<String>, Vec<String>) = uri_patterns
+            .into_iter()
+            .partition(|pattern| pattern.ends_with('*'));
+        let mut condition: Box<dyn BoxableExpression<LabelSchema::label, Pg, SqlType = Bool>> =
+            Box::new(LabelSchema::uri.eq_any(exact));
+        for prefix in prefixes {
+            let prefix = escape_like(prefix.trim_end_matches('*'));
+            condition = Box::new(condition.or(LabelSchema::uri.like(format!("{prefix}%"))));
+        }
+        builder = builder.filter(condition);
+    }
+
+    let keyset = LabelSeqKeySet {};
+    let res: Vec<models::Label> = paginate(
+        builder,
+        &keyset,
+        KeySetPaginateOpts {
+            limit: Some(limit),
+            cursor,
+            direction: Some("asc".to_string()),
+        },
+    )?
+    .load(conn)?;
+    Ok(QueryLabelsOutput {
+        cursor: keyset.pack_from_result(&res)?,
+        labels: res.into_iter().map(row_to_label).collect(),
+    })
+}
+
+pub async fn curr_seq() -> Result<Option<i64>> {
+    use crate::schema::pds::label::dsl as LabelSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = LabelSchema::label
+        .select(dsl::max(LabelSchema::seq))
+        .first::<Option<i64>>(conn)?;
+    Ok(res)
+}
+
+/// Labels sequenced after `seq`, oldest first
+pub async fn labels_after(seq: i64, limit: i64) -> Result<Vec<models::Label>> {
+    use crate::schema::pds::label::dsl as LabelSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = LabelSchema::label
+        .filter(LabelSchema::seq.gt(seq))
+        .order(LabelSchema::seq.asc())
+        .limit(limit)
+        .select(models::Label::as_select())
+        .load(conn)?;
+    Ok(res)
+}
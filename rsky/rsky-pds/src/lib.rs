@@ -14,6 +14,7 @@ use event_emitter_rs::EventEmitter;
 use lazy_static::lazy_static;
 use rocket_sync_db_pools::database;
 use rsky_identity::IdResolver;
+use std::sync::Arc;
 use tokio::sync::RwLock;
 
 pub static APP_USER_AGENT: &str = concat!(
@@ -29,8 +30,10 @@ const INVALID_HANDLE: &'static str = "handle.invalid";
 #[database("pg_db")]
 pub struct DbConn(PgConnection);
 
+// Cloned into background jobs, which sequence events through the same instance as requests
+#[derive(Clone)]
 pub struct SharedSequencer {
-    pub sequencer: RwLock<Sequencer>,
+    pub sequencer: Arc<RwLock<Sequencer>>,
 }
 
 pub struct SharedIdResolver {
@@ -41,6 +44,11 @@ pub struct SharedLocalViewer {
     pub local_viewer: RwLock<LocalViewerCreator>,
 }
 
+pub struct SharedIroh {
+    pub endpoint: iroh::net::Endpoint,
+    pub node_id: iroh::net::NodeId,
+}
+
 pub struct SharedATPAgent {
     pub app_view_agent: Option<RwLock<AtpServiceClient<ReqwestClient>>>,
 }
@@ -53,24 +61,37 @@ lazy_static! {
 
 pub mod account_manager;
 pub mod apis;
+pub mod archive;
 pub mod auth_verifier;
 pub mod car;
+pub mod chat;
 pub mod common;
 pub mod config;
 pub mod context;
 pub mod crawlers;
 pub mod db;
+pub mod graph;
 pub mod image;
+pub mod jobs;
+pub mod labeler;
 pub mod lexicon;
+pub mod local_appview;
 pub mod mailer;
 pub mod models;
+pub mod moderation;
+pub mod notification;
+pub mod oauth;
 pub mod pipethrough;
 pub mod plc;
+pub mod push;
+pub mod rate_limiter;
 pub mod read_after_write;
 pub mod repo;
 pub mod schema;
+pub mod search;
 pub mod sequencer;
 pub mod storage;
 mod vendored;
+pub mod video;
 pub mod well_known;
 pub mod xrpc_server;
@@ -0,0 +1,889 @@
+use crate::account_manager::AccountManager;
+use crate::common::time::from_str_to_utc;
+use crate::config::ServerConfig;
+use crate::db::establish_connection;
+use crate::db::pagination::{paginate, KeySet, KeySetPaginateOpts, TimeKeyResult, TimeKeySet};
+use crate::graph;
+use crate::models::models;
+use crate::notification::did_from_uri;
+use crate::read_after_write::types::RecordDescript;
+use crate::read_after_write::viewer::LocalViewer;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::types::Ids;
+use crate::repo::ActorStore;
+use crate::{SharedLocalViewer, INVALID_HANDLE};
+use anyhow::Result;
+use aws_config::SdkConfig;
+use diesel::*;
+use libipld::Cid;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rsky_lexicon::app::bsky::actor::{
+    Profile, ProfileView, ProfileViewBasic, ProfileViewDetailed, ViewerState,
+};
+use rsky_lexicon::app::bsky::embed::{EmbedViews, MediaViewUnion};
+use rsky_lexicon::app::bsky::feed::{
+    AuthorFeed, BlockedAuthor, BlockedPost, FeedViewPost, GetLikesLike, GetLikesOutput,
+    GetPostThreadOutput, NotFoundPost, Post, PostView, ReasonRepost, ReplyRefUnion, ReplyRefView,
+    ThreadViewPost, ThreadViewPostEnum,
+};
+use rsky_syntax::aturi::AtUri;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Feed items are ordered by when their record was indexed, tie-broken by uri
+pub const FEED_KEYSET: TimeKeySet = TimeKeySet {
+    created_at: r#""record"."indexedAt""#,
+    key: r#""record"."uri""#,
+};
+
+#[derive(Error, Debug)]
+pub enum LocalAppViewError {
+    #[error("Profile not found")]
+    ProfileNotFound,
+    #[error("Post not found: {0}")]
+    PostNotFound(String),
+    #[error("Likes can only be listed by the account that made them")]
+    LikesNotVisible,
+    #[error("BlockedActor: requester has blocked actor: {0}")]
+    BlockedActor(String),
+    #[error("BlockedByActor: requester is blocked by actor: {0}")]
+    BlockedByActor(String),
+}
+
+/// Matches when `app.bsky.*` views are built locally
+pub struct UseLocalAppView;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UseLocalAppView {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.rocket().state::<ServerConfig>() {
+            Some(cfg) if cfg.local_appview.enabled => Outcome::Success(UseLocalAppView),
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
+
+/// Matches when `app.bsky.*` views come from the appview. Routes proxying to the appview
+/// take this guard first so they step aside for the local routes, ranked after them.
+pub struct UseUpstreamAppView;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UseUpstreamAppView {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.rocket().state::<ServerConfig>() {
+            Some(cfg) if cfg.local_appview.enabled => Outcome::Forward(Status::NotFound),
+            _ => Outcome::Success(UseUpstreamAppView),
+        }
+    }
+}
+
+fn json_str<'a>(value: &'a JsonValue, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer)?.as_str()
+}
+
+fn has_media(post: &PostView) -> bool {
+    match &post.embed {
+        Some(EmbedViews::ImagesView(_)) | Some(EmbedViews::VideoView(_)) => true,
+        Some(EmbedViews::RecordWithMediaView(view)) => {
+            !matches!(view.media, MediaViewUnion::ExternalView(_))
+        }
+        _ => false,
+    }
+}
+
+fn get_record_block(uri: &String) -> Result<Option<(models::Record, models::RepoBlock)>> {
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .inner_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
+        .select((models::Record::as_select(), models::RepoBlock::as_select()))
+        .filter(RecordSchema::uri.eq(uri))
+        .filter(RecordSchema::takedownRef.is_null())
+        .first(conn)
+        .optional()?;
+    Ok(res)
+}
+
+pub async fn get_profile_record(did: &String) -> Result<Option<Profile>> {
+    let uri = format!("at://{did}/{}/self", Ids::AppBskyActorProfile.as_str());
+    match get_record_block(&uri)? {
+        Some((_, block)) => Ok(serde_ipld_dagcbor::from_slice(block.content.as_slice())?),
+        None => Ok(None),
+    }
+}
+
+fn count_records(did: &String, collection: &str) -> Result<i64> {
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .filter(RecordSchema::did.eq(did))
+        .filter(RecordSchema::collection.eq(collection))
+        .filter(RecordSchema::takedownRef.is_null())
+        .count()
+        .get_result::<i64>(conn)?;
+    Ok(res)
+}
+
+/// Records in `collection` linking to `link_to` through `path`, e.g. the likes of a post
+fn count_backlinks(collection: &str, path: &str, link_to: &String) -> Result<i64> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::collection.eq(collection))
+        .filter(RecordSchema::takedownRef.is_null())
+        .filter(BacklinkSchema::path.eq(path))
+        .filter(BacklinkSchema::linkTo.eq(link_to))
+        .count()
+        .get_result::<i64>(conn)?;
+    Ok(res)
+}
+
+/// Uri of `did`'s record in `collection` linking to `link_to`, e.g. their follow of an account
+fn find_backlink(
+    did: &String,
+    collection: &str,
+    path: &str,
+    link_to: &String,
+) -> Result<Option<String>> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::did.eq(did))
+        .filter(RecordSchema::collection.eq(collection))
+        .filter(BacklinkSchema::path.eq(path))
+        .filter(BacklinkSchema::linkTo.eq(link_to))
+        .select(RecordSchema::uri)
+        .first::<String>(conn)
+        .optional()?;
+    Ok(res)
+}
+
+/// What the record at `uri` links to through `path`, e.g. the post a like is for
+fn get_link(uri: &String, path: &str) -> Result<Option<String>> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = BacklinkSchema::backlink
+        .filter(BacklinkSchema::uri.eq(uri))
+        .filter(BacklinkSchema::path.eq(path))
+        .select(BacklinkSchema::linkTo)
+        .first::<String>(conn)
+        .optional()?;
+    Ok(res)
+}
+
+fn get_follows(did: &String) -> Result<Vec<String>> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::did.eq(did))
+        .filter(RecordSchema::collection.eq(Ids::AppBskyGraphFollow.as_str()))
+        .filter(BacklinkSchema::path.eq("subject"))
+        .select(BacklinkSchema::linkTo)
+        .load::<String>(conn)?;
+    Ok(res)
+}
+
+/// Replies to the post at `uri`, oldest first
+fn get_replies(uri: &String) -> Result<Vec<String>> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::collection.eq(Ids::AppBskyFeedPost.as_str()))
+        .filter(RecordSchema::takedownRef.is_null())
+        .filter(BacklinkSchema::path.eq("reply.parent.uri"))
+        .filter(BacklinkSchema::linkTo.eq(uri))
+        .order((RecordSchema::indexedAt.asc(), RecordSchema::uri.asc()))
+        .select(RecordSchema::uri)
+        .load::<String>(conn)?;
+    Ok(res)
+}
+
+/// Posts and reposts by `dids`, newest first
+fn get_feed_records(
+    dids: Vec<String>,
+    limit: i64,
+    cursor: Option<String>,
+) -> Result<Vec<models::Record>> {
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let builder = RecordSchema::record
+        .filter(RecordSchema::did.eq_any(dids))
+        .filter(RecordSchema::collection.eq_any(vec![
+            Ids::AppBskyFeedPost.as_str(),
+            Ids::AppBskyFeedRepost.as_str(),
+        ]))
+        .filter(RecordSchema::takedownRef.is_null())
+        .select(models::Record::as_select())
+        .into_boxed();
+    let res = paginate(
+        builder,
+        &FEED_KEYSET,
+        KeySetPaginateOpts {
+            limit: Some(limit),
+            cursor,
+            direction: None,
+        },
+    )?
+    .load(conn)?;
+    Ok(res)
+}
+
+fn feed_cursor(records: &[models::Record]) -> Result<Option<String>> {
+    match records.last() {
+        None => Ok(None),
+        Some(last) => FEED_KEYSET.pack_from_result(&[TimeKeyResult {
+            created_at: last.indexed_at.clone(),
+            key: last.uri.clone(),
+        }]),
+    }
+}
+
+fn build_thread(
+    post: PostView,
+    parent: Option<Box<ThreadViewPostEnum>>,
+    depth: u16,
+    views: &mut BTreeMap<String, PostView>,
+    children: &BTreeMap<String, Vec<String>>,
+) -> ThreadViewPost {
+    let replies = match depth {
+        0 => None,
+        _ => {
+            let mut replies = Vec::new();
+            for uri in children.get(&post.uri).cloned().unwrap_or_default() {
+                if let Some(reply) = views.remove(&uri) {
+                    replies.push(Box::new(ThreadViewPostEnum::ThreadViewPost(build_thread(
+                        reply,
+                        None,
+                        depth - 1,
+                        views,
+                        children,
+                    ))));
+                }
+            }
+            Some(replies)
+        }
+    };
+    ThreadViewPost {
+        post,
+        parent,
+        replies,
+    }
+}
+
+/// Builds `app.bsky.*` views from the records stored on this PDS, for deployments without
+/// an appview. Posts and profiles are formatted by each author's `LocalViewer`; counts and
+/// relationships come from the backlink index. Only accounts hosted here have views: this
+/// PDS doesn't mirror repos from peers, so their actors and posts are not found.
+pub struct LocalAppView<'a> {
+    s3_config: &'a SdkConfig,
+    shared_local_viewer: &'a SharedLocalViewer,
+    /// The account views are built for, used for viewer state
+    requester: Option<String>,
+    profiles: BTreeMap<String, Option<ProfileViewBasic>>,
+    /// Whether the requester and an account block each other, either way
+    blocks: BTreeMap<String, bool>,
+}
+
+impl<'a> LocalAppView<'a> {
+    pub fn new(
+        s3_config: &'a SdkConfig,
+        shared_local_viewer: &'a SharedLocalViewer,
+        requester: Option<String>,
+    ) -> Self {
+        LocalAppView {
+            s3_config,
+            shared_local_viewer,
+            requester,
+            profiles: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+        }
+    }
+
+    async fn local_viewer(&self, did: &String) -> LocalViewer {
+        let actor_store =
+            ActorStore::new(did.clone(), S3BlobStore::new(did.clone(), self.s3_config));
+        let local_viewer_lock = self.shared_local_viewer.local_viewer.read().await;
+        local_viewer_lock(actor_store)
+    }
+
+    /// Resolves a handle or did to the did of an account on this PDS
+    pub async fn resolve_actor(&self, actor: &String) -> Result<String> {
+        match AccountManager::get_account(actor, None).await? {
+            Some(account) => Ok(account.did),
+            None => Err(LocalAppViewError::ProfileNotFound.into()),
+        }
+    }
+
+    /// Whether the requester blocks `did` or is blocked by them. Their content is left out
+    /// of feeds and shown as blocked in threads, like the appview does.
+    fn is_blocked(&mut self, did: &String) -> Result<bool> {
+        let Some(requester) = self.requester.clone() else {
+            return Ok(false);
+        };
+        if &requester == did {
+            return Ok(false);
+        }
+        if let Some(blocked) = self.blocks.get(did) {
+            return Ok(*blocked);
+        }
+        let blocked = graph::is_blocked_between(&requester, did)?;
+        self.blocks.insert(did.clone(), blocked);
+        Ok(blocked)
+    }
+
+    fn blocked_post(&self, uri: &String, did: &String) -> Result<BlockedPost> {
+        Ok(BlockedPost {
+            uri: uri.clone(),
+            blocked: true,
+            author: BlockedAuthor {
+                did: did.clone(),
+                viewer: self.viewer_state(did)?,
+            },
+        })
+    }
+
+    fn viewer_state(&self, did: &String) -> Result<Option<ViewerState>> {
+        let follow = Ids::AppBskyGraphFollow.as_str();
+        match &self.requester {
+            Some(requester) if requester != did => Ok(Some(ViewerState {
+                muted: Some(graph::is_muted(requester, did)?),
+                muted_by_list: None,
+                blocked_by: Some(graph::get_block(did, requester)?.is_some()),
+                blocking: graph::get_block(requester, did)?,
+                blocking_by_list: None,
+                following: find_backlink(requester, follow, "subject", did)?,
+                followed_by: find_backlink(did, follow, "subject", requester)?,
+                known_followers: None,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    pub async fn profile_basic(&mut self, did: &String) -> Result<Option<ProfileViewBasic>> {
+        if let Some(profile) = self.profiles.get(did) {
+            return Ok(profile.clone());
+        }
+        let profile = match self.local_viewer(did).await.get_profile_basic().await? {
+            Some(mut profile) => {
+                profile.viewer = self.viewer_state(did)?;
+                Some(profile)
+            }
+            None => None,
+        };
+        self.profiles.insert(did.clone(), profile.clone());
+        Ok(profile)
+    }
+
+    pub async fn profile_detailed(
+        &mut self,
+        actor: &String,
+    ) -> Result<Option<ProfileViewDetailed>> {
+        let Some(account) = AccountManager::get_account(actor, None).await? else {
+            return Ok(None);
+        };
+        let did = account.did;
+        let view = ProfileViewDetailed {
+            did: did.clone(),
+            handle: account.handle.unwrap_or_else(|| INVALID_HANDLE.to_string()),
+            display_name: None,
+            description: None,
+            avatar: None,
+            banner: None,
+            followers_count: Some(count_backlinks(
+                Ids::AppBskyGraphFollow.as_str(),
+                "subject",
+                &did,
+            )? as usize),
+            follows_count: Some(count_records(&did, Ids::AppBskyGraphFollow.as_str())? as usize),
+            posts_count: Some(count_records(&did, Ids::AppBskyFeedPost.as_str())? as usize),
+            associated: None,
+            joined_via_starter_pack: None,
+            viewer: self.viewer_state(&did)?,
+            labels: vec![],
+            indexed_at: None,
+            created_at: Some(account.created_at),
+        };
+        match get_profile_record(&did).await? {
+            None => Ok(Some(view)),
+            Some(profile) => {
+                let description = profile.description.clone();
+                let local_viewer = self.local_viewer(&did).await;
+                Ok(Some(ProfileViewDetailed {
+                    description,
+                    ..local_viewer.update_profile_detailed(view, profile)
+                }))
+            }
+        }
+    }
+
+    pub async fn profile_view(&mut self, did: &String) -> Result<Option<ProfileView>> {
+        let Some(profile) = self.profile_basic(did).await? else {
+            return Ok(None);
+        };
+        let description = match get_profile_record(did).await? {
+            Some(record) => record.description,
+            None => None,
+        };
+        Ok(Some(ProfileView {
+            did: profile.did,
+            handle: profile.handle,
+            display_name: profile.display_name,
+            description,
+            avatar: profile.avatar,
+            labels: vec![],
+            indexed_at: None,
+        }))
+    }
+
+    pub async fn post_view(&mut self, uri: &String) -> Result<Option<PostView>> {
+        let Some((record, block)) = get_record_block(uri)? else {
+            return Ok(None);
+        };
+        if record.collection != Ids::AppBskyFeedPost.as_str() {
+            return Ok(None);
+        }
+        let Some(author) = self.profile_basic(&record.did).await? else {
+            return Ok(None);
+        };
+        let descript = RecordDescript {
+            uri: AtUri::new(record.uri.clone(), None)?,
+            cid: Cid::from_str(&record.cid)?,
+            indexed_at: record.indexed_at,
+            record: serde_ipld_dagcbor::from_slice::<Post>(block.content.as_slice())?,
+        };
+        let local_viewer = self.local_viewer(&record.did).await;
+        let Some(post) = local_viewer.get_post(descript).await? else {
+            return Ok(None);
+        };
+        Ok(Some(PostView {
+            author,
+            reply_count: Some(count_backlinks(
+                Ids::AppBskyFeedPost.as_str(),
+                "reply.parent.uri",
+                uri,
+            )? as usize),
+            repost_count: Some(
+                count_backlinks(Ids::AppBskyFeedRepost.as_str(), "subject.uri", uri)? as usize,
+            ),
+            like_count: Some(
+                count_backlinks(Ids::AppBskyFeedLike.as_str(), "subject.uri", uri)? as usize,
+            ),
+            ..post
+        }))
+    }
+
+    async fn reply_ref_union(&mut self, uri: &String) -> Result<ReplyRefUnion> {
+        if let Some(did) = did_from_uri(uri) {
+            if self.is_blocked(&did)? {
+                return Ok(ReplyRefUnion::BlockedPost(self.blocked_post(uri, &did)?));
+            }
+        }
+        Ok(match self.post_view(uri).await? {
+            Some(post) => ReplyRefUnion::PostView(post),
+            None => ReplyRefUnion::NotFoundPost(NotFoundPost {
+                uri: uri.clone(),
+                not_found: true,
+            }),
+        })
+    }
+
+    async fn reply_ref(&mut self, post: &PostView) -> Result<Option<ReplyRefView>> {
+        let (Some(parent_uri), Some(root_uri)) = (
+            json_str(&post.record, "/reply/parent/uri"),
+            json_str(&post.record, "/reply/root/uri"),
+        ) else {
+            return Ok(None);
+        };
+        let (parent_uri, root_uri) = (parent_uri.to_string(), root_uri.to_string());
+        let parent = self.reply_ref_union(&parent_uri).await?;
+        let root = match root_uri == parent_uri {
+            true => parent.clone(),
+            false => self.reply_ref_union(&root_uri).await?,
+        };
+        let grandparent_did = match &parent {
+            ReplyRefUnion::PostView(parent) => {
+                json_str(&parent.record, "/reply/parent/uri").and_then(did_from_uri)
+            }
+            _ => None,
+        };
+        let grandparent_author = match grandparent_did {
+            Some(did) => self.profile_basic(&did).await?,
+            None => None,
+        };
+        Ok(Some(ReplyRefView {
+            root,
+            parent,
+            grandparent_author,
+        }))
+    }
+
+    /// A post or repost as a feed item, or `None` when it's gone or involves an account
+    /// the requester blocks or is blocked by
+    async fn feed_item(&mut self, record: models::Record) -> Result<Option<FeedViewPost>> {
+        if self.is_blocked(&record.did)? {
+            return Ok(None);
+        }
+        let item = self.unfiltered_feed_item(record).await?;
+        let Some(item) = item else {
+            return Ok(None);
+        };
+        if self.is_blocked(&item.post.author.did)? {
+            return Ok(None);
+        }
+        let blocked_reply = match &item.reply {
+            Some(reply) => {
+                matches!(reply.parent, ReplyRefUnion::BlockedPost(_))
+                    || matches!(reply.root, ReplyRefUnion::BlockedPost(_))
+            }
+            None => false,
+        };
+        match blocked_reply {
+            true => Ok(None),
+            false => Ok(Some(item)),
+        }
+    }
+
+    async fn unfiltered_feed_item(
+        &mut self,
+        record: models::Record,
+    ) -> Result<Option<FeedViewPost>> {
+        if record.collection == Ids::AppBskyFeedRepost.as_str() {
+            let Some(subject) = get_link(&record.uri, "subject.uri")? else {
+                return Ok(None);
+            };
+            let Some(post) = self.post_view(&subject).await? else {
+                return Ok(None);
+            };
+            let Some(by) = self.profile_basic(&record.did).await? else {
+                return Ok(None);
+            };
+            let reply = self.reply_ref(&post).await?;
+            Ok(Some(FeedViewPost {
+                post,
+                reply,
+                reason: Some(ReasonRepost {
+                    by,
+                    indexed_at: record.indexed_at,
+                }),
+                feed_context: None,
+            }))
+        } else {
+            let Some(post) = self.post_view(&record.uri).await? else {
+                return Ok(None);
+            };
+            let reply = self.reply_ref(&post).await?;
+            Ok(Some(FeedViewPost {
+                post,
+                reply,
+                reason: None,
+                feed_context: None,
+            }))
+        }
+    }
+
+    pub async fn author_feed(
+        &mut self,
+        actor: &String,
+        limit: i64,
+        cursor: Option<String>,
+        filter: Option<String>,
+    ) -> Result<AuthorFeed> {
+        let did = self.resolve_actor(actor).await?;
+        if let Some(requester) = self.requester.clone() {
+            if graph::get_block(&requester, &did)?.is_some() {
+                return Err(LocalAppViewError::BlockedActor(did).into());
+            }
+            if graph::get_block(&did, &requester)?.is_some() {
+                return Err(LocalAppViewError::BlockedByActor(did).into());
+            }
+        }
+        let records = get_feed_records(vec![did.clone()], limit, cursor)?;
+        let cursor = feed_cursor(&records)?;
+
+        let mut feed = Vec::new();
+        for record in records {
+            let Some(item) = self.feed_item(record).await? else {
+                continue;
+            };
+            let include = match filter.as_deref() {
+                Some("posts_no_replies") => item.reason.is_some() || item.reply.is_none(),
+                Some("posts_with_media") => item.reason.is_none() && has_media(&item.post),
+                Some("posts_and_author_threads") => match (&item.reason, &item.reply) {
+                    (None, Some(reply)) => match &reply.parent {
+                        ReplyRefUnion::PostView(parent) => parent.author.did == did,
+                        _ => false,
+                    },
+                    _ => true,
+                },
+                _ => true,
+            };
+            if include {
+                feed.push(item);
+            }
+        }
+        Ok(AuthorFeed { cursor, feed })
+    }
+
+    /// Posts and reposts by the requester and the accounts they follow, newest first.
+    /// Replies are only included when they're to one of those accounts, and posts by
+    /// muted or blocked accounts are left out.
+    pub async fn timeline(&mut self, limit: i64, cursor: Option<String>) -> Result<AuthorFeed> {
+        let requester = self.requester.clone().unwrap_or_default();
+        let hidden = graph::get_hidden_actors(&requester)?;
+        let mut dids = get_follows(&requester)?;
+        dids.retain(|did| !hidden.contains(did));
+        dids.push(requester);
+        let records = get_feed_records(dids.clone(), limit, cursor)?;
+        let cursor = feed_cursor(&records)?;
+
+        let mut feed = Vec::new();
+        for record in records {
+            let Some(item) = self.feed_item(record).await? else {
+                continue;
+            };
+            let include = match &item.reply {
+                Some(reply) if item.reason.is_none() => match &reply.parent {
+                    ReplyRefUnion::PostView(parent) => dids.contains(&parent.author.did),
+                    _ => false,
+                },
+                _ => !hidden.contains(&item.post.author.did),
+            };
+            if include {
+                feed.push(item);
+            }
+        }
+        Ok(AuthorFeed { cursor, feed })
+    }
+
+    pub async fn post_thread(
+        &mut self,
+        uri: &String,
+        depth: u16,
+        parent_height: u16,
+    ) -> Result<GetPostThreadOutput> {
+        if let Some(did) = did_from_uri(uri) {
+            if self.is_blocked(&did)? {
+                return Ok(GetPostThreadOutput {
+                    thread: ThreadViewPostEnum::BlockedPost(self.blocked_post(uri, &did)?),
+                });
+            }
+        }
+        let Some(post) = self.post_view(uri).await? else {
+            return Err(LocalAppViewError::PostNotFound(uri.clone()).into());
+        };
+
+        // Walk up the reply chain, stopping at the first parent that isn't available
+        let mut ancestors: Vec<ThreadViewPostEnum> = Vec::new();
+        let mut parent_uri = json_str(&post.record, "/reply/parent/uri").map(|uri| uri.to_string());
+        while let Some(uri) = parent_uri {
+            if ancestors.len() >= parent_height as usize {
+                break;
+            }
+            if let Some(did) = did_from_uri(&uri) {
+                if self.is_blocked(&did)? {
+                    ancestors.push(ThreadViewPostEnum::BlockedPost(
+                        self.blocked_post(&uri, &did)?,
+                    ));
+                    break;
+                }
+            }
+            match self.post_view(&uri).await? {
+                Some(parent) => {
+                    parent_uri =
+                        json_str(&parent.record, "/reply/parent/uri").map(|uri| uri.to_string());
+                    ancestors.push(ThreadViewPostEnum::ThreadViewPost(ThreadViewPost {
+                        post: parent,
+                        parent: None,
+                        replies: None,
+                    }));
+                }
+                None => {
+                    ancestors.push(ThreadViewPostEnum::NotFoundPost(NotFoundPost {
+                        uri,
+                        not_found: true,
+                    }));
+                    parent_uri = None;
+                }
+            }
+        }
+        let mut parent: Option<Box<ThreadViewPostEnum>> = None;
+        for ancestor in ancestors.into_iter().rev() {
+            parent = Some(Box::new(match ancestor {
+                ThreadViewPostEnum::ThreadViewPost(ancestor) => {
+                    ThreadViewPostEnum::ThreadViewPost(ThreadViewPost { parent, ..ancestor })
+                }
+                ancestor => ancestor,
+            }));
+        }
+
+        // Gather replies level by level, then assemble the tree
+        let mut views: BTreeMap<String, PostView> = BTreeMap::new();
+        let mut children: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut level = vec![uri.clone()];
+        for _ in 0..depth {
+            let mut next_level = Vec::new();
+            for parent_uri in level {
+                let mut replies = Vec::new();
+                for reply_uri in get_replies(&parent_uri)? {
+                    let blocked = match did_from_uri(&reply_uri) {
+                        Some(did) => self.is_blocked(&did)?,
+                        None => false,
+                    };
+                    if blocked {
+                        continue;
+                    }
+                    if let Some(reply) = self.post_view(&reply_uri).await? {
+                        views.insert(reply_uri.clone(), reply);
+                        replies.push(reply_uri);
+                    }
+                }
+                next_level.extend(replies.iter().cloned());
+                children.insert(parent_uri, replies);
+            }
+            if next_level.is_empty() {
+                break;
+            }
+            level = next_level;
+        }
+        let thread = build_thread(post, parent, depth, &mut views, &children);
+        Ok(GetPostThreadOutput {
+            thread: ThreadViewPostEnum::ThreadViewPost(thread),
+        })
+    }
+
+    /// Posts liked by `actor`, most recently liked first. Only visible to the actor.
+    pub async fn actor_likes(
+        &mut self,
+        actor: &String,
+        limit: i64,
+        cursor: Option<String>,
+    ) -> Result<AuthorFeed> {
+        use crate::schema::pds::record::dsl as RecordSchema;
+        let did = self.resolve_actor(actor).await?;
+        if self.requester.as_ref() != Some(&did) {
+            return Err(LocalAppViewError::LikesNotVisible.into());
+        }
+        let records: Vec<models::Record> = {
+            let conn = &mut establish_connection()?;
+            let builder = RecordSchema::record
+                .filter(RecordSchema::did.eq(&did))
+                .filter(RecordSchema::collection.eq(Ids::AppBskyFeedLike.as_str()))
+                .filter(RecordSchema::takedownRef.is_null())
+                .select(models::Record::as_select())
+                .into_boxed();
+            paginate(
+                builder,
+                &FEED_KEYSET,
+                KeySetPaginateOpts {
+                    limit: Some(limit),
+                    cursor,
+                    direction: None,
+                },
+            )?
+            .load(conn)?
+        };
+        let cursor = feed_cursor(&records)?;
+
+        let mut feed = Vec::new();
+        for record in records {
+            let Some(subject) = get_link(&record.uri, "subject.uri")? else {
+                continue;
+            };
+            let Some(post) = self.post_view(&subject).await? else {
+                continue;
+            };
+            if self.is_blocked(&post.author.did)? {
+                continue;
+            }
+            let reply = self.reply_ref(&post).await?;
+            feed.push(FeedViewPost {
+                post,
+                reply,
+                reason: None,
+                feed_context: None,
+            });
+        }
+        Ok(AuthorFeed { cursor, feed })
+    }
+
+    /// Accounts that liked the post at `uri`, most recent first
+    pub async fn likes(
+        &mut self,
+        uri: String,
+        cid: Option<String>,
+        limit: i64,
+        cursor: Option<String>,
+    ) -> Result<GetLikesOutput> {
+        use crate::schema::pds::backlink::dsl as BacklinkSchema;
+        use crate::schema::pds::record::dsl as RecordSchema;
+        let records: Vec<models::Record> = {
+            let conn = &mut establish_connection()?;
+            let builder = RecordSchema::record
+                .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+                .filter(RecordSchema::collection.eq(Ids::AppBskyFeedLike.as_str()))
+                .filter(RecordSchema::takedownRef.is_null())
+                .filter(BacklinkSchema::path.eq("subject.uri"))
+                .filter(BacklinkSchema::linkTo.eq(&uri))
+                .select(models::Record::as_select())
+                .into_boxed();
+            paginate(
+                builder,
+                &FEED_KEYSET,
+                KeySetPaginateOpts {
+                    limit: Some(limit),
+                    cursor,
+                    direction: None,
+                },
+            )?
+            .load(conn)?
+        };
+        let cursor = feed_cursor(&records)?;
+
+        let mut likes = Vec::new();
+        for record in records {
+            if self.is_blocked(&record.did)? {
+                continue;
+            }
+            let Some(actor) = self.profile_view(&record.did).await? else {
+                continue;
+            };
+            let indexed_at = from_str_to_utc(&record.indexed_at);
+            likes.push(GetLikesLike {
+                created_at: indexed_at,
+                indexed_at,
+                actor,
+            });
+        }
+        Ok(GetLikesOutput {
+            uri,
+            cid,
+            likes,
+            cursor,
+        })
+    }
+}
@@ -1,12 +1,26 @@
 pub mod moderation;
+pub mod templates;
+pub mod transport;
 
-extern crate mailgun_rs;
-
-use anyhow::Result;
-use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message};
+use crate::mailer::transport::{transport_from_env, Mail, MailTransport};
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::env;
 
+lazy_static! {
+    static ref TRANSPORT: Result<Box<dyn MailTransport>> = transport_from_env();
+}
+
+/// Sends through the configured transport, erroring instead of panicking when
+/// email isn't set up on this PDS
+pub async fn send_mail(mail: Mail) -> Result<()> {
+    match &*TRANSPORT {
+        Ok(transport) => transport.send(mail).await,
+        Err(error) => Err(anyhow!("Email is not configured: {error}")),
+    }
+}
+
 pub struct MailOpts {
     pub to: String,
     pub subject: String,
@@ -25,6 +39,12 @@ pub struct TokenParam {
     pub token: String,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoginLockoutParams {
+    pub identifier: String,
+    pub locked_until: String,
+}
+
 pub async fn send_template(opts: MailOpts) -> Result<()> {
     let MailOpts {
         to,
@@ -33,27 +53,23 @@ pub async fn send_template(opts: MailOpts) -> Result<()> {
         template_vars,
     } = opts;
 
-    let recipient = EmailAddress::address(&to);
-    let message = Message {
-        to: vec![recipient],
-        subject,
-        template,
-        template_vars,
-        ..Default::default()
+    let (from_name, from_address) = match (
+        env::var("PDS_EMAIL_FROM_NAME"),
+        env::var("PDS_EMAIL_FROM_ADDRESS"),
+    ) {
+        (Ok(from_name), Ok(from_address)) => (from_name, from_address),
+        (Err(_), Ok(from_address)) => (from_address.clone(), from_address),
+        _ => return Err(anyhow!("PDS_EMAIL_FROM_ADDRESS is not configured")),
     };
-
-    let client = Mailgun {
-        api_key: env::var("PDS_MAILGUN_API_KEY").unwrap(),
-        domain: env::var("PDS_MAILGUN_DOMAIN").unwrap(),
-        message,
-    };
-    let sender = EmailAddress::name_address(
-        &env::var("PDS_EMAIL_FROM_NAME").unwrap(),
-        &env::var("PDS_EMAIL_FROM_ADDRESS").unwrap(),
-    );
-
-    client.async_send(MailgunRegion::US, &sender).await?;
-    Ok(())
+    let html = templates::render(&template, &template_vars).await?;
+    send_mail(Mail {
+        from_name,
+        from_address,
+        to,
+        subject,
+        html,
+    })
+    .await
 }
 
 pub async fn send_reset_password(to: String, params: IdentifierAndTokenParams) -> Result<()> {
@@ -63,7 +79,7 @@ pub async fn send_reset_password(to: String, params: IdentifierAndTokenParams) -
     send_template(MailOpts {
         to,
         subject: "Password Reset Requested".to_string(),
-        template: "reset password".to_string(),
+        template: "reset_password".to_string(),
         template_vars,
     })
     .await
@@ -75,7 +91,7 @@ pub async fn send_account_delete(to: String, params: TokenParam) -> Result<()> {
     send_template(MailOpts {
         to,
         subject: "Account Deletion Requested".to_string(),
-        template: "delete account".to_string(),
+        template: "delete_account".to_string(),
         template_vars,
     })
     .await
@@ -87,7 +103,7 @@ pub async fn send_confirm_email(to: String, params: TokenParam) -> Result<()> {
     send_template(MailOpts {
         to,
         subject: "Email Confirmation".to_string(),
-        template: "confirm email".to_string(),
+        template: "confirm_email".to_string(),
         template_vars,
     })
     .await
@@ -99,7 +115,7 @@ pub async fn send_update_email(to: String, params: TokenParam) -> Result<()> {
     send_template(MailOpts {
         to,
         subject: "Email Update Requested".to_string(),
-        template: "email update".to_string(),
+        template: "update_email".to_string(),
         template_vars,
     })
     .await
@@ -111,7 +127,32 @@ pub async fn send_plc_operation(to: String, params: TokenParam) -> Result<()> {
     send_template(MailOpts {
         to,
         subject: "PLC Update Operation Requested".to_string(),
-        template: "plc operation".to_string(),
+        template: "plc_operation".to_string(),
+        template_vars,
+    })
+    .await
+}
+
+pub async fn send_sign_in_token(to: String, params: TokenParam) -> Result<()> {
+    let mut template_vars = HashMap::new();
+    template_vars.insert("token".to_string(), params.token);
+    send_template(MailOpts {
+        to,
+        subject: "Sign-in Code".to_string(),
+        template: "sign_in_token".to_string(),
+        template_vars,
+    })
+    .await
+}
+
+pub async fn send_login_lockout(to: String, params: LoginLockoutParams) -> Result<()> {
+    let mut template_vars = HashMap::new();
+    template_vars.insert("identifier".to_string(), params.identifier);
+    template_vars.insert("lockedUntil".to_string(), params.locked_until);
+    send_template(MailOpts {
+        to,
+        subject: "Account Temporarily Locked".to_string(),
+        template: "login_lockout".to_string(),
         template_vars,
     })
     .await
@@ -1,5 +1,6 @@
-use anyhow::Result;
-use mailgun_rs::{EmailAddress, Mailgun, MailgunRegion, Message};
+use crate::mailer::send_mail;
+use crate::mailer::transport::Mail;
+use anyhow::{anyhow, Result};
 use std::env;
 
 pub struct HtmlMailOpts {
@@ -14,25 +15,20 @@ impl ModerationMailer {
     pub async fn send_html(opts: HtmlMailOpts) -> Result<()> {
         let HtmlMailOpts { to, subject, html } = opts;
 
-        let recipient = EmailAddress::address(&to);
-        let message = Message {
-            to: vec![recipient],
+        let from_address = env::var("PDS_MODERATION_EMAIL_FROM_ADDRESS")
+            .or_else(|_| env::var("PDS_EMAIL_FROM_ADDRESS"))
+            .map_err(|_| anyhow!("PDS_MODERATION_EMAIL_FROM_ADDRESS is not configured"))?;
+        let from_name = env::var("PDS_MODERATION_EMAIL_FROM_NAME")
+            .or_else(|_| env::var("PDS_EMAIL_FROM_NAME"))
+            .unwrap_or(from_address.clone());
+
+        send_mail(Mail {
+            from_name,
+            from_address,
+            to,
             subject,
             html,
-            ..Default::default()
-        };
-
-        let client = Mailgun {
-            api_key: env::var("PDS_MAILGUN_API_KEY").unwrap(),
-            domain: env::var("PDS_MAILGUN_DOMAIN").unwrap(),
-            message,
-        };
-        let sender = EmailAddress::name_address(
-            &env::var("PDS_MODERATION_EMAIL_FROM_NAME").unwrap(),
-            &env::var("PDS_MODERATION_EMAIL_FROM_ADDRESS").unwrap(),
-        );
-
-        client.async_send(MailgunRegion::US, &sender).await?;
-        Ok(())
+        })
+        .await
     }
 }
@@ -0,0 +1,158 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+const BUILT_IN: [(&str, &str); 7] = [
+    (
+        "reset_password",
+        include_str!("templates/reset_password.html"),
+    ),
+    (
+        "delete_account",
+        include_str!("templates/delete_account.html"),
+    ),
+    (
+        "confirm_email",
+        include_str!("templates/confirm_email.html"),
+    ),
+    ("update_email", include_str!("templates/update_email.html")),
+    (
+        "plc_operation",
+        include_str!("templates/plc_operation.html"),
+    ),
+    (
+        "sign_in_token",
+        include_str!("templates/sign_in_token.html"),
+    ),
+    (
+        "login_lockout",
+        include_str!("templates/login_lockout.html"),
+    ),
+];
+
+/// Loads `<name>.html` from `PDS_EMAIL_TEMPLATES_DIR` if the operator provided one,
+/// falling back to the template bundled with the PDS.
+async fn load_template(name: &str) -> Result<String> {
+    let dir = env::var("PDS_EMAIL_TEMPLATES_DIR").ok().map(PathBuf::from);
+    load_template_from(dir, name).await
+}
+
+async fn load_template_from(dir: Option<PathBuf>, name: &str) -> Result<String> {
+    if let Some(dir) = dir {
+        let path = dir.join(format!("{name}.html"));
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(tokio::fs::read_to_string(path).await?);
+        }
+    }
+    match BUILT_IN.iter().find(|(built_in, _)| *built_in == name) {
+        Some((_, template)) => Ok(template.to_string()),
+        None => bail!("Unknown email template `{name}`"),
+    }
+}
+
+/// Renders a template, substituting each `{{var}}` with its html-escaped value
+pub async fn render(name: &str, vars: &HashMap<String, String>) -> Result<String> {
+    Ok(substitute(&load_template(name).await?, vars))
+}
+
+/// Single pass over the template, so a value that looks like a `{{var}}` is left as is.
+/// Unknown vars are kept verbatim.
+fn substitute(template: &str, vars: &HashMap<String, String>) -> String {
+    let mut html = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}").map(|end| end + 2) else {
+            break;
+        };
+        html.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + len];
+        match vars.get(&placeholder[2..len - 2]) {
+            Some(value) => html.push_str(&escape_html(value)),
+            None => html.push_str(placeholder),
+        }
+        rest = &rest[start + len..];
+    }
+    html.push_str(rest);
+    html
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitutes_every_occurrence() {
+        let html = substitute(
+            "<p>{{token}}</p><p>{{handle}} {{token}}</p>",
+            &vars(&[("token", "ABC-123"), ("handle", "alice.test")]),
+        );
+        assert_eq!(html, "<p>ABC-123</p><p>alice.test ABC-123</p>");
+    }
+
+    #[test]
+    fn keeps_unknown_and_unclosed_vars() {
+        let html = substitute("{{missing}} {{token}} {{open", &vars(&[("token", "x")]));
+        assert_eq!(html, "{{missing}} x {{open");
+    }
+
+    #[test]
+    fn does_not_substitute_inside_values() {
+        let html = substitute(
+            "{{identifier}} {{token}}",
+            &vars(&[("identifier", "{{token}}"), ("token", "secret")]),
+        );
+        assert_eq!(html, "{{token}} secret");
+    }
+
+    #[test]
+    fn escapes_html_in_values() {
+        let html = substitute(
+            "<b>{{identifier}}</b>",
+            &vars(&[("identifier", "<script>alert('x')</script> & \"y\"")]),
+        );
+        assert_eq!(
+            html,
+            "<b>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; &quot;y&quot;</b>"
+        );
+    }
+
+    #[tokio::test]
+    async fn prefers_operator_template_and_falls_back_to_built_in() {
+        let dir = env::temp_dir().join(format!("rsky-pds-templates-{}", std::process::id()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("sign_in_token.html"), "Custom {{token}}")
+            .await
+            .unwrap();
+
+        let overridden = load_template_from(Some(dir.clone()), "sign_in_token")
+            .await
+            .unwrap();
+        assert_eq!(overridden, "Custom {{token}}");
+        let fallback = load_template_from(Some(dir.clone()), "reset_password")
+            .await
+            .unwrap();
+        assert_eq!(fallback, include_str!("templates/reset_password.html"));
+        assert!(load_template_from(Some(dir.clone()), "unknown")
+            .await
+            .is_err());
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+}
@@ -21,16 +21,32 @@ use rsky_identity::types::{DidCache, IdentityResolverOpts};
 use rsky_identity::IdResolver;
 use rsky_pds::account_manager::AccountManager;
 use rsky_pds::apis::*;
+use rsky_pds::chat::delivery::{ChatProtocol, CHAT_ALPN};
 use rsky_pds::common::env::env_list;
-use rsky_pds::config::env_to_cfg;
+use rsky_pds::config::{ServerConfig, SERVER_CONFIG};
 use rsky_pds::crawlers::Crawlers;
+use rsky_pds::image::cdn::get_image;
+use rsky_pds::jobs::backfill_search::BackfillSearchJob;
+use rsky_pds::jobs::blob_gc::BlobGcJob;
+use rsky_pds::jobs::process_videos::ProcessVideosJob;
+use rsky_pds::jobs::purge_accounts::PurgeAccountsJob;
+use rsky_pds::jobs::reindex_backlinks::ReindexBacklinksJob;
+use rsky_pds::jobs::JobScheduler;
+use rsky_pds::oauth;
+use rsky_pds::oauth::dpop::{DpopManager, DpopNonceFairing};
+use rsky_pds::rate_limiter::{RateLimitHeaders, RateLimiter};
 use rsky_pds::read_after_write::viewer::{LocalViewer, LocalViewerCreatorParams};
 use rsky_pds::sequencer::Sequencer;
-use rsky_pds::well_known::well_known;
+use rsky_pds::well_known::{
+    iroh_node, oauth_authorization_server, oauth_protected_resource, well_known,
+};
 use rsky_pds::{
-    DbConn, SharedATPAgent, SharedIdResolver, SharedLocalViewer, SharedSequencer, APP_USER_AGENT,
+    DbConn, SharedATPAgent, SharedIdResolver, SharedIroh, SharedLocalViewer, SharedSequencer,
+    APP_USER_AGENT,
 };
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 pub struct CORS;
@@ -90,6 +106,15 @@ async fn default_catcher() -> Json<rsky_pds::models::ErrorMessageResponse> {
     Json(internal_error)
 }
 
+#[catch(429)]
+async fn too_many_requests() -> Json<rsky_pds::models::ErrorMessageResponse> {
+    let rate_limit_error = rsky_pds::models::ErrorMessageResponse {
+        code: Some(rsky_pds::models::ErrorCode::RateLimitExceeded),
+        message: Some("Rate Limit Exceeded".to_string()),
+    };
+    Json(rate_limit_error)
+}
+
 /// Catches all OPTION requests in order to get the CORS related Fairing triggered.
 #[options("/<_..>")]
 async fn all_options() {
@@ -113,6 +138,34 @@ impl Fairing for CORS {
         ));
         response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
         response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+        response.set_header(Header::new(
+            "Access-Control-Expose-Headers",
+            "DPoP-Nonce, WWW-Authenticate, RateLimit-Limit, RateLimit-Remaining, RateLimit-Reset, RateLimit-Policy, Retry-After",
+        ));
+    }
+}
+
+fn local_viewer_params(cfg: &ServerConfig) -> LocalViewerCreatorParams {
+    LocalViewerCreatorParams {
+        account_manager: AccountManager {},
+        pds_hostname: cfg.service.hostname.clone(),
+        appview_agent: match cfg.bsky_app_view {
+            None => None,
+            Some(ref bsky_app_view) => Some(bsky_app_view.url.clone()),
+        },
+        appview_did: match cfg.bsky_app_view {
+            None => None,
+            Some(ref bsky_app_view) => Some(bsky_app_view.did.clone()),
+        },
+        appview_cdn_url_pattern: match cfg.bsky_app_view {
+            None => None,
+            Some(ref bsky_app_view) => bsky_app_view.cdn_url_pattern.clone(),
+        },
+        appview_video_cdn_url_pattern: match cfg.bsky_app_view {
+            None => None,
+            Some(ref bsky_app_view) => bsky_app_view.video_cdn_url_pattern.clone(),
+        },
+        local_cdn_url_pattern: cfg.images.cdn_url_pattern.clone(),
     }
 }
 
@@ -120,12 +173,6 @@ impl Fairing for CORS {
 async fn rocket() -> _ {
     dotenv().ok();
 
-    let iroh = iroh::node::Node::memory().spawn().await.unwrap();
-    println!(
-        "Iroh is running & online. public key: {:?}\n\n",
-        iroh.net().node_id().await
-    );
-
     let db_url = env::var("DATABASE_URL").unwrap_or("".into());
 
     let db: Map<_, Value> = map! {
@@ -137,13 +184,14 @@ async fn rocket() -> _ {
     let figment = rocket::Config::figment()
         .merge(("databases", map!["pg_db" => db]))
         .merge(("limits", Limits::default().limit("file", 100.mebibytes())));
-    let cfg = env_to_cfg();
+    let cfg = SERVER_CONFIG.clone();
+    let rate_limiter = RateLimiter::new(cfg.rate_limits.clone());
 
     let sequencer = SharedSequencer {
-        sequencer: RwLock::new(Sequencer::new(
+        sequencer: Arc::new(RwLock::new(Sequencer::new(
             Crawlers::new(cfg.service.hostname.clone(), cfg.crawlers.clone()),
             None,
-        )),
+        ))),
     };
     let mut background_sequencer = sequencer.sequencer.write().await.clone();
     tokio::spawn(async move { background_sequencer.start().await });
@@ -153,6 +201,45 @@ async fn rocket() -> _ {
         .load()
         .await;
 
+    let scheduler = JobScheduler::new();
+    if cfg.jobs.enabled {
+        scheduler
+            .schedule(Arc::new(PurgeAccountsJob {
+                interval: Duration::from_millis(cfg.jobs.account_purge_interval_ms),
+                s3_config: aws_sdk_config.clone(),
+                sequencer: sequencer.clone(),
+            }))
+            .await;
+        scheduler
+            .schedule(Arc::new(BlobGcJob {
+                interval: Duration::from_millis(cfg.jobs.blob_gc_interval_ms),
+                s3_config: aws_sdk_config.clone(),
+                dry_run: cfg.jobs.blob_gc_dry_run,
+                min_age_ms: cfg.jobs.blob_gc_min_age_ms,
+            }))
+            .await;
+        scheduler
+            .schedule(Arc::new(BackfillSearchJob {
+                interval: Duration::from_millis(cfg.jobs.search_backfill_interval_ms),
+            }))
+            .await;
+        scheduler
+            .schedule(Arc::new(ReindexBacklinksJob {
+                interval: Duration::from_millis(cfg.jobs.backlink_reindex_interval_ms),
+            }))
+            .await;
+    }
+
+    // Not gated on jobs.enabled, since uploaded videos wait on it
+    if cfg.video.local {
+        scheduler
+            .schedule(Arc::new(ProcessVideosJob {
+                interval: Duration::from_millis(cfg.video.process_interval_ms),
+                s3_config: aws_sdk_config.clone(),
+            }))
+            .await;
+    }
+
     let id_resolver = SharedIdResolver {
         id_resolver: RwLock::new(IdResolver::new(IdentityResolverOpts {
             timeout: None,
@@ -183,23 +270,38 @@ async fn rocket() -> _ {
         }
     };
     let local_viewer = SharedLocalViewer {
-        local_viewer: RwLock::new(LocalViewer::creator(LocalViewerCreatorParams {
-            account_manager: AccountManager {},
-            pds_hostname: cfg.service.hostname.clone(),
-            appview_agent: match cfg.bsky_app_view {
-                None => None,
-                Some(ref bsky_app_view) => Some(bsky_app_view.url.clone()),
-            },
-            appview_did: match cfg.bsky_app_view {
-                None => None,
-                Some(ref bsky_app_view) => Some(bsky_app_view.did.clone()),
-            },
-            appview_cdn_url_pattern: match cfg.bsky_app_view {
-                None => None,
-                Some(ref bsky_app_view) => bsky_app_view.cdn_url_pattern.clone(),
-            },
-        })),
+        local_viewer: RwLock::new(LocalViewer::creator(local_viewer_params(&cfg))),
+    };
+
+    let iroh = iroh::node::Node::memory()
+        .build()
+        .await
+        .unwrap()
+        .accept(
+            CHAT_ALPN,
+            Arc::new(ChatProtocol {
+                s3_config: aws_sdk_config.clone(),
+                local_viewer: SharedLocalViewer {
+                    local_viewer: RwLock::new(LocalViewer::creator(local_viewer_params(&cfg))),
+                },
+            }),
+        )
+        .spawn()
+        .await
+        .unwrap();
+    println!(
+        "Iroh is running & online. public key: {:?}\n\n",
+        iroh.node_id()
+    );
+    let shared_iroh = SharedIroh {
+        endpoint: iroh.endpoint().clone(),
+        node_id: iroh.node_id(),
     };
+    // The node shuts down when dropped
+    tokio::spawn(async move {
+        let _iroh = iroh;
+        std::future::pending::<()>().await
+    });
 
     let shield = Shield::default().enable(NoSniff::Enable);
 
@@ -215,15 +317,21 @@ async fn rocket() -> _ {
                 com::atproto::admin::disable_invite_codes::disable_invite_codes,
                 com::atproto::admin::enable_account_invites::enable_account_invites,
                 com::atproto::admin::get_account_info::get_account_info,
+                com::atproto::admin::get_account_infos::get_account_infos,
                 com::atproto::admin::get_invite_codes::get_invite_codes,
                 com::atproto::admin::get_subject_status::get_subject_status,
+                com::atproto::admin::search_accounts::search_accounts,
                 com::atproto::admin::send_email::send_email,
                 com::atproto::admin::update_account_password::update_account_password,
                 com::atproto::admin::update_account_email::update_account_email,
                 com::atproto::admin::update_account_handle::update_account_handle,
+                com::atproto::admin::update_account_signing_key::update_account_signing_key,
                 com::atproto::admin::update_subject_status::update_subject_status,
                 com::atproto::identity::resolve_handle::resolve_handle,
                 com::atproto::identity::update_handle::update_handle,
+                com::atproto::label::query_labels::query_labels,
+                com::atproto::label::subscribe_labels::subscribe_labels,
+                com::atproto::moderation::create_report::create_report,
                 com::atproto::repo::apply_writes::apply_writes,
                 com::atproto::repo::create_record::create_record,
                 com::atproto::repo::delete_record::delete_record,
@@ -268,16 +376,65 @@ async fn rocket() -> _ {
                 com::atproto::sync::list_blobs::list_blobs,
                 com::atproto::sync::list_repos::list_repos,
                 com::atproto::sync::subscribe_repos::subscribe_repos,
+                com::rsky::admin::audit_plc_log::audit_plc_log,
+                com::rsky::admin::collect_blob_garbage::collect_blob_garbage,
+                com::rsky::admin::create_labels::create_labels,
+                com::rsky::admin::get_job_statuses::get_job_statuses,
+                com::rsky::admin::list_reports::list_reports,
+                com::rsky::admin::resolve_report::resolve_report,
+                com::rsky::admin::set_account_quota::set_account_quota,
+                com::rsky::identity::nullify_plc_operation::nullify_plc_operation,
+                com::rsky::repo::complete_upload::complete_upload,
+                com::rsky::repo::create_upload::create_upload,
+                com::rsky::repo::get_backlinks::get_backlinks,
+                com::rsky::repo::get_upload::get_upload,
+                com::rsky::repo::upload_chunk::upload_chunk,
+                com::rsky::server::export_account::export_account,
+                com::rsky::server::get_account_usage::get_account_usage,
+                com::rsky::server::list_sessions::list_sessions,
+                com::rsky::server::revoke_session::revoke_all_sessions,
+                com::rsky::server::revoke_session::revoke_session,
                 app::bsky::actor::get_preferences::get_preferences,
                 app::bsky::actor::get_profile::get_profile,
+                app::bsky::actor::get_profile::local_get_profile,
                 app::bsky::actor::get_profiles::get_profiles,
+                app::bsky::actor::get_profiles::local_get_profiles,
                 app::bsky::actor::put_preferences::put_preferences,
+                app::bsky::actor::search_actors::search_actors,
+                app::bsky::actor::search_actors_typeahead::search_actors_typeahead,
                 app::bsky::feed::get_actor_likes::get_actor_likes,
+                app::bsky::feed::get_actor_likes::local_get_actor_likes,
                 app::bsky::feed::get_author_feed::get_author_feed,
+                app::bsky::feed::get_author_feed::local_get_author_feed,
                 app::bsky::feed::get_feed::get_feed,
+                app::bsky::feed::get_likes::get_likes,
                 app::bsky::feed::get_post_thread::get_post_thread,
+                app::bsky::feed::get_post_thread::local_get_post_thread,
                 app::bsky::feed::get_timeline::get_timeline,
+                app::bsky::feed::get_timeline::local_get_timeline,
+                app::bsky::feed::search_posts::search_posts,
+                app::bsky::graph::get_mutes::get_mutes,
+                app::bsky::graph::mute_actor::mute_actor,
+                app::bsky::graph::unmute_actor::unmute_actor,
+                app::bsky::notification::get_unread_count::get_unread_count,
+                app::bsky::notification::list_notifications::list_notifications,
                 app::bsky::notification::register_push::register_push,
+                app::bsky::notification::update_seen::update_seen,
+                app::bsky::video::get_job_status::get_job_status,
+                app::bsky::video::get_upload_limits::get_upload_limits,
+                app::bsky::video::upload_video::upload_video,
+                chat::convo::delete_message_for_self,
+                chat::convo::get_convo,
+                chat::convo::get_convo_for_members,
+                chat::convo::get_log,
+                chat::convo::get_messages,
+                chat::convo::leave_convo,
+                chat::convo::list_convos,
+                chat::convo::mute_convo,
+                chat::convo::send_message,
+                chat::convo::send_message_batch,
+                chat::convo::unmute_convo,
+                chat::convo::update_read,
                 chat::delete_message_for_self,
                 chat::delete_account,
                 chat::export_account_data,
@@ -293,18 +450,33 @@ async fn rocket() -> _ {
                 chat::unmute_convo,
                 chat::update_read,
                 bsky_api_forwarder,
+                oauth::routes::par,
+                oauth::routes::authorize,
+                oauth::routes::authorize_sign_in,
+                oauth::routes::token,
+                oauth::routes::revoke,
                 well_known,
+                iroh_node,
+                oauth_authorization_server,
+                oauth_protected_resource,
+                get_image,
                 all_options
             ],
         )
-        .register("/", catchers![default_catcher])
+        .register("/", catchers![default_catcher, too_many_requests])
         .attach(CORS)
         .attach(DbConn::fairing())
         .attach(shield)
+        .attach(DpopNonceFairing)
+        .attach(RateLimitHeaders)
         .manage(sequencer)
         .manage(aws_sdk_config)
         .manage(id_resolver)
         .manage(cfg)
         .manage(local_viewer)
         .manage(app_view_agent)
+        .manage(DpopManager::new())
+        .manage(rate_limiter)
+        .manage(scheduler)
+        .manage(shared_iroh)
 }
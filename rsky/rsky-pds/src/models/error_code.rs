@@ -50,6 +50,11 @@ pub enum ErrorCode {
     LoopDetected,
     NotExtended,
     NetworkAuthenticationRequired,
+    RateLimitExceeded,
+    AuthFactorTokenRequired,
+    AuthenticationRequired,
+    InvalidToken,
+    ExpiredToken,
 }
 
 impl ErrorCode {
@@ -102,6 +107,11 @@ impl ErrorCode {
             "LoopDetected" => Ok(Self::LoopDetected),
             "NotExtended" => Ok(Self::NotExtended),
             "NetworkAuthenticationRequired" => Ok(Self::NetworkAuthenticationRequired),
+            "RateLimitExceeded" => Ok(Self::RateLimitExceeded),
+            "AuthFactorTokenRequired" => Ok(Self::AuthFactorTokenRequired),
+            "AuthenticationRequired" => Ok(Self::AuthenticationRequired),
+            "InvalidToken" => Ok(Self::InvalidToken),
+            "ExpiredToken" => Ok(Self::ExpiredToken),
             _ => bail!("Invalid ErrorCode: `{code:?}` is not a valid error code"),
         }
     }
@@ -157,6 +167,11 @@ impl Display for ErrorCode {
             Self::LoopDetected => String::from("LoopDetected"),
             Self::NotExtended => String::from("NotExtended"),
             Self::NetworkAuthenticationRequired => String::from("NetworkAuthenticationRequired"),
+            Self::RateLimitExceeded => String::from("RateLimitExceeded"),
+            Self::AuthFactorTokenRequired => String::from("AuthFactorTokenRequired"),
+            Self::AuthenticationRequired => String::from("AuthenticationRequired"),
+            Self::InvalidToken => String::from("InvalidToken"),
+            Self::ExpiredToken => String::from("ExpiredToken"),
         };
         write!(f, "{}", str)
     }
@@ -9,6 +9,9 @@ pub use self::models::DidDoc;
 pub use self::models::EmailToken;
 pub use self::models::InviteCode;
 pub use self::models::InviteCodeUse;
+pub use self::models::OAuthAuthorizedClient;
+pub use self::models::OAuthRequest;
+pub use self::models::OAuthToken;
 pub use self::models::Record;
 pub use self::models::RecordBlob;
 pub use self::models::RefreshToken;
@@ -35,6 +35,9 @@ pub struct Account {
     #[diesel(column_name = emailConfirmedAt)]
     #[serde(rename = "emailConfirmedAt")]
     pub email_confirmed_at: Option<String>,
+    #[diesel(column_name = emailAuthFactor)]
+    #[serde(rename = "emailAuthFactor")]
+    pub email_auth_factor: bool,
 }
 
 #[derive(
@@ -59,6 +62,28 @@ pub struct AccountPref {
     pub value_json: Option<String>,
 }
 
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(did))]
+#[diesel(table_name = crate::schema::pds::account_quota)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AccountQuota {
+    pub did: String,
+    #[diesel(column_name = maxBlobBytes)]
+    #[serde(rename = "maxBlobBytes")]
+    pub max_blob_bytes: Option<i64>,
+    #[diesel(column_name = maxRecords)]
+    #[serde(rename = "maxRecords")]
+    pub max_records: Option<i64>,
+    #[diesel(column_name = maxRepoBytes)]
+    #[serde(rename = "maxRepoBytes")]
+    pub max_repo_bytes: Option<i64>,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
 #[derive(
     Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -147,6 +172,127 @@ pub struct Blob {
     pub takedown_ref: Option<String>,
 }
 
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::blob_upload)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct BlobUpload {
+    pub id: String,
+    pub did: String,
+    #[diesel(column_name = mimeType)]
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: i64,
+    // Bytes received so far, which is where the next chunk goes
+    pub received: i64,
+    // Sha256 cid the reassembled chunks have to match
+    pub cid: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::chat_convo)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChatConvo {
+    pub id: String,
+    pub rev: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::chat_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChatLog {
+    pub id: i64,
+    pub did: String,
+    #[diesel(column_name = convoId)]
+    #[serde(rename = "convoId")]
+    pub convo_id: String,
+    pub rev: String,
+    #[diesel(column_name = logType)]
+    #[serde(rename = "logType")]
+    pub log_type: String,
+    #[diesel(column_name = messageId)]
+    #[serde(rename = "messageId")]
+    pub message_id: Option<String>,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(convoId, did))]
+#[diesel(table_name = crate::schema::pds::chat_member)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChatMember {
+    #[diesel(column_name = convoId)]
+    #[serde(rename = "convoId")]
+    pub convo_id: String,
+    pub did: String,
+    pub muted: bool,
+    #[diesel(column_name = lastReadRev)]
+    #[serde(rename = "lastReadRev")]
+    pub last_read_rev: Option<String>,
+    #[diesel(column_name = leftAt)]
+    #[serde(rename = "leftAt")]
+    pub left_at: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(convoId, id))]
+#[diesel(table_name = crate::schema::pds::chat_message)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ChatMessage {
+    #[diesel(column_name = convoId)]
+    #[serde(rename = "convoId")]
+    pub convo_id: String,
+    pub id: String,
+    pub rev: String,
+    pub sender: String,
+    pub text: String,
+    /// Facets as JSON
+    pub facets: Option<String>,
+    /// Embed view as JSON
+    pub embed: Option<String>,
+    #[diesel(column_name = sentAt)]
+    #[serde(rename = "sentAt")]
+    pub sent_at: String,
+}
+
 #[derive(
     Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
 )]
@@ -172,6 +318,7 @@ pub enum EmailTokenPurpose {
     ResetPassword,
     DeleteAccount,
     PlcOperation,
+    SignIn,
 }
 
 impl EmailTokenPurpose {
@@ -182,6 +329,7 @@ impl EmailTokenPurpose {
             EmailTokenPurpose::ResetPassword => "reset_password",
             EmailTokenPurpose::DeleteAccount => "delete_account",
             EmailTokenPurpose::PlcOperation => "plc_operation",
+            EmailTokenPurpose::SignIn => "sign_in",
         }
     }
 
@@ -192,6 +340,7 @@ impl EmailTokenPurpose {
             "reset_password" => Ok(EmailTokenPurpose::ResetPassword),
             "delete_account" => Ok(EmailTokenPurpose::DeleteAccount),
             "plc_operation" => Ok(EmailTokenPurpose::PlcOperation),
+            "sign_in" => Ok(EmailTokenPurpose::SignIn),
             _ => bail!("Unable to parse as EmailTokenPurpose: `{s:?}`"),
         }
     }
@@ -282,6 +431,208 @@ pub struct InviteCodeUse {
     pub used_at: String,
 }
 
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(seq))]
+#[diesel(table_name = crate::schema::pds::label)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Label {
+    pub seq: i64,
+    pub src: String,
+    pub uri: String,
+    pub cid: Option<String>,
+    pub val: String,
+    pub neg: bool,
+    pub cts: String,
+    pub exp: Option<String>,
+    pub sig: Vec<u8>,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(table_name = crate::schema::pds::moderation_report)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ModerationReport {
+    pub id: i32,
+    #[diesel(column_name = reasonType)]
+    #[serde(rename = "reasonType")]
+    pub reason_type: String,
+    pub reason: Option<String>,
+    #[diesel(column_name = subjectDid)]
+    #[serde(rename = "subjectDid")]
+    pub subject_did: String,
+    #[diesel(column_name = subjectUri)]
+    #[serde(rename = "subjectUri")]
+    pub subject_uri: Option<String>,
+    #[diesel(column_name = subjectCid)]
+    #[serde(rename = "subjectCid")]
+    pub subject_cid: Option<String>,
+    #[diesel(column_name = reportedBy)]
+    #[serde(rename = "reportedBy")]
+    pub reported_by: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = resolvedAt)]
+    #[serde(rename = "resolvedAt")]
+    pub resolved_at: Option<String>,
+    #[diesel(column_name = resolvedBy)]
+    #[serde(rename = "resolvedBy")]
+    pub resolved_by: Option<String>,
+    #[diesel(column_name = resolutionNote)]
+    #[serde(rename = "resolutionNote")]
+    pub resolution_note: Option<String>,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(table_name = crate::schema::pds::notification)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Notification {
+    pub id: i64,
+    pub did: String,
+    #[diesel(column_name = recordUri)]
+    #[serde(rename = "recordUri")]
+    pub record_uri: String,
+    #[diesel(column_name = recordCid)]
+    #[serde(rename = "recordCid")]
+    pub record_cid: String,
+    pub author: String,
+    pub reason: String,
+    #[diesel(column_name = reasonSubject)]
+    #[serde(rename = "reasonSubject")]
+    pub reason_subject: Option<String>,
+    #[diesel(column_name = sortAt)]
+    #[serde(rename = "sortAt")]
+    pub sort_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did, clientId))]
+#[diesel(table_name = crate::schema::pds::oauth_authorized_client)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthAuthorizedClient {
+    pub did: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub scope: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::oauth_request)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthRequest {
+    pub id: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub parameters: String,
+    #[diesel(column_name = dpopJkt)]
+    #[serde(rename = "dpopJkt")]
+    pub dpop_jkt: Option<String>,
+    pub did: Option<String>,
+    pub code: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::oauth_token)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OAuthToken {
+    pub id: String,
+    pub did: String,
+    #[diesel(column_name = clientId)]
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub scope: String,
+    #[diesel(column_name = dpopJkt)]
+    #[serde(rename = "dpopJkt")]
+    pub dpop_jkt: String,
+    #[diesel(column_name = refreshToken)]
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[diesel(column_name = expiresAt)]
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+#[derive(
+    Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Default, Serialize, Deserialize,
+)]
+#[diesel(primary_key(did, token))]
+#[diesel(table_name = crate::schema::pds::push_registration)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PushRegistration {
+    pub did: String,
+    pub token: String,
+    pub platform: String,
+    #[diesel(column_name = serviceDid)]
+    #[serde(rename = "serviceDid")]
+    pub service_did: String,
+    #[diesel(column_name = appId)]
+    #[serde(rename = "appId")]
+    pub app_id: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
 #[derive(
     Queryable,
     Identifiable,
@@ -357,6 +708,19 @@ pub struct RefreshToken {
     #[diesel(column_name = appPasswordName)]
     #[serde(rename = "appPasswordName")]
     pub app_password_name: Option<String>,
+    #[diesel(column_name = sessionId)]
+    #[serde(rename = "sessionId")]
+    pub session_id: String,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    #[diesel(column_name = lastRefreshedAt)]
+    #[serde(rename = "lastRefreshedAt")]
+    pub last_refreshed_at: Option<String>,
+    #[diesel(column_name = userAgent)]
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
 }
 
 #[derive(
@@ -443,3 +807,99 @@ impl RepoSeq {
         }
     }
 }
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(uri))]
+#[diesel(table_name = crate::schema::pds::search_post)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SearchPost {
+    pub uri: String,
+    pub did: String,
+    pub text: String,
+    pub langs: Vec<String>,
+    pub tags: Vec<String>,
+    pub mentions: Vec<String>,
+    #[diesel(column_name = sortAt)]
+    #[serde(rename = "sortAt")]
+    pub sort_at: String,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(did))]
+#[diesel(table_name = crate::schema::pds::search_profile)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SearchProfile {
+    pub did: String,
+    #[diesel(column_name = displayName)]
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(
+    Queryable,
+    Identifiable,
+    Selectable,
+    Insertable,
+    Clone,
+    Debug,
+    PartialEq,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(primary_key(id))]
+#[diesel(table_name = crate::schema::pds::video_job)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct VideoJob {
+    pub id: String,
+    pub did: String,
+    pub state: String,
+    pub progress: i32,
+    // Where the upload waits in the blob store until it's processed
+    #[diesel(column_name = tempKey)]
+    #[serde(rename = "tempKey")]
+    pub temp_key: String,
+    #[diesel(column_name = mimeType)]
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: i64,
+    #[diesel(column_name = blobCid)]
+    #[serde(rename = "blobCid")]
+    pub blob_cid: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    #[diesel(column_name = durationMs)]
+    #[serde(rename = "durationMs")]
+    pub duration_ms: Option<i64>,
+    pub error: Option<String>,
+    pub message: Option<String>,
+    #[diesel(column_name = createdAt)]
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[diesel(column_name = updatedAt)]
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+}
@@ -0,0 +1,203 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::AccountManager;
+use crate::common;
+use crate::db::establish_connection;
+use crate::db::pagination::{paginate, Cursor, KeySet, KeySetPaginateOpts};
+use crate::models::models;
+use anyhow::{anyhow, bail, Result};
+use diesel::*;
+use rsky_lexicon::com::atproto::admin::RepoRef;
+use rsky_lexicon::com::atproto::moderation::{CreateReportInput, ReportSubject};
+use rsky_lexicon::com::atproto::repo::StrongRef;
+use rsky_lexicon::com::rsky::admin::ReportView;
+
+const REASON_TYPES: [&str; 7] = [
+    "com.atproto.moderation.defs#reasonSpam",
+    "com.atproto.moderation.defs#reasonViolation",
+    "com.atproto.moderation.defs#reasonMisleading",
+    "com.atproto.moderation.defs#reasonSexual",
+    "com.atproto.moderation.defs#reasonRude",
+    "com.atproto.moderation.defs#reasonOther",
+    "com.atproto.moderation.defs#reasonAppeal",
+];
+
+const MAX_REASON_LENGTH: usize = 20_000;
+
+/// Locally stored reports are listed newest first, by id
+pub struct ReportIdKeySet {}
+
+impl KeySet for ReportIdKeySet {
+    type Result = ReportView;
+
+    fn primary(&self) -> String {
+        r#""moderation_report"."id""#.to_string()
+    }
+
+    fn primary_type(&self) -> &'static str {
+        "integer"
+    }
+
+    fn label_result(&self, result: &ReportView) -> Result<Cursor> {
+        Ok(Cursor {
+            primary: result.id.to_string(),
+            secondary: None,
+        })
+    }
+
+    fn cursor_to_labeled_result(&self, cursor: Cursor) -> Result<Cursor> {
+        cursor
+            .primary
+            .parse::<i32>()
+            .map_err(|_| anyhow!("Malformed cursor"))?;
+        Ok(cursor)
+    }
+}
+
+pub struct ListReportsOpts {
+    pub resolved: Option<bool>,
+    pub subject: Option<String>,
+    pub limit: i64,
+    pub cursor: Option<String>,
+}
+
+fn subject_parts(subject: &ReportSubject) -> Result<(String, Option<String>, Option<String>)> {
+    match subject {
+        ReportSubject::RepoRef(RepoRef { did }) => Ok((did.clone(), None, None)),
+        ReportSubject::StrongRef(StrongRef { uri, cid }) => {
+            // @TODO: Use ATUri
+            let did = match uri.strip_prefix("at://") {
+                Some(rest) => rest.split("/").next().unwrap_or_default().to_string(),
+                None => bail!("Invalid report subject uri: {uri}"),
+            };
+            Ok((did, Some(uri.clone()), Some(cid.clone())))
+        }
+    }
+}
+
+pub fn report_to_view(report: models::ModerationReport) -> ReportView {
+    let subject = match (report.subject_uri, report.subject_cid) {
+        (Some(uri), Some(cid)) => ReportSubject::StrongRef(StrongRef { uri, cid }),
+        _ => ReportSubject::RepoRef(RepoRef {
+            did: report.subject_did,
+        }),
+    };
+    ReportView {
+        id: report.id as i64,
+        reason_type: report.reason_type,
+        reason: report.reason,
+        subject,
+        reported_by: report.reported_by,
+        created_at: report.created_at,
+        resolved_at: report.resolved_at,
+        resolved_by: report.resolved_by,
+        resolution_note: report.resolution_note,
+    }
+}
+
+/// Stores a report for the PDS admin. Only accounts hosted on this PDS, and their
+/// records, can be reported since those are the only ones the admin can act on.
+pub async fn create_report(input: CreateReportInput, reported_by: String) -> Result<ReportView> {
+    use crate::schema::pds::moderation_report::dsl as ReportSchema;
+    let CreateReportInput {
+        reason_type,
+        reason,
+        subject,
+    } = input;
+    if !REASON_TYPES.contains(&reason_type.as_str()) {
+        bail!("Invalid reason type: {reason_type}")
+    }
+    if reason
+        .as_ref()
+        .is_some_and(|reason| reason.len() > MAX_REASON_LENGTH)
+    {
+        bail!("Report reason is too long")
+    }
+    let (subject_did, subject_uri, subject_cid) = subject_parts(&subject)?;
+    let account = AccountManager::get_account(
+        &subject_did,
+        Some(AvailabilityFlags {
+            include_taken_down: Some(true),
+            include_deactivated: Some(true),
+        }),
+    )
+    .await?;
+    if account.is_none() {
+        bail!("Report subject not found")
+    }
+
+    let conn = &mut establish_connection()?;
+    let report = insert_into(ReportSchema::moderation_report)
+        .values((
+            ReportSchema::reasonType.eq(reason_type),
+            ReportSchema::reason.eq(reason),
+            ReportSchema::subjectDid.eq(subject_did),
+            ReportSchema::subjectUri.eq(subject_uri),
+            ReportSchema::subjectCid.eq(subject_cid),
+            ReportSchema::reportedBy.eq(reported_by),
+            ReportSchema::createdAt.eq(common::now()),
+        ))
+        .returning(models::ModerationReport::as_returning())
+        .get_result(conn)?;
+    Ok(report_to_view(report))
+}
+
+pub async fn list_reports(opts: ListReportsOpts) -> Result<Vec<ReportView>> {
+    use crate::schema::pds::moderation_report::dsl as ReportSchema;
+    let conn = &mut establish_connection()?;
+    let ListReportsOpts {
+        resolved,
+        subject,
+        limit,
+        cursor,
+    } = opts;
+
+    let mut builder = ReportSchema::moderation_report
+        .select(models::ModerationReport::as_select())
+        .into_boxed();
+    match resolved {
+        Some(true) => builder = builder.filter(ReportSchema::resolvedAt.is_not_null()),
+        Some(false) => builder = builder.filter(ReportSchema::resolvedAt.is_null()),
+        None => (),
+    }
+    if let Some(subject) = subject {
+        builder = match subject.starts_with("did:") {
+            true => builder.filter(ReportSchema::subjectDid.eq(subject)),
+            false => builder.filter(ReportSchema::subjectUri.eq(subject)),
+        };
+    }
+    let res: Vec<models::ModerationReport> = paginate(
+        builder,
+        &ReportIdKeySet {},
+        KeySetPaginateOpts {
+            limit: Some(limit),
+            cursor,
+            direction: None,
+        },
+    )?
+    .load(conn)?;
+    Ok(res.into_iter().map(report_to_view).collect())
+}
+
+pub async fn resolve_report(
+    id: i64,
+    resolved_by: String,
+    note: Option<String>,
+) -> Result<ReportView> {
+    use crate::schema::pds::moderation_report::dsl as ReportSchema;
+    let conn = &mut establish_connection()?;
+
+    let report = update(ReportSchema::moderation_report)
+        .filter(ReportSchema::id.eq(i32::try_from(id)?))
+        .set((
+            ReportSchema::resolvedAt.eq(common::now()),
+            ReportSchema::resolvedBy.eq(resolved_by),
+            ReportSchema::resolutionNote.eq(note),
+        ))
+        .returning(models::ModerationReport::as_returning())
+        .get_result(conn)
+        .optional()?;
+    match report {
+        Some(report) => Ok(report_to_view(report)),
+        None => bail!("Report not found"),
+    }
+}
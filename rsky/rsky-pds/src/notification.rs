@@ -0,0 +1,422 @@
+use crate::account_manager::AccountManager;
+use crate::common::time::from_str_to_utc;
+use crate::common::RFC3339_VARIANT;
+use crate::config::{ServerConfig, SERVER_CONFIG};
+use crate::db::establish_connection;
+use crate::db::pagination::{paginate, Cursor, KeySet, KeySetPaginateOpts};
+use crate::graph;
+use crate::models::models;
+use crate::repo::record::{get_backlinks, RecordReader};
+use crate::repo::types::{Ids, RepoRecord};
+use crate::INVALID_HANDLE;
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use diesel::dsl::not;
+use diesel::*;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rsky_lexicon::app::bsky::actor::{Profile, ProfileView};
+use rsky_lexicon::app::bsky::notification::{
+    ListNotificationsOutput, Notification as NotificationView,
+};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+
+const MENTION_FEATURE_TYPE: &str = "app.bsky.richtext.facet#mention";
+
+/// Someone to notify about a newly created record
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub recipient: String,
+    /// like, repost, follow, reply, mention or quote
+    pub reason: String,
+    /// The record that was liked, reposted, replied to or quoted
+    pub reason_subject: Option<String>,
+}
+
+/// Only matches when local notifications are enabled, otherwise the request is
+/// forwarded to the appview by `bsky_api_forwarder`
+pub struct LocalNotifications;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LocalNotifications {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.rocket().state::<ServerConfig>() {
+            Some(cfg) if cfg.notifications.local => Outcome::Success(LocalNotifications),
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
+
+/// Notifications are listed newest first, by id
+pub struct NotificationIdKeySet {}
+
+impl KeySet for NotificationIdKeySet {
+    type Result = models::Notification;
+
+    fn primary(&self) -> String {
+        r#""notification"."id""#.to_string()
+    }
+
+    fn primary_type(&self) -> &'static str {
+        "bigint"
+    }
+
+    fn label_result(&self, result: &models::Notification) -> Result<Cursor> {
+        Ok(Cursor {
+            primary: result.id.to_string(),
+            secondary: None,
+        })
+    }
+
+    fn cursor_to_labeled_result(&self, cursor: Cursor) -> Result<Cursor> {
+        cursor
+            .primary
+            .parse::<i64>()
+            .map_err(|_| anyhow!("Malformed cursor"))?;
+        Ok(cursor)
+    }
+}
+
+pub struct ListNotificationsOpts {
+    pub limit: i64,
+    pub cursor: Option<String>,
+    /// Only notifications for these reasons, all of them when empty
+    pub reasons: Vec<String>,
+}
+
+pub fn did_from_uri(uri: &str) -> Option<String> {
+    let did = uri.strip_prefix("at://")?.split('/').next()?;
+    match did.starts_with("did:") {
+        true => Some(did.to_string()),
+        false => None,
+    }
+}
+
+fn json_str<'a>(value: &'a JsonValue, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer)?.as_str()
+}
+
+/// Who should hear about a newly created record. Likes, reposts and follows come from
+/// the record's backlinks; replies, mentions and quotes from the post itself. Each
+/// recipient is notified once and authors are never notified of their own records.
+pub fn get_notifications(uri: &String, record: &RepoRecord) -> Result<Vec<Notification>> {
+    let Some(author) = did_from_uri(uri) else {
+        return Ok(Vec::new());
+    };
+    let record_json = serde_json::to_value(record)?;
+    let record_type = json_str(&record_json, "/$type").unwrap_or_default();
+    let mut notifications: BTreeMap<String, Notification> = BTreeMap::new();
+    let mut notify = |recipient: Option<String>, reason: &str, reason_subject: Option<String>| {
+        if let Some(recipient) = recipient {
+            if recipient != author && !notifications.contains_key(&recipient) {
+                notifications.insert(
+                    recipient.clone(),
+                    Notification {
+                        recipient,
+                        reason: reason.to_string(),
+                        reason_subject,
+                    },
+                );
+            }
+        }
+    };
+
+    if record_type == Ids::AppBskyFeedPost.as_str() {
+        if let Some(facets) = record_json.get("facets").and_then(JsonValue::as_array) {
+            for feature in facets
+                .iter()
+                .filter_map(|facet| facet.get("features")?.as_array())
+                .flatten()
+            {
+                if json_str(feature, "/$type") == Some(MENTION_FEATURE_TYPE) {
+                    notify(
+                        json_str(feature, "/did").map(|did| did.to_string()),
+                        "mention",
+                        None,
+                    );
+                }
+            }
+        }
+        for pointer in ["/reply/parent/uri", "/reply/root/uri"] {
+            if let Some(subject) = json_str(&record_json, pointer) {
+                notify(did_from_uri(subject), "reply", Some(subject.to_string()));
+            }
+        }
+        for pointer in ["/embed/record/uri", "/embed/record/record/uri"] {
+            if let Some(subject) = json_str(&record_json, pointer) {
+                notify(did_from_uri(subject), "quote", Some(subject.to_string()));
+            }
+        }
+    } else {
+        for backlink in get_backlinks(uri, record)? {
+            if record_type == Ids::AppBskyGraphFollow.as_str() {
+                notify(Some(backlink.link_to), "follow", None);
+            } else if record_type == Ids::AppBskyFeedLike.as_str() {
+                notify(
+                    did_from_uri(&backlink.link_to),
+                    "like",
+                    Some(backlink.link_to),
+                );
+            } else if record_type == Ids::AppBskyFeedRepost.as_str() {
+                notify(
+                    did_from_uri(&backlink.link_to),
+                    "repost",
+                    Some(backlink.link_to),
+                );
+            }
+        }
+    }
+    Ok(notifications.into_values().collect())
+}
+
+/// Drops the notifications whose recipients block or mute the author of the record at
+/// `uri`, or are blocked by them
+pub fn without_hidden(uri: &String, notifications: Vec<Notification>) -> Result<Vec<Notification>> {
+    let Some(author) = did_from_uri(uri) else {
+        return Ok(notifications);
+    };
+    let mut visible = Vec::with_capacity(notifications.len());
+    for notification in notifications {
+        if !graph::get_hidden_actors(&notification.recipient)?.contains(&author) {
+            visible.push(notification);
+        }
+    }
+    Ok(visible)
+}
+
+/// Stores the notifications whose recipients have an account on this PDS, when local
+/// notifications are enabled
+pub async fn index_notifications(
+    uri: &String,
+    cid: &String,
+    sort_at: &String,
+    notifications: &Vec<Notification>,
+) -> Result<()> {
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::notification::dsl as NotificationSchema;
+    if !SERVER_CONFIG.notifications.local || notifications.is_empty() {
+        return Ok(());
+    }
+    let Some(author) = did_from_uri(uri) else {
+        return Ok(());
+    };
+    let conn = &mut establish_connection()?;
+
+    let recipients = notifications
+        .iter()
+        .map(|notification| notification.recipient.clone())
+        .collect::<Vec<String>>();
+    let local_recipients: Vec<String> = ActorSchema::actor
+        .filter(ActorSchema::did.eq_any(recipients))
+        .select(ActorSchema::did)
+        .load(conn)?;
+    let rows = notifications
+        .iter()
+        .filter(|notification| local_recipients.contains(&notification.recipient))
+        .map(|notification| {
+            (
+                NotificationSchema::did.eq(&notification.recipient),
+                NotificationSchema::recordUri.eq(uri),
+                NotificationSchema::recordCid.eq(cid),
+                NotificationSchema::author.eq(&author),
+                NotificationSchema::reason.eq(&notification.reason),
+                NotificationSchema::reasonSubject.eq(&notification.reason_subject),
+                NotificationSchema::sortAt.eq(sort_at),
+            )
+        })
+        .collect::<Vec<_>>();
+    if !rows.is_empty() {
+        insert_into(NotificationSchema::notification)
+            .values(&rows)
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+pub async fn delete_notifications_for_record(uri: &String) -> Result<()> {
+    use crate::schema::pds::notification::dsl as NotificationSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(NotificationSchema::notification)
+        .filter(NotificationSchema::recordUri.eq(uri))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn get_seen_at(did: &String) -> Result<Option<String>> {
+    use crate::schema::pds::notification_seen::dsl as NotificationSeenSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = NotificationSeenSchema::notification_seen
+        .find(did)
+        .select(NotificationSeenSchema::seenAt)
+        .first::<String>(conn)
+        .optional()?;
+    Ok(res)
+}
+
+/// `seen_at` is client provided, so it's normalized to the format notifications are
+/// sorted by before being stored
+pub async fn update_seen(did: &String, seen_at: &String) -> Result<()> {
+    use crate::schema::pds::notification_seen::dsl as NotificationSeenSchema;
+    let seen_at =
+        DateTime::parse_from_rfc3339(seen_at).map_err(|_| anyhow!("Invalid seenAt: {seen_at}"))?;
+    let seen_at = format!("{}", seen_at.naive_utc().format(RFC3339_VARIANT));
+    let conn = &mut establish_connection()?;
+
+    insert_into(NotificationSeenSchema::notification_seen)
+        .values((
+            NotificationSeenSchema::did.eq(did),
+            NotificationSeenSchema::seenAt.eq(&seen_at),
+        ))
+        .on_conflict(NotificationSeenSchema::did)
+        .do_update()
+        .set(NotificationSeenSchema::seenAt.eq(&seen_at))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn get_unread_count(did: &String) -> Result<i64> {
+    use crate::schema::pds::notification::dsl as NotificationSchema;
+    let seen_at = get_seen_at(did).await?;
+    let conn = &mut establish_connection()?;
+
+    let hidden = graph::get_hidden_actors(did)?;
+    let mut builder = NotificationSchema::notification
+        .filter(NotificationSchema::did.eq(did))
+        .filter(not(NotificationSchema::author.eq_any(hidden)))
+        .into_boxed();
+    if let Some(seen_at) = seen_at {
+        builder = builder.filter(NotificationSchema::sortAt.gt(seen_at));
+    }
+    let res = builder.count().get_result::<i64>(conn)?;
+    Ok(res)
+}
+
+/// Profile view of a local account, built from its `app.bsky.actor.profile` record
+pub async fn get_profile_view(did: &String) -> Result<Option<ProfileView>> {
+    let Some(account) = AccountManager::get_account(did, None).await? else {
+        return Ok(None);
+    };
+    let profile_block: Option<models::RepoBlock> = {
+        use crate::schema::pds::record::dsl as RecordSchema;
+        use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+        let conn = &mut establish_connection()?;
+        RecordSchema::record
+            .inner_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
+            .select(models::RepoBlock::as_select())
+            .filter(RecordSchema::did.eq(did))
+            .filter(RecordSchema::collection.eq(Ids::AppBskyActorProfile.as_str()))
+            .filter(RecordSchema::rkey.eq("self"))
+            .first(conn)
+            .optional()?
+    };
+    let profile: Option<Profile> = match profile_block {
+        Some(profile_block) => serde_ipld_dagcbor::from_slice(profile_block.content.as_slice())?,
+        None => None,
+    };
+    let (display_name, description, avatar) = match profile {
+        Some(profile) => (
+            profile.display_name,
+            profile.description,
+            profile.avatar.and_then(|avatar| avatar.r#ref).map(|cid| {
+                format!(
+                    "https://{}/xrpc/{}?did={did}&cid={cid}",
+                    SERVER_CONFIG.service.hostname,
+                    Ids::ComAtprotoSyncGetBlob.as_str()
+                )
+            }),
+        ),
+        None => (None, None, None),
+    };
+    Ok(Some(ProfileView {
+        did: did.clone(),
+        handle: account.handle.unwrap_or_else(|| INVALID_HANDLE.to_string()),
+        display_name,
+        description,
+        avatar,
+        labels: vec![],
+        indexed_at: None,
+    }))
+}
+
+pub async fn list_notifications(
+    did: &String,
+    opts: ListNotificationsOpts,
+) -> Result<ListNotificationsOutput> {
+    use crate::schema::pds::notification::dsl as NotificationSchema;
+    let ListNotificationsOpts {
+        limit,
+        cursor,
+        reasons,
+    } = opts;
+    let seen_at = get_seen_at(did).await?;
+
+    // Blocks and mutes made since a notification was indexed hide it too
+    let hidden = graph::get_hidden_actors(did)?;
+    let mut builder = NotificationSchema::notification
+        .filter(NotificationSchema::did.eq(did))
+        .filter(not(NotificationSchema::author.eq_any(hidden)))
+        .select(models::Notification::as_select())
+        .into_boxed();
+    if !reasons.is_empty() {
+        builder = builder.filter(NotificationSchema::reason.eq_any(reasons));
+    }
+    let keyset = NotificationIdKeySet {};
+    let rows: Vec<models::Notification> = {
+        let conn = &mut establish_connection()?;
+        paginate(
+            builder,
+            &keyset,
+            KeySetPaginateOpts {
+                limit: Some(limit),
+                cursor,
+                direction: Some("desc".to_string()),
+            },
+        )?
+        .load(conn)?
+    };
+    let cursor = keyset.pack_from_result(&rows)?;
+
+    let mut authors: BTreeMap<String, Option<ProfileView>> = BTreeMap::new();
+    let mut notifications = Vec::with_capacity(rows.len());
+    for row in rows {
+        if !authors.contains_key(&row.author) {
+            authors.insert(row.author.clone(), get_profile_view(&row.author).await?);
+        }
+        let Some(Some(author)) = authors.get(&row.author) else {
+            continue;
+        };
+        // Skip records that were since taken down
+        let Some(record) = RecordReader::new(row.author.clone())
+            .get_record(&row.record_uri, Some(row.record_cid.clone()), None)
+            .await?
+        else {
+            continue;
+        };
+        notifications.push(NotificationView {
+            uri: row.record_uri,
+            cid: row.record_cid,
+            author: author.clone(),
+            reason: row.reason,
+            reason_subject: row.reason_subject,
+            record: serde_json::to_value(record.value)?,
+            is_read: match &seen_at {
+                Some(seen_at) => &row.sort_at <= seen_at,
+                None => false,
+            },
+            indexed_at: from_str_to_utc(&row.sort_at).to_rfc3339(),
+            labels: vec![],
+        });
+    }
+    Ok(ListNotificationsOutput {
+        cursor,
+        notifications,
+        priority: None,
+        seen_at: seen_at.map(|seen_at| from_str_to_utc(&seen_at).to_rfc3339()),
+    })
+}
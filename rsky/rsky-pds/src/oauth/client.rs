@@ -0,0 +1,186 @@
+use crate::oauth::types::OAuthClientMetadata;
+use crate::oauth::{OAuthError, ATPROTO_SCOPE, TRANSITION_GENERIC_SCOPE};
+use crate::push::resolve_public_endpoint;
+use crate::APP_USER_AGENT;
+use anyhow::{anyhow, Result};
+use reqwest::redirect::Policy;
+use url::Url;
+
+const LOOPBACK_CLIENT_ID: &str = "http://localhost";
+
+/// Resolves the metadata for `client_id`, which is either the https URL of the
+/// client metadata document or, for development, a `http://localhost` loopback client.
+pub async fn get_client_metadata(client_id: &String) -> Result<OAuthClientMetadata> {
+    let invalid_client = |message: String| anyhow!(OAuthError::InvalidClient(message));
+
+    let url = Url::parse(client_id)
+        .map_err(|_| invalid_client(format!("Invalid client_id `{client_id}`")))?;
+    let is_loopback = url.scheme() == "http" && url.host_str() == Some("localhost");
+    let metadata = if is_loopback {
+        loopback_client_metadata(client_id, &url)?
+    } else if url.scheme() == "https" {
+        // Anyone can hand us a client_id, so it must not reach this PDS or its private network
+        let (host, addr) = resolve_public_endpoint(&url).await.map_err(|_| {
+            invalid_client(format!(
+                "client_id `{client_id}` does not resolve to a public address"
+            ))
+        })?;
+        let client = reqwest::Client::builder()
+            .user_agent(APP_USER_AGENT)
+            .timeout(std::time::Duration::from_secs(5))
+            .redirect(Policy::none())
+            .resolve(&host, addr)
+            .build()?;
+        let res = client
+            .get(client_id)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(|error| invalid_client(format!("Failed to fetch client metadata: {error}")))?;
+        if !res.status().is_success() {
+            return Err(invalid_client(format!(
+                "Failed to fetch client metadata: {}",
+                res.status()
+            )));
+        }
+        let metadata: OAuthClientMetadata = res
+            .json()
+            .await
+            .map_err(|error| invalid_client(format!("Invalid client metadata: {error}")))?;
+        if &metadata.client_id != client_id {
+            return Err(invalid_client(
+                "client_id does not match the metadata document".to_string(),
+            ));
+        }
+        metadata
+    } else {
+        return Err(invalid_client(format!("Invalid client_id `{client_id}`")));
+    };
+    validate_client_metadata(&metadata, &url, is_loopback)?;
+    Ok(metadata)
+}
+
+/// Loopback clients have no metadata document. Redirect URIs and scope can be passed as
+/// query parameters of the client_id, per the atproto OAuth profile, but only loopback
+/// IP redirects are accepted since anyone can claim to be `http://localhost`.
+fn loopback_client_metadata(client_id: &String, url: &Url) -> Result<OAuthClientMetadata> {
+    let mut redirect_uris: Vec<String> = Vec::new();
+    let mut scope: Option<String> = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "redirect_uri" => {
+                let is_loopback_ip = Url::parse(&value).map_or(false, |redirect_uri| {
+                    is_loopback_redirect_uri(&redirect_uri)
+                });
+                if !is_loopback_ip {
+                    return Err(anyhow!(OAuthError::InvalidClient(format!(
+                        "Loopback clients can only redirect to http://127.0.0.1 or http://[::1], not `{value}`"
+                    ))));
+                }
+                redirect_uris.push(value.to_string())
+            }
+            "scope" => scope = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    if redirect_uris.is_empty() {
+        redirect_uris = vec!["http://127.0.0.1/".to_string(), "http://[::1]/".to_string()];
+    }
+    Ok(OAuthClientMetadata {
+        client_id: client_id.clone(),
+        client_name: Some(LOOPBACK_CLIENT_ID.to_string()),
+        client_uri: None,
+        logo_uri: None,
+        tos_uri: None,
+        policy_uri: None,
+        redirect_uris,
+        grant_types: vec![
+            "authorization_code".to_string(),
+            "refresh_token".to_string(),
+        ],
+        response_types: vec!["code".to_string()],
+        // Tokens are only accepted with `transition:generic`, so that is granted by default
+        scope: Some(scope.unwrap_or(format!("{ATPROTO_SCOPE} {TRANSITION_GENERIC_SCOPE}"))),
+        token_endpoint_auth_method: Some("none".to_string()),
+        application_type: Some("native".to_string()),
+        dpop_bound_access_tokens: true,
+    })
+}
+
+/// `http://127.0.0.1[:port]/…` or `http://[::1][:port]/…`
+fn is_loopback_redirect_uri(redirect_uri: &Url) -> bool {
+    redirect_uri.scheme() == "http"
+        && matches!(redirect_uri.host_str(), Some("127.0.0.1") | Some("[::1]"))
+        && redirect_uri.username().is_empty()
+        && redirect_uri.password().is_none()
+}
+
+/// Whether a client identified by `client_url` may send codes to `redirect_uri`. Loopback
+/// clients only get loopback IPs. Web clients get https on their own host and native apps
+/// can also use loopback IPs or a custom scheme named after their host, e.g.
+/// `com.example.app:/callback` for `https://app.example.com/client-metadata.json`.
+fn is_allowed_redirect_uri(
+    redirect_uri: &Url,
+    client_url: &Url,
+    is_loopback: bool,
+    is_native: bool,
+) -> bool {
+    if is_loopback {
+        return is_loopback_redirect_uri(redirect_uri);
+    }
+    let client_host = client_url.host_str().unwrap_or_default();
+    match redirect_uri.scheme() {
+        "https" => redirect_uri.host_str() == Some(client_host),
+        "http" => is_native && is_loopback_redirect_uri(redirect_uri),
+        scheme => {
+            let reversed_host = client_host
+                .split('.')
+                .rev()
+                .collect::<Vec<&str>>()
+                .join(".");
+            is_native && scheme == reversed_host
+        }
+    }
+}
+
+fn validate_client_metadata(
+    metadata: &OAuthClientMetadata,
+    client_url: &Url,
+    is_loopback: bool,
+) -> Result<()> {
+    let invalid_client = |message: &str| anyhow!(OAuthError::InvalidClient(message.to_string()));
+
+    if metadata.redirect_uris.is_empty() {
+        return Err(invalid_client(
+            "Client must register at least one redirect_uri",
+        ));
+    }
+    let is_native = metadata.application_type.as_deref() == Some("native");
+    for redirect_uri in &metadata.redirect_uris {
+        let allowed = Url::parse(redirect_uri).map_or(false, |url| {
+            is_allowed_redirect_uri(&url, client_url, is_loopback, is_native)
+        });
+        if !allowed {
+            return Err(anyhow!(OAuthError::InvalidClient(format!(
+                "redirect_uri `{redirect_uri}` is not allowed for this client"
+            ))));
+        }
+    }
+    if !metadata.dpop_bound_access_tokens {
+        return Err(invalid_client("Client must use DPoP bound access tokens"));
+    }
+    match &metadata.token_endpoint_auth_method {
+        None => (),
+        Some(method) if method == "none" => (),
+        Some(method) => {
+            return Err(invalid_client(&format!(
+                "Unsupported token_endpoint_auth_method `{method}`"
+            )))
+        }
+    }
+    let scopes = metadata.scope.clone().unwrap_or_default();
+    if !scopes.split(" ").any(|scope| scope == ATPROTO_SCOPE) {
+        return Err(invalid_client("Client must request the `atproto` scope"));
+    }
+    Ok(())
+}
@@ -0,0 +1,424 @@
+use crate::oauth::types::{DpopProofHeader, DpopProofPayload, Jwk};
+use crate::oauth::OAuthError;
+use anyhow::{anyhow, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use rand::RngCore;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Request, Response};
+use rsky_crypto::p256::operations::verify_sig;
+use rsky_crypto::types::VerifyOptions;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// How often the server nonce rotates. The previous and next nonces are still accepted
+/// to allow for clock drift and requests in flight.
+const NONCE_ROTATION_SECS: u64 = 60;
+/// Proofs older (or newer) than this are rejected, which bounds the replay cache.
+const MAX_PROOF_AGE_SECS: u64 = 5 * 60;
+
+pub const DPOP_NONCE_HEADER: &str = "DPoP-Nonce";
+
+pub struct VerifiedDpopProof {
+    /// JWK thumbprint of the key that signed the proof
+    pub jkt: String,
+}
+
+/// Set when a request failed because of a missing or stale DPoP nonce so that
+/// `DpopNonceFairing` can signal it through `WWW-Authenticate`.
+pub struct UseDpopNonce(pub bool);
+
+pub struct DpopManager {
+    secret: [u8; 32],
+    seen_jtis: Mutex<BTreeMap<String, u64>>,
+}
+
+impl DpopManager {
+    pub fn new() -> Self {
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        Self {
+            secret,
+            seen_jtis: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn nonce_for_counter(&self, counter: u64) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.secret);
+        hasher.update(counter.to_be_bytes());
+        Base64UrlUnpadded::encode_string(hasher.finalize().as_slice())
+    }
+
+    fn counter() -> u64 {
+        now_secs() / NONCE_ROTATION_SECS
+    }
+
+    pub fn next_nonce(&self) -> String {
+        self.nonce_for_counter(Self::counter())
+    }
+
+    pub fn check_nonce(&self, nonce: &String) -> bool {
+        let counter = Self::counter();
+        [counter.saturating_sub(1), counter, counter + 1]
+            .into_iter()
+            .any(|counter| &self.nonce_for_counter(counter) == nonce)
+    }
+
+    /// Verifies a DPoP proof (RFC 9449) for a request to `htu` with method `htm`.
+    /// `access_token` must be passed when the proof accompanies an access token
+    /// so that its `ath` claim can be checked.
+    pub fn verify_proof(
+        &self,
+        proof: &str,
+        htm: &str,
+        htu: &str,
+        access_token: Option<&str>,
+    ) -> Result<VerifiedDpopProof> {
+        let invalid = |message: &str| anyhow!(OAuthError::InvalidDpopProof(message.to_string()));
+
+        let parts = proof.split(".").collect::<Vec<&str>>();
+        let (header_b64, payload_b64, sig_b64) = match (parts.get(0), parts.get(1), parts.get(2)) {
+            (Some(header), Some(payload), Some(sig)) if parts.len() == 3 => {
+                (*header, *payload, *sig)
+            }
+            _ => return Err(invalid("Malformed DPoP proof")),
+        };
+        let header: DpopProofHeader = serde_json::from_slice(
+            Base64UrlUnpadded::decode_vec(header_b64)
+                .map_err(|_| invalid("Malformed DPoP proof header"))?
+                .as_slice(),
+        )
+        .map_err(|_| invalid("Malformed DPoP proof header"))?;
+        let payload: DpopProofPayload = serde_json::from_slice(
+            Base64UrlUnpadded::decode_vec(payload_b64)
+                .map_err(|_| invalid("Malformed DPoP proof payload"))?
+                .as_slice(),
+        )
+        .map_err(|_| invalid("Malformed DPoP proof payload"))?;
+
+        if header.typ != "dpop+jwt" {
+            return Err(invalid("Invalid DPoP proof type"));
+        }
+        if header.alg != "ES256" || header.jwk.kty != "EC" || header.jwk.crv != "P-256" {
+            return Err(invalid("Unsupported DPoP proof algorithm"));
+        }
+        let sig = Base64UrlUnpadded::decode_vec(sig_b64)
+            .map_err(|_| invalid("Malformed DPoP proof signature"))?;
+        let valid_sig = verify_sig(
+            &jwk_to_sec1(&header.jwk)?,
+            format!("{header_b64}.{payload_b64}").as_bytes(),
+            sig.as_slice(),
+            Some(VerifyOptions {
+                allow_malleable_sig: Some(true),
+            }),
+        )?;
+        if !valid_sig {
+            return Err(invalid("Invalid DPoP proof signature"));
+        }
+
+        if !payload.htm.eq_ignore_ascii_case(htm) {
+            return Err(invalid("DPoP htm mismatch"));
+        }
+        if strip_query(&payload.htu) != strip_query(htu) {
+            return Err(invalid("DPoP htu mismatch"));
+        }
+        let now = now_secs();
+        if payload.iat + MAX_PROOF_AGE_SECS < now || payload.iat > now + MAX_PROOF_AGE_SECS {
+            return Err(invalid("DPoP proof is expired"));
+        }
+        if let Some(access_token) = access_token {
+            let ath = Base64UrlUnpadded::encode_string(
+                Sha256::digest(access_token.as_bytes()).as_slice(),
+            );
+            if payload.ath != Some(ath) {
+                return Err(invalid("DPoP ath mismatch"));
+            }
+        }
+        match &payload.nonce {
+            Some(nonce) if self.check_nonce(nonce) => (),
+            _ => {
+                return Err(anyhow!(OAuthError::UseDpopNonce(
+                    "Authorization server requires nonce in DPoP proof".to_string()
+                )))
+            }
+        }
+        self.check_replay(&payload.jti, payload.iat)?;
+
+        Ok(VerifiedDpopProof {
+            jkt: jwk_thumbprint(&header.jwk)?,
+        })
+    }
+
+    fn check_replay(&self, jti: &String, iat: u64) -> Result<()> {
+        let mut seen_jtis = self.seen_jtis.lock().unwrap();
+        let now = now_secs();
+        seen_jtis.retain(|_, seen_iat| *seen_iat + MAX_PROOF_AGE_SECS >= now);
+        if seen_jtis.contains_key(jti) {
+            return Err(anyhow!(OAuthError::InvalidDpopProof(
+                "DPoP proof replayed".to_string()
+            )));
+        }
+        seen_jtis.insert(jti.clone(), iat);
+        Ok(())
+    }
+}
+
+/// RFC 7638 JWK thumbprint
+pub fn jwk_thumbprint(jwk: &Jwk) -> Result<String> {
+    // Members in lexicographic order, no whitespace
+    let canonical = format!(
+        r#"{{"crv":"{}","kty":"{}","x":"{}","y":"{}"}}"#,
+        jwk.crv, jwk.kty, jwk.x, jwk.y
+    );
+    Ok(Base64UrlUnpadded::encode_string(
+        Sha256::digest(canonical.as_bytes()).as_slice(),
+    ))
+}
+
+fn jwk_to_sec1(jwk: &Jwk) -> Result<Vec<u8>> {
+    let x = Base64UrlUnpadded::decode_vec(&jwk.x).map_err(|error| anyhow!(error.to_string()))?;
+    let y = Base64UrlUnpadded::decode_vec(&jwk.y).map_err(|error| anyhow!(error.to_string()))?;
+    Ok([vec![0x04], x, y].concat())
+}
+
+fn strip_query(url: &str) -> &str {
+    url.split(['?', '#']).next().unwrap_or(url)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in secs since UNIX epoch")
+        .as_secs()
+}
+
+/// Hands out a fresh `DPoP-Nonce` on every OAuth or DPoP authenticated response.
+pub struct DpopNonceFairing;
+
+#[rocket::async_trait]
+impl Fairing for DpopNonceFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Add DPoP-Nonce headers to responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let is_dpop_request = request.headers().get_one("DPoP").is_some()
+            || request.uri().path().starts_with("/oauth/");
+        if !is_dpop_request {
+            return;
+        }
+        if let Some(dpop_manager) = request.rocket().state::<DpopManager>() {
+            response.set_header(Header::new(DPOP_NONCE_HEADER, dpop_manager.next_nonce()));
+        }
+        if request.local_cache(|| UseDpopNonce(false)).0 {
+            response.set_header(Header::new(
+                "WWW-Authenticate",
+                r#"DPoP error="use_dpop_nonce", error_description="Resource server requires nonce in DPoP proof""#,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::{Signature, SigningKey};
+    use serde_json::json;
+
+    const HTU: &str = "https://pds.example.com/xrpc/com.atproto.repo.createRecord";
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_slice(&[7u8; 32]).unwrap()
+    }
+
+    fn jwk(key: &SigningKey) -> Jwk {
+        let point = key.verifying_key().to_encoded_point(false);
+        Jwk {
+            kty: "EC".to_string(),
+            crv: "P-256".to_string(),
+            x: Base64UrlUnpadded::encode_string(point.x().unwrap()),
+            y: Base64UrlUnpadded::encode_string(point.y().unwrap()),
+        }
+    }
+
+    fn sign(key: &SigningKey, header: serde_json::Value, payload: serde_json::Value) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            Base64UrlUnpadded::encode_string(header.to_string().as_bytes()),
+            Base64UrlUnpadded::encode_string(payload.to_string().as_bytes())
+        );
+        let sig: Signature = key.sign(signing_input.as_bytes());
+        format!(
+            "{signing_input}.{}",
+            Base64UrlUnpadded::encode_string(&sig.to_bytes())
+        )
+    }
+
+    fn header(key: &SigningKey) -> serde_json::Value {
+        json!({ "typ": "dpop+jwt", "alg": "ES256", "jwk": jwk(key) })
+    }
+
+    fn payload(manager: &DpopManager, jti: &str) -> serde_json::Value {
+        json!({
+            "jti": jti,
+            "htm": "POST",
+            "htu": HTU,
+            "iat": now_secs(),
+            "nonce": manager.next_nonce(),
+        })
+    }
+
+    fn proof(manager: &DpopManager, jti: &str) -> String {
+        let key = signing_key();
+        sign(&key, header(&key), payload(manager, jti))
+    }
+
+    fn is_invalid_proof(result: Result<VerifiedDpopProof>) -> bool {
+        matches!(
+            result
+                .err()
+                .and_then(|error| error.downcast::<OAuthError>().ok()),
+            Some(OAuthError::InvalidDpopProof(_))
+        )
+    }
+
+    #[test]
+    fn verifies_proofs() -> Result<()> {
+        let manager = DpopManager::new();
+        let verified = manager.verify_proof(&proof(&manager, "a"), "POST", HTU, None)?;
+        assert_eq!(verified.jkt, jwk_thumbprint(&jwk(&signing_key()))?);
+        // The query and fragment aren't part of htu
+        let url = format!("{HTU}?validate=true");
+        manager.verify_proof(&proof(&manager, "b"), "post", &url, None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_replayed_proofs() -> Result<()> {
+        let manager = DpopManager::new();
+        let proof = proof(&manager, "a");
+        manager.verify_proof(&proof, "POST", HTU, None)?;
+        assert!(is_invalid_proof(
+            manager.verify_proof(&proof, "POST", HTU, None)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_proofs_for_other_requests() {
+        let manager = DpopManager::new();
+        assert!(is_invalid_proof(manager.verify_proof(
+            &proof(&manager, "a"),
+            "GET",
+            HTU,
+            None
+        )));
+        let other = "https://pds.example.com/xrpc/com.atproto.repo.deleteRecord";
+        assert!(is_invalid_proof(manager.verify_proof(
+            &proof(&manager, "b"),
+            "POST",
+            other,
+            None
+        )));
+    }
+
+    #[test]
+    fn rejects_stale_proofs() {
+        let manager = DpopManager::new();
+        let key = signing_key();
+        for iat in [
+            now_secs() - MAX_PROOF_AGE_SECS - 10,
+            now_secs() + MAX_PROOF_AGE_SECS + 10,
+        ] {
+            let mut payload = payload(&manager, &format!("{iat}"));
+            payload["iat"] = json!(iat);
+            let proof = sign(&key, header(&key), payload);
+            assert!(is_invalid_proof(
+                manager.verify_proof(&proof, "POST", HTU, None)
+            ));
+        }
+    }
+
+    #[test]
+    fn requires_a_current_nonce() {
+        let manager = DpopManager::new();
+        let key = signing_key();
+        for nonce in [json!(null), json!("stale")] {
+            let mut payload = payload(&manager, "a");
+            payload["nonce"] = nonce;
+            let proof = sign(&key, header(&key), payload);
+            let error = manager
+                .verify_proof(&proof, "POST", HTU, None)
+                .err()
+                .unwrap();
+            assert!(matches!(
+                error.downcast::<OAuthError>(),
+                Ok(OAuthError::UseDpopNonce(_))
+            ));
+        }
+        // Nonces from another server don't count either
+        let other = DpopManager::new();
+        assert!(manager
+            .verify_proof(&proof(&other, "b"), "POST", HTU, None)
+            .is_err());
+    }
+
+    #[test]
+    fn checks_the_access_token_hash() -> Result<()> {
+        let manager = DpopManager::new();
+        let key = signing_key();
+        let token = "access-token";
+        let mut bound = payload(&manager, "a");
+        bound["ath"] = json!(Base64UrlUnpadded::encode_string(
+            Sha256::digest(token.as_bytes()).as_slice()
+        ));
+        manager.verify_proof(&sign(&key, header(&key), bound), "POST", HTU, Some(token))?;
+        assert!(is_invalid_proof(manager.verify_proof(
+            &proof(&manager, "b"),
+            "POST",
+            HTU,
+            Some(token)
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_signatures_and_headers() {
+        let manager = DpopManager::new();
+        let key = signing_key();
+        // Signed by a different key than the one in the header
+        let other_key = SigningKey::from_slice(&[9u8; 32]).unwrap();
+        let forged = sign(&other_key, header(&key), payload(&manager, "a"));
+        assert!(is_invalid_proof(
+            manager.verify_proof(&forged, "POST", HTU, None)
+        ));
+
+        let mut wrong_type = header(&key);
+        wrong_type["typ"] = json!("JWT");
+        let proof = sign(&key, wrong_type, payload(&manager, "b"));
+        assert!(is_invalid_proof(
+            manager.verify_proof(&proof, "POST", HTU, None)
+        ));
+
+        let mut wrong_alg = header(&key);
+        wrong_alg["alg"] = json!("ES256K");
+        let proof = sign(&key, wrong_alg, payload(&manager, "c"));
+        assert!(is_invalid_proof(
+            manager.verify_proof(&proof, "POST", HTU, None)
+        ));
+
+        assert!(is_invalid_proof(manager.verify_proof(
+            "not.a-proof",
+            "POST",
+            HTU,
+            None
+        )));
+    }
+}
@@ -0,0 +1,473 @@
+use crate::auth_verifier::AuthScope;
+use crate::common;
+use crate::common::get_random_str;
+use crate::common::time::{from_millis_to_str, from_str_to_utc, MINUTE};
+use crate::config::ServerConfig;
+use crate::db::establish_connection;
+use crate::models::{OAuthRequest, OAuthToken};
+use crate::oauth::types::{
+    Cnf, OAuthAuthorizationServerMetadata, OAuthClaimObj, OAuthProtectedResourceMetadata,
+    PushedAuthorizationRequest,
+};
+use anyhow::{anyhow, bail, Result};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use diesel::dsl::exists;
+use diesel::*;
+use jwt_simple::prelude::*;
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use sha2::{Digest, Sha256};
+use std::env;
+use thiserror::Error;
+
+pub const ATPROTO_SCOPE: &str = "atproto";
+pub const TRANSITION_GENERIC_SCOPE: &str = "transition:generic";
+pub const TRANSITION_CHAT_SCOPE: &str = "transition:chat.bsky";
+
+const REQUEST_URI_PREFIX: &str = "urn:ietf:params:oauth:request_uri:";
+const PAR_EXPIRES_IN_MS: i32 = 5 * MINUTE;
+const CODE_EXPIRES_IN_MS: i32 = MINUTE;
+const ACCESS_TOKEN_EXPIRES_IN_SECS: u64 = 60 * 60;
+const REFRESH_TOKEN_EXPIRES_IN_DAYS: u64 = 90;
+
+#[derive(Error, Debug)]
+pub enum OAuthError {
+    #[error("invalid_request: `{0}`")]
+    InvalidRequest(String),
+    #[error("invalid_client: `{0}`")]
+    InvalidClient(String),
+    #[error("invalid_grant: `{0}`")]
+    InvalidGrant(String),
+    #[error("invalid_scope: `{0}`")]
+    InvalidScope(String),
+    #[error("unsupported_grant_type: `{0}`")]
+    UnsupportedGrantType(String),
+    #[error("access_denied: `{0}`")]
+    AccessDenied(String),
+    #[error("invalid_dpop_proof: `{0}`")]
+    InvalidDpopProof(String),
+    #[error("use_dpop_nonce: `{0}`")]
+    UseDpopNonce(String),
+    #[error("invalid_token: `{0}`")]
+    InvalidToken(String),
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::InvalidRequest(_) => "invalid_request",
+            OAuthError::InvalidClient(_) => "invalid_client",
+            OAuthError::InvalidGrant(_) => "invalid_grant",
+            OAuthError::InvalidScope(_) => "invalid_scope",
+            OAuthError::UnsupportedGrantType(_) => "unsupported_grant_type",
+            OAuthError::AccessDenied(_) => "access_denied",
+            OAuthError::InvalidDpopProof(_) => "invalid_dpop_proof",
+            OAuthError::UseDpopNonce(_) => "use_dpop_nonce",
+            OAuthError::InvalidToken(_) => "invalid_token",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            OAuthError::InvalidRequest(message)
+            | OAuthError::InvalidClient(message)
+            | OAuthError::InvalidGrant(message)
+            | OAuthError::InvalidScope(message)
+            | OAuthError::UnsupportedGrantType(message)
+            | OAuthError::AccessDenied(message)
+            | OAuthError::InvalidDpopProof(message)
+            | OAuthError::UseDpopNonce(message)
+            | OAuthError::InvalidToken(message) => message.clone(),
+        }
+    }
+}
+
+/// Verified claims of a DPoP bound access token
+pub struct OAuthAccessToken {
+    pub did: String,
+    pub client_id: String,
+    pub scope: String,
+    pub jkt: String,
+    pub token_id: String,
+}
+
+pub fn authorization_server_metadata(cfg: &ServerConfig) -> OAuthAuthorizationServerMetadata {
+    let issuer = cfg.service.public_url.clone();
+    OAuthAuthorizationServerMetadata {
+        authorization_endpoint: format!("{issuer}/oauth/authorize"),
+        token_endpoint: format!("{issuer}/oauth/token"),
+        revocation_endpoint: format!("{issuer}/oauth/revoke"),
+        pushed_authorization_request_endpoint: format!("{issuer}/oauth/par"),
+        require_pushed_authorization_requests: true,
+        scopes_supported: vec![
+            ATPROTO_SCOPE.to_string(),
+            TRANSITION_GENERIC_SCOPE.to_string(),
+            TRANSITION_CHAT_SCOPE.to_string(),
+        ],
+        response_types_supported: vec!["code".to_string()],
+        response_modes_supported: vec!["query".to_string(), "fragment".to_string()],
+        grant_types_supported: vec![
+            "authorization_code".to_string(),
+            "refresh_token".to_string(),
+        ],
+        code_challenge_methods_supported: vec!["S256".to_string()],
+        token_endpoint_auth_methods_supported: vec!["none".to_string()],
+        dpop_signing_alg_values_supported: vec!["ES256".to_string()],
+        authorization_response_iss_parameter_supported: true,
+        client_id_metadata_document_supported: true,
+        protected_resources: vec![issuer.clone()],
+        issuer,
+    }
+}
+
+pub fn protected_resource_metadata(cfg: &ServerConfig) -> OAuthProtectedResourceMetadata {
+    OAuthProtectedResourceMetadata {
+        resource: cfg.service.public_url.clone(),
+        authorization_servers: vec![cfg.service.public_url.clone()],
+        scopes_supported: vec![],
+        bearer_methods_supported: vec!["header".to_string()],
+        resource_documentation: "https://atproto.com".to_string(),
+    }
+}
+
+/// Maps the OAuth scopes granted to a client onto the legacy session scopes used by
+/// the rest of the auth verifier.
+pub fn scope_to_auth_scope(scope: &String) -> Result<AuthScope> {
+    let scopes = scope.split(" ").collect::<Vec<&str>>();
+    if !scopes.contains(&ATPROTO_SCOPE) {
+        bail!(OAuthError::InvalidScope(
+            "Missing `atproto` scope".to_string()
+        ))
+    }
+    if !scopes.contains(&TRANSITION_GENERIC_SCOPE) {
+        bail!(OAuthError::InvalidScope(
+            "Token is not authorized for generic access".to_string()
+        ))
+    }
+    if scopes.contains(&TRANSITION_CHAT_SCOPE) {
+        Ok(AuthScope::AppPassPrivileged)
+    } else {
+        Ok(AuthScope::AppPass)
+    }
+}
+
+pub fn verify_pkce(code_challenge: &String, code_verifier: &String) -> bool {
+    let computed =
+        Base64UrlUnpadded::encode_string(Sha256::digest(code_verifier.as_bytes()).as_slice());
+    &computed == code_challenge
+}
+
+pub fn request_id_from_uri(request_uri: &String) -> Result<String> {
+    match request_uri.strip_prefix(REQUEST_URI_PREFIX) {
+        Some(id) => Ok(id.to_string()),
+        None => bail!(OAuthError::InvalidRequest(
+            "Invalid request_uri".to_string()
+        )),
+    }
+}
+
+fn expires_at(ms: i32) -> String {
+    from_millis_to_str(chrono::Utc::now().timestamp_millis() + ms as i64)
+}
+
+fn is_expired(expires_at: &String) -> bool {
+    from_str_to_utc(expires_at) <= chrono::Utc::now()
+}
+
+// Requests
+// ----------
+
+pub async fn create_request(
+    params: PushedAuthorizationRequest,
+    dpop_jkt: Option<String>,
+) -> Result<(String, i64)> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let id = get_random_str();
+    insert_into(OAuthRequestSchema::oauth_request)
+        .values((
+            OAuthRequestSchema::id.eq(&id),
+            OAuthRequestSchema::clientId.eq(&params.client_id),
+            OAuthRequestSchema::parameters.eq(serde_json::to_string(&params)?),
+            OAuthRequestSchema::dpopJkt.eq(dpop_jkt),
+            OAuthRequestSchema::createdAt.eq(common::now()),
+            OAuthRequestSchema::expiresAt.eq(expires_at(PAR_EXPIRES_IN_MS)),
+        ))
+        .execute(conn)?;
+    Ok((
+        format!("{REQUEST_URI_PREFIX}{id}"),
+        (PAR_EXPIRES_IN_MS / 1000) as i64,
+    ))
+}
+
+pub async fn get_request(
+    request_uri: &String,
+    client_id: &String,
+) -> Result<(OAuthRequest, PushedAuthorizationRequest)> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let id = request_id_from_uri(request_uri)?;
+    let request = OAuthRequestSchema::oauth_request
+        .find(&id)
+        .select(OAuthRequest::as_select())
+        .first(conn)
+        .optional()?;
+    match request {
+        Some(request) if &request.client_id == client_id && !is_expired(&request.expires_at) => {
+            let params: PushedAuthorizationRequest = serde_json::from_str(&request.parameters)?;
+            Ok((request, params))
+        }
+        _ => bail!(OAuthError::InvalidRequest(
+            "Unknown or expired request_uri".to_string()
+        )),
+    }
+}
+
+/// Binds a pushed request to the account that signed in and issues a one-time code
+pub async fn authorize_request(
+    request: &OAuthRequest,
+    did: &String,
+    scope: &String,
+) -> Result<String> {
+    use crate::schema::pds::oauth_authorized_client::dsl as AuthorizedClientSchema;
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let code = format!("cod-{}", get_random_str());
+    let now = common::now();
+    update(OAuthRequestSchema::oauth_request)
+        .filter(OAuthRequestSchema::id.eq(&request.id))
+        .set((
+            OAuthRequestSchema::did.eq(did),
+            OAuthRequestSchema::code.eq(&code),
+            OAuthRequestSchema::expiresAt.eq(expires_at(CODE_EXPIRES_IN_MS)),
+        ))
+        .execute(conn)?;
+    insert_into(AuthorizedClientSchema::oauth_authorized_client)
+        .values((
+            AuthorizedClientSchema::did.eq(did),
+            AuthorizedClientSchema::clientId.eq(&request.client_id),
+            AuthorizedClientSchema::scope.eq(scope),
+            AuthorizedClientSchema::createdAt.eq(&now),
+            AuthorizedClientSchema::updatedAt.eq(&now),
+        ))
+        .on_conflict((
+            AuthorizedClientSchema::did,
+            AuthorizedClientSchema::clientId,
+        ))
+        .do_update()
+        .set((
+            AuthorizedClientSchema::scope.eq(scope),
+            AuthorizedClientSchema::updatedAt.eq(&now),
+        ))
+        .execute(conn)?;
+    Ok(code)
+}
+
+pub async fn delete_request(id: &String) -> Result<()> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(OAuthRequestSchema::oauth_request)
+        .filter(OAuthRequestSchema::id.eq(id))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Consumes an authorization code. Codes are single use: the request is deleted whether or
+/// not the exchange succeeds.
+pub async fn consume_code(code: &String) -> Result<(OAuthRequest, PushedAuthorizationRequest)> {
+    use crate::schema::pds::oauth_request::dsl as OAuthRequestSchema;
+    let conn = &mut establish_connection()?;
+
+    let request = delete(OAuthRequestSchema::oauth_request)
+        .filter(OAuthRequestSchema::code.eq(code))
+        .returning(OAuthRequest::as_select())
+        .get_result(conn)
+        .optional()?;
+    match request {
+        Some(request) if request.did.is_some() && !is_expired(&request.expires_at) => {
+            let params: PushedAuthorizationRequest = serde_json::from_str(&request.parameters)?;
+            Ok((request, params))
+        }
+        _ => bail!(OAuthError::InvalidGrant(
+            "Invalid or expired authorization code".to_string()
+        )),
+    }
+}
+
+// Tokens
+// ----------
+
+fn jwt_key() -> Keypair {
+    let secp = Secp256k1::new();
+    let private_key = env::var("PDS_JWT_KEY_K256_PRIVATE_KEY_HEX").unwrap();
+    let secret_key = SecretKey::from_slice(&hex::decode(private_key.as_bytes()).unwrap()).unwrap();
+    Keypair::from_secret_key(&secp, &secret_key)
+}
+
+fn create_access_token(
+    token_id: &String,
+    did: &String,
+    client_id: &String,
+    scope: &String,
+    jkt: &String,
+) -> Result<String> {
+    let claims = Claims::with_custom_claims(
+        OAuthClaimObj {
+            scope: scope.clone(),
+            client_id: client_id.clone(),
+            cnf: Cnf { jkt: jkt.clone() },
+        },
+        Duration::from_secs(ACCESS_TOKEN_EXPIRES_IN_SECS),
+    )
+    .with_audience(env::var("PDS_SERVICE_DID").unwrap())
+    .with_subject(did)
+    .with_jwt_id(token_id);
+    // alg ES256K
+    let key = ES256kKeyPair::from_bytes(jwt_key().secret_bytes().as_slice())?;
+    Ok(key.sign(claims)?)
+}
+
+pub async fn create_token(
+    did: &String,
+    client_id: &String,
+    scope: &String,
+    jkt: &String,
+) -> Result<(String, String)> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let token_id = format!("tok-{}", get_random_str());
+    let refresh_token = format!("ref-{}", get_random_str());
+    let now = common::now();
+    let expires_at = from_millis_to_str(
+        chrono::Utc::now().timestamp_millis()
+            + Duration::from_days(REFRESH_TOKEN_EXPIRES_IN_DAYS).as_millis() as i64,
+    );
+    insert_into(OAuthTokenSchema::oauth_token)
+        .values((
+            OAuthTokenSchema::id.eq(&token_id),
+            OAuthTokenSchema::did.eq(did),
+            OAuthTokenSchema::clientId.eq(client_id),
+            OAuthTokenSchema::scope.eq(scope),
+            OAuthTokenSchema::dpopJkt.eq(jkt),
+            OAuthTokenSchema::refreshToken.eq(&refresh_token),
+            OAuthTokenSchema::createdAt.eq(&now),
+            OAuthTokenSchema::updatedAt.eq(&now),
+            OAuthTokenSchema::expiresAt.eq(&expires_at),
+        ))
+        .execute(conn)?;
+    let access_token = create_access_token(&token_id, did, client_id, scope, jkt)?;
+    Ok((access_token, refresh_token))
+}
+
+/// Rotates a refresh token. The token id stays the same so that revoking it
+/// invalidates every access token handed out for this session.
+pub async fn refresh_token(
+    refresh_token: &String,
+    client_id: &String,
+    jkt: &String,
+) -> Result<(OAuthToken, String, String)> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let token = OAuthTokenSchema::oauth_token
+        .filter(OAuthTokenSchema::refreshToken.eq(refresh_token))
+        .select(OAuthToken::as_select())
+        .first(conn)
+        .optional()?;
+    let token = match token {
+        Some(token) if !is_expired(&token.expires_at) => token,
+        _ => bail!(OAuthError::InvalidGrant(
+            "Invalid refresh token".to_string()
+        )),
+    };
+    if &token.client_id != client_id {
+        bail!(OAuthError::InvalidGrant(
+            "Refresh token was issued to another client".to_string()
+        ))
+    }
+    if &token.dpop_jkt != jkt {
+        bail!(OAuthError::InvalidDpopProof(
+            "DPoP key mismatch".to_string()
+        ))
+    }
+    let next_refresh_token = format!("ref-{}", get_random_str());
+    update(OAuthTokenSchema::oauth_token)
+        .filter(OAuthTokenSchema::id.eq(&token.id))
+        .filter(OAuthTokenSchema::refreshToken.eq(refresh_token))
+        .set((
+            OAuthTokenSchema::refreshToken.eq(&next_refresh_token),
+            OAuthTokenSchema::updatedAt.eq(common::now()),
+        ))
+        .execute(conn)?;
+    let access_token =
+        create_access_token(&token.id, &token.did, &token.client_id, &token.scope, jkt)?;
+    Ok((token, access_token, next_refresh_token))
+}
+
+/// Revokes the session that `token` (access or refresh token) belongs to
+pub async fn revoke_token(token: &String) -> Result<()> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    let conn = &mut establish_connection()?;
+
+    let token_id = match decode_access_token(token) {
+        Ok(claims) => Some(claims.token_id),
+        Err(_) => None,
+    };
+    delete(OAuthTokenSchema::oauth_token)
+        .filter(
+            OAuthTokenSchema::refreshToken
+                .eq(token)
+                .or(OAuthTokenSchema::id.eq(token_id.unwrap_or_default())),
+        )
+        .execute(conn)?;
+    Ok(())
+}
+
+fn decode_access_token(token: &String) -> Result<OAuthAccessToken> {
+    let mut options = VerificationOptions::default();
+    options.allowed_audiences = Some(HashSet::from_strings(&[
+        env::var("PDS_SERVICE_DID").unwrap()
+    ]));
+    let key = ES256kKeyPair::from_bytes(jwt_key().secret_bytes().as_slice())?;
+    let claims = key
+        .public_key()
+        .verify_token::<OAuthClaimObj>(token, Some(options))
+        .map_err(|error| anyhow!(OAuthError::InvalidToken(error.to_string())))?;
+    match (claims.subject, claims.jwt_id) {
+        (Some(did), Some(token_id)) => Ok(OAuthAccessToken {
+            did,
+            client_id: claims.custom.client_id,
+            scope: claims.custom.scope,
+            jkt: claims.custom.cnf.jkt,
+            token_id,
+        }),
+        _ => bail!(OAuthError::InvalidToken("Malformed token".to_string())),
+    }
+}
+
+/// Verifies an access token and checks that its session hasn't been revoked
+pub async fn verify_access_token(token: &String) -> Result<OAuthAccessToken> {
+    use crate::schema::pds::oauth_token::dsl as OAuthTokenSchema;
+    let access_token = decode_access_token(token)?;
+    let conn = &mut establish_connection()?;
+
+    let exists = select(exists(
+        OAuthTokenSchema::oauth_token.filter(OAuthTokenSchema::id.eq(&access_token.token_id)),
+    ))
+    .get_result::<bool>(conn)?;
+    if !exists {
+        bail!(OAuthError::InvalidToken(
+            "Token has been revoked".to_string()
+        ))
+    }
+    Ok(access_token)
+}
+
+pub mod client;
+pub mod dpop;
+pub mod routes;
+pub mod types;
@@ -0,0 +1,487 @@
+use crate::account_manager::helpers::account::AvailabilityFlags;
+use crate::account_manager::helpers::email_token::EmailTokenError;
+use crate::account_manager::AccountManager;
+use crate::apis::com::atproto::server::create_session::{
+    record_login_failure, verify_login, InvalidLogin,
+};
+use crate::config::ServerConfig;
+use crate::mailer;
+use crate::mailer::TokenParam;
+use crate::models::models::EmailTokenPurpose;
+use crate::oauth;
+use crate::oauth::client::get_client_metadata;
+use crate::oauth::dpop::{DpopManager, VerifiedDpopProof};
+use crate::oauth::types::{
+    AuthorizeSignInForm, OAuthErrorResponse, PushedAuthorizationRequest,
+    PushedAuthorizationResponse, RevokeRequest, TokenRequest, TokenResponse,
+};
+use crate::oauth::OAuthError;
+use crate::rate_limiter::{LoginRateLimit, RateLimit, RateLimitExceeded};
+use anyhow::{anyhow, bail, Result};
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::content::RawHtml;
+use rocket::response::{status, Redirect};
+use rocket::serde::json::Json;
+use rocket::{Request, State};
+use url::Url;
+
+/// DPoP proof sent along with a request, plus what it must have been signed for
+pub struct DpopRequest {
+    pub proof: Option<String>,
+    pub htm: String,
+    pub htu: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DpopRequest {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let cfg = req.guard::<&State<ServerConfig>>().await.unwrap();
+        Outcome::Success(DpopRequest {
+            proof: req.headers().get_one("DPoP").map(|proof| proof.to_string()),
+            htm: req.method().as_str().to_string(),
+            htu: format!("{}{}", cfg.service.public_url, req.uri().path()),
+        })
+    }
+}
+
+impl DpopRequest {
+    pub fn verify(&self, dpop_manager: &DpopManager) -> Result<VerifiedDpopProof> {
+        match &self.proof {
+            None => bail!(OAuthError::InvalidDpopProof(
+                "DPoP proof required".to_string()
+            )),
+            Some(proof) => dpop_manager.verify_proof(proof, &self.htm, &self.htu, None),
+        }
+    }
+}
+
+fn oauth_error(error: anyhow::Error) -> status::Custom<Json<OAuthErrorResponse>> {
+    eprintln!("@LOG: OAUTH ERROR: {error}");
+    match error.downcast_ref::<OAuthError>() {
+        Some(error) => {
+            let status = match error {
+                OAuthError::InvalidClient(_) | OAuthError::InvalidToken(_) => Status::Unauthorized,
+                _ => Status::BadRequest,
+            };
+            status::Custom(
+                status,
+                Json(OAuthErrorResponse {
+                    error: error.code().to_string(),
+                    error_description: Some(error.description()),
+                }),
+            )
+        }
+        None => status::Custom(
+            Status::InternalServerError,
+            Json(OAuthErrorResponse {
+                error: "server_error".to_string(),
+                error_description: Some("Internal error.".to_string()),
+            }),
+        ),
+    }
+}
+
+async fn inner_par(
+    params: PushedAuthorizationRequest,
+    dpop: DpopRequest,
+    dpop_manager: &State<DpopManager>,
+) -> Result<PushedAuthorizationResponse> {
+    let client = get_client_metadata(&params.client_id).await?;
+    if params.response_type != "code" {
+        bail!(OAuthError::InvalidRequest(
+            "Only the `code` response_type is supported".to_string()
+        ))
+    }
+    if params.code_challenge_method != "S256" {
+        bail!(OAuthError::InvalidRequest(
+            "Only the `S256` code_challenge_method is supported".to_string()
+        ))
+    }
+    if let Some(redirect_uri) = &params.redirect_uri {
+        if !client.redirect_uris.contains(redirect_uri) {
+            bail!(OAuthError::InvalidRequest(
+                "redirect_uri is not registered for this client".to_string()
+            ))
+        }
+    }
+    let client_scopes = client.scope.clone().unwrap_or_default();
+    let client_scopes = client_scopes.split(" ").collect::<Vec<&str>>();
+    if let Some(scope) = &params.scope {
+        if let Some(unregistered) = scope.split(" ").find(|s| !client_scopes.contains(s)) {
+            bail!(OAuthError::InvalidScope(format!(
+                "Scope `{unregistered}` is not registered for this client"
+            )))
+        }
+    }
+    let dpop_jkt = match &dpop.proof {
+        None => None,
+        Some(_) => Some(dpop.verify(dpop_manager)?.jkt),
+    };
+    let (request_uri, expires_in) = oauth::create_request(params, dpop_jkt).await?;
+    Ok(PushedAuthorizationResponse {
+        request_uri,
+        expires_in,
+    })
+}
+
+#[rocket::post("/oauth/par", data = "<body>")]
+pub async fn par(
+    body: Form<PushedAuthorizationRequest>,
+    dpop: DpopRequest,
+    dpop_manager: &State<DpopManager>,
+) -> Result<
+    status::Created<Json<PushedAuthorizationResponse>>,
+    status::Custom<Json<OAuthErrorResponse>>,
+> {
+    match inner_par(body.into_inner(), dpop, dpop_manager).await {
+        Ok(res) => Ok(status::Created::new("").body(Json(res))),
+        Err(error) => Err(oauth_error(error)),
+    }
+}
+
+fn escape_html(input: &str) -> String {
+    input
+        .replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+        .replace("'", "&#39;")
+}
+
+fn render_authorize_page(
+    client_name: &str,
+    client_id: &str,
+    request_uri: &str,
+    scope: &str,
+    login_hint: &str,
+    error: Option<&str>,
+    auth_factor: bool,
+) -> String {
+    let scopes = scope
+        .split(" ")
+        .map(|scope| format!("<li><code>{}</code></li>", escape_html(scope)))
+        .collect::<Vec<String>>()
+        .join("");
+    let error = match error {
+        Some(error) => format!(r#"<p class="error">{}</p>"#, escape_html(error)),
+        None => String::new(),
+    };
+    let auth_factor = match auth_factor {
+        true => {
+            r#"<label for="auth_factor_token">Sign-in code sent to your email</label>
+<input id="auth_factor_token" name="auth_factor_token" autocomplete="one-time-code" required>"#
+        }
+        false => "",
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Sign in to {client_name}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 24rem; margin: 4rem auto; padding: 0 1rem; }}
+input {{ display: block; width: 100%; margin-bottom: 1rem; padding: 0.5rem; box-sizing: border-box; }}
+button {{ padding: 0.5rem 1rem; margin-right: 0.5rem; }}
+.error {{ color: #b00020; }}
+</style>
+</head>
+<body>
+<h1>Sign in</h1>
+<p><strong>{client_name}</strong> (<code>{client_id}</code>) is asking for access to your account:</p>
+<ul>{scopes}</ul>
+{error}
+<form method="post" action="/oauth/authorize/sign-in">
+<input type="hidden" name="client_id" value="{client_id}">
+<input type="hidden" name="request_uri" value="{request_uri}">
+<label for="identifier">Handle or email</label>
+<input id="identifier" name="identifier" value="{login_hint}" autocomplete="username" required>
+<label for="password">Password</label>
+<input id="password" name="password" type="password" autocomplete="current-password">
+{auth_factor}
+<button type="submit" name="decision" value="accept">Authorize</button>
+<button type="submit" name="decision" value="deny" formnovalidate>Deny</button>
+</form>
+</body>
+</html>"#,
+        client_name = escape_html(client_name),
+        client_id = escape_html(client_id),
+        request_uri = escape_html(request_uri),
+        login_hint = escape_html(login_hint),
+    )
+}
+
+#[rocket::get("/oauth/authorize?<client_id>&<request_uri>")]
+pub async fn authorize(
+    client_id: String,
+    request_uri: String,
+) -> Result<RawHtml<String>, status::Custom<Json<OAuthErrorResponse>>> {
+    let result: Result<String> = async {
+        let (_, params) = oauth::get_request(&request_uri, &client_id).await?;
+        let client = get_client_metadata(&client_id).await?;
+        Ok(render_authorize_page(
+            client.client_name.as_deref().unwrap_or(client_id.as_str()),
+            &client_id,
+            &request_uri,
+            &params
+                .scope
+                .clone()
+                .unwrap_or(client.scope.clone().unwrap_or_default()),
+            &params.login_hint.clone().unwrap_or_default(),
+            None,
+            false,
+        ))
+    }
+    .await;
+    match result {
+        Ok(page) => Ok(RawHtml(page)),
+        Err(error) => Err(oauth_error(error)),
+    }
+}
+
+fn redirect_with(redirect_uri: &String, query: Vec<(&str, String)>) -> Result<Redirect> {
+    let mut url = Url::parse(redirect_uri)?;
+    {
+        let mut pairs = url.query_pairs_mut();
+        for (key, value) in query {
+            pairs.append_pair(key, &value);
+        }
+    }
+    Ok(Redirect::to(url.to_string()))
+}
+
+async fn inner_authorize_sign_in(
+    form: AuthorizeSignInForm,
+    cfg: &State<ServerConfig>,
+    rate_limit: &RateLimit<'_>,
+) -> Result<std::result::Result<Redirect, RawHtml<String>>> {
+    let (request, params) = oauth::get_request(&form.request_uri, &form.client_id).await?;
+    let client = get_client_metadata(&form.client_id).await?;
+    let redirect_uri = params
+        .redirect_uri
+        .clone()
+        .unwrap_or(client.redirect_uris[0].clone());
+    let scope = params
+        .scope
+        .clone()
+        .unwrap_or(client.scope.clone().unwrap_or_default());
+    let mut response = vec![("iss", cfg.service.public_url.clone())];
+    if let Some(state) = &params.state {
+        response.push(("state", state.clone()));
+    }
+
+    if form.decision != "accept" {
+        oauth::delete_request(&request.id).await?;
+        response.push(("error", "access_denied".to_string()));
+        return Ok(Ok(redirect_with(&redirect_uri, response)?));
+    }
+
+    // Bad credentials: show the page again so the user can retry
+    let retry = |message: &str, auth_factor: bool| {
+        RawHtml(render_authorize_page(
+            client
+                .client_name
+                .as_deref()
+                .unwrap_or(form.client_id.as_str()),
+            &form.client_id,
+            &form.request_uri,
+            &scope,
+            &form.identifier,
+            Some(message),
+            auth_factor,
+        ))
+    };
+    let identifier = form.identifier.to_lowercase();
+    let flags = Some(AvailabilityFlags {
+        include_deactivated: Some(true),
+        include_taken_down: Some(true),
+    });
+    let user = match identifier.contains("@") {
+        true => AccountManager::get_account_by_email(&identifier, flags).await?,
+        false => AccountManager::get_account(&identifier, flags).await?,
+    };
+    // App passwords are only for clients that can't do OAuth
+    match verify_login(
+        rate_limit,
+        &identifier,
+        user.as_ref(),
+        &form.password,
+        false,
+    )
+    .await
+    {
+        Ok(_) => (),
+        Err(error) if error.is::<InvalidLogin>() => {
+            return Ok(Err(retry(&error.to_string(), false)))
+        }
+        Err(error) if error.is::<RateLimitExceeded>() => {
+            let message = error.downcast::<RateLimitExceeded>().unwrap().message;
+            return Ok(Err(retry(&message, false)));
+        }
+        Err(error) => return Err(error),
+    }
+    let Some(user) = user else {
+        return Ok(Err(retry(&InvalidLogin.to_string(), false)));
+    };
+    if user.takedown_ref.is_some() {
+        return Ok(Err(retry("Account has been taken down", false)));
+    }
+    if let (true, Some(email)) = (
+        AccountManager::get_email_auth_factor(&user.did).await?,
+        &user.email,
+    ) {
+        match &form.auth_factor_token {
+            None => {
+                let token =
+                    AccountManager::create_email_token(&user.did, EmailTokenPurpose::SignIn)
+                        .await?;
+                mailer::send_sign_in_token(email.clone(), TokenParam { token }).await?;
+                return Ok(Err(retry(
+                    "A sign in code has been sent to your email address",
+                    true,
+                )));
+            }
+            Some(token) => {
+                match AccountManager::assert_valid_email_token(
+                    &user.did,
+                    EmailTokenPurpose::SignIn,
+                    token,
+                )
+                .await
+                {
+                    Ok(()) => (),
+                    Err(error) if error.is::<EmailTokenError>() => {
+                        record_login_failure(rate_limit, &user).await;
+                        let message = match error.downcast_ref::<EmailTokenError>() {
+                            Some(EmailTokenError::Expired) => "Sign in code has expired",
+                            _ => "Invalid sign in code",
+                        };
+                        return Ok(Err(retry(message, true)));
+                    }
+                    Err(error) => return Err(error),
+                }
+                AccountManager::delete_email_token(&user.did, EmailTokenPurpose::SignIn).await?;
+            }
+        }
+    }
+    rate_limit.record_success(&user.did);
+    let code = oauth::authorize_request(&request, &user.did, &scope).await?;
+    response.push(("code", code));
+    Ok(Ok(redirect_with(&redirect_uri, response)?))
+}
+
+#[rocket::post("/oauth/authorize/sign-in", data = "<body>")]
+pub async fn authorize_sign_in(
+    body: Form<AuthorizeSignInForm>,
+    cfg: &State<ServerConfig>,
+    rate_limit: LoginRateLimit<'_>,
+) -> Result<std::result::Result<Redirect, RawHtml<String>>, status::Custom<Json<OAuthErrorResponse>>>
+{
+    match inner_authorize_sign_in(body.into_inner(), cfg, &rate_limit.0).await {
+        Ok(res) => Ok(res),
+        Err(error) => Err(oauth_error(error)),
+    }
+}
+
+async fn inner_token(
+    body: TokenRequest,
+    dpop: DpopRequest,
+    dpop_manager: &State<DpopManager>,
+) -> Result<TokenResponse> {
+    let proof = dpop.verify(dpop_manager)?;
+    match body.grant_type.as_str() {
+        "authorization_code" => {
+            let (code, code_verifier) = match (&body.code, &body.code_verifier) {
+                (Some(code), Some(code_verifier)) => (code, code_verifier),
+                _ => bail!(OAuthError::InvalidRequest(
+                    "code and code_verifier are required".to_string()
+                )),
+            };
+            let (request, params) = oauth::consume_code(code).await?;
+            if request.client_id != body.client_id {
+                bail!(OAuthError::InvalidGrant(
+                    "Code was issued to another client".to_string()
+                ))
+            }
+            if params.redirect_uri.is_some() && params.redirect_uri != body.redirect_uri {
+                bail!(OAuthError::InvalidGrant(
+                    "redirect_uri mismatch".to_string()
+                ))
+            }
+            if !oauth::verify_pkce(&params.code_challenge, code_verifier) {
+                bail!(OAuthError::InvalidGrant(
+                    "Invalid code_verifier".to_string()
+                ))
+            }
+            if let Some(dpop_jkt) = &request.dpop_jkt {
+                if dpop_jkt != &proof.jkt {
+                    bail!(OAuthError::InvalidDpopProof(
+                        "DPoP key mismatch".to_string()
+                    ))
+                }
+            }
+            let client = get_client_metadata(&body.client_id).await?;
+            let did = request.did.unwrap();
+            let scope = params.scope.unwrap_or(client.scope.unwrap_or_default());
+            let (access_token, refresh_token) =
+                oauth::create_token(&did, &body.client_id, &scope, &proof.jkt).await?;
+            Ok(TokenResponse {
+                access_token,
+                token_type: "DPoP".to_string(),
+                expires_in: 60 * 60,
+                refresh_token,
+                scope,
+                sub: did,
+            })
+        }
+        "refresh_token" => {
+            let refresh_token = match &body.refresh_token {
+                Some(refresh_token) => refresh_token,
+                None => bail!(OAuthError::InvalidRequest(
+                    "refresh_token is required".to_string()
+                )),
+            };
+            let (token, access_token, refresh_token) =
+                oauth::refresh_token(refresh_token, &body.client_id, &proof.jkt).await?;
+            Ok(TokenResponse {
+                access_token,
+                token_type: "DPoP".to_string(),
+                expires_in: 60 * 60,
+                refresh_token,
+                scope: token.scope,
+                sub: token.did,
+            })
+        }
+        grant_type => Err(anyhow!(OAuthError::UnsupportedGrantType(format!(
+            "Unsupported grant_type `{grant_type}`"
+        )))),
+    }
+}
+
+#[rocket::post("/oauth/token", data = "<body>")]
+pub async fn token(
+    body: Form<TokenRequest>,
+    dpop: DpopRequest,
+    dpop_manager: &State<DpopManager>,
+) -> Result<Json<TokenResponse>, status::Custom<Json<OAuthErrorResponse>>> {
+    match inner_token(body.into_inner(), dpop, dpop_manager).await {
+        Ok(res) => Ok(Json(res)),
+        Err(error) => Err(oauth_error(error)),
+    }
+}
+
+#[rocket::post("/oauth/revoke", data = "<body>")]
+pub async fn revoke(
+    body: Form<RevokeRequest>,
+) -> Result<(), status::Custom<Json<OAuthErrorResponse>>> {
+    // RFC 7009: respond with 200 whether or not the token was valid
+    match oauth::revoke_token(&body.token).await {
+        Ok(_) => Ok(()),
+        Err(error) => Err(oauth_error(error)),
+    }
+}
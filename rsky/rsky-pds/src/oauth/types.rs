@@ -0,0 +1,154 @@
+use rocket::form::FromForm;
+
+/// RFC 8414 authorization server metadata, as served at
+/// `/.well-known/oauth-authorization-server`
+#[derive(Debug, Serialize, Clone)]
+pub struct OAuthAuthorizationServerMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub revocation_endpoint: String,
+    pub pushed_authorization_request_endpoint: String,
+    pub require_pushed_authorization_requests: bool,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub response_modes_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub dpop_signing_alg_values_supported: Vec<String>,
+    pub authorization_response_iss_parameter_supported: bool,
+    pub client_id_metadata_document_supported: bool,
+    pub protected_resources: Vec<String>,
+}
+
+/// RFC 9728 protected resource metadata, as served at `/.well-known/oauth-protected-resource`
+#[derive(Debug, Serialize, Clone)]
+pub struct OAuthProtectedResourceMetadata {
+    pub resource: String,
+    pub authorization_servers: Vec<String>,
+    pub scopes_supported: Vec<String>,
+    pub bearer_methods_supported: Vec<String>,
+    pub resource_documentation: String,
+}
+
+/// Client metadata document, fetched from the URL used as `client_id`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OAuthClientMetadata {
+    pub client_id: String,
+    pub client_name: Option<String>,
+    pub client_uri: Option<String>,
+    pub logo_uri: Option<String>,
+    pub tos_uri: Option<String>,
+    pub policy_uri: Option<String>,
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub grant_types: Vec<String>,
+    #[serde(default)]
+    pub response_types: Vec<String>,
+    pub scope: Option<String>,
+    pub token_endpoint_auth_method: Option<String>,
+    pub application_type: Option<String>,
+    #[serde(default)]
+    pub dpop_bound_access_tokens: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, FromForm)]
+pub struct PushedAuthorizationRequest {
+    pub client_id: String,
+    pub response_type: String,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: String,
+    pub code_challenge_method: String,
+    pub login_hint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PushedAuthorizationResponse {
+    pub request_uri: String,
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Clone, FromForm)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub client_id: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub scope: String,
+    pub sub: String,
+}
+
+#[derive(Debug, Clone, FromForm)]
+pub struct RevokeRequest {
+    pub token: String,
+    pub client_id: Option<String>,
+}
+
+/// Submitted by the sign-in and consent page
+#[derive(Debug, Clone, FromForm)]
+pub struct AuthorizeSignInForm {
+    pub client_id: String,
+    pub request_uri: String,
+    pub identifier: String,
+    pub password: String,
+    pub auth_factor_token: Option<String>,
+    pub decision: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    pub x: String,
+    pub y: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DpopProofHeader {
+    pub typ: String,
+    pub alg: String,
+    pub jwk: Jwk,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DpopProofPayload {
+    pub jti: String,
+    pub htm: String,
+    pub htu: String,
+    pub iat: u64,
+    pub ath: Option<String>,
+    pub nonce: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Cnf {
+    pub jkt: String,
+}
+
+/// Custom claims of OAuth access tokens issued by this PDS
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OAuthClaimObj {
+    pub scope: String,
+    pub client_id: String,
+    pub cnf: Cnf,
+}
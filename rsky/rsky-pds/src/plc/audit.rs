@@ -0,0 +1,300 @@
+use crate::common::sign::atproto_signing_hash;
+use crate::plc::operations::normalize_op;
+use crate::plc::types::{CompatibleOp, CompatibleOpOrTombstone, ExportedOp, Operation};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use rsky_crypto::verify::verify_signature;
+use serde_json::Value as JsonValue;
+
+/// Window during which a higher-priority rotation key can nullify an operation
+pub const RECOVERY_WINDOW_HOURS: i64 = 72;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditFindingKind {
+    InvalidSignature,
+    BrokenPrevChain,
+    UnexpectedRotationKeys,
+    UnexpectedPdsEndpoint,
+    Tombstoned,
+}
+
+impl AuditFindingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditFindingKind::InvalidSignature => "invalidSignature",
+            AuditFindingKind::BrokenPrevChain => "brokenPrevChain",
+            AuditFindingKind::UnexpectedRotationKeys => "unexpectedRotationKeys",
+            AuditFindingKind::UnexpectedPdsEndpoint => "unexpectedPdsEndpoint",
+            AuditFindingKind::Tombstoned => "tombstoned",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditFinding {
+    pub cid: String,
+    pub kind: AuditFindingKind,
+    pub message: String,
+    pub created_at: String,
+    pub recoverable: bool,
+}
+
+/// What the operator expects a healthy DID hosted on this PDS to look like.
+pub struct AuditOpts {
+    /// Rotation keys that may appear in the log without being flagged,
+    /// e.g. the server's PLC rotation key and the account's recovery key.
+    pub trusted_rotation_keys: Vec<String>,
+    pub pds_endpoint: String,
+}
+
+/// Result of checking an op's signature against the rotation keys of its `prev`.
+/// `signer_index` is the position of the key that signed, which determines its priority
+/// when nullifying.
+pub struct VerifiedOp {
+    pub signer: String,
+    pub signer_index: usize,
+}
+
+pub fn audit_log(log: &Vec<ExportedOp>, opts: &AuditOpts) -> Result<Vec<AuditFinding>> {
+    let mut findings: Vec<AuditFinding> = Vec::new();
+    let mut prev_entry: Option<&ExportedOp> = None;
+    let mut prev_op: Option<Operation> = None;
+
+    for entry in log.iter().filter(|entry| !entry.nullified) {
+        let recoverable = prev_entry.is_some() && within_recovery_window(&entry.created_at)?;
+        let mut push_finding = |kind: AuditFindingKind, message: String| {
+            findings.push(AuditFinding {
+                cid: entry.cid.clone(),
+                kind,
+                message,
+                created_at: entry.created_at.clone(),
+                recoverable,
+            })
+        };
+
+        let expected_prev = prev_entry.map(|prev| &prev.cid);
+        if entry.operation.get_prev() != expected_prev {
+            push_finding(
+                AuditFindingKind::BrokenPrevChain,
+                format!(
+                    "Operation prev `{:?}` does not match previous operation `{:?}`",
+                    entry.operation.get_prev(),
+                    expected_prev
+                ),
+            );
+        }
+
+        let allowed_keys = match (&prev_op, &entry.operation) {
+            (Some(prev_op), _) => prev_op.rotation_keys.clone(),
+            (None, CompatibleOpOrTombstone::Operation(op)) => op.rotation_keys.clone(),
+            (None, CompatibleOpOrTombstone::CreateOpV1(op)) => {
+                vec![op.recovery_key.clone(), op.signing_key.clone()]
+            }
+            (None, CompatibleOpOrTombstone::Tombstone(_)) => vec![],
+        };
+        if verify_op_signature(&entry.operation, &allowed_keys)?.is_none() {
+            push_finding(
+                AuditFindingKind::InvalidSignature,
+                "Operation is not signed by any rotation key of its prev".to_string(),
+            );
+        }
+
+        let op = match &entry.operation {
+            CompatibleOpOrTombstone::Tombstone(_) => {
+                push_finding(
+                    AuditFindingKind::Tombstoned,
+                    "DID has been tombstoned".to_string(),
+                );
+                prev_entry = Some(entry);
+                prev_op = None;
+                continue;
+            }
+            CompatibleOpOrTombstone::CreateOpV1(op) => {
+                normalize_op(CompatibleOp::CreateOpV1(op.clone()))
+            }
+            CompatibleOpOrTombstone::Operation(op) => op.clone(),
+        };
+
+        let prev_rotation_keys = match &prev_op {
+            Some(prev_op) => prev_op.rotation_keys.clone(),
+            None => vec![],
+        };
+        let unexpected_keys = op
+            .rotation_keys
+            .iter()
+            .filter(|key| {
+                !prev_rotation_keys.contains(key) && !opts.trusted_rotation_keys.contains(key)
+            })
+            .cloned()
+            .collect::<Vec<String>>();
+        if !unexpected_keys.is_empty() {
+            push_finding(
+                AuditFindingKind::UnexpectedRotationKeys,
+                format!(
+                    "Untrusted rotation keys added: {}",
+                    unexpected_keys.join(", ")
+                ),
+            );
+        }
+
+        let pds_endpoint = op
+            .services
+            .get("atproto_pds")
+            .map(|service| service.endpoint.clone());
+        let prev_pds_endpoint = prev_op
+            .as_ref()
+            .and_then(|prev_op| prev_op.services.get("atproto_pds"))
+            .map(|service| service.endpoint.clone());
+        if pds_endpoint != prev_pds_endpoint && pds_endpoint.as_ref() != Some(&opts.pds_endpoint) {
+            push_finding(
+                AuditFindingKind::UnexpectedPdsEndpoint,
+                format!(
+                    "PDS endpoint changed to `{}`",
+                    pds_endpoint.unwrap_or_default()
+                ),
+            );
+        }
+
+        prev_entry = Some(entry);
+        prev_op = Some(op);
+    }
+    Ok(findings)
+}
+
+/// The op as it was signed: without `sig`, but with `prev: null` on genesis ops, which PLC
+/// signs over and serializing our types would drop.
+fn unsigned_op(op: &CompatibleOpOrTombstone) -> Result<JsonValue> {
+    let mut unsigned = serde_json::to_value(op)?;
+    if let JsonValue::Object(fields) = &mut unsigned {
+        fields.remove("sig");
+        fields.entry("prev").or_insert(JsonValue::Null);
+    }
+    Ok(unsigned)
+}
+
+/// Returns the key that signed `op` if it is one of `allowed_keys`.
+pub fn verify_op_signature(
+    op: &CompatibleOpOrTombstone,
+    allowed_keys: &Vec<String>,
+) -> Result<Option<VerifiedOp>> {
+    let sig = match op.clone().get_sig() {
+        None => return Ok(None),
+        Some(sig) => base64_url::decode(sig)?,
+    };
+    let hash = atproto_signing_hash(&unsigned_op(op)?)?;
+    for (signer_index, key) in allowed_keys.iter().enumerate() {
+        if let Ok(true) = verify_signature(key, hash.as_slice(), sig.as_slice(), None) {
+            return Ok(Some(VerifiedOp {
+                signer: key.clone(),
+                signer_index,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+pub fn within_recovery_window(created_at: &String) -> Result<bool> {
+    let created_at = match DateTime::parse_from_rfc3339(created_at) {
+        Ok(created_at) => created_at.with_timezone(&Utc),
+        Err(_) => bail!("Invalid operation timestamp `{created_at}`"),
+    };
+    Ok(Utc::now() - created_at < Duration::hours(RECOVERY_WINDOW_HOURS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rotation and recovery keys of the DID in `fixtures/audit_log.json`, an audit log in the
+    // format plc.directory serves from `/:did/log/audit`. Its third op is signed with the
+    // recovery key and hands rotation and the PDS over to another party.
+    const ROTATION_KEY: &str = "did:key:zQ3shbggNfptwtHJubdrNKbXUfMpTze8Sy87z9nnaNa1A53H2";
+    const RECOVERY_KEY: &str = "did:key:zQ3shbaSmRBAkexrBtn8jwtvbR1zFSBJfRTf1REGLmBxaG48L";
+
+    fn audit_log_fixture() -> Vec<ExportedOp> {
+        serde_json::from_str(include_str!("fixtures/audit_log.json")).unwrap()
+    }
+
+    fn opts() -> AuditOpts {
+        AuditOpts {
+            trusted_rotation_keys: vec![ROTATION_KEY.to_string(), RECOVERY_KEY.to_string()],
+            pds_endpoint: "https://pds.test".to_string(),
+        }
+    }
+
+    fn kinds(findings: &Vec<AuditFinding>) -> Vec<AuditFindingKind> {
+        findings
+            .iter()
+            .map(|finding| finding.kind.clone())
+            .collect()
+    }
+
+    #[test]
+    fn verifies_genesis_op_signed_with_null_prev() {
+        let log = audit_log_fixture();
+        let keys = vec![ROTATION_KEY.to_string(), RECOVERY_KEY.to_string()];
+        let verified = verify_op_signature(&log[0].operation, &keys)
+            .unwrap()
+            .unwrap();
+        assert_eq!(verified.signer, ROTATION_KEY);
+        assert_eq!(verified.signer_index, 0);
+    }
+
+    #[test]
+    fn finds_nothing_in_healthy_log() {
+        let log = audit_log_fixture()[..2].to_vec();
+        assert!(audit_log(&log, &opts()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_tampered_op_signature() {
+        let mut log = audit_log_fixture()[..2].to_vec();
+        if let CompatibleOpOrTombstone::Operation(op) = &mut log[1].operation {
+            op.also_known_as = vec!["at://mallory.test".to_string()];
+        }
+        let findings = audit_log(&log, &opts()).unwrap();
+        assert_eq!(kinds(&findings), vec![AuditFindingKind::InvalidSignature]);
+        assert_eq!(findings[0].cid, log[1].cid);
+    }
+
+    #[test]
+    fn reports_broken_prev_chain() {
+        let log = audit_log_fixture();
+        let log = vec![log[0].clone(), log[2].clone()];
+        let findings = audit_log(&log, &opts()).unwrap();
+        assert_eq!(findings[0].kind, AuditFindingKind::BrokenPrevChain);
+        assert!(!kinds(&findings).contains(&AuditFindingKind::InvalidSignature));
+    }
+
+    #[test]
+    fn reports_rotation_key_and_endpoint_changes() {
+        let log = audit_log_fixture();
+        let findings = audit_log(&log, &opts()).unwrap();
+        assert_eq!(
+            kinds(&findings),
+            vec![
+                AuditFindingKind::UnexpectedRotationKeys,
+                AuditFindingKind::UnexpectedPdsEndpoint
+            ]
+        );
+        assert!(findings.iter().all(|finding| finding.cid == log[2].cid));
+        // Long past the recovery window
+        assert!(findings.iter().all(|finding| !finding.recoverable));
+    }
+
+    #[test]
+    fn marks_recent_findings_recoverable() {
+        let mut log = audit_log_fixture();
+        log[2].created_at = (Utc::now() - Duration::hours(1)).to_rfc3339();
+        let findings = audit_log(&log, &opts()).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().all(|finding| finding.recoverable));
+    }
+
+    #[test]
+    fn skips_nullified_ops() {
+        let mut log = audit_log_fixture();
+        log[2].nullified = true;
+        assert!(audit_log(&log, &opts()).unwrap().is_empty());
+    }
+}
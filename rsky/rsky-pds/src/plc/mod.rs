@@ -1,6 +1,7 @@
 use crate::common::encode_uri_component;
-use crate::plc::operations::update_handle_op;
-use crate::plc::types::{CompatibleOp, OpOrTombstone};
+use crate::plc::audit::{verify_op_signature, within_recovery_window};
+use crate::plc::operations::{normalize_op, update_atproto_key_op, update_handle_op};
+use crate::plc::types::{CompatibleOp, ExportedOp, OpOrTombstone, Operation};
 use crate::APP_USER_AGENT;
 use anyhow::{bail, Result};
 use secp256k1::SecretKey;
@@ -91,6 +92,100 @@ impl Client {
         }
     }
 
+    pub async fn get_audit_log(&self, did: &String) -> Result<Vec<ExportedOp>> {
+        match self
+            .make_get_req(
+                format!("{0}/{1}/log/audit", self.url, encode_uri_component(did)),
+                None,
+            )
+            .await
+        {
+            Ok(res) => Ok(res),
+            Err(error) => bail!(error.to_string()),
+        }
+    }
+
+    /// Finds the operation to nullify and the last good operation before it
+    async fn get_recovery_point(
+        &self,
+        did: &String,
+        op_cid: &String,
+    ) -> Result<(ExportedOp, ExportedOp)> {
+        let log = self.get_audit_log(did).await?;
+        let log = log
+            .into_iter()
+            .filter(|entry| !entry.nullified)
+            .collect::<Vec<ExportedOp>>();
+        let hostile = match log.iter().find(|entry| &entry.cid == op_cid) {
+            None => bail!("Operation `{op_cid}` not found in log for {did}"),
+            Some(hostile) => hostile.clone(),
+        };
+        let last_good = match hostile.operation.get_prev() {
+            None => bail!("Cannot nullify the genesis operation"),
+            Some(prev) => match log.iter().find(|entry| &entry.cid == prev) {
+                None => bail!("Prev of `{op_cid}` not found in log for {did}"),
+                Some(last_good) => last_good.clone(),
+            },
+        };
+        if !within_recovery_window(&hostile.created_at)? {
+            bail!("Operation `{op_cid}` is outside of the recovery window")
+        }
+        if let CompatibleOpOrTombstone::Tombstone(_) = last_good.operation {
+            bail!("Cannot recover from a tombstone")
+        }
+        Ok((hostile, last_good))
+    }
+
+    /// The unsigned operation that nullifies `op_cid` (and everything after it) by forking
+    /// the log at its `prev` with a copy of the last good state
+    pub async fn create_nullify_op(&self, did: &String, op_cid: &String) -> Result<Operation> {
+        let (_, last_good) = self.get_recovery_point(did, op_cid).await?;
+        Ok(recovery_op(&last_good))
+    }
+
+    /// Submits a signed nullification of `op_cid`. PLC only accepts this if it's signed by a
+    /// rotation key of higher priority than the one that signed the nullified op and the op
+    /// is still within the recovery window, which we check first for a clearer error.
+    pub async fn nullify_op(&self, did: &String, op_cid: &String, op: Operation) -> Result<()> {
+        let (hostile, last_good) = self.get_recovery_point(did, op_cid).await?;
+        let mut unsigned = op.clone();
+        unsigned.sig = None;
+        if serde_json::to_value(&unsigned)? != serde_json::to_value(recovery_op(&last_good))? {
+            bail!("Operation does not restore the state before `{op_cid}`")
+        }
+        let rotation_keys = recovery_op(&last_good).rotation_keys;
+        let signed = CompatibleOpOrTombstone::Operation(op.clone());
+        let signer_index = match verify_op_signature(&signed, &rotation_keys)? {
+            None => bail!("Operation is not signed by a rotation key for {did}"),
+            Some(signer) => signer.signer_index,
+        };
+        if let Some(hostile_signer) = verify_op_signature(&hostile.operation, &rotation_keys)? {
+            if hostile_signer.signer_index <= signer_index {
+                bail!("Recovery key does not have a higher priority than the key that signed `{op_cid}`")
+            }
+        }
+        self.send_operation(did, &OpOrTombstone::Operation(op))
+            .await
+    }
+
+    pub async fn update_atproto_key(
+        &self,
+        did: &String,
+        signer: &SecretKey,
+        atproto_key: &String,
+    ) -> Result<()> {
+        let last_op: CompatibleOp = match self.ensure_last_op(did).await? {
+            CompatibleOpOrTombstone::CreateOpV1(last_op) => CompatibleOp::CreateOpV1(last_op),
+            CompatibleOpOrTombstone::Operation(last_op) => CompatibleOp::Operation(last_op),
+            CompatibleOpOrTombstone::Tombstone(_) => {
+                panic!("ensure_last_op() didn't prevent tombstone")
+            }
+        };
+        let op = update_atproto_key_op(last_op, signer, atproto_key.clone()).await?;
+        self.send_operation(&did, &OpOrTombstone::Operation(op))
+            .await
+    }
+
     pub async fn update_handle(
         &self,
         did: &String,
@@ -110,5 +205,21 @@ impl Client {
     }
 }
 
+/// A copy of `last_good` that follows it, undoing every operation after it
+fn recovery_op(last_good: &ExportedOp) -> Operation {
+    let last_good_op = match &last_good.operation {
+        CompatibleOpOrTombstone::CreateOpV1(op) => CompatibleOp::CreateOpV1(op.clone()),
+        CompatibleOpOrTombstone::Operation(op) => CompatibleOp::Operation(op.clone()),
+        CompatibleOpOrTombstone::Tombstone(_) => {
+            panic!("get_recovery_point() didn't prevent tombstone")
+        }
+    };
+    let mut op = normalize_op(last_good_op);
+    op.sig = None;
+    op.prev = Some(last_good.cid.clone());
+    op
+}
+
+pub mod audit;
 pub mod operations;
 pub mod types;
@@ -86,6 +86,14 @@ impl CompatibleOpOrTombstone {
             Self::Tombstone(tombstone) => &tombstone.sig,
         }
     }
+
+    pub fn get_prev(&self) -> Option<&String> {
+        match self {
+            Self::CreateOpV1(create) => create.prev.as_ref(),
+            Self::Operation(op) => op.prev.as_ref(),
+            Self::Tombstone(tombstone) => Some(&tombstone.prev),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -132,3 +140,14 @@ impl OpOrTombstone {
         }
     }
 }
+
+/// Entry of a DID's operation log, as returned by `/:did/log/audit`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ExportedOp {
+    pub did: String,
+    pub operation: CompatibleOpOrTombstone,
+    pub cid: String,
+    pub nullified: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
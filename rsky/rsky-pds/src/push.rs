@@ -0,0 +1,325 @@
+use crate::common;
+use crate::config::SERVER_CONFIG;
+use crate::db::establish_connection;
+use crate::models::models;
+use crate::notification::{did_from_uri, Notification};
+use crate::APP_USER_AGENT;
+use anyhow::{bail, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use diesel::*;
+use reqwest::redirect::Policy;
+use rsky_lexicon::app::bsky::notification::RegisterPushInput;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+/// Registrations on this platform push straight to the Web Push subscription in their token
+pub const UNIFIED_PUSH_PLATFORM: &str = "unifiedpush";
+
+/// How long the push service holds on to a notification for an offline device
+const WEB_PUSH_TTL_SECS: u32 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PushPayload {
+    pub recipient: String,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason_subject: Option<String>,
+    pub uri: String,
+    pub cid: String,
+    pub author: String,
+    pub indexed_at: String,
+    pub token: String,
+    pub platform: String,
+    pub app_id: String,
+    pub service_did: String,
+}
+
+#[derive(Error, Debug)]
+pub enum PushError {
+    #[error("UnifiedPush token must be a Web Push subscription with an https endpoint and keys")]
+    InvalidSubscription,
+    #[error("Push endpoint {0} does not resolve to a public address")]
+    PrivateEndpoint(String),
+}
+
+/// A `PushSubscription` as serialized by the client, which UnifiedPush registrations send
+/// as their token. The keys are the subscriber's, for RFC 8291 message encryption.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WebPushSubscription {
+    pub endpoint: String,
+    pub keys: WebPushKeys,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct WebPushKeys {
+    /// Base64url P-256 public key of the subscriber
+    pub p256dh: String,
+    /// Base64url authentication secret
+    pub auth: String,
+}
+
+impl WebPushSubscription {
+    pub fn parse(token: &String) -> Result<Self> {
+        let Ok(subscription) = serde_json::from_str::<WebPushSubscription>(token) else {
+            bail!(PushError::InvalidSubscription)
+        };
+        match Url::parse(&subscription.endpoint) {
+            Ok(url) if url.scheme() == "https" && url.host_str().is_some() => Ok(subscription),
+            _ => bail!(PushError::InvalidSubscription),
+        }
+    }
+}
+
+/// Whether an address is reachable on the public internet, so that push endpoints and other
+/// user-supplied URLs can't be pointed at this PDS or its private network
+pub(crate) fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Shared address space, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7
+                    || (first & 0xfe00) == 0xfc00
+                    // Link local, fe80::/10
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves a push endpoint, refusing it unless every address it resolves to is public.
+/// The address is then pinned for the request so a second lookup can't be rebound.
+pub(crate) async fn resolve_public_endpoint(endpoint: &Url) -> Result<(String, SocketAddr)> {
+    let Some(host) = endpoint.host_str() else {
+        bail!(PushError::InvalidSubscription)
+    };
+    let port = endpoint.port_or_known_default().unwrap_or(443);
+    let addrs = tokio::net::lookup_host((host, port))
+        .await?
+        .collect::<Vec<SocketAddr>>();
+    match addrs.first() {
+        Some(addr) if addrs.iter().all(|addr| is_public_ip(addr.ip())) => {
+            Ok((host.to_string(), *addr))
+        }
+        _ => bail!(PushError::PrivateEndpoint(host.to_string())),
+    }
+}
+
+/// Posts the payload to a Web Push endpoint, encrypted for the subscriber (RFC 8291)
+async fn send_web_push(payload: &PushPayload) -> Result<()> {
+    let subscription = WebPushSubscription::parse(&payload.token)?;
+    let endpoint = Url::parse(&subscription.endpoint)?;
+    let (host, addr) = resolve_public_endpoint(&endpoint).await?;
+    let body = ece::encrypt(
+        &URL_SAFE_NO_PAD.decode(subscription.keys.p256dh.trim_end_matches('='))?,
+        &URL_SAFE_NO_PAD.decode(subscription.keys.auth.trim_end_matches('='))?,
+        &serde_json::to_vec(payload)?,
+    )?;
+    let client = reqwest::ClientBuilder::new()
+        .user_agent(APP_USER_AGENT)
+        .timeout(Duration::from_secs(5))
+        .redirect(Policy::none())
+        .resolve(&host, addr)
+        .build()?;
+    let res = client
+        .post(endpoint)
+        .header("Content-Encoding", "aes128gcm")
+        .header("Content-Type", "application/octet-stream")
+        .header("TTL", WEB_PUSH_TTL_SECS.to_string())
+        .body(body)
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        bail!("push endpoint responded with {}", res.status())
+    }
+    Ok(())
+}
+
+pub async fn register(did: &String, registration: RegisterPushInput) -> Result<()> {
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
+    let conn = &mut establish_connection()?;
+    let RegisterPushInput {
+        service_did,
+        token,
+        platform,
+        app_id,
+    } = registration;
+
+    insert_into(PushRegistrationSchema::push_registration)
+        .values((
+            PushRegistrationSchema::did.eq(did),
+            PushRegistrationSchema::token.eq(&token),
+            PushRegistrationSchema::platform.eq(&platform),
+            PushRegistrationSchema::serviceDid.eq(&service_did),
+            PushRegistrationSchema::appId.eq(&app_id),
+            PushRegistrationSchema::createdAt.eq(common::now()),
+        ))
+        .on_conflict((PushRegistrationSchema::did, PushRegistrationSchema::token))
+        .do_update()
+        .set((
+            PushRegistrationSchema::platform.eq(&platform),
+            PushRegistrationSchema::serviceDid.eq(&service_did),
+            PushRegistrationSchema::appId.eq(&app_id),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+pub async fn get_registrations(dids: Vec<String>) -> Result<Vec<models::PushRegistration>> {
+    use crate::schema::pds::push_registration::dsl as PushRegistrationSchema;
+    let conn = &mut establish_connection()?;
+
+    let res = PushRegistrationSchema::push_registration
+        .filter(PushRegistrationSchema::did.eq_any(dids))
+        .select(models::PushRegistration::as_select())
+        .load(conn)?;
+    Ok(res)
+}
+
+async fn send(client: &reqwest::Client, payload: &PushPayload) -> Result<()> {
+    let endpoint = match payload.platform.as_str() {
+        UNIFIED_PUSH_PLATFORM => return send_web_push(payload).await,
+        _ => match &SERVER_CONFIG.push.webhook_url {
+            Some(webhook_url) => webhook_url.clone(),
+            None => return Ok(()),
+        },
+    };
+    let res = client.post(endpoint).json(payload).send().await?;
+    if !res.status().is_success() {
+        bail!("push endpoint responded with {}", res.status())
+    }
+    Ok(())
+}
+
+async fn dispatch(
+    uri: String,
+    cid: String,
+    indexed_at: String,
+    notifications: Vec<Notification>,
+) -> Result<()> {
+    let recipients = notifications
+        .iter()
+        .map(|notification| notification.recipient.clone())
+        .collect::<Vec<String>>();
+    let registrations = get_registrations(recipients).await?;
+    if registrations.is_empty() {
+        return Ok(());
+    }
+    let author = did_from_uri(&uri).unwrap_or_default();
+    let client = reqwest::ClientBuilder::new()
+        .user_agent(APP_USER_AGENT)
+        .timeout(Duration::from_secs(5))
+        .build()?;
+
+    for notification in notifications {
+        for registration in registrations
+            .iter()
+            .filter(|registration| registration.did == notification.recipient)
+        {
+            let payload = PushPayload {
+                recipient: notification.recipient.clone(),
+                reason: notification.reason.clone(),
+                reason_subject: notification.reason_subject.clone(),
+                uri: uri.clone(),
+                cid: cid.clone(),
+                author: author.clone(),
+                indexed_at: indexed_at.clone(),
+                token: registration.token.clone(),
+                platform: registration.platform.clone(),
+                app_id: registration.app_id.clone(),
+                service_did: registration.service_did.clone(),
+            };
+            // One failing device shouldn't keep the others from being notified
+            if let Err(error) = send(&client, &payload).await {
+                eprintln!(
+                    "@LOG: ERROR: failed to push {} notification for {uri} to {}: {error}",
+                    payload.reason, payload.recipient
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pushes notifications for a newly indexed record to local users registered through
+/// `app.bsky.notification.registerPush`. Dispatching happens in the background so
+/// writes aren't held up by push endpoints.
+pub fn dispatch_for_record(
+    uri: &String,
+    cid: &String,
+    indexed_at: &String,
+    notifications: Vec<Notification>,
+) {
+    if !SERVER_CONFIG.push.local || notifications.is_empty() {
+        return;
+    }
+    let (uri, cid, indexed_at) = (uri.clone(), cid.clone(), indexed_at.clone());
+    tokio::spawn(async move {
+        if let Err(error) = dispatch(uri.clone(), cid, indexed_at, notifications).await {
+            eprintln!("@LOG: ERROR: failed to dispatch push notifications for {uri}: {error}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn rejects_private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(IpAddr::from_str(ip).unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for ip in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(IpAddr::from_str(ip).unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn parses_subscriptions() {
+        let token =
+            r#"{"endpoint":"https://push.example.com/abc","keys":{"p256dh":"BNc","auth":"tBH"}}"#;
+        assert!(WebPushSubscription::parse(&token.to_string()).is_ok());
+        let http =
+            r#"{"endpoint":"http://push.example.com/abc","keys":{"p256dh":"BNc","auth":"tBH"}}"#;
+        assert!(WebPushSubscription::parse(&http.to_string()).is_err());
+        assert!(WebPushSubscription::parse(&"https://push.example.com/abc".to_string()).is_err());
+    }
+}
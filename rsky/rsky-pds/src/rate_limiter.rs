@@ -0,0 +1,479 @@
+use crate::config::{RateLimitConfig, RateLimitsConfig};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Request, Response, State};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// Windows are swept for stale keys once the map grows past this many entries
+const SWEEP_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitStatus {
+    pub limit: usize,
+    pub remaining: usize,
+    /// Seconds until the oldest counted request leaves the window
+    pub reset_secs: u64,
+    pub window_secs: u64,
+}
+
+#[derive(Error, Debug)]
+#[error("RateLimitExceeded: `{message}`")]
+pub struct RateLimitExceeded {
+    pub message: String,
+    pub status: RateLimitStatus,
+}
+
+struct LoginFailures {
+    count: usize,
+    locked_until: Option<u64>,
+    last_failure: u64,
+}
+
+/// Current time in millis since the UNIX epoch
+type Clock = Box<dyn Fn() -> u64 + Send + Sync>;
+
+/// In-memory sliding window rate limiter, plus failed login tracking used to slow
+/// down and temporarily lock out password guessing against a single account.
+pub struct RateLimiter {
+    cfg: RateLimitsConfig,
+    windows: Mutex<BTreeMap<String, VecDeque<u64>>>,
+    failures: Mutex<BTreeMap<String, LoginFailures>>,
+    clock: Clock,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: RateLimitsConfig) -> Self {
+        Self::with_clock(cfg, Box::new(now_ms))
+    }
+
+    fn with_clock(cfg: RateLimitsConfig, clock: Clock) -> Self {
+        Self {
+            cfg,
+            windows: Mutex::new(BTreeMap::new()),
+            failures: Mutex::new(BTreeMap::new()),
+            clock,
+        }
+    }
+
+    pub fn cfg(&self) -> &RateLimitsConfig {
+        &self.cfg
+    }
+
+    pub fn is_bypassed(&self, ip: &Option<String>) -> bool {
+        !self.cfg.enabled
+            || match ip {
+                Some(ip) => self.cfg.bypass_ips.contains(ip),
+                None => false,
+            }
+    }
+
+    /// Counts a request against `key` and errors if `limit` has been used up
+    /// within the trailing window.
+    pub fn consume(
+        &self,
+        key: &str,
+        limit: &RateLimitConfig,
+    ) -> Result<RateLimitStatus, RateLimitExceeded> {
+        let now = (self.clock)();
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() > SWEEP_THRESHOLD {
+            let max_window_ms = [
+                &self.cfg.login_per_ip,
+                &self.cfg.login_per_identifier,
+                &self.cfg.email,
+            ]
+            .iter()
+            .map(|limit| limit.duration_ms)
+            .max()
+            .unwrap_or(limit.duration_ms);
+            windows.retain(|_, hits| match hits.back() {
+                Some(last) => *last + max_window_ms > now,
+                None => false,
+            });
+        }
+        let hits = windows.entry(key.to_string()).or_default();
+        while let Some(oldest) = hits.front() {
+            if *oldest + limit.duration_ms <= now {
+                hits.pop_front();
+            } else {
+                break;
+            }
+        }
+        let full = hits.len() >= limit.points;
+        if !full {
+            hits.push_back(now);
+        }
+        let reset_ms = match hits.front() {
+            Some(oldest) => (*oldest + limit.duration_ms).saturating_sub(now),
+            None => limit.duration_ms,
+        };
+        let status = RateLimitStatus {
+            limit: limit.points,
+            remaining: limit.points.saturating_sub(hits.len()),
+            reset_secs: reset_ms.div_ceil(1000),
+            window_secs: limit.duration_ms / 1000,
+        };
+        match full {
+            false => Ok(status),
+            true => Err(RateLimitExceeded {
+                message: "Rate Limit Exceeded".to_string(),
+                status,
+            }),
+        }
+    }
+
+    /// Errors while the account is locked out after too many failed logins
+    pub fn check_lockout(&self, did: &str) -> Result<(), RateLimitExceeded> {
+        if !self.cfg.enabled {
+            return Ok(());
+        }
+        let now = (self.clock)();
+        let failures = self.failures.lock().unwrap();
+        match failures.get(did).and_then(|failures| failures.locked_until) {
+            Some(locked_until) if locked_until > now => {
+                let reset_secs = (locked_until - now).div_ceil(1000);
+                Err(RateLimitExceeded {
+                    message: "Too many failed login attempts, try again later".to_string(),
+                    status: RateLimitStatus {
+                        limit: self.cfg.lockout_threshold,
+                        remaining: 0,
+                        reset_secs,
+                        window_secs: self.cfg.lockout_duration_ms / 1000,
+                    },
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Progressive delay to apply before checking a password for `did`, doubling
+    /// with every recent failed attempt.
+    pub fn failure_delay(&self, did: &str) -> Duration {
+        if !self.cfg.enabled {
+            return Duration::ZERO;
+        }
+        let failures = self.failures.lock().unwrap();
+        match failures.get(did) {
+            Some(failures) if failures.count > 0 => {
+                let exponent = (failures.count - 1).min(16) as u32;
+                let delay = self
+                    .cfg
+                    .failure_delay_ms
+                    .saturating_mul(2u64.pow(exponent))
+                    .min(self.cfg.max_failure_delay_ms);
+                Duration::from_millis(delay)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Records a failed login, returning when the lockout ends if this failure started one
+    pub fn record_failure(&self, did: &str) -> Option<u64> {
+        if !self.cfg.enabled {
+            return None;
+        }
+        let now = (self.clock)();
+        let mut failures = self.failures.lock().unwrap();
+        // Failures older than a lockout period no longer count
+        failures.retain(|_, failures| {
+            failures.last_failure + self.cfg.lockout_duration_ms > now
+                || failures.locked_until.is_some_and(|until| until > now)
+        });
+        let entry = failures.entry(did.to_string()).or_insert(LoginFailures {
+            count: 0,
+            locked_until: None,
+            last_failure: now,
+        });
+        // A lockout that has run out starts the count over, so guessing on afterwards
+        // locks the account again
+        if entry.locked_until.is_some_and(|until| until <= now) {
+            entry.count = 0;
+            entry.locked_until = None;
+        }
+        entry.count += 1;
+        entry.last_failure = now;
+        if entry.count >= self.cfg.lockout_threshold && entry.locked_until.is_none() {
+            entry.locked_until = Some(now + self.cfg.lockout_duration_ms);
+            entry.locked_until
+        } else {
+            None
+        }
+    }
+
+    pub fn record_success(&self, did: &str) {
+        self.failures.lock().unwrap().remove(did);
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in millis since UNIX epoch")
+        .as_millis() as u64
+}
+
+/// Rate limit applied to the current request, reported back through `RateLimit-*` headers.
+/// When several limits apply, the most recently checked one is reported.
+#[derive(Default)]
+pub struct AppliedRateLimit(Mutex<Option<RateLimitStatus>>);
+
+impl AppliedRateLimit {
+    pub fn set(&self, status: &RateLimitStatus) {
+        *self.0.lock().unwrap() = Some(status.clone());
+    }
+
+    pub fn get(&self) -> Option<RateLimitStatus> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Client IP that passed its rate limit check, along with a handle to apply further
+/// limits (per identifier, per account) to the same request.
+pub struct RateLimit<'r> {
+    pub ip: Option<String>,
+    limiter: &'r RateLimiter,
+    applied: &'r AppliedRateLimit,
+}
+
+impl<'r> RateLimit<'r> {
+    pub fn cfg(&self) -> &RateLimitsConfig {
+        self.limiter.cfg()
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.limiter.is_bypassed(&self.ip)
+    }
+
+    pub fn consume(&self, key: &str, limit: &RateLimitConfig) -> Result<(), RateLimitExceeded> {
+        if self.is_bypassed() {
+            return Ok(());
+        }
+        match self.limiter.consume(key, limit) {
+            Ok(status) => Ok(self.applied.set(&status)),
+            Err(error) => {
+                self.applied.set(&error.status);
+                Err(error)
+            }
+        }
+    }
+
+    pub fn check_lockout(&self, did: &str) -> Result<(), RateLimitExceeded> {
+        if self.is_bypassed() {
+            return Ok(());
+        }
+        self.limiter.check_lockout(did).map_err(|error| {
+            self.applied.set(&error.status);
+            error
+        })
+    }
+
+    pub fn failure_delay(&self, did: &str) -> Duration {
+        match self.is_bypassed() {
+            true => Duration::ZERO,
+            false => self.limiter.failure_delay(did),
+        }
+    }
+
+    pub fn record_failure(&self, did: &str) -> Option<u64> {
+        match self.is_bypassed() {
+            true => None,
+            false => self.limiter.record_failure(did),
+        }
+    }
+
+    pub fn record_success(&self, did: &str) {
+        self.limiter.record_success(did)
+    }
+}
+
+async fn ip_rate_limit<'r>(
+    req: &'r Request<'_>,
+    name: &str,
+    limit: impl Fn(&RateLimitsConfig) -> &RateLimitConfig,
+) -> Outcome<RateLimit<'r>, RateLimitExceeded> {
+    let limiter = req.guard::<&State<RateLimiter>>().await.unwrap();
+    let rate_limit = RateLimit {
+        ip: req.client_ip().map(|ip| ip.to_string()),
+        limiter: limiter.inner(),
+        applied: req.local_cache(|| AppliedRateLimit::default()),
+    };
+    let key = format!("{name}:{}", rate_limit.ip.clone().unwrap_or_default());
+    match rate_limit.consume(&key, limit(limiter.cfg())) {
+        Ok(_) => Outcome::Success(rate_limit),
+        Err(error) => Outcome::Error((Status::TooManyRequests, error)),
+    }
+}
+
+/// Per client IP limit for `createSession`
+pub struct LoginRateLimit<'r>(pub RateLimit<'r>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LoginRateLimit<'r> {
+    type Error = RateLimitExceeded;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        ip_rate_limit(req, "login", |cfg| &cfg.login_per_ip)
+            .await
+            .map(LoginRateLimit)
+    }
+}
+
+/// Per client IP limit for endpoints that send email
+pub struct EmailRateLimit<'r>(pub RateLimit<'r>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for EmailRateLimit<'r> {
+    type Error = RateLimitExceeded;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        ip_rate_limit(req, "email", |cfg| &cfg.email)
+            .await
+            .map(EmailRateLimit)
+    }
+}
+
+/// Adds `RateLimit-*` headers (draft-ietf-httpapi-ratelimit-headers) to rate limited responses.
+pub struct RateLimitHeaders;
+
+#[rocket::async_trait]
+impl Fairing for RateLimitHeaders {
+    fn info(&self) -> Info {
+        Info {
+            name: "Add RateLimit headers to responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if let Some(status) = request.local_cache(|| AppliedRateLimit::default()).get() {
+            response.set_header(Header::new("RateLimit-Limit", status.limit.to_string()));
+            response.set_header(Header::new(
+                "RateLimit-Remaining",
+                status.remaining.to_string(),
+            ));
+            response.set_header(Header::new(
+                "RateLimit-Reset",
+                status.reset_secs.to_string(),
+            ));
+            response.set_header(Header::new(
+                "RateLimit-Policy",
+                format!("{};w={}", status.limit, status.window_secs),
+            ));
+            if response.status() == Status::TooManyRequests {
+                response.set_header(Header::new("Retry-After", status.reset_secs.to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    const DID: &str = "did:example:alice";
+
+    fn limiter(lockout_duration_ms: u64) -> RateLimiter {
+        RateLimiter::new(cfg(lockout_duration_ms))
+    }
+
+    /// A limiter whose time only moves when the returned clock is advanced
+    fn limiter_with_clock(lockout_duration_ms: u64) -> (RateLimiter, Arc<AtomicU64>) {
+        let now = Arc::new(AtomicU64::new(1_000_000));
+        let clock = now.clone();
+        let limiter = RateLimiter::with_clock(
+            cfg(lockout_duration_ms),
+            Box::new(move || clock.load(Ordering::SeqCst)),
+        );
+        (limiter, now)
+    }
+
+    fn cfg(lockout_duration_ms: u64) -> RateLimitsConfig {
+        RateLimitsConfig {
+            enabled: true,
+            bypass_ips: vec![],
+            login_per_ip: RateLimitConfig {
+                points: 3,
+                duration_ms: 60_000,
+            },
+            login_per_identifier: RateLimitConfig {
+                points: 3,
+                duration_ms: 60_000,
+            },
+            email: RateLimitConfig {
+                points: 3,
+                duration_ms: 60_000,
+            },
+            lockout_threshold: 3,
+            lockout_duration_ms,
+            failure_delay_ms: 100,
+            max_failure_delay_ms: 1_000,
+        }
+    }
+
+    #[test]
+    fn consume_limits_requests_in_window() {
+        let limiter = limiter(60_000);
+        let limit = &limiter.cfg().login_per_ip.clone();
+        assert_eq!(limiter.consume("key", limit).unwrap().remaining, 2);
+        assert_eq!(limiter.consume("key", limit).unwrap().remaining, 1);
+        assert_eq!(limiter.consume("key", limit).unwrap().remaining, 0);
+        let error = limiter.consume("key", limit).unwrap_err();
+        assert_eq!(error.status.remaining, 0);
+        assert!(error.status.reset_secs > 0);
+        // Other keys have their own window
+        assert!(limiter.consume("other", limit).is_ok());
+    }
+
+    #[test]
+    fn failure_delay_doubles_up_to_max() {
+        let limiter = limiter(60_000);
+        assert_eq!(limiter.failure_delay(DID), Duration::ZERO);
+        limiter.record_failure(DID);
+        assert_eq!(limiter.failure_delay(DID), Duration::from_millis(100));
+        limiter.record_failure(DID);
+        assert_eq!(limiter.failure_delay(DID), Duration::from_millis(200));
+        for _ in 0..10 {
+            limiter.record_failure(DID);
+        }
+        assert_eq!(limiter.failure_delay(DID), Duration::from_millis(1_000));
+        limiter.record_success(DID);
+        assert_eq!(limiter.failure_delay(DID), Duration::ZERO);
+    }
+
+    #[test]
+    fn locks_out_again_after_lockout_expires() {
+        let (limiter, now) = limiter_with_clock(100);
+        for cycle in 0..2 {
+            assert!(limiter.check_lockout(DID).is_ok(), "cycle {cycle}");
+            assert_eq!(limiter.record_failure(DID), None);
+            assert_eq!(limiter.record_failure(DID), None);
+            assert!(limiter.record_failure(DID).is_some(), "cycle {cycle}");
+            assert!(limiter.check_lockout(DID).is_err(), "cycle {cycle}");
+            // Guessing on while locked neither extends the lockout nor lets the
+            // failures age out
+            now.fetch_add(70, Ordering::SeqCst);
+            assert_eq!(limiter.record_failure(DID), None);
+            assert!(limiter.check_lockout(DID).is_err(), "cycle {cycle}");
+            now.fetch_add(30, Ordering::SeqCst);
+        }
+        assert!(limiter.check_lockout(DID).is_ok());
+    }
+
+    #[test]
+    fn disabled_limiter_never_locks_out() {
+        let mut cfg = limiter(60_000).cfg().clone();
+        cfg.enabled = false;
+        let limiter = RateLimiter::new(cfg);
+        for _ in 0..10 {
+            assert_eq!(limiter.record_failure(DID), None);
+        }
+        assert!(limiter.check_lockout(DID).is_ok());
+        assert_eq!(limiter.failure_delay(DID), Duration::ZERO);
+    }
+}
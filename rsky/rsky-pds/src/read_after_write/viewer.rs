@@ -40,6 +40,7 @@ use rsky_lexicon::app::bsky::embed::record::{
 use rsky_lexicon::app::bsky::embed::record_with_media::{
     RecordWithMedia, View as RecordWithMediaView,
 };
+use rsky_lexicon::app::bsky::embed::video::{Video, View as VideoView};
 use rsky_lexicon::app::bsky::embed::{record, EmbedViews, Embeds, MediaUnion, MediaViewUnion};
 use rsky_lexicon::app::bsky::feed::{FeedViewPost, GeneratorView, Post, PostView};
 use rsky_lexicon::app::bsky::graph::ListView;
@@ -58,6 +59,8 @@ pub struct LocalViewerCreatorParams {
     pub appview_agent: Option<String>,
     pub appview_did: Option<String>,
     pub appview_cdn_url_pattern: Option<String>,
+    pub appview_video_cdn_url_pattern: Option<String>,
+    pub local_cdn_url_pattern: Option<String>,
 }
 
 pub struct LocalViewer {
@@ -68,6 +71,8 @@ pub struct LocalViewer {
     appview_agent_str: Option<String>,
     pub appview_did: Option<String>,
     pub appview_cdn_url_pattern: Option<String>,
+    pub appview_video_cdn_url_pattern: Option<String>,
+    pub local_cdn_url_pattern: Option<String>,
 }
 
 impl LocalViewer {
@@ -78,6 +83,8 @@ impl LocalViewer {
         appview_agent_str: Option<String>,
         appview_did: Option<String>,
         appview_cdn_url_pattern: Option<String>,
+        appview_video_cdn_url_pattern: Option<String>,
+        local_cdn_url_pattern: Option<String>,
     ) -> Self {
         LocalViewer {
             did: actor_store.did.clone(),
@@ -87,6 +94,8 @@ impl LocalViewer {
             appview_agent_str,
             appview_did,
             appview_cdn_url_pattern,
+            appview_video_cdn_url_pattern,
+            local_cdn_url_pattern,
         }
     }
 
@@ -116,22 +125,55 @@ impl LocalViewer {
                 },
                 params.appview_did.clone(),
                 params.appview_cdn_url_pattern.clone(),
+                params.appview_video_cdn_url_pattern.clone(),
+                params.local_cdn_url_pattern.clone(),
             )
         });
     }
 
+    fn get_blob_url(&self, cid: String) -> String {
+        format!(
+            "https://{}/xrpc/{}?did={}&cid={}",
+            self.pds_hostname,
+            Ids::ComAtprotoSyncGetBlob.as_str(),
+            self.did,
+            cid
+        )
+    }
+
+    /// The appview CDN if there is one, else this PDS's own image endpoint, else the blob
     pub fn get_image_url(&self, pattern: String, cid: String) -> String {
-        match &self.appview_cdn_url_pattern {
-            None => format!(
-                "https://{}/xrpc/{}?did={}&cid={}",
-                self.pds_hostname,
-                Ids::ComAtprotoSyncGetBlob.as_str(),
-                self.did,
-                cid
-            ),
-            Some(appview_cdn_url_pattern) => {
-                util::nodejs_format(&*appview_cdn_url_pattern, &[&pattern, &self.did, &cid])
+        match (&self.appview_cdn_url_pattern, &self.local_cdn_url_pattern) {
+            (Some(cdn_url_pattern), _) | (None, Some(cdn_url_pattern)) => {
+                util::nodejs_format(&*cdn_url_pattern, &[&pattern, &self.did, &cid])
             }
+            (None, None) => self.get_blob_url(cid),
+        }
+    }
+
+    /// HLS playlist for a video. Without a video CDN there is no playlist: `playlist` points
+    /// at the original MP4 blob, which only clients that hand it to a native player (AVPlayer,
+    /// ExoPlayer, a `<video>` element) can play. Clients that load it with an HLS library
+    /// such as hls.js will fail.
+    pub fn get_video_playlist_url(&self, cid: String) -> String {
+        match &self.appview_video_cdn_url_pattern {
+            None => self.get_blob_url(cid),
+            Some(appview_video_cdn_url_pattern) => util::nodejs_format(
+                &*appview_video_cdn_url_pattern,
+                &[&self.did, &cid, &"playlist.m3u8"],
+            ),
+        }
+    }
+
+    /// Thumbnail for a video, only available from the video CDN since the PDS
+    /// doesn't transcode
+    pub fn get_video_thumbnail_url(&self, cid: String) -> Option<String> {
+        match &self.appview_video_cdn_url_pattern {
+            None => None,
+            Some(appview_video_cdn_url_pattern) => Some(util::nodejs_format(
+                &*appview_video_cdn_url_pattern,
+                &[&self.did, &cid, &"thumbnail.jpg"],
+            )),
         }
     }
 
@@ -301,6 +343,9 @@ impl LocalViewer {
                 Embeds::Images(embed) => Ok(Some(
                     self.format_simple_embed(MediaUnion::Images(embed)).await,
                 )),
+                Embeds::Video(embed) => Ok(Some(
+                    self.format_simple_embed(MediaUnion::Video(embed)).await,
+                )),
                 Embeds::External(embed) => Ok(Some(
                     self.format_simple_embed(MediaUnion::External(embed)).await,
                 )),
@@ -310,7 +355,6 @@ impl LocalViewer {
                 Embeds::RecordWithMedia(embed) => Ok(Some(EmbedViews::RecordWithMediaView(
                     self.format_record_with_media_embed(embed).await?,
                 ))),
-                _ => Ok(None), // @TODO: Handle video
             },
         }
     }
@@ -358,7 +402,25 @@ impl LocalViewer {
                     },
                 })
             }
-            _ => panic!("Can't handle video"), // @TODO: Handle video
+            MediaUnion::Video(embed) => {
+                let Video {
+                    video,
+                    alt,
+                    aspect_ratio,
+                    ..
+                } = embed;
+                let cid = match video.r#ref {
+                    Some(r#ref) => r#ref.to_string(),
+                    None => video.cid.unwrap_or_default(),
+                };
+                EmbedViews::VideoView(VideoView {
+                    playlist: self.get_video_playlist_url(cid.clone()),
+                    thumbnail: self.get_video_thumbnail_url(cid.clone()),
+                    cid,
+                    alt,
+                    aspect_ratio,
+                })
+            }
         }
     }
 
@@ -468,6 +530,7 @@ impl LocalViewer {
     ) -> Result<RecordWithMediaView> {
         let media = match self.format_simple_embed(embed.media).await {
             EmbedViews::ImagesView(media) => MediaViewUnion::ImagesView(media),
+            EmbedViews::VideoView(media) => MediaViewUnion::VideoView(media),
             EmbedViews::ExternalView(media) => MediaViewUnion::ExternalView(media),
             _ => bail!("Unexpected enum for media."),
         };
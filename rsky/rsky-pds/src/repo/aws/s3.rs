@@ -2,19 +2,27 @@ use std::str::FromStr;
 // based on https://github.com/bluesky-social/atproto/blob/main/packages/aws/src/s3.ts
 use crate::common::env::env_str;
 use crate::common::get_random_str;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use aws_config::SdkConfig;
 use aws_sdk_s3 as s3;
 use aws_sdk_s3::error::SdkError;
 use aws_sdk_s3::primitives::ByteStream;
 use aws_sdk_s3::types::{Delete, ObjectCannedAcl, ObjectIdentifier};
 use lexicon_cid::Cid;
+use std::collections::BTreeSet;
 
 struct MoveObject {
     from: String,
     to: String,
 }
 
+/// An object listed from the store. `key` is the temp key or the cid, without its prefix.
+#[derive(Debug, Clone)]
+pub struct ListedObject {
+    pub key: String,
+    pub last_modified_ms: Option<i64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct S3BlobStore {
     client: s3::Client,
@@ -54,6 +62,16 @@ impl S3BlobStore {
         format!("quarantine/{0}/{1}", self.bucket, cid.to_string())
     }
 
+    // Rendered variants of an image are kept next to it, one per preset
+    fn get_variant_path(&self, cid: Cid) -> String {
+        format!("variants/{0}/{1}/", self.bucket, cid.to_string())
+    }
+
+    // Chunks of a resumable upload are keyed by their offset
+    fn get_upload_path(&self, upload_id: &String) -> String {
+        format!("uploads/{0}/{1}/", self.bucket, upload_id)
+    }
+
     pub async fn put_temp(&self, bytes: Vec<u8>) -> Result<String> {
         let key = self.gen_key();
         let body = ByteStream::from(bytes);
@@ -114,12 +132,14 @@ impl S3BlobStore {
             .await?)
     }
 
-    async fn get_object(&self, cid: Cid) -> Result<ByteStream> {
+    /// `range` is an HTTP byte range, e.g. `bytes=0-1023`
+    async fn get_object(&self, key: String, range: Option<String>) -> Result<ByteStream> {
         let res = self
             .client
             .get_object()
             .bucket(&self.bucket)
-            .key(self.get_stored_path(cid))
+            .key(key)
+            .set_range(range)
             .send()
             .await;
         match res {
@@ -130,27 +150,191 @@ impl S3BlobStore {
     }
 
     pub async fn get_bytes(&self, cid: Cid) -> Result<Vec<u8>> {
-        let res = self.get_object(cid).await?;
+        let res = self.get_object(self.get_stored_path(cid), None).await?;
+        let bytes = res.collect().await.map(|data| data.into_bytes())?;
+        Ok(bytes.to_vec())
+    }
+
+    pub async fn get_temp_bytes(&self, key: String) -> Result<Vec<u8>> {
+        let res = self.get_object(self.get_tmp_path(&key), None).await?;
         let bytes = res.collect().await.map(|data| data.into_bytes())?;
         Ok(bytes.to_vec())
     }
 
     pub async fn get_stream(&self, cid: Cid) -> Result<ByteStream> {
-        Ok(self.get_object(cid).await?)
+        Ok(self.get_object(self.get_stored_path(cid), None).await?)
     }
 
-    pub async fn delete(&self, cid: String) -> Result<()> {
+    /// Bytes `start` through `end` of a stored blob, inclusive
+    pub async fn get_range_stream(&self, cid: Cid, start: u64, end: u64) -> Result<ByteStream> {
         Ok(self
-            .delete_key(self.get_stored_path(Cid::from_str(&cid)?))
+            .get_object(
+                self.get_stored_path(cid),
+                Some(format!("bytes={start}-{end}")),
+            )
             .await?)
     }
 
+    pub async fn delete(&self, cid: String) -> Result<()> {
+        let cid = Cid::from_str(&cid)?;
+        self.delete_variants(cid).await?;
+        Ok(self.delete_key(self.get_stored_path(cid)).await?)
+    }
+
     pub async fn delete_many(&self, cids: Vec<Cid>) -> Result<()> {
-        let keys: Vec<String> = cids
+        // One listing for all the variants rather than one per blob
+        let variants_prefix = format!("variants/{0}/", self.bucket);
+        let deleted: BTreeSet<String> = cids.iter().map(|cid| cid.to_string()).collect();
+        let mut keys: Vec<String> = self
+            .list_prefix(variants_prefix.clone())
+            .await?
             .into_iter()
-            .map(|cid| self.get_stored_path(cid))
+            .filter(|object| match object.key.split_once("/") {
+                Some((cid, _)) => deleted.contains(cid),
+                None => false,
+            })
+            .map(|object| format!("{variants_prefix}{}", object.key))
             .collect();
-        Ok(self.delete_many_keys(keys).await?)
+        keys.extend(cids.into_iter().map(|cid| self.get_stored_path(cid)));
+        for chunk in keys.chunks(1000) {
+            self.delete_many_keys(chunk.to_vec()).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn put_variant(&self, cid: Cid, preset: &str, bytes: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object()
+            .body(ByteStream::from(bytes))
+            .bucket(&self.bucket)
+            .key(format!("{}{preset}", self.get_variant_path(cid)))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// A variant rendered before, if there is one
+    pub async fn get_variant_bytes(&self, cid: Cid, preset: &str) -> Result<Option<Vec<u8>>> {
+        let key = format!("{}{preset}", self.get_variant_path(cid));
+        if !self.has_key(key.clone()).await {
+            return Ok(None);
+        }
+        let res = self.get_object(key, None).await?;
+        let bytes = res.collect().await.map(|data| data.into_bytes())?;
+        Ok(Some(bytes.to_vec()))
+    }
+
+    async fn delete_variants(&self, cid: Cid) -> Result<()> {
+        let prefix = self.get_variant_path(cid);
+        let keys: Vec<String> = self
+            .list_prefix(prefix.clone())
+            .await?
+            .into_iter()
+            .map(|object| format!("{prefix}{}", object.key))
+            .collect();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        self.delete_many_keys(keys).await
+    }
+
+    pub async fn put_upload_chunk(
+        &self,
+        upload_id: &String,
+        offset: i64,
+        bytes: Vec<u8>,
+    ) -> Result<()> {
+        self.client
+            .put_object()
+            .body(ByteStream::from(bytes))
+            .bucket(&self.bucket)
+            .key(format!("{}{offset}", self.get_upload_path(upload_id)))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Puts the chunks of an upload back together, following them from offset 0. Chunks
+    /// left over from retried requests that don't line up are skipped.
+    pub async fn get_upload_bytes(&self, upload_id: &String, size: i64) -> Result<Vec<u8>> {
+        let prefix = self.get_upload_path(upload_id);
+        let offsets: BTreeSet<i64> = self
+            .list_prefix(prefix.clone())
+            .await?
+            .into_iter()
+            .filter_map(|object| i64::from_str(&object.key).ok())
+            .collect();
+        let mut bytes: Vec<u8> = Vec::with_capacity(size as usize);
+        while (bytes.len() as i64) < size {
+            let offset = bytes.len() as i64;
+            if !offsets.contains(&offset) {
+                bail!("Upload {upload_id} is missing the chunk at offset {offset}")
+            }
+            let chunk = self.get_object(format!("{prefix}{offset}"), None).await?;
+            let chunk = chunk.collect().await.map(|data| data.into_bytes())?;
+            if chunk.is_empty() {
+                bail!("Upload {upload_id} has an empty chunk at offset {offset}")
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        bytes.truncate(size as usize);
+        Ok(bytes)
+    }
+
+    pub async fn delete_upload(&self, upload_id: &String) -> Result<()> {
+        let prefix = self.get_upload_path(upload_id);
+        let keys: Vec<String> = self
+            .list_prefix(prefix.clone())
+            .await?
+            .into_iter()
+            .map(|object| format!("{prefix}{}", object.key))
+            .collect();
+        for chunk in keys.chunks(1000) {
+            self.delete_many_keys(chunk.to_vec()).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn delete_temp(&self, key: String) -> Result<()> {
+        Ok(self.delete_key(self.get_tmp_path(&key)).await?)
+    }
+
+    pub async fn list_temp(&self) -> Result<Vec<ListedObject>> {
+        self.list_prefix(format!("tmp/{0}/", self.bucket)).await
+    }
+
+    pub async fn list_stored(&self) -> Result<Vec<ListedObject>> {
+        self.list_prefix(format!("blocks/{0}/", self.bucket)).await
+    }
+
+    async fn list_prefix(&self, prefix: String) -> Result<Vec<ListedObject>> {
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let res = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+            for object in res.contents() {
+                if let Some(key) = object.key() {
+                    objects.push(ListedObject {
+                        key: key.trim_start_matches(&prefix).to_string(),
+                        last_modified_ms: object
+                            .last_modified()
+                            .map(|last_modified| last_modified.secs() * 1000),
+                    });
+                }
+            }
+            match res.next_continuation_token() {
+                Some(token) => continuation_token = Some(token.to_string()),
+                None => break,
+            }
+        }
+        Ok(objects)
     }
 
     pub async fn has_stored(&self, cid: Cid) -> Result<bool> {
@@ -1,6 +1,7 @@
 use crate::common::ipld::sha256_raw_to_cid;
 use crate::common::now;
 use crate::db::establish_connection;
+use crate::db::pagination::{paginate, KeySetPaginateOpts, StringKeySet};
 use crate::models::models;
 use crate::repo::aws::s3::S3BlobStore;
 use crate::repo::blob_refs::BlobRef;
@@ -41,6 +42,11 @@ pub struct ListMissingBlobsOpts {
     pub limit: u16,
 }
 
+/// Blobs are listed in cid order, with the last cid as the cursor
+pub const LIST_BLOBS_KEYSET: StringKeySet = StringKeySet {
+    key: r#""record_blob"."blobCid""#,
+};
+
 pub struct ListBlobsOpts {
     pub since: Option<String>,
     pub cursor: Option<String>,
@@ -75,12 +81,14 @@ impl BlobReader {
             .filter(BlobSchema::did.eq(&self.did))
             .filter(BlobSchema::cid.eq(&cid.to_string()))
             .filter(BlobSchema::takedownRef.is_null())
+            // Blobs still in temp storage haven't been referenced by a record yet
+            .filter(BlobSchema::tempKey.is_null())
             .select(models::Blob::as_select())
             .first(conn)
             .optional()?;
 
         match found {
-            None => bail!("Blob not found"),
+            None => bail!(BlobError::BlobNotFoundError),
             Some(found) => Ok(GetBlobMetadataOutput {
                 size: found.size,
                 mime_type: Some(found.mime_type),
@@ -89,8 +97,22 @@ impl BlobReader {
     }
 
     pub async fn get_blob(&self, cid: Cid) -> Result<GetBlobOutput> {
+        self.get_blob_range(cid, None).await
+    }
+
+    /// Like get_blob, but only streams bytes `start` through `end` (inclusive) when given a
+    /// range. `size` is still the size of the whole blob.
+    pub async fn get_blob_range(
+        &self,
+        cid: Cid,
+        range: Option<(u64, u64)>,
+    ) -> Result<GetBlobOutput> {
         let metadata = self.get_blob_metadata(cid).await?;
-        let blob_stream = match self.blobstore.get_stream(cid).await {
+        let blob_stream = match range {
+            None => self.blobstore.get_stream(cid).await,
+            Some((start, end)) => self.blobstore.get_range_stream(cid, start, end).await,
+        };
+        let blob_stream = match blob_stream {
             Ok(res) => res,
             Err(e) => {
                 return match e.downcast_ref() {
@@ -131,8 +153,18 @@ impl BlobReader {
     ) -> Result<BlobMetadata> {
         let blob_stream = blob.open(100.mebibytes());
         let bytes = blob_stream.into_bytes().await?;
-        let size = bytes.n.written;
-        let bytes = bytes.into_inner();
+        self.put_temp_and_get_metadata(user_suggested_mime, bytes.into_inner())
+            .await
+    }
+
+    /// Applies the image upload policy, then stores the blob under a temp key
+    pub async fn put_temp_and_get_metadata(
+        &self,
+        user_suggested_mime: String,
+        bytes: Vec<u8>,
+    ) -> Result<BlobMetadata> {
+        let bytes = image::process_upload(bytes).await?;
+        let size = bytes.len();
         let (temp_key, sha256, img_info, sniffed_mime) = try_join!(
             self.blobstore.put_temp(bytes.clone()),
             sha256_stream(bytes.clone()),
@@ -215,14 +247,14 @@ impl BlobReader {
                 Ok::<(), anyhow::Error>(match write {
                     PreparedWrite::Create(w) => {
                         for blob in w.blobs {
-                            self.verify_blob_and_make_permanent(blob.clone()).await?;
-                            self.associate_blob(blob, w.uri.clone()).await?;
+                            self.make_permanent_and_associate(blob, w.uri.clone())
+                                .await?;
                         }
                     }
                     PreparedWrite::Update(w) => {
                         for blob in w.blobs {
-                            self.verify_blob_and_make_permanent(blob.clone()).await?;
-                            self.associate_blob(blob, w.uri.clone()).await?;
+                            self.make_permanent_and_associate(blob, w.uri.clone())
+                                .await?;
                         }
                     }
                     _ => (),
@@ -235,6 +267,25 @@ impl BlobReader {
         Ok(())
     }
 
+    /// Associates the blobs of imported records, making permanent the ones already uploaded.
+    /// The rest show up in listMissingBlobs until uploaded.
+    pub async fn process_import_blobs(&self, writes: Vec<PreparedWrite>) -> Result<()> {
+        self.delete_dereferenced_blobs(writes.clone()).await?;
+        for write in writes {
+            let (uri, blobs) = match write {
+                PreparedWrite::Create(w) | PreparedWrite::Update(w) => (w.uri, w.blobs),
+                PreparedWrite::Delete(_) => continue,
+            };
+            for blob in blobs {
+                match self.has_blob(blob.cid).await? {
+                    true => self.make_permanent_and_associate(blob, uri.clone()).await?,
+                    false => self.associate_blob(blob, uri.clone()).await?,
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub async fn delete_dereferenced_blobs(&self, writes: Vec<PreparedWrite>) -> Result<()> {
         use crate::schema::pds::blob::dsl as BlobSchema;
         use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
@@ -314,7 +365,26 @@ impl BlobReader {
     }
 
     pub async fn verify_blob_and_make_permanent(&self, blob: PreparedBlobRef) -> Result<()> {
+        self.make_permanent(blob, None).await
+    }
+
+    /// Makes the blob permanent and references it from `record_uri` in one transaction, so
+    /// blob GC never sees it permanent but unreferenced.
+    pub async fn make_permanent_and_associate(
+        &self,
+        blob: PreparedBlobRef,
+        record_uri: String,
+    ) -> Result<()> {
+        self.make_permanent(blob, Some(record_uri)).await
+    }
+
+    async fn make_permanent(
+        &self,
+        blob: PreparedBlobRef,
+        record_uri: Option<String>,
+    ) -> Result<()> {
         use crate::schema::pds::blob::dsl as BlobSchema;
+        use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
         let conn = &mut establish_connection()?;
 
         let found = BlobSchema::blob
@@ -333,11 +403,23 @@ impl BlobReader {
                     .make_permanent(temp_key.clone(), blob.cid)
                     .await?;
             }
-            update(BlobSchema::blob)
-                .filter(BlobSchema::tempKey.eq(found.temp_key))
-                .set(BlobSchema::tempKey.eq::<Option<String>>(None))
-                .execute(conn)?;
-            Ok(())
+            conn.transaction::<_, anyhow::Error, _>(|conn| {
+                update(BlobSchema::blob)
+                    .filter(BlobSchema::tempKey.eq(found.temp_key))
+                    .set(BlobSchema::tempKey.eq::<Option<String>>(None))
+                    .execute(conn)?;
+                if let Some(record_uri) = record_uri {
+                    insert_into(RecordBlobSchema::record_blob)
+                        .values((
+                            RecordBlobSchema::blobCid.eq(blob.cid.to_string()),
+                            RecordBlobSchema::recordUri.eq(record_uri),
+                            RecordBlobSchema::did.eq(&self.did),
+                        ))
+                        .on_conflict_do_nothing()
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
         } else {
             bail!("Cound not find blob: {:?}", blob.cid.to_string())
         }
@@ -358,6 +440,33 @@ impl BlobReader {
         Ok(())
     }
 
+    pub async fn has_blob(&self, cid: Cid) -> Result<bool> {
+        use crate::schema::pds::blob::dsl as BlobSchema;
+        let conn = &mut establish_connection()?;
+
+        let res = select(exists(
+            BlobSchema::blob
+                .filter(BlobSchema::did.eq(&self.did))
+                .filter(BlobSchema::cid.eq(cid.to_string())),
+        ))
+        .get_result(conn)?;
+        Ok(res)
+    }
+
+    /// Every blob referenced by the actor's records, e.g. for an account export
+    pub async fn list_record_blob_cids(&self) -> Result<Vec<String>> {
+        use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+        let conn = &mut establish_connection()?;
+
+        let res = RecordBlobSchema::record_blob
+            .filter(RecordBlobSchema::did.eq(&self.did))
+            .select(RecordBlobSchema::blobCid)
+            .distinct()
+            .order(RecordBlobSchema::blobCid.asc())
+            .get_results(conn)?;
+        Ok(res)
+    }
+
     pub async fn blob_count(&self) -> Result<i64> {
         use crate::schema::pds::blob::dsl as BlobSchema;
         let conn = &mut establish_connection()?;
@@ -444,34 +553,28 @@ impl BlobReader {
             limit,
         } = opts;
 
+        let keyset = LIST_BLOBS_KEYSET;
+        let opts = KeySetPaginateOpts {
+            limit: Some(limit as i64),
+            cursor,
+            direction: Some("asc".to_string()),
+        };
         let res: Vec<String> = if let Some(since) = since {
-            let mut builder = RecordBlobSchema::record_blob
+            let builder = RecordBlobSchema::record_blob
                 .inner_join(
                     RecordSchema::record.on(RecordSchema::uri.eq(RecordBlobSchema::recordUri)),
                 )
                 .filter(RecordSchema::repoRev.gt(&since))
                 .select(RecordBlobSchema::blobCid)
                 .distinct()
-                .order(RecordBlobSchema::blobCid.asc())
-                .limit(limit as i64)
                 .into_boxed();
-
-            if let Some(cursor) = cursor {
-                builder = builder.filter(RecordBlobSchema::blobCid.gt(cursor));
-            }
-            builder.load(conn)?
+            paginate(builder, &keyset, opts)?.load(conn)?
         } else {
-            let mut builder = RecordBlobSchema::record_blob
+            let builder = RecordBlobSchema::record_blob
                 .select(RecordBlobSchema::blobCid)
                 .distinct()
-                .order(RecordBlobSchema::blobCid.asc())
-                .limit(limit as i64)
                 .into_boxed();
-
-            if let Some(cursor) = cursor {
-                builder = builder.filter(RecordBlobSchema::blobCid.gt(cursor));
-            }
-            builder.load(conn)?
+            paginate(builder, &keyset, opts)?.load(conn)?
         };
         Ok(res)
     }
@@ -584,3 +687,5 @@ pub async fn sha256_stream(to_hash: Vec<u8>) -> Result<Vec<u8>> {
     let hash: &[u8] = digest.as_ref();
     Ok(hash.to_vec())
 }
+
+pub mod upload;
@@ -0,0 +1,236 @@
+// Resumable uploads: a session is created with the blob's size, chunks are appended at the
+// offset received so far, and once every byte is in the chunks are put back together and
+// stored like any other upload. Chunks live under their own prefix in the blob store so a
+// dropped connection only costs the chunk in flight.
+
+use crate::common::ipld::sha256_raw_to_cid;
+use crate::common::time::{from_millis_to_str, from_str_to_millis};
+use crate::common::{get_random_str, now};
+use crate::config::SERVER_CONFIG;
+use crate::db::establish_connection;
+use crate::models::models;
+use crate::repo::blob::{BlobMetadata, BlobReader};
+use crate::repo::quota;
+use anyhow::{bail, Result};
+use diesel::*;
+use lexicon_cid::Cid;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use std::time::SystemTime;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum UploadError {
+    #[error("UploadNotFound: upload {0} doesn't exist or has expired")]
+    NotFound(String),
+    #[error("InvalidSize: uploads must be between 1 and {max} bytes")]
+    InvalidSize { max: u64 },
+    #[error("InvalidCid: {0}")]
+    InvalidCid(String),
+    #[error("ChunkTooLarge: chunks are limited to {max} bytes")]
+    ChunkTooLarge { max: u64 },
+    #[error("ChunkPastEnd: the chunk ends past the upload's {size} bytes")]
+    ChunkPastEnd { size: i64 },
+    #[error("InvalidOffset: upload is at offset {expected}")]
+    OffsetMismatch { expected: i64 },
+    #[error("UploadIncomplete: {received} of {size} bytes received")]
+    Incomplete { received: i64, size: i64 },
+    #[error("TooManyUploads: accounts can have {max} uploads in progress")]
+    TooManyUploads { max: u64 },
+    #[error("CidMismatch: expected {expected} but the uploaded blob is {actual}")]
+    CidMismatch { expected: String, actual: String },
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in millis since UNIX epoch")
+        .as_millis() as i64
+}
+
+fn is_expired(upload: &models::BlobUpload) -> bool {
+    from_str_to_millis(&upload.expires_at).map_or(true, |expires_at| expires_at <= now_ms())
+}
+
+/// Uploads past their expiry, across all accounts, for the blob GC to clean up
+pub fn list_expired_uploads() -> Result<Vec<models::BlobUpload>> {
+    use crate::schema::pds::blob_upload::dsl as BlobUploadSchema;
+    let conn = &mut establish_connection()?;
+
+    let uploads: Vec<models::BlobUpload> = BlobUploadSchema::blob_upload
+        .select(models::BlobUpload::as_select())
+        .load(conn)?;
+    Ok(uploads.into_iter().filter(is_expired).collect())
+}
+
+impl BlobReader {
+    /// Uploads the account hasn't completed or abandoned yet
+    fn list_open_uploads(&self) -> Result<Vec<models::BlobUpload>> {
+        use crate::schema::pds::blob_upload::dsl as BlobUploadSchema;
+        let conn = &mut establish_connection()?;
+
+        let uploads: Vec<models::BlobUpload> = BlobUploadSchema::blob_upload
+            .filter(BlobUploadSchema::did.eq(&self.did))
+            .select(models::BlobUpload::as_select())
+            .load(conn)?;
+        Ok(uploads
+            .into_iter()
+            .filter(|upload| !is_expired(upload))
+            .collect())
+    }
+
+    /// Opens an upload session. Bytes promised to the account's other open uploads count
+    /// toward its blob quota, so sessions can't be used to get around it.
+    pub async fn create_upload(
+        &self,
+        size: i64,
+        mime_type: String,
+        cid: String,
+    ) -> Result<models::BlobUpload> {
+        use crate::schema::pds::blob_upload::dsl as BlobUploadSchema;
+
+        let max = SERVER_CONFIG.uploads.max_size;
+        if size < 1 || size as u64 > max {
+            bail!(UploadError::InvalidSize { max })
+        }
+        if let Err(error) = Cid::from_str(&cid) {
+            bail!(UploadError::InvalidCid(error.to_string()))
+        }
+        let open = self.list_open_uploads()?;
+        if open.len() as u64 >= SERVER_CONFIG.uploads.max_open_per_account {
+            bail!(UploadError::TooManyUploads {
+                max: SERVER_CONFIG.uploads.max_open_per_account
+            })
+        }
+        let pending: i64 = open.iter().map(|upload| upload.size).sum();
+        quota::check_blob_upload(&self.did, pending + size).await?;
+        let upload = models::BlobUpload {
+            id: get_random_str(),
+            did: self.did.clone(),
+            mime_type,
+            size,
+            received: 0,
+            cid,
+            created_at: now(),
+            expires_at: from_millis_to_str(now_ms() + SERVER_CONFIG.uploads.expiry_ms as i64),
+        };
+        let conn = &mut establish_connection()?;
+        insert_into(BlobUploadSchema::blob_upload)
+            .values(&upload)
+            .execute(conn)?;
+        Ok(upload)
+    }
+
+    pub async fn get_upload(&self, upload_id: &String) -> Result<models::BlobUpload> {
+        use crate::schema::pds::blob_upload::dsl as BlobUploadSchema;
+        let conn = &mut establish_connection()?;
+
+        let found = BlobUploadSchema::blob_upload
+            .filter(BlobUploadSchema::id.eq(upload_id))
+            .filter(BlobUploadSchema::did.eq(&self.did))
+            .select(models::BlobUpload::as_select())
+            .first(conn)
+            .optional()?;
+        match found {
+            Some(upload) if !is_expired(&upload) => Ok(upload),
+            _ => bail!(UploadError::NotFound(upload_id.clone())),
+        }
+    }
+
+    /// Appends a chunk at `offset`, which has to be the number of bytes received so far.
+    /// Resending the last chunk after a dropped response fails with the new offset, which
+    /// tells the client where to pick up.
+    pub async fn append_upload_chunk(
+        &self,
+        upload_id: &String,
+        offset: i64,
+        bytes: Vec<u8>,
+    ) -> Result<models::BlobUpload> {
+        use crate::schema::pds::blob_upload::dsl as BlobUploadSchema;
+
+        let upload = self.get_upload(upload_id).await?;
+        if offset != upload.received {
+            bail!(UploadError::OffsetMismatch {
+                expected: upload.received
+            })
+        }
+        if bytes.len() as u64 > SERVER_CONFIG.uploads.max_chunk_size {
+            bail!(UploadError::ChunkTooLarge {
+                max: SERVER_CONFIG.uploads.max_chunk_size
+            })
+        }
+        let received = offset + bytes.len() as i64;
+        if received > upload.size {
+            bail!(UploadError::ChunkPastEnd { size: upload.size })
+        }
+        if bytes.is_empty() {
+            return Ok(upload);
+        }
+        self.blobstore
+            .put_upload_chunk(upload_id, offset, bytes)
+            .await?;
+
+        // Only moves forward if no other chunk got in first
+        let conn = &mut establish_connection()?;
+        let updated = update(BlobUploadSchema::blob_upload)
+            .filter(BlobUploadSchema::id.eq(upload_id))
+            .filter(BlobUploadSchema::received.eq(offset))
+            .set(BlobUploadSchema::received.eq(received))
+            .execute(conn)?;
+        if updated == 0 {
+            let upload = self.get_upload(upload_id).await?;
+            bail!(UploadError::OffsetMismatch {
+                expected: upload.received
+            })
+        }
+        Ok(models::BlobUpload { received, ..upload })
+    }
+
+    /// Puts the chunks back together and checks them against the cid given when the upload
+    /// was created, so chunks overwritten by a racing request can't slip through. The blob
+    /// is then stored under a temp key, ready to be tracked like a blob from uploadBlob.
+    ///
+    /// Like an uploadBlob body, the whole blob is held in memory while it is verified and
+    /// stored, and copied again for image processing. `PDS_UPLOAD_MAX_SIZE` bounds how much
+    /// each completing upload buffers.
+    pub async fn complete_upload(&self, upload_id: &String) -> Result<BlobMetadata> {
+        let upload = self.get_upload(upload_id).await?;
+        if upload.received < upload.size {
+            bail!(UploadError::Incomplete {
+                received: upload.received,
+                size: upload.size
+            })
+        }
+        let bytes = self
+            .blobstore
+            .get_upload_bytes(upload_id, upload.size)
+            .await?;
+        let cid = sha256_raw_to_cid(Sha256::digest(&bytes).to_vec());
+        if Cid::from_str(&upload.cid)? != cid {
+            // The chunks are no good, so the client has to start over
+            self.discard_upload(upload_id).await?;
+            bail!(UploadError::CidMismatch {
+                expected: upload.cid,
+                actual: cid.to_string()
+            })
+        }
+        let metadata = self
+            .put_temp_and_get_metadata(upload.mime_type, bytes)
+            .await?;
+        self.discard_upload(upload_id).await?;
+        Ok(metadata)
+    }
+
+    /// Deletes an upload's chunks and session
+    pub async fn discard_upload(&self, upload_id: &String) -> Result<()> {
+        use crate::schema::pds::blob_upload::dsl as BlobUploadSchema;
+
+        self.blobstore.delete_upload(upload_id).await?;
+        let conn = &mut establish_connection()?;
+        delete(BlobUploadSchema::blob_upload)
+            .filter(BlobUploadSchema::id.eq(upload_id))
+            .filter(BlobUploadSchema::did.eq(&self.did))
+            .execute(conn)?;
+        Ok(())
+    }
+}
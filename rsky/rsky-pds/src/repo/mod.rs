@@ -1,6 +1,7 @@
 // based on https://github.com/bluesky-social/atproto/blob/main/packages/repo/src/repo.ts
 // also adds components from https://github.com/bluesky-social/atproto/blob/main/packages/pds/src/actor-store/repo/transactor.ts
 
+use crate::account_manager::helpers::account::escape_like;
 use crate::common;
 use crate::common::ipld::data_to_cbor_block;
 use crate::common::tid::{Ticker, TID};
@@ -22,6 +23,7 @@ use crate::repo::types::{
     RecordWriteOp, RepoContents, RepoRecord, UnsignedCommit, WriteOpAction,
 };
 use crate::repo::util::{cbor_to_lex, lex_to_ipld};
+use crate::search;
 use crate::storage::{Ipld, SqlRepoReader};
 use anyhow::{anyhow, bail, Result};
 use diesel::*;
@@ -153,12 +155,13 @@ impl ActorStore {
         writes: Vec<PreparedWrite>,
         swap_commit_cid: Option<Cid>,
     ) -> Result<CommitData> {
+        quota::check_writes(&self.did, &writes).await?;
         let commit = self.format_commit(writes.clone(), swap_commit_cid).await?;
         {
             let immutable_borrow = &self;
             // & send to indexing
             immutable_borrow
-                .index_writes(writes.clone(), &commit.rev)
+                .index_writes(writes.clone(), &commit.rev, false)
                 .await?;
         }
         try_join!(
@@ -170,6 +173,25 @@ impl ActorStore {
         Ok(commit)
     }
 
+    /// Commits records restored by importRepo. Unlike process_writes, their blobs may not
+    /// have been uploaded yet: they are tracked against the records and made permanent on upload.
+    /// They're indexed at their own `createdAt` and don't notify anyone.
+    pub async fn process_import(&mut self, writes: Vec<PreparedWrite>) -> Result<CommitData> {
+        quota::check_writes(&self.did, &writes).await?;
+        let commit = self.format_commit(writes.clone(), None).await?;
+        {
+            let immutable_borrow = &self;
+            immutable_borrow
+                .index_writes(writes.clone(), &commit.rev, true)
+                .await?;
+        }
+        try_join!(
+            self.storage.apply_commit(commit.clone(), None),
+            self.blob.process_import_blobs(writes)
+        )?;
+        Ok(commit)
+    }
+
     pub async fn format_commit(
         &mut self,
         writes: Vec<PreparedWrite>,
@@ -267,13 +289,24 @@ impl ActorStore {
         }
     }
 
-    pub async fn index_writes(&self, writes: Vec<PreparedWrite>, rev: &String) -> Result<()> {
+    /// Indexes records at the current time, or for imports at the time they were created
+    pub async fn index_writes(
+        &self,
+        writes: Vec<PreparedWrite>,
+        rev: &String,
+        imported: bool,
+    ) -> Result<()> {
         let now: &str = &common::now();
 
         let _ = stream::iter(writes)
             .then(|write| async move {
                 Ok::<(), anyhow::Error>(match write {
-                    PreparedWrite::Create(write) => {
+                    PreparedWrite::Create(write) | PreparedWrite::Update(write) => {
+                        let indexed_at = match imported {
+                            true => imported_at(&write.record, now),
+                            false => now.to_string(),
+                        };
+                        search::index_record(&write.uri, &write.record, &indexed_at).await?;
                         self.record
                             .index_record(
                                 write.uri,
@@ -281,23 +314,15 @@ impl ActorStore {
                                 Some(write.record),
                                 Some(write.action),
                                 rev.clone(),
-                                Some(now.to_string()),
+                                Some(indexed_at),
+                                !imported,
                             )
                             .await?
                     }
-                    PreparedWrite::Update(write) => {
-                        self.record
-                            .index_record(
-                                write.uri,
-                                write.cid,
-                                Some(write.record),
-                                Some(write.action),
-                                rev.clone(),
-                                Some(now.to_string()),
-                            )
-                            .await?
+                    PreparedWrite::Delete(write) => {
+                        search::delete_record(&write.uri).await?;
+                        self.record.delete_record(write.uri).await?
                     }
-                    PreparedWrite::Delete(write) => self.record.delete_record(write.uri).await?,
                 })
             })
             .collect::<Vec<_>>()
@@ -327,9 +352,44 @@ impl ActorStore {
             .await
             .into_iter()
             .collect::<Result<Vec<_>, _>>()?;
+        self.delete_repo_rows()?;
         Ok(())
     }
 
+    /// Removes everything the actor store holds for the repo once its blobs are gone:
+    /// records and their indexes, blocks, blob metadata and preferences
+    fn delete_repo_rows(&self) -> Result<()> {
+        use crate::schema::pds::account_pref::dsl as AccountPrefSchema;
+        use crate::schema::pds::backlink::dsl as BacklinkSchema;
+        use crate::schema::pds::blob::dsl as BlobSchema;
+        use crate::schema::pds::record::dsl as RecordSchema;
+        use crate::schema::pds::record_blob::dsl as RecordBlobSchema;
+        use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+        let conn = &mut establish_connection()?;
+
+        conn.transaction::<_, anyhow::Error, _>(|conn| {
+            delete(BacklinkSchema::backlink)
+                .filter(BacklinkSchema::uri.like(format!("at://{}/%", escape_like(&self.did))))
+                .execute(conn)?;
+            delete(RecordBlobSchema::record_blob)
+                .filter(RecordBlobSchema::did.eq(&self.did))
+                .execute(conn)?;
+            delete(RecordSchema::record)
+                .filter(RecordSchema::did.eq(&self.did))
+                .execute(conn)?;
+            delete(RepoBlockSchema::repo_block)
+                .filter(RepoBlockSchema::did.eq(&self.did))
+                .execute(conn)?;
+            delete(BlobSchema::blob)
+                .filter(BlobSchema::did.eq(&self.did))
+                .execute(conn)?;
+            delete(AccountPrefSchema::account_pref)
+                .filter(AccountPrefSchema::did.eq(&self.did))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
     // @TODO: Use AtUri
     pub async fn get_duplicate_record_cids(
         &self,
@@ -356,6 +416,22 @@ impl ActorStore {
     }
 }
 
+/// When an imported record was created, going by its `createdAt`. Falls back to `now` for
+/// records without a valid one, or claiming to be from the future.
+fn imported_at(record: &RepoRecord, now: &str) -> String {
+    let created_at =
+        serde_json::to_value(record)
+            .ok()
+            .and_then(|record| match record.get("createdAt") {
+                Some(JsonValue::String(created_at)) => common::time::normalize_datetime(created_at),
+                _ => None,
+            });
+    match created_at {
+        Some(created_at) if created_at.as_str() <= now => created_at,
+        _ => now.to_string(),
+    }
+}
+
 impl Repo {
     // static
     pub fn new(storage: SqlRepoReader, data: MST, commit: Commit, cid: Cid) -> Self {
@@ -377,76 +453,7 @@ impl Repo {
         match commit_cid {
             Some(commit_cid) => {
                 let commit_bytes: Vec<u8> = storage.get_bytes(&commit_cid)?;
-                let block = Block::<DefaultParams>::new(commit_cid, commit_bytes.clone())?;
-                let ipld = block.decode::<DagCborCodec, VendorIpld>()?;
-                // Convert Ipld to Commit
-                let commit: Commit = match ipld {
-                    VendorIpld::Map(m) => Commit {
-                        did: m
-                            .get("did")
-                            .and_then(|v| {
-                                if let VendorIpld::String(s) = v {
-                                    Some(s)
-                                } else {
-                                    None
-                                }
-                            })
-                            .ok_or_else(|| anyhow!("Missing or invalid 'did'"))?
-                            .clone(),
-                        rev: m
-                            .get("rev")
-                            .and_then(|v| {
-                                if let VendorIpld::String(s) = v {
-                                    Some(s)
-                                } else {
-                                    None
-                                }
-                            })
-                            .ok_or_else(|| anyhow!("Missing or invalid 'rev'"))?
-                            .clone(),
-                        data: m
-                            .get("data")
-                            .and_then(|v| {
-                                if let VendorIpld::Link(cid) = v {
-                                    Some(cid)
-                                } else {
-                                    None
-                                }
-                            })
-                            .ok_or_else(|| anyhow!("Missing or invalid 'data'"))?
-                            .clone(),
-                        prev: m.get("prev").and_then(|v| {
-                            if let VendorIpld::Link(cid) = v {
-                                Some(cid.clone())
-                            } else {
-                                None
-                            }
-                        }),
-                        version: m
-                            .get("version")
-                            .and_then(|v| {
-                                if let VendorIpld::Integer(i) = v {
-                                    Some(*i)
-                                } else {
-                                    None
-                                }
-                            })
-                            .ok_or_else(|| anyhow!("Missing or invalid 'version'"))?
-                            as u8,
-                        sig: m
-                            .get("sig")
-                            .and_then(|v| {
-                                if let VendorIpld::Bytes(b) = v {
-                                    Some(b)
-                                } else {
-                                    None
-                                }
-                            })
-                            .ok_or_else(|| anyhow!("Missing or invalid 'sig'"))?
-                            .clone(),
-                    },
-                    _ => return Err(anyhow!("Invalid Ipld format for Commit")),
-                };
+                let commit = decode_commit(commit_cid, commit_bytes)?;
                 let data = MST::load(storage.clone(), commit.data, None)?;
                 Ok(Repo::new(storage.clone(), data, commit, commit_cid))
             }
@@ -694,6 +701,79 @@ impl Repo {
     }
 }
 
+pub fn decode_commit(cid: Cid, bytes: Vec<u8>) -> Result<Commit> {
+    let block = Block::<DefaultParams>::new(cid, bytes)?;
+    let ipld = block.decode::<DagCborCodec, VendorIpld>()?;
+    // Convert Ipld to Commit
+    let commit: Commit = match ipld {
+        VendorIpld::Map(m) => Commit {
+            did: m
+                .get("did")
+                .and_then(|v| {
+                    if let VendorIpld::String(s) = v {
+                        Some(s)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| anyhow!("Missing or invalid 'did'"))?
+                .clone(),
+            rev: m
+                .get("rev")
+                .and_then(|v| {
+                    if let VendorIpld::String(s) = v {
+                        Some(s)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| anyhow!("Missing or invalid 'rev'"))?
+                .clone(),
+            data: m
+                .get("data")
+                .and_then(|v| {
+                    if let VendorIpld::Link(cid) = v {
+                        Some(cid)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| anyhow!("Missing or invalid 'data'"))?
+                .clone(),
+            prev: m.get("prev").and_then(|v| {
+                if let VendorIpld::Link(cid) = v {
+                    Some(cid.clone())
+                } else {
+                    None
+                }
+            }),
+            version: m
+                .get("version")
+                .and_then(|v| {
+                    if let VendorIpld::Integer(i) = v {
+                        Some(*i)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| anyhow!("Missing or invalid 'version'"))? as u8,
+            sig: m
+                .get("sig")
+                .and_then(|v| {
+                    if let VendorIpld::Bytes(b) = v {
+                        Some(b)
+                    } else {
+                        None
+                    }
+                })
+                .ok_or_else(|| anyhow!("Missing or invalid 'sig'"))?
+                .clone(),
+        },
+        _ => return Err(anyhow!("Invalid Ipld format for Commit")),
+    };
+    Ok(commit)
+}
+
 pub fn blobs_for_write(record: RepoRecord, validate: bool) -> Result<Vec<PreparedBlobRef>> {
     let refs = find_blob_refs(Lex::Map(record.clone()), None, None);
     let record_type = match record.get("$type") {
@@ -935,6 +1015,7 @@ pub mod error;
 pub mod mst;
 pub mod parse;
 pub mod preference;
+pub mod quota;
 pub mod record;
 pub mod sync;
 pub mod types;
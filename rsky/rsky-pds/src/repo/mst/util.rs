@@ -3,12 +3,15 @@ use crate::common;
 use crate::common::ipld;
 use crate::common::ipld::cid_for_cbor;
 use crate::common::tid::Ticker;
+use crate::repo::block_map::BlockMap;
+use crate::repo::parse::get_and_parse_by_kind;
 use crate::storage::SqlRepoReader;
 use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use lexicon_cid::Cid;
 use rand::{thread_rng, Rng};
 use regex::Regex;
+use serde_cbor::Value as CborValue;
 use serde_json::json;
 use sha2::{Digest, Sha256};
 use std::collections::BTreeMap;
@@ -148,6 +151,38 @@ pub fn deserialize_node_data(
     Ok(entries)
 }
 
+/// Collects the leaves of the tree rooted at `root` from a standalone set of blocks
+/// (e.g. a CAR being imported), in key order
+pub fn leaves_from_blocks(blocks: &BlockMap, root: Cid) -> Result<Vec<Leaf>> {
+    let data = get_and_parse_by_kind(blocks, root, |obj: &CborValue| {
+        serde_cbor::value::from_value::<NodeData>(obj.clone()).is_ok()
+    })?;
+    let data: NodeData = serde_cbor::value::from_value(data.obj)?;
+    let mut leaves: Vec<Leaf> = Vec::new();
+    if let Some(l) = data.l {
+        leaves.append(&mut leaves_from_blocks(blocks, l)?);
+    }
+    let mut last_key: String = "".to_owned();
+    for entry in data.e {
+        let key_str = str::from_utf8(entry.k.as_ref())?;
+        let p = usize::try_from(entry.p)?;
+        if p > last_key.len() {
+            return Err(anyhow!("Invalid MST prefix length: {}", p));
+        }
+        let key = format!("{}{}", &last_key[0..p], key_str);
+        ensure_valid_mst_key(&key)?;
+        leaves.push(Leaf {
+            key: key.clone(),
+            value: entry.v,
+        });
+        last_key = key;
+        if let Some(t) = entry.t {
+            leaves.append(&mut leaves_from_blocks(blocks, t)?);
+        }
+    }
+    Ok(leaves)
+}
+
 pub fn layer_for_entries(entries: Vec<NodeEntry>) -> Result<Option<u32>> {
     let first_leaf = entries.into_iter().find(|entry| entry.is_leaf());
     if let Some(f) = first_leaf {
@@ -0,0 +1,205 @@
+use crate::common;
+use crate::common::ipld::data_to_cbor_block;
+use crate::config::SERVER_CONFIG;
+use crate::db::establish_connection;
+use crate::models::models;
+use crate::repo::types::{Lex, PreparedWrite};
+use crate::repo::util::lex_to_ipld;
+use anyhow::Result;
+use diesel::dsl::sum;
+use diesel::*;
+use lazy_static::lazy_static;
+use rsky_lexicon::com::rsky::server::{AccountLimits, AccountUsage};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// How long a summed usage is trusted before quota checks sum it again
+const USAGE_TTL: Duration = Duration::from_secs(60);
+/// Past this many cached accounts, stale entries are dropped on insert
+const USAGE_CACHE_SWEEP: usize = 10_000;
+
+lazy_static! {
+    static ref USAGE_CACHE: Mutex<HashMap<String, (Instant, AccountUsage)>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Error, Debug)]
+pub enum QuotaError {
+    #[error("QuotaExceeded: blob storage is limited to {max} bytes and {used} bytes are in use")]
+    BlobBytes { used: i64, max: i64 },
+    #[error("QuotaExceeded: accounts are limited to {max} records")]
+    Records { max: i64 },
+    #[error("QuotaExceeded: repos are limited to {max} bytes")]
+    RepoBytes { max: i64 },
+}
+
+pub async fn get_usage(did: &String) -> Result<AccountUsage> {
+    use crate::schema::pds::blob::dsl as BlobSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+    let conn = &mut establish_connection()?;
+
+    let (blob_bytes, blob_count) = BlobSchema::blob
+        .filter(BlobSchema::did.eq(did))
+        .select((sum(BlobSchema::size), dsl::count(BlobSchema::cid)))
+        .first::<(Option<i64>, i64)>(conn)?;
+    let record_count = RecordSchema::record
+        .filter(RecordSchema::did.eq(did))
+        .count()
+        .get_result::<i64>(conn)?;
+    let repo_bytes = RepoBlockSchema::repo_block
+        .filter(RepoBlockSchema::did.eq(did))
+        .select(sum(RepoBlockSchema::size))
+        .first::<Option<i64>>(conn)?;
+    Ok(AccountUsage {
+        blob_bytes: blob_bytes.unwrap_or(0),
+        blob_count,
+        record_count,
+        repo_bytes: repo_bytes.unwrap_or(0),
+    })
+}
+
+/// Usage for quota checks: summed at most once per `USAGE_TTL` and bumped by the checks
+/// that pass in between, so a write does not scan every block and blob of the account
+async fn get_cached_usage(did: &String) -> Result<AccountUsage> {
+    if let Some((at, usage)) = USAGE_CACHE.lock().unwrap().get(did) {
+        if at.elapsed() < USAGE_TTL {
+            return Ok(usage.clone());
+        }
+    }
+    let usage = get_usage(did).await?;
+    let mut cache = USAGE_CACHE.lock().unwrap();
+    if cache.len() >= USAGE_CACHE_SWEEP {
+        cache.retain(|_, (at, _)| at.elapsed() < USAGE_TTL);
+    }
+    cache.insert(did.clone(), (Instant::now(), usage.clone()));
+    Ok(usage)
+}
+
+fn bump_cached_usage(did: &String, bump: impl FnOnce(&mut AccountUsage)) {
+    if let Some((_, usage)) = USAGE_CACHE.lock().unwrap().get_mut(did) {
+        bump(usage);
+    }
+}
+
+/// Drops the account's cached usage, e.g. once it is deleted
+pub fn forget_usage(did: &String) {
+    USAGE_CACHE.lock().unwrap().remove(did);
+}
+
+/// The account's overrides, falling back to the server defaults for any limit not set
+pub async fn get_limits(did: &String) -> Result<AccountLimits> {
+    use crate::schema::pds::account_quota::dsl as AccountQuotaSchema;
+    let conn = &mut establish_connection()?;
+
+    let quota = AccountQuotaSchema::account_quota
+        .find(did)
+        .select(models::AccountQuota::as_select())
+        .first(conn)
+        .optional()?;
+    let (max_blob_bytes, max_records, max_repo_bytes) = match quota {
+        Some(quota) => (
+            quota.max_blob_bytes,
+            quota.max_records,
+            quota.max_repo_bytes,
+        ),
+        None => (None, None, None),
+    };
+    Ok(AccountLimits {
+        max_blob_bytes: max_blob_bytes.or(SERVER_CONFIG.quotas.max_blob_bytes),
+        max_records: max_records.or(SERVER_CONFIG.quotas.max_records),
+        max_repo_bytes: max_repo_bytes.or(SERVER_CONFIG.quotas.max_repo_bytes),
+    })
+}
+
+pub async fn set_limits(did: &String, limits: AccountLimits) -> Result<()> {
+    use crate::schema::pds::account_quota::dsl as AccountQuotaSchema;
+    let conn = &mut establish_connection()?;
+    let now = common::now();
+
+    insert_into(AccountQuotaSchema::account_quota)
+        .values((
+            AccountQuotaSchema::did.eq(did),
+            AccountQuotaSchema::maxBlobBytes.eq(limits.max_blob_bytes),
+            AccountQuotaSchema::maxRecords.eq(limits.max_records),
+            AccountQuotaSchema::maxRepoBytes.eq(limits.max_repo_bytes),
+            AccountQuotaSchema::updatedAt.eq(&now),
+        ))
+        .on_conflict(AccountQuotaSchema::did)
+        .do_update()
+        .set((
+            AccountQuotaSchema::maxBlobBytes.eq(limits.max_blob_bytes),
+            AccountQuotaSchema::maxRecords.eq(limits.max_records),
+            AccountQuotaSchema::maxRepoBytes.eq(limits.max_repo_bytes),
+            AccountQuotaSchema::updatedAt.eq(&now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Fails with a `QuotaError` if storing `incoming_bytes` more would exceed the
+/// account's blob storage quota
+pub async fn check_blob_upload(did: &String, incoming_bytes: i64) -> Result<()> {
+    let limits = get_limits(did).await?;
+    if let Some(max) = limits.max_blob_bytes {
+        let usage = get_cached_usage(did).await?;
+        if usage.blob_bytes + incoming_bytes > max {
+            return Err(QuotaError::BlobBytes {
+                used: usage.blob_bytes,
+                max,
+            }
+            .into());
+        }
+        bump_cached_usage(did, |usage| usage.blob_bytes += incoming_bytes);
+    }
+    Ok(())
+}
+
+/// Fails with a `QuotaError` if applying `writes` would exceed the account's record or
+/// repo size quota. The repo grows by at least the new record blocks; MST nodes are left
+/// out. Writes that only delete are always allowed so accounts can get back under their
+/// quota.
+pub async fn check_writes(did: &String, writes: &[PreparedWrite]) -> Result<()> {
+    let mut new_records: i64 = 0;
+    let mut new_bytes: i64 = 0;
+    let mut only_deletes = true;
+    for write in writes {
+        match write {
+            PreparedWrite::Create(w) | PreparedWrite::Update(w) => {
+                if matches!(write, PreparedWrite::Create(_)) {
+                    new_records += 1;
+                }
+                only_deletes = false;
+                let block = data_to_cbor_block(&lex_to_ipld(Lex::Map(w.record.clone())))?;
+                new_bytes += block.data().len() as i64;
+            }
+            PreparedWrite::Delete(_) => new_records -= 1,
+        }
+    }
+    if only_deletes {
+        return Ok(());
+    }
+
+    let limits = get_limits(did).await?;
+    if limits.max_records.is_none() && limits.max_repo_bytes.is_none() {
+        return Ok(());
+    }
+    let usage = get_cached_usage(did).await?;
+    if let Some(max) = limits.max_records {
+        if new_records > 0 && usage.record_count + new_records > max {
+            return Err(QuotaError::Records { max }.into());
+        }
+    }
+    if let Some(max) = limits.max_repo_bytes {
+        if usage.repo_bytes + new_bytes > max {
+            return Err(QuotaError::RepoBytes { max }.into());
+        }
+    }
+    bump_cached_usage(did, |usage| {
+        usage.record_count += new_records;
+        usage.repo_bytes += new_bytes;
+    });
+    Ok(())
+}
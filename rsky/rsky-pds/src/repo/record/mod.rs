@@ -1,6 +1,11 @@
 use crate::common;
 use crate::db::establish_connection;
+use crate::db::pagination::{
+    paginate, KeySet, KeySetPaginateOpts, StringKeySet, TimeKeyResult, TimeKeySet,
+};
 use crate::models::{models, Backlink, Record};
+use crate::notification;
+use crate::push;
 use crate::repo::types::{Ids, Lex, RepoRecord, WriteOpAction};
 use crate::repo::util::cbor_to_lex_record;
 use crate::storage::Ipld;
@@ -24,6 +29,27 @@ pub struct GetRecord {
     pub takedown_ref: Option<String>,
 }
 
+/// Records in a collection are listed in rkey order, with the last rkey as the cursor
+pub const LIST_RECORDS_KEYSET: StringKeySet = StringKeySet {
+    key: r#""record"."rkey""#,
+};
+
+/// Backlinks are listed newest first. A record can link to the same subject through
+/// several paths (a reply to a thread root), so the path is part of the key.
+pub const BACKLINKS_KEYSET: TimeKeySet = TimeKeySet {
+    created_at: r#""record"."indexedAt""#,
+    key: r#"("backlink"."uri" || ' ' || "backlink"."path")"#,
+};
+
+pub struct ListBacklinksOpts {
+    /// An at-uri or did
+    pub subject: String,
+    pub collection: Option<String>,
+    pub path: Option<String>,
+    pub limit: i64,
+    pub cursor: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct RecordsForCollection {
     pub uri: String,
@@ -66,6 +92,155 @@ pub fn get_backlinks(uri: &String, record: &RepoRecord) -> Result<Vec<models::Ba
     Ok(Vec::new())
 }
 
+/// The string at a dotted `path` in a record, e.g. `reply.parent.uri`
+fn get_path<'a>(record: &'a RepoRecord, path: &str) -> Option<&'a String> {
+    let keys = path.split(".").collect::<Vec<&str>>();
+    let (last, parents) = keys.split_last()?;
+    let mut map = record;
+    for key in parents {
+        match map.get(*key) {
+            Some(Lex::Map(child)) => map = child,
+            _ => return None,
+        }
+    }
+    match map.get(*last) {
+        Some(Lex::Ipld(Ipld::Json(JsonValue::String(value)))) => Some(value),
+        _ => None,
+    }
+}
+
+/// Links that reference other records or accounts without ever conflicting with each
+/// other: reply parents/roots, quoted records and list items. Kept apart from
+/// `get_backlinks` since those links are also used for conflict detection.
+pub fn get_reference_backlinks(uri: &String, record: &RepoRecord) -> Result<Vec<models::Backlink>> {
+    let paths: Vec<&str> = match record.get("$type") {
+        Some(Lex::Ipld(Ipld::Json(JsonValue::String(record_type))))
+            if record_type == Ids::AppBskyFeedPost.as_str() =>
+        {
+            vec![
+                "reply.parent.uri",
+                "reply.root.uri",
+                // app.bsky.embed.record
+                "embed.record.uri",
+                // app.bsky.embed.recordWithMedia
+                "embed.record.record.uri",
+            ]
+        }
+        Some(Lex::Ipld(Ipld::Json(JsonValue::String(record_type))))
+            if record_type == Ids::AppBskyGraphListitem.as_str() =>
+        {
+            vec!["subject", "list"]
+        }
+        _ => return Ok(Vec::new()),
+    };
+    Ok(paths
+        .into_iter()
+        .filter_map(|path| {
+            get_path(record, path).map(|link_to| models::Backlink {
+                uri: uri.clone(),
+                path: path.to_owned(),
+                link_to: link_to.clone(),
+            })
+        })
+        .collect())
+}
+
+/// Adds reference backlinks for up to `limit` posts and list items from after `cursor`,
+/// ordered by uri, for records indexed before those links were. Returns how many records
+/// were processed and the cursor to continue from, if there may be more.
+pub async fn reindex_reference_backlinks(
+    cursor: &String,
+    limit: i64,
+) -> Result<(u64, Option<String>)> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+    let conn = &mut establish_connection()?;
+
+    let records: Vec<(String, Vec<u8>)> = RecordSchema::record
+        .inner_join(
+            RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                .eq(RecordSchema::cid)
+                .and(RepoBlockSchema::did.eq(RecordSchema::did))),
+        )
+        .filter(RecordSchema::collection.eq_any(vec![
+            Ids::AppBskyFeedPost.as_str(),
+            Ids::AppBskyGraphListitem.as_str(),
+        ]))
+        .filter(RecordSchema::uri.gt(cursor))
+        .select((RecordSchema::uri, RepoBlockSchema::content))
+        .order(RecordSchema::uri.asc())
+        .limit(limit)
+        .load(conn)?;
+    let next_cursor = match records.len() as i64 == limit {
+        true => records.last().map(|(uri, _)| uri.clone()),
+        false => None,
+    };
+    let mut backlinks = Vec::new();
+    let processed = records.len() as u64;
+    for (uri, content) in records {
+        match cbor_to_lex_record(content) {
+            Ok(record) => backlinks.extend(get_reference_backlinks(&uri, &record)?),
+            Err(error) => eprintln!("@LOG: ERROR: failed to reindex backlinks for {uri}: {error}"),
+        }
+    }
+    if !backlinks.is_empty() {
+        insert_into(BacklinkSchema::backlink)
+            .values(&backlinks)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+    }
+    Ok((processed, next_cursor))
+}
+
+/// Records from every repo on this PDS linking to a subject, with the path of each link
+pub async fn list_backlinks(opts: ListBacklinksOpts) -> Result<Vec<(Record, String)>> {
+    use crate::schema::pds::backlink::dsl as BacklinkSchema;
+    use crate::schema::pds::record::dsl as RecordSchema;
+    let conn = &mut establish_connection()?;
+
+    let ListBacklinksOpts {
+        subject,
+        collection,
+        path,
+        limit,
+        cursor,
+    } = opts;
+    let mut builder = RecordSchema::record
+        .inner_join(BacklinkSchema::backlink.on(BacklinkSchema::uri.eq(RecordSchema::uri)))
+        .filter(BacklinkSchema::linkTo.eq(subject))
+        .filter(RecordSchema::takedownRef.is_null())
+        .select((Record::as_select(), BacklinkSchema::path))
+        .into_boxed();
+    if let Some(collection) = collection {
+        builder = builder.filter(RecordSchema::collection.eq(collection));
+    }
+    if let Some(path) = path {
+        builder = builder.filter(BacklinkSchema::path.eq(path));
+    }
+    let res = paginate(
+        builder,
+        &BACKLINKS_KEYSET,
+        KeySetPaginateOpts {
+            limit: Some(limit),
+            cursor,
+            direction: None,
+        },
+    )?
+    .load::<(Record, String)>(conn)?;
+    Ok(res)
+}
+
+pub fn backlinks_cursor(backlinks: &[(Record, String)]) -> Result<Option<String>> {
+    match backlinks.last() {
+        None => Ok(None),
+        Some((record, path)) => BACKLINKS_KEYSET.pack_from_result(&[TimeKeyResult {
+            created_at: record.indexed_at.clone(),
+            key: format!("{} {path}", record.uri),
+        }]),
+    }
+}
+
 pub struct RecordReader {
     pub did: String,
 }
@@ -119,7 +294,6 @@ impl RecordReader {
         };
         let mut builder = RecordSchema::record
             .inner_join(RepoBlockSchema::repo_block.on(RepoBlockSchema::cid.eq(RecordSchema::cid)))
-            .limit(limit)
             .select((models::Record::as_select(), models::RepoBlock::as_select()))
             .filter(RecordSchema::did.eq(&self.did))
             .filter(RecordSchema::collection.eq(collection))
@@ -127,19 +301,7 @@ impl RecordReader {
         if !include_soft_deleted {
             builder = builder.filter(RecordSchema::takedownRef.is_null());
         }
-        if reverse {
-            builder = builder.order(RecordSchema::rkey.asc());
-        } else {
-            builder = builder.order(RecordSchema::rkey.desc());
-        }
-
-        if let Some(cursor) = cursor {
-            if reverse {
-                builder = builder.filter(RecordSchema::rkey.gt(cursor));
-            } else {
-                builder = builder.filter(RecordSchema::rkey.lt(cursor));
-            }
-        } else {
+        if cursor.is_none() {
             if let Some(rkey_start) = rkey_start {
                 builder = builder.filter(RecordSchema::rkey.gt(rkey_start));
             }
@@ -147,6 +309,15 @@ impl RecordReader {
                 builder = builder.filter(RecordSchema::rkey.lt(rkey_end));
             }
         }
+        let builder = paginate(
+            builder,
+            &LIST_RECORDS_KEYSET,
+            KeySetPaginateOpts {
+                limit: Some(limit),
+                cursor,
+                direction: Some(if reverse { "asc" } else { "desc" }.to_string()),
+            },
+        )?;
         let res: Vec<(models::Record, models::RepoBlock)> = builder.load(conn)?;
         Ok(res
             .into_iter()
@@ -343,6 +514,7 @@ impl RecordReader {
         action: Option<WriteOpAction>, // Create or update with a default of create
         repo_rev: String,
         timestamp: Option<String>,
+        notify: bool, // False for records that aren't new, e.g. imported ones
     ) -> Result<()> {
         println!("@LOG DEBUG RecordReader::index_record, indexing record {uri}");
         let action = action.unwrap_or(WriteOpAction::Create);
@@ -387,13 +559,24 @@ impl RecordReader {
 
                 if let Some(record) = record {
                     // Maintain backlinks
-                    let backlinks = get_backlinks(&uri, &record)?;
+                    let mut backlinks = get_backlinks(&uri, &record)?;
+                    backlinks.extend(get_reference_backlinks(&uri, &record)?);
                     if let WriteOpAction::Update = action {
                         // On update just recreate backlinks from scratch for the record, so we can clear out
                         // the old ones. E.g. for weird cases like updating a follow to be for a different did.
                         self.remove_backlinks_by_uri(&uri).await?;
                     }
                     self.add_backlinks(backlinks).await?;
+                    if let (WriteOpAction::Create, true) = (&action, notify) {
+                        let notifications = notification::without_hidden(
+                            &uri,
+                            notification::get_notifications(&uri, &record)?,
+                        )?;
+                        let cid = cid.to_string();
+                        notification::index_notifications(&uri, &cid, &indexed_at, &notifications)
+                            .await?;
+                        push::dispatch_for_record(&uri, &cid, &indexed_at, notifications);
+                    }
                 }
                 println!("@LOG DEBUG RecordReader::index_record, indexed record {uri}");
                 Ok(())
@@ -416,6 +599,7 @@ impl RecordReader {
         delete(BacklinkSchema::backlink)
             .filter(BacklinkSchema::uri.eq(&uri))
             .execute(conn)?;
+        notification::delete_notifications_for_record(&uri).await?;
         println!("@LOG DEBUG RecordReader::delete_record, deleted indexed record {uri}");
         Ok(())
     }
@@ -499,6 +499,7 @@ pub enum Ids {
     ToolsOzoneTeamDeleteMember,
     ToolsOzoneTeamListMembers,
     ToolsOzoneTeamUpdateMember,
+    ChatBskyActorDeclaration,
     ChatBskyActorDeleteAccount,
     ChatBskyActorExportAccountData,
     ChatBskyConvoDeleteMessageForSelf,
@@ -702,6 +703,7 @@ impl Ids {
             Ids::ToolsOzoneTeamDeleteMember => "tools.ozone.team.deleteMember",
             Ids::ToolsOzoneTeamListMembers => "tools.ozone.team.listMembers",
             Ids::ToolsOzoneTeamUpdateMember => "tools.ozone.team.updateMember",
+            Ids::ChatBskyActorDeclaration => "chat.bsky.actor.declaration",
             Ids::ChatBskyActorDeleteAccount => "chat.bsky.actor.deleteAccount",
             Ids::ChatBskyActorExportAccountData => "chat.bsky.actor.exportAccountData",
             Ids::ChatBskyConvoDeleteMessageForSelf => "chat.bsky.convo.deleteMessageForSelf",
@@ -927,6 +929,7 @@ impl Ids {
             "tools.ozone.team.deleteMember" => Ok(Ids::ToolsOzoneTeamDeleteMember),
             "tools.ozone.team.listMembers" => Ok(Ids::ToolsOzoneTeamListMembers),
             "tools.ozone.team.updateMember" => Ok(Ids::ToolsOzoneTeamUpdateMember),
+            "chat.bsky.actor.declaration" => Ok(Ids::ChatBskyActorDeclaration),
             "chat.bsky.actor.deleteAccount" => Ok(Ids::ChatBskyActorDeleteAccount),
             "chat.bsky.actor.exportAccountData" => Ok(Ids::ChatBskyActorExportAccountData),
             "chat.bsky.convo.deleteMessageForSelf" => Ok(Ids::ChatBskyConvoDeleteMessageForSelf),
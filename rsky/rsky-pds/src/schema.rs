@@ -10,6 +10,7 @@ pub mod pds {
             createdAt -> Varchar,
             invitesDisabled -> Int2,
             emailConfirmedAt -> Nullable<Varchar>,
+            emailAuthFactor -> Bool,
         }
     }
 
@@ -22,6 +23,16 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.account_quota (did) {
+            did -> Varchar,
+            maxBlobBytes -> Nullable<Int8>,
+            maxRecords -> Nullable<Int8>,
+            maxRepoBytes -> Nullable<Int8>,
+            updatedAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.actor (did) {
             did -> Varchar,
@@ -33,6 +44,14 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.actor_mute (did, subjectDid) {
+            did -> Varchar,
+            subjectDid -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.app_password (did, name) {
             did -> Varchar,
@@ -64,6 +83,69 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.blob_upload (id) {
+            id -> Varchar,
+            did -> Varchar,
+            mimeType -> Varchar,
+            size -> Int8,
+            received -> Int8,
+            cid -> Varchar,
+            createdAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.chat_convo (id) {
+            id -> Varchar,
+            rev -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.chat_log (id) {
+            id -> Int8,
+            did -> Varchar,
+            convoId -> Varchar,
+            rev -> Varchar,
+            logType -> Varchar,
+            messageId -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.chat_member (convoId, did) {
+            convoId -> Varchar,
+            did -> Varchar,
+            muted -> Bool,
+            lastReadRev -> Nullable<Varchar>,
+            leftAt -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.chat_message (convoId, id) {
+            convoId -> Varchar,
+            id -> Varchar,
+            rev -> Varchar,
+            sender -> Varchar,
+            text -> Text,
+            facets -> Nullable<Text>,
+            embed -> Nullable<Text>,
+            sentAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.chat_message_deletion (did, convoId, messageId) {
+            did -> Varchar,
+            convoId -> Varchar,
+            messageId -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.did_doc (did) {
             did -> Varchar,
@@ -100,6 +182,112 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.job_cursor (name) {
+            name -> Varchar,
+            cursor -> Varchar,
+            completedAt -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.label (seq) {
+            seq -> Int8,
+            src -> Varchar,
+            uri -> Varchar,
+            cid -> Nullable<Varchar>,
+            val -> Varchar,
+            neg -> Bool,
+            cts -> Varchar,
+            exp -> Nullable<Varchar>,
+            sig -> Bytea,
+        }
+    }
+
+    diesel::table! {
+        pds.moderation_report (id) {
+            id -> Int4,
+            reasonType -> Varchar,
+            reason -> Nullable<Varchar>,
+            subjectDid -> Varchar,
+            subjectUri -> Nullable<Varchar>,
+            subjectCid -> Nullable<Varchar>,
+            reportedBy -> Varchar,
+            createdAt -> Varchar,
+            resolvedAt -> Nullable<Varchar>,
+            resolvedBy -> Nullable<Varchar>,
+            resolutionNote -> Nullable<Varchar>,
+        }
+    }
+
+    diesel::table! {
+        pds.notification (id) {
+            id -> Int8,
+            did -> Varchar,
+            recordUri -> Varchar,
+            recordCid -> Varchar,
+            author -> Varchar,
+            reason -> Varchar,
+            reasonSubject -> Nullable<Varchar>,
+            sortAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.notification_seen (did) {
+            did -> Varchar,
+            seenAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_authorized_client (did, clientId) {
+            did -> Varchar,
+            clientId -> Varchar,
+            scope -> Varchar,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_request (id) {
+            id -> Varchar,
+            clientId -> Varchar,
+            parameters -> Text,
+            dpopJkt -> Nullable<Varchar>,
+            did -> Nullable<Varchar>,
+            code -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.oauth_token (id) {
+            id -> Varchar,
+            did -> Varchar,
+            clientId -> Varchar,
+            scope -> Varchar,
+            dpopJkt -> Varchar,
+            refreshToken -> Varchar,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+            expiresAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.push_registration (did, token) {
+            did -> Varchar,
+            token -> Varchar,
+            platform -> Varchar,
+            serviceDid -> Varchar,
+            appId -> Varchar,
+            createdAt -> Varchar,
+        }
+    }
+
     diesel::table! {
         pds.record (uri) {
             uri -> Varchar,
@@ -128,6 +316,11 @@ pub mod pds {
             expiresAt -> Varchar,
             nextId -> Nullable<Varchar>,
             appPasswordName -> Nullable<Varchar>,
+            sessionId -> Varchar,
+            createdAt -> Nullable<Varchar>,
+            lastRefreshedAt -> Nullable<Varchar>,
+            userAgent -> Nullable<Varchar>,
+            ip -> Nullable<Varchar>,
         }
     }
 
@@ -161,22 +354,82 @@ pub mod pds {
         }
     }
 
+    diesel::table! {
+        pds.search_post (uri) {
+            uri -> Varchar,
+            did -> Varchar,
+            text -> Text,
+            langs -> Array<Varchar>,
+            tags -> Array<Varchar>,
+            mentions -> Array<Varchar>,
+            sortAt -> Varchar,
+        }
+    }
+
+    diesel::table! {
+        pds.search_profile (did) {
+            did -> Varchar,
+            displayName -> Nullable<Varchar>,
+            description -> Nullable<Text>,
+        }
+    }
+
+    diesel::table! {
+        pds.video_job (id) {
+            id -> Varchar,
+            did -> Varchar,
+            state -> Varchar,
+            progress -> Int4,
+            tempKey -> Varchar,
+            mimeType -> Varchar,
+            size -> Int8,
+            blobCid -> Nullable<Varchar>,
+            width -> Nullable<Int4>,
+            height -> Nullable<Int4>,
+            durationMs -> Nullable<Int8>,
+            error -> Nullable<Varchar>,
+            message -> Nullable<Varchar>,
+            createdAt -> Varchar,
+            updatedAt -> Varchar,
+        }
+    }
+
     diesel::allow_tables_to_appear_in_same_query!(
         account,
         account_pref,
+        account_quota,
         actor,
+        actor_mute,
         app_password,
         backlink,
         blob,
+        blob_upload,
+        chat_convo,
+        chat_log,
+        chat_member,
+        chat_message,
+        chat_message_deletion,
         did_doc,
         email_token,
         invite_code,
         invite_code_use,
+        job_cursor,
+        label,
+        moderation_report,
+        notification,
+        notification_seen,
+        oauth_authorized_client,
+        oauth_request,
+        oauth_token,
+        push_registration,
         record,
         record_blob,
         refresh_token,
         repo_block,
         repo_root,
         repo_seq,
+        search_post,
+        search_profile,
+        video_job,
     );
 }
@@ -0,0 +1,420 @@
+use crate::account_manager::helpers::account::escape_like;
+use crate::account_manager::AccountManager;
+use crate::common::RFC3339_VARIANT;
+use crate::db::establish_connection;
+use crate::db::pagination::{paginate, KeySet, KeySetPaginateOpts, TimeKeyResult, TimeKeySet};
+use crate::models::models;
+use crate::notification::did_from_uri;
+use crate::repo::types::{Ids, RepoRecord};
+use crate::repo::util::cbor_to_lex_record;
+use anyhow::Result;
+use chrono::DateTime;
+use diesel::dsl::sql;
+use diesel::sql_types::{Bool, Text};
+use diesel::upsert::excluded;
+use diesel::*;
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+
+const MENTION_FEATURE_TYPE: &str = "app.bsky.richtext.facet#mention";
+const TAG_FEATURE_TYPE: &str = "app.bsky.richtext.facet#tag";
+
+/// Posts are searched newest first by `sortAt`, the earlier of `createdAt` and `indexedAt`
+pub const SEARCH_POST_KEYSET: TimeKeySet = TimeKeySet {
+    created_at: r#""search_post"."sortAt""#,
+    key: r#""search_post"."uri""#,
+};
+
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("Invalid datetime: {0}")]
+    InvalidDatetime(String),
+    #[error("Invalid cursor")]
+    InvalidCursor,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchPostsOpts {
+    /// Search query string, in `websearch_to_tsquery` syntax
+    pub q: String,
+    /// Only posts by this account, a handle or did
+    pub author: Option<String>,
+    /// Only posts mentioning this account, a handle or did
+    pub mentions: Option<String>,
+    pub lang: Option<String>,
+    /// Only posts at or after this datetime
+    pub since: Option<String>,
+    /// Only posts before this datetime
+    pub until: Option<String>,
+    /// Only posts with all of these hashtags, without the leading `#`
+    pub tags: Vec<String>,
+    pub limit: i64,
+    pub cursor: Option<String>,
+}
+
+fn json_str<'a>(value: &'a JsonValue, pointer: &str) -> Option<&'a str> {
+    value.pointer(pointer)?.as_str()
+}
+
+fn normalize_datetime(datetime: &str) -> Result<String> {
+    let datetime = DateTime::parse_from_rfc3339(datetime)
+        .map_err(|_| SearchError::InvalidDatetime(datetime.to_string()))?;
+    Ok(format!("{}", datetime.naive_utc().format(RFC3339_VARIANT)))
+}
+
+/// Resolves a handle or did to the did of an account on this PDS
+async fn resolve_actor(actor: &String) -> Result<Option<String>> {
+    if actor.starts_with("did:") {
+        return Ok(Some(actor.clone()));
+    }
+    let handle = actor.trim_start_matches('@').to_lowercase();
+    match AccountManager::get_account(&handle, None).await? {
+        Some(account) => Ok(Some(account.did)),
+        None => Ok(None),
+    }
+}
+
+fn get_post_row(uri: &String, record: &JsonValue, indexed_at: &String) -> models::SearchPost {
+    let features = record
+        .get("facets")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|facet| facet.get("features")?.as_array())
+        .flatten()
+        .collect::<Vec<&JsonValue>>();
+    let mut tags = record
+        .get("tags")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(JsonValue::as_str)
+        .chain(
+            features
+                .iter()
+                .filter(|feature| json_str(feature, "/$type") == Some(TAG_FEATURE_TYPE))
+                .filter_map(|feature| json_str(feature, "/tag")),
+        )
+        .map(|tag| tag.trim_start_matches('#').to_lowercase())
+        .collect::<Vec<String>>();
+    tags.sort();
+    tags.dedup();
+    let mut mentions = features
+        .iter()
+        .filter(|feature| json_str(feature, "/$type") == Some(MENTION_FEATURE_TYPE))
+        .filter_map(|feature| json_str(feature, "/did"))
+        .map(|did| did.to_string())
+        .collect::<Vec<String>>();
+    mentions.sort();
+    mentions.dedup();
+    let langs = record
+        .get("langs")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(JsonValue::as_str)
+        .map(|lang| lang.to_string())
+        .collect::<Vec<String>>();
+    // Clients can backdate createdAt, so sort on whichever came first
+    let sort_at = match json_str(record, "/createdAt").map(normalize_datetime) {
+        Some(Ok(created_at)) if &created_at < indexed_at => created_at,
+        _ => indexed_at.clone(),
+    };
+
+    models::SearchPost {
+        uri: uri.clone(),
+        did: did_from_uri(uri).unwrap_or_default(),
+        text: json_str(record, "/text").unwrap_or_default().to_string(),
+        langs,
+        tags,
+        mentions,
+        sort_at,
+    }
+}
+
+/// Adds a post or profile to the search index, replacing any earlier version
+pub async fn index_record(uri: &String, record: &RepoRecord, indexed_at: &String) -> Result<()> {
+    use crate::schema::pds::search_post::dsl as SearchPostSchema;
+    use crate::schema::pds::search_profile::dsl as SearchProfileSchema;
+    let record_json = serde_json::to_value(record)?;
+    let record_type = json_str(&record_json, "/$type").unwrap_or_default();
+
+    if record_type == Ids::AppBskyFeedPost.as_str() {
+        let conn = &mut establish_connection()?;
+        insert_into(SearchPostSchema::search_post)
+            .values(get_post_row(uri, &record_json, indexed_at))
+            .on_conflict(SearchPostSchema::uri)
+            .do_update()
+            .set((
+                SearchPostSchema::text.eq(excluded(SearchPostSchema::text)),
+                SearchPostSchema::langs.eq(excluded(SearchPostSchema::langs)),
+                SearchPostSchema::tags.eq(excluded(SearchPostSchema::tags)),
+                SearchPostSchema::mentions.eq(excluded(SearchPostSchema::mentions)),
+                SearchPostSchema::sortAt.eq(excluded(SearchPostSchema::sortAt)),
+            ))
+            .execute(conn)?;
+    } else if record_type == Ids::AppBskyActorProfile.as_str() && uri.ends_with("/self") {
+        let Some(did) = did_from_uri(uri) else {
+            return Ok(());
+        };
+        let display_name = json_str(&record_json, "/displayName").map(|name| name.to_string());
+        let description = json_str(&record_json, "/description").map(|desc| desc.to_string());
+        let conn = &mut establish_connection()?;
+        insert_into(SearchProfileSchema::search_profile)
+            .values((
+                SearchProfileSchema::did.eq(&did),
+                SearchProfileSchema::displayName.eq(&display_name),
+                SearchProfileSchema::description.eq(&description),
+            ))
+            .on_conflict(SearchProfileSchema::did)
+            .do_update()
+            .set((
+                SearchProfileSchema::displayName.eq(&display_name),
+                SearchProfileSchema::description.eq(&description),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Posts and profiles stored before search indexing existed, or otherwise missing from the
+/// index, ordered by uri from after `cursor`. Each comes with its `indexedAt` and content.
+fn get_unindexed_records(cursor: &String, limit: i64) -> Result<Vec<(String, String, Vec<u8>)>> {
+    use crate::schema::pds::record::dsl as RecordSchema;
+    use crate::schema::pds::repo_block::dsl as RepoBlockSchema;
+    use crate::schema::pds::search_post::dsl as SearchPostSchema;
+    use crate::schema::pds::search_profile::dsl as SearchProfileSchema;
+    let conn = &mut establish_connection()?;
+
+    let mut records: Vec<(String, String, Vec<u8>)> = RecordSchema::record
+        .inner_join(
+            RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                .eq(RecordSchema::cid)
+                .and(RepoBlockSchema::did.eq(RecordSchema::did))),
+        )
+        .left_join(SearchPostSchema::search_post.on(SearchPostSchema::uri.eq(RecordSchema::uri)))
+        .filter(RecordSchema::collection.eq(Ids::AppBskyFeedPost.as_str()))
+        .filter(SearchPostSchema::uri.is_null())
+        .filter(RecordSchema::uri.gt(cursor))
+        .select((
+            RecordSchema::uri,
+            RecordSchema::indexedAt,
+            RepoBlockSchema::content,
+        ))
+        .order(RecordSchema::uri.asc())
+        .limit(limit)
+        .load(conn)?;
+    records.extend(
+        RecordSchema::record
+            .inner_join(
+                RepoBlockSchema::repo_block.on(RepoBlockSchema::cid
+                    .eq(RecordSchema::cid)
+                    .and(RepoBlockSchema::did.eq(RecordSchema::did))),
+            )
+            .left_join(
+                SearchProfileSchema::search_profile
+                    .on(SearchProfileSchema::did.eq(RecordSchema::did)),
+            )
+            .filter(RecordSchema::collection.eq(Ids::AppBskyActorProfile.as_str()))
+            .filter(RecordSchema::rkey.eq("self"))
+            .filter(SearchProfileSchema::did.is_null())
+            .filter(RecordSchema::uri.gt(cursor))
+            .select((
+                RecordSchema::uri,
+                RecordSchema::indexedAt,
+                RepoBlockSchema::content,
+            ))
+            .order(RecordSchema::uri.asc())
+            .limit(limit)
+            .load::<(String, String, Vec<u8>)>(conn)?,
+    );
+    records.sort_by(|a, b| a.0.cmp(&b.0));
+    records.truncate(limit as usize);
+    Ok(records)
+}
+
+/// Indexes up to `limit` posts and profiles missing from the search index, from after
+/// `cursor`. Returns how many were indexed and the cursor to continue from, if there may
+/// be more. Records that fail to index are logged and skipped.
+pub async fn backfill(cursor: &String, limit: i64) -> Result<(u64, Option<String>)> {
+    let records = get_unindexed_records(cursor, limit)?;
+    let next_cursor = match records.len() as i64 == limit {
+        true => records.last().map(|(uri, _, _)| uri.clone()),
+        false => None,
+    };
+    let mut indexed = 0;
+    for (uri, indexed_at, content) in records {
+        let res = match cbor_to_lex_record(content) {
+            Ok(record) => index_record(&uri, &record, &indexed_at).await,
+            Err(error) => Err(error),
+        };
+        match res {
+            Ok(()) => indexed += 1,
+            Err(error) => eprintln!("@LOG: ERROR: failed to backfill search for {uri}: {error}"),
+        }
+    }
+    Ok((indexed, next_cursor))
+}
+
+pub async fn delete_record(uri: &String) -> Result<()> {
+    use crate::schema::pds::search_post::dsl as SearchPostSchema;
+    use crate::schema::pds::search_profile::dsl as SearchProfileSchema;
+    let conn = &mut establish_connection()?;
+
+    delete(SearchPostSchema::search_post)
+        .filter(SearchPostSchema::uri.eq(uri))
+        .execute(conn)?;
+    let profile_collection = format!("/{}/self", Ids::AppBskyActorProfile.as_str());
+    if uri.ends_with(&profile_collection) {
+        if let Some(did) = did_from_uri(uri) {
+            delete(SearchProfileSchema::search_profile)
+                .filter(SearchProfileSchema::did.eq(did))
+                .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+/// Uris of matching posts, newest first, and the cursor for the next page
+pub async fn search_posts(opts: SearchPostsOpts) -> Result<(Vec<String>, Option<String>)> {
+    use crate::schema::pds::search_post::dsl as SearchPostSchema;
+    let SearchPostsOpts {
+        q,
+        author,
+        mentions,
+        lang,
+        since,
+        until,
+        tags,
+        limit,
+        cursor,
+    } = opts;
+
+    let mut builder = SearchPostSchema::search_post
+        .filter(
+            sql::<Bool>(r#"("search_post"."document" @@ websearch_to_tsquery('simple', "#)
+                .bind::<Text, _>(q)
+                .sql("))"),
+        )
+        .select(models::SearchPost::as_select())
+        .into_boxed();
+    if let Some(author) = author {
+        match resolve_actor(&author).await? {
+            Some(did) => builder = builder.filter(SearchPostSchema::did.eq(did)),
+            None => return Ok((Vec::new(), None)),
+        }
+    }
+    if let Some(mentions) = mentions {
+        match resolve_actor(&mentions).await? {
+            Some(did) => builder = builder.filter(SearchPostSchema::mentions.contains(vec![did])),
+            None => return Ok((Vec::new(), None)),
+        }
+    }
+    if let Some(lang) = lang {
+        builder = builder.filter(SearchPostSchema::langs.contains(vec![lang]));
+    }
+    if let Some(since) = since {
+        builder = builder.filter(SearchPostSchema::sortAt.ge(normalize_datetime(&since)?));
+    }
+    if let Some(until) = until {
+        builder = builder.filter(SearchPostSchema::sortAt.lt(normalize_datetime(&until)?));
+    }
+    if !tags.is_empty() {
+        let tags = tags
+            .into_iter()
+            .map(|tag| tag.trim_start_matches('#').to_lowercase())
+            .collect::<Vec<String>>();
+        builder = builder.filter(SearchPostSchema::tags.contains(tags));
+    }
+
+    let conn = &mut establish_connection()?;
+    let res: Vec<models::SearchPost> = paginate(
+        builder,
+        &SEARCH_POST_KEYSET,
+        KeySetPaginateOpts {
+            limit: Some(limit),
+            cursor,
+            direction: None,
+        },
+    )?
+    .load(conn)?;
+    let cursor = match res.last() {
+        Some(last) if res.len() as i64 == limit => {
+            SEARCH_POST_KEYSET.pack_from_result(&[TimeKeyResult {
+                created_at: last.sort_at.clone(),
+                key: last.uri.clone(),
+            }])?
+        }
+        _ => None,
+    };
+    Ok((res.into_iter().map(|row| row.uri).collect(), cursor))
+}
+
+/// Dids of accounts whose handle contains `q` or whose display name or description match it.
+/// The cursor is an offset, as results are ordered by handle.
+pub async fn search_actors(
+    q: String,
+    limit: i64,
+    cursor: Option<String>,
+) -> Result<(Vec<String>, Option<String>)> {
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::search_profile::dsl as SearchProfileSchema;
+    let offset = match cursor {
+        None => 0,
+        Some(cursor) => cursor
+            .parse::<i64>()
+            .map_err(|_| SearchError::InvalidCursor)?,
+    };
+    let term = q.trim_start_matches('@').to_lowercase();
+    let conn = &mut establish_connection()?;
+
+    let res = ActorSchema::actor
+        .left_join(
+            SearchProfileSchema::search_profile.on(SearchProfileSchema::did.eq(ActorSchema::did)),
+        )
+        .filter(ActorSchema::takedownRef.is_null())
+        .filter(ActorSchema::deactivatedAt.is_null())
+        .filter(
+            sql::<Bool>(r#"("actor"."handle" ILIKE "#)
+                .bind::<Text, _>(format!("%{}%", escape_like(&term)))
+                .sql(r#" OR "search_profile"."document" @@ websearch_to_tsquery('simple', "#)
+                .bind::<Text, _>(q)
+                .sql("))"),
+        )
+        .order(ActorSchema::handle.asc())
+        .offset(offset)
+        .limit(limit)
+        .select(ActorSchema::did)
+        .load::<String>(conn)?;
+    let cursor = match res.len() as i64 == limit {
+        true => Some((offset + limit).to_string()),
+        false => None,
+    };
+    Ok((res, cursor))
+}
+
+/// Dids of accounts whose handle or display name starts with `q`, for autocomplete
+pub async fn search_actors_typeahead(q: String, limit: i64) -> Result<Vec<String>> {
+    use crate::schema::pds::actor::dsl as ActorSchema;
+    use crate::schema::pds::search_profile::dsl as SearchProfileSchema;
+    let term = escape_like(&q.trim_start_matches('@').to_lowercase());
+    let conn = &mut establish_connection()?;
+
+    let res = ActorSchema::actor
+        .left_join(
+            SearchProfileSchema::search_profile.on(SearchProfileSchema::did.eq(ActorSchema::did)),
+        )
+        .filter(ActorSchema::takedownRef.is_null())
+        .filter(ActorSchema::deactivatedAt.is_null())
+        .filter(
+            ActorSchema::handle
+                .ilike(format!("{term}%"))
+                .or(SearchProfileSchema::displayName.ilike(format!("{term}%")))
+                .or(SearchProfileSchema::displayName.ilike(format!("% {term}%"))),
+        )
+        .order(ActorSchema::handle.asc())
+        .limit(limit)
+        .select(ActorSchema::did)
+        .load::<String>(conn)?;
+    Ok(res)
+}
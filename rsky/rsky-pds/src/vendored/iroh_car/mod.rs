@@ -6,5 +6,5 @@ mod util;
 mod writer;
 
 pub use header::CarHeader;
-//pub use reader::CarReader;
+pub use reader::CarReader;
 pub use writer::CarWriter;
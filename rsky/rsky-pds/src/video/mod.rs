@@ -0,0 +1,383 @@
+// A local video service for `app.bsky.video`. Uploads wait in the blob store's temp space
+// while a job in the database queues them; `ProcessVideosJob` then checks each one's MP4
+// container against the configured limits and tracks it as a blob, ready to be embedded
+// with `app.bsky.embed.video`. Nothing is transcoded, so no external tools are needed.
+// That also means there are no HLS renditions: without a video CDN, embed views point their
+// `playlist` at the MP4 blob itself, so only clients that play MP4 natively are supported.
+
+use crate::apis::com::atproto::repo::upload_blob::track_uploaded_blob;
+use crate::common::ipld::sha256_raw_to_cid;
+use crate::common::time::{from_millis_to_str, DAY, MINUTE};
+use crate::common::{get_random_str, now};
+use crate::config::{ServerConfig, SERVER_CONFIG};
+use crate::db::establish_connection;
+use crate::models::models;
+use crate::repo::aws::s3::S3BlobStore;
+use crate::repo::blob::{sha256_stream, BlobMetadata};
+use crate::repo::quota::QuotaError;
+use crate::repo::ActorStore;
+use anyhow::{bail, Result};
+use aws_config::SdkConfig;
+use diesel::*;
+use lexicon_cid::Cid;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::Request;
+use rsky_lexicon::app::bsky::video::{
+    GetUploadLimitsOutput, JobStatus, JOB_STATE_COMPLETED, JOB_STATE_CREATED, JOB_STATE_FAILED,
+    JOB_STATE_PROCESSING,
+};
+use rsky_lexicon::com::atproto::repo::Blob;
+use std::str::FromStr;
+use std::time::SystemTime;
+use thiserror::Error;
+
+pub mod mp4;
+
+// QuickTime files share the MP4 container, and app.bsky.embed.video only accepts video/mp4
+const VIDEO_BLOB_MIME_TYPE: &str = "video/mp4";
+// A job left processing this long was interrupted, e.g. by a restart, and is picked up again
+const STALE_PROCESSING_MS: i64 = 10 * MINUTE as i64;
+
+#[derive(Error, Debug)]
+pub enum VideoError {
+    #[error("Unsupported video type: {0}")]
+    UnsupportedMimeType(String),
+    #[error("Videos are limited to {max} bytes")]
+    TooLarge { max: u64 },
+    #[error("{0}")]
+    LimitExceeded(String),
+    #[error("Video job not found: {0}")]
+    JobNotFound(String),
+    #[error("Invalid video: {0}")]
+    InvalidVideo(String),
+    #[error("Video is {duration_ms}ms long, videos are limited to {max_ms}ms")]
+    TooLong { duration_ms: u64, max_ms: u64 },
+    #[error("Video is {width}x{height}, videos are limited to {max}x{max}")]
+    DimensionsTooLarge { width: u32, height: u32, max: u32 },
+    #[error("Unsupported video codec: {0}")]
+    UnsupportedCodec(String),
+}
+
+impl VideoError {
+    /// The `error` of a failed job
+    pub fn code(&self) -> &'static str {
+        match self {
+            VideoError::UnsupportedMimeType(_) | VideoError::UnsupportedCodec(_) => {
+                "UnsupportedVideo"
+            }
+            VideoError::TooLarge { .. } | VideoError::DimensionsTooLarge { .. } => "VideoTooLarge",
+            VideoError::TooLong { .. } => "VideoTooLong",
+            VideoError::LimitExceeded(_) => "LimitExceeded",
+            VideoError::JobNotFound(_) => "JobNotFound",
+            VideoError::InvalidVideo(_) => "InvalidVideo",
+        }
+    }
+}
+
+/// Matches when `app.bsky.video` is served from this PDS
+pub struct LocalVideo;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LocalVideo {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.rocket().state::<ServerConfig>() {
+            Some(cfg) if cfg.video.local => Outcome::Success(LocalVideo),
+            _ => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("timestamp in millis since UNIX epoch")
+        .as_millis() as i64
+}
+
+/// Videos uploaded in the last day count towards the daily limits, unless they failed
+pub fn get_upload_limits(did: &String) -> Result<GetUploadLimitsOutput> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let conn = &mut establish_connection()?;
+
+    let sizes: Vec<i64> = VideoJobSchema::video_job
+        .filter(VideoJobSchema::did.eq(did))
+        .filter(VideoJobSchema::createdAt.ge(from_millis_to_str(now_ms() - DAY as i64)))
+        .filter(VideoJobSchema::state.ne(JOB_STATE_FAILED))
+        .select(VideoJobSchema::size)
+        .load(conn)?;
+    let remaining_videos = (SERVER_CONFIG.video.daily_videos - sizes.len() as i64).max(0);
+    let remaining_bytes = (SERVER_CONFIG.video.daily_bytes - sizes.iter().sum::<i64>()).max(0);
+    let can_upload = remaining_videos > 0 && remaining_bytes > 0;
+    Ok(GetUploadLimitsOutput {
+        can_upload,
+        remaining_daily_videos: Some(remaining_videos),
+        remaining_daily_bytes: Some(remaining_bytes),
+        message: match can_upload {
+            true => None,
+            false => Some("You have reached your daily video upload limit".to_string()),
+        },
+        error: None,
+    })
+}
+
+/// Checks an upload against the accepted types and the account's daily limits
+pub fn check_upload(did: &String, mime_type: &String, size: i64) -> Result<()> {
+    if !SERVER_CONFIG.video.mime_types.contains(mime_type) {
+        bail!(VideoError::UnsupportedMimeType(mime_type.clone()))
+    }
+    if size as u64 > SERVER_CONFIG.video.max_size {
+        bail!(VideoError::TooLarge {
+            max: SERVER_CONFIG.video.max_size
+        })
+    }
+    let limits = get_upload_limits(did)?;
+    if !limits.can_upload
+        || limits
+            .remaining_daily_bytes
+            .is_some_and(|bytes| bytes < size)
+    {
+        bail!(VideoError::LimitExceeded(
+            "Uploading this video would exceed your daily video upload limit".to_string()
+        ))
+    }
+    Ok(())
+}
+
+/// Queues an upload stored under `temp_key` for processing
+pub fn create_job(
+    did: &String,
+    temp_key: String,
+    mime_type: String,
+    size: i64,
+) -> Result<models::VideoJob> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let conn = &mut establish_connection()?;
+
+    let created_at = now();
+    let job = models::VideoJob {
+        id: get_random_str(),
+        did: did.clone(),
+        state: JOB_STATE_CREATED.to_string(),
+        progress: 0,
+        temp_key,
+        mime_type,
+        size,
+        blob_cid: None,
+        width: None,
+        height: None,
+        duration_ms: None,
+        error: None,
+        message: None,
+        created_at: created_at.clone(),
+        updated_at: created_at,
+    };
+    insert_into(VideoJobSchema::video_job)
+        .values(&job)
+        .execute(conn)?;
+    Ok(job)
+}
+
+pub fn get_job(job_id: &String, did: &String) -> Result<models::VideoJob> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let conn = &mut establish_connection()?;
+
+    let found = VideoJobSchema::video_job
+        .filter(VideoJobSchema::id.eq(job_id))
+        .filter(VideoJobSchema::did.eq(did))
+        .select(models::VideoJob::as_select())
+        .first(conn)
+        .optional()?;
+    match found {
+        Some(job) => Ok(job),
+        None => bail!(VideoError::JobNotFound(job_id.clone())),
+    }
+}
+
+pub fn job_status(job: models::VideoJob) -> Result<JobStatus> {
+    let blob = match job.blob_cid {
+        Some(cid) if job.state == JOB_STATE_COMPLETED => Some(Blob {
+            r#type: Some("blob".to_string()),
+            r#ref: Some(Cid::from_str(&cid)?),
+            cid: None,
+            mime_type: VIDEO_BLOB_MIME_TYPE.to_string(),
+            size: Some(job.size),
+            original: None,
+        }),
+        _ => None,
+    };
+    Ok(JobStatus {
+        job_id: job.id,
+        did: job.did,
+        state: job.state,
+        progress: Some(job.progress as i64),
+        blob,
+        error: job.error,
+        message: job.message,
+    })
+}
+
+/// Takes the oldest queued job, or one whose processing was interrupted. The state is
+/// compared and set in one update so two workers never take the same job.
+pub fn claim_next_job() -> Result<Option<models::VideoJob>> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let conn = &mut establish_connection()?;
+
+    let stale = from_millis_to_str(now_ms() - STALE_PROCESSING_MS);
+    let candidates: Vec<models::VideoJob> = VideoJobSchema::video_job
+        .filter(
+            VideoJobSchema::state
+                .eq(JOB_STATE_CREATED)
+                .or(VideoJobSchema::state
+                    .eq(JOB_STATE_PROCESSING)
+                    .and(VideoJobSchema::updatedAt.lt(stale))),
+        )
+        .order(VideoJobSchema::createdAt.asc())
+        .limit(10)
+        .select(models::VideoJob::as_select())
+        .load(conn)?;
+    for job in candidates {
+        let updated_at = now();
+        let claimed = update(VideoJobSchema::video_job)
+            .filter(VideoJobSchema::id.eq(&job.id))
+            .filter(VideoJobSchema::state.eq(&job.state))
+            .filter(VideoJobSchema::updatedAt.eq(&job.updated_at))
+            .set((
+                VideoJobSchema::state.eq(JOB_STATE_PROCESSING),
+                VideoJobSchema::updatedAt.eq(&updated_at),
+            ))
+            .execute(conn)?;
+        if claimed > 0 {
+            return Ok(Some(models::VideoJob {
+                state: JOB_STATE_PROCESSING.to_string(),
+                updated_at,
+                ..job
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn set_progress(job_id: &String, progress: i32) -> Result<()> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let conn = &mut establish_connection()?;
+
+    update(VideoJobSchema::video_job)
+        .filter(VideoJobSchema::id.eq(job_id))
+        .set((
+            VideoJobSchema::progress.eq(progress),
+            VideoJobSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+fn fail_job(job_id: &String, error: &str, message: String) -> Result<()> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+    let conn = &mut establish_connection()?;
+
+    update(VideoJobSchema::video_job)
+        .filter(VideoJobSchema::id.eq(job_id))
+        .set((
+            VideoJobSchema::state.eq(JOB_STATE_FAILED),
+            VideoJobSchema::error.eq(Some(error)),
+            VideoJobSchema::message.eq(Some(message)),
+            VideoJobSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Reads the container and checks it against the configured limits
+pub fn check_video(bytes: &[u8]) -> Result<mp4::Mp4Info, VideoError> {
+    let info = mp4::parse(bytes).map_err(|error| VideoError::InvalidVideo(error.to_string()))?;
+    if info.duration_ms > SERVER_CONFIG.video.max_duration_ms {
+        return Err(VideoError::TooLong {
+            duration_ms: info.duration_ms,
+            max_ms: SERVER_CONFIG.video.max_duration_ms,
+        });
+    }
+    let max = SERVER_CONFIG.video.max_dimension;
+    if info.width > max || info.height > max {
+        return Err(VideoError::DimensionsTooLarge {
+            width: info.width,
+            height: info.height,
+            max,
+        });
+    }
+    if !SERVER_CONFIG.video.codecs.contains(&info.video_codec) {
+        return Err(VideoError::UnsupportedCodec(info.video_codec));
+    }
+    Ok(info)
+}
+
+async fn track_video(job: &models::VideoJob, actor_store: &ActorStore) -> Result<()> {
+    use crate::schema::pds::video_job::dsl as VideoJobSchema;
+
+    let bytes = actor_store
+        .blob
+        .blobstore
+        .get_temp_bytes(job.temp_key.clone())
+        .await?;
+    let info = match check_video(&bytes) {
+        Ok(info) => info,
+        Err(rejection) => {
+            fail_job(&job.id, rejection.code(), rejection.to_string())?;
+            actor_store
+                .blob
+                .blobstore
+                .delete_temp(job.temp_key.clone())
+                .await?;
+            return Ok(());
+        }
+    };
+    set_progress(&job.id, 50)?;
+
+    let cid = sha256_raw_to_cid(sha256_stream(bytes).await?);
+    let metadata = BlobMetadata {
+        temp_key: job.temp_key.clone(),
+        size: job.size,
+        cid,
+        mime_type: VIDEO_BLOB_MIME_TYPE.to_string(),
+        width: Some(info.width as i32),
+        height: Some(info.height as i32),
+    };
+    // Also deletes the upload if it doesn't fit in the account's quota
+    if let Err(error) = track_uploaded_blob(actor_store, &job.did, metadata).await {
+        return match error.downcast_ref::<QuotaError>() {
+            Some(quota_error) => fail_job(&job.id, "QuotaExceeded", quota_error.to_string()),
+            None => Err(error),
+        };
+    }
+
+    let conn = &mut establish_connection()?;
+    update(VideoJobSchema::video_job)
+        .filter(VideoJobSchema::id.eq(&job.id))
+        .set((
+            VideoJobSchema::state.eq(JOB_STATE_COMPLETED),
+            VideoJobSchema::progress.eq(100),
+            VideoJobSchema::blobCid.eq(Some(cid.to_string())),
+            VideoJobSchema::width.eq(Some(info.width as i32)),
+            VideoJobSchema::height.eq(Some(info.height as i32)),
+            VideoJobSchema::durationMs.eq(Some(info.duration_ms as i64)),
+            VideoJobSchema::updatedAt.eq(now()),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Processes a claimed job. Anything that goes wrong fails the job rather than leaving
+/// it to be retried forever.
+pub async fn process_job(job: models::VideoJob, s3_config: &SdkConfig) -> Result<()> {
+    let actor_store = ActorStore::new(
+        job.did.clone(),
+        S3BlobStore::new(job.did.clone(), s3_config),
+    );
+    if let Err(error) = track_video(&job, &actor_store).await {
+        eprintln!("@LOG: ERROR: video job {} failed: {error}", job.id);
+        fail_job(&job.id, "ProcessingFailed", error.to_string())?;
+    }
+    Ok(())
+}
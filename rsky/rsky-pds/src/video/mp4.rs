@@ -0,0 +1,370 @@
+// Just enough of ISO BMFF, the container behind MP4 and QuickTime, to check an upload:
+// duration, dimensions and codecs are read from the boxes under `moov`. No samples are
+// decoded.
+
+use anyhow::{bail, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mp4Info {
+    /// Major brand from `ftyp`, e.g. `isom` or `qt  `
+    pub brand: String,
+    pub duration_ms: u64,
+    /// Display dimensions of the first video track, with its rotation applied
+    pub width: u32,
+    pub height: u32,
+    /// Sample entry type of the first video track, e.g. `avc1`
+    pub video_codec: String,
+    pub audio_codec: Option<String>,
+}
+
+struct Mp4Box<'a> {
+    kind: [u8; 4],
+    body: &'a [u8],
+}
+
+fn fourcc(kind: &[u8]) -> String {
+    String::from_utf8_lossy(kind).to_string()
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<u16> {
+    match bytes.get(at..at + 2) {
+        Some(b) => Ok(u16::from_be_bytes([b[0], b[1]])),
+        None => bail!("Truncated box"),
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32> {
+    match bytes.get(at..at + 4) {
+        Some(b) => Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]])),
+        None => bail!("Truncated box"),
+    }
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Result<u64> {
+    match bytes.get(at..at + 8) {
+        Some(b) => Ok(u64::from_be_bytes([
+            b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+        ])),
+        None => bail!("Truncated box"),
+    }
+}
+
+/// The boxes packed one after another in `bytes`
+fn parse_boxes(bytes: &[u8]) -> Result<Vec<Mp4Box<'_>>> {
+    let mut boxes = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let size = read_u32(bytes, i)? as usize;
+        let Some(kind) = bytes.get(i + 4..i + 8) else {
+            bail!("Truncated box header at byte {i}")
+        };
+        let (header, size) = match size {
+            // Runs to the end of its parent, e.g. an `mdat` being written out
+            0 => (8, bytes.len() - i),
+            1 => (16, read_u64(bytes, i + 8)? as usize),
+            size => (8, size),
+        };
+        let end = match i.checked_add(size) {
+            Some(end) if size >= header && end <= bytes.len() => end,
+            _ => bail!("Box {} at byte {i} overruns its parent", fourcc(kind)),
+        };
+        boxes.push(Mp4Box {
+            kind: [kind[0], kind[1], kind[2], kind[3]],
+            body: &bytes[i + header..end],
+        });
+        i = end;
+    }
+    Ok(boxes)
+}
+
+fn find<'a>(boxes: &'a [Mp4Box<'a>], kind: &[u8; 4]) -> Option<&'a Mp4Box<'a>> {
+    boxes.iter().find(|b| &b.kind == kind)
+}
+
+fn child<'a>(parent: &Mp4Box<'a>, kind: &[u8; 4]) -> Result<Option<Mp4Box<'a>>> {
+    Ok(parse_boxes(parent.body)?
+        .into_iter()
+        .find(|b| &b.kind == kind))
+}
+
+/// Timescale and duration of an `mvhd` or `mdhd`, which share their layout up to there
+fn timescale_and_duration(full_box: &[u8]) -> Result<(u32, u64)> {
+    match full_box.first() {
+        Some(1) => Ok((read_u32(full_box, 20)?, read_u64(full_box, 24)?)),
+        _ => Ok((
+            read_u32(full_box, 12)?,
+            match read_u32(full_box, 16)? {
+                // All ones means the duration is unknown
+                u32::MAX => 0,
+                duration => duration as u64,
+            },
+        )),
+    }
+}
+
+fn to_ms(duration: u64, timescale: u32) -> u64 {
+    match timescale {
+        0 => 0,
+        timescale => (duration as u128 * 1000 / timescale as u128) as u64,
+    }
+}
+
+/// Width and height from a `tkhd`, swapped if its matrix rotates the track a quarter turn
+fn track_dimensions(tkhd: &[u8]) -> Result<(u32, u32)> {
+    let matrix_at = match tkhd.first() {
+        Some(1) => 52,
+        _ => 40,
+    };
+    // 16.16 fixed point
+    let width = read_u32(tkhd, matrix_at + 36)? >> 16;
+    let height = read_u32(tkhd, matrix_at + 40)? >> 16;
+    let (a, d) = (read_u32(tkhd, matrix_at)?, read_u32(tkhd, matrix_at + 16)?);
+    match a == 0 && d == 0 {
+        true => Ok((height, width)),
+        false => Ok((width, height)),
+    }
+}
+
+struct Track {
+    handler: [u8; 4],
+    codec: String,
+    duration_ms: u64,
+    width: u32,
+    height: u32,
+}
+
+fn parse_track(trak: &Mp4Box) -> Result<Option<Track>> {
+    let (Some(tkhd), Some(mdia)) = (child(trak, b"tkhd")?, child(trak, b"mdia")?) else {
+        return Ok(None);
+    };
+    let (Some(mdhd), Some(hdlr), Some(minf)) = (
+        child(&mdia, b"mdhd")?,
+        child(&mdia, b"hdlr")?,
+        child(&mdia, b"minf")?,
+    ) else {
+        return Ok(None);
+    };
+    let Some(stbl) = child(&minf, b"stbl")? else {
+        return Ok(None);
+    };
+    let Some(stsd) = child(&stbl, b"stsd")? else {
+        return Ok(None);
+    };
+    let Some(handler) = hdlr.body.get(8..12) else {
+        bail!("Truncated hdlr box")
+    };
+    // Sample entries follow the full box header and entry count
+    let Some(entries) = stsd.body.get(8..) else {
+        bail!("Truncated stsd box")
+    };
+    let Some(entry) = parse_boxes(entries)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let (timescale, duration) = timescale_and_duration(mdhd.body)?;
+    let (mut width, mut height) = track_dimensions(tkhd.body)?;
+    if (width == 0 || height == 0) && &handler == b"vide" {
+        // Visual sample entries have their coded size after 24 bytes of other fields
+        width = read_u16(entry.body, 24)? as u32;
+        height = read_u16(entry.body, 26)? as u32;
+    }
+    Ok(Some(Track {
+        handler: [handler[0], handler[1], handler[2], handler[3]],
+        codec: fourcc(&entry.kind),
+        duration_ms: to_ms(duration, timescale),
+        width,
+        height,
+    }))
+}
+
+pub fn parse(bytes: &[u8]) -> Result<Mp4Info> {
+    if bytes.get(4..8) != Some(b"ftyp".as_slice()) {
+        bail!("Not an MP4 file: it doesn't start with an ftyp box")
+    }
+    let boxes = parse_boxes(bytes)?;
+    let Some(ftyp) = find(&boxes, b"ftyp") else {
+        bail!("Not an MP4 file: no ftyp box")
+    };
+    let Some(brand) = ftyp.body.get(0..4) else {
+        bail!("Truncated ftyp box")
+    };
+    let Some(moov) = find(&boxes, b"moov") else {
+        bail!("MP4 file has no moov box")
+    };
+    let moov_boxes = parse_boxes(moov.body)?;
+    let Some(mvhd) = find(&moov_boxes, b"mvhd") else {
+        bail!("MP4 file has no mvhd box")
+    };
+    let (timescale, duration) = timescale_and_duration(mvhd.body)?;
+    let mut duration_ms = to_ms(duration, timescale);
+    if duration_ms == 0 {
+        // Fragmented files give the overall duration in mvex instead
+        if let Some(mvex) = find(&moov_boxes, b"mvex") {
+            if let Some(mehd) = child(mvex, b"mehd")? {
+                let fragment_duration = match mehd.body.first() {
+                    Some(1) => read_u64(mehd.body, 4)?,
+                    _ => read_u32(mehd.body, 4)? as u64,
+                };
+                duration_ms = to_ms(fragment_duration, timescale);
+            }
+        }
+    }
+
+    let mut video: Option<Track> = None;
+    let mut audio: Option<Track> = None;
+    for trak in moov_boxes.iter().filter(|b| &b.kind == b"trak") {
+        let Some(track) = parse_track(trak)? else {
+            continue;
+        };
+        match &track.handler {
+            b"vide" if video.is_none() => video = Some(track),
+            b"soun" if audio.is_none() => audio = Some(track),
+            _ => (),
+        }
+    }
+    let Some(video) = video else {
+        bail!("MP4 file has no video track")
+    };
+    if duration_ms == 0 {
+        duration_ms = video.duration_ms;
+    }
+    Ok(Mp4Info {
+        brand: fourcc(brand),
+        duration_ms,
+        width: video.width,
+        height: video.height,
+        video_codec: video.codec,
+        audio_codec: audio.map(|audio| audio.codec),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut bytes = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        bytes.extend_from_slice(kind);
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    fn header(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut body = vec![0; 12];
+        body.extend_from_slice(&timescale.to_be_bytes());
+        body.extend_from_slice(&duration.to_be_bytes());
+        body
+    }
+
+    fn tkhd(width: u32, height: u32, rotated: bool) -> Vec<u8> {
+        let mut body = vec![0; 40];
+        let (a, b, c, d): (u32, u32, u32, u32) = match rotated {
+            true => (0, 0x0001_0000, 0xffff_0000, 0),
+            false => (0x0001_0000, 0, 0, 0x0001_0000),
+        };
+        let mut matrix = vec![0u8; 36];
+        matrix[0..4].copy_from_slice(&a.to_be_bytes());
+        matrix[4..8].copy_from_slice(&b.to_be_bytes());
+        matrix[12..16].copy_from_slice(&c.to_be_bytes());
+        matrix[16..20].copy_from_slice(&d.to_be_bytes());
+        matrix[32..36].copy_from_slice(&0x4000_0000u32.to_be_bytes());
+        body.extend_from_slice(&matrix);
+        body.extend_from_slice(&(width << 16).to_be_bytes());
+        body.extend_from_slice(&(height << 16).to_be_bytes());
+        body
+    }
+
+    fn trak(handler: &[u8; 4], codec: &[u8; 4], tkhd_body: Vec<u8>) -> Vec<u8> {
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(handler);
+        hdlr.extend_from_slice(&[0; 13]);
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend_from_slice(&mp4_box(codec, &[0; 78]));
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+        let minf = mp4_box(b"minf", &stbl);
+        let mdia = [
+            mp4_box(b"mdhd", &header(48_000, 240_000)),
+            mp4_box(b"hdlr", &hdlr),
+            minf,
+        ]
+        .concat();
+        mp4_box(
+            b"trak",
+            &[mp4_box(b"tkhd", &tkhd_body), mp4_box(b"mdia", &mdia)].concat(),
+        )
+    }
+
+    fn ftyp() -> Vec<u8> {
+        mp4_box(b"ftyp", b"isom\0\0\x02\0isomavc1")
+    }
+
+    fn file(rotated: bool) -> Vec<u8> {
+        let moov = [
+            mp4_box(b"mvhd", &header(1000, 5_000)),
+            trak(b"vide", b"avc1", tkhd(1920, 1080, rotated)),
+            trak(b"soun", b"mp4a", tkhd(0, 0, false)),
+        ]
+        .concat();
+        [ftyp(), mp4_box(b"moov", &moov)].concat()
+    }
+
+    #[test]
+    fn parses_tracks() -> Result<()> {
+        let info = parse(&file(false))?;
+        assert_eq!(
+            info,
+            Mp4Info {
+                brand: "isom".to_string(),
+                duration_ms: 5_000,
+                width: 1920,
+                height: 1080,
+                video_codec: "avc1".to_string(),
+                audio_codec: Some("mp4a".to_string()),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn swaps_dimensions_of_rotated_tracks() -> Result<()> {
+        let info = parse(&file(true))?;
+        assert_eq!((info.width, info.height), (1080, 1920));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_files_without_ftyp() {
+        let bytes = mp4_box(b"moov", &mp4_box(b"mvhd", &header(1000, 5_000)));
+        assert!(parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_boxes() {
+        let bytes = file(false);
+        for len in [ftyp().len() + 3, ftyp().len() + 6, bytes.len() - 1] {
+            assert!(parse(&bytes[..len]).is_err(), "parsed {len} bytes");
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_boxes() {
+        let mut bytes = file(false);
+        let at = ftyp().len();
+        bytes[at..at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(parse(&bytes).is_err());
+
+        // A 64-bit size that would overflow the offset
+        let mut large = ftyp();
+        large.extend_from_slice(&1u32.to_be_bytes());
+        large.extend_from_slice(b"moov");
+        large.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(parse(&large).is_err());
+    }
+
+    #[test]
+    fn rejects_boxes_smaller_than_their_header() {
+        let mut bytes = file(false);
+        let at = ftyp().len();
+        bytes[at..at + 4].copy_from_slice(&4u32.to_be_bytes());
+        assert!(parse(&bytes).is_err());
+    }
+}
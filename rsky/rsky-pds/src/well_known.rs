@@ -1,9 +1,14 @@
 use crate::account_manager::AccountManager;
+use crate::chat::delivery::IrohNode;
 use crate::config::ServerConfig;
+use crate::oauth;
+use crate::oauth::types::{OAuthAuthorizationServerMetadata, OAuthProtectedResourceMetadata};
+use crate::SharedIroh;
 use anyhow::Result;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
 use rocket::response::status;
+use rocket::serde::json::Json;
 use rocket::{Request, State};
 
 pub struct HostHeader(pub String);
@@ -58,3 +63,25 @@ pub async fn well_known(
         )),
     }
 }
+
+#[rocket::get("/.well-known/oauth-authorization-server")]
+pub async fn oauth_authorization_server(
+    cfg: &State<ServerConfig>,
+) -> Json<OAuthAuthorizationServerMetadata> {
+    Json(oauth::authorization_server_metadata(cfg))
+}
+
+#[rocket::get("/.well-known/oauth-protected-resource")]
+pub async fn oauth_protected_resource(
+    cfg: &State<ServerConfig>,
+) -> Json<OAuthProtectedResourceMetadata> {
+    Json(oauth::protected_resource_metadata(cfg))
+}
+
+/// The iroh node other PDSes dial to deliver chat messages
+#[rocket::get("/.well-known/iroh-node")]
+pub async fn iroh_node(iroh: &State<SharedIroh>) -> Json<IrohNode> {
+    Json(IrohNode {
+        node_id: iroh.node_id.to_string(),
+    })
+}
@@ -11,6 +11,7 @@ pub struct ServiceJwtPayload {
     pub iss: String,
     pub aud: String,
     pub exp: Option<Duration>,
+    pub lxm: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
@@ -18,6 +19,9 @@ pub struct JwtPayload {
     pub iss: String,
     pub aud: String,
     pub exp: u64,
+    /// The method the token is bound to, if any
+    #[serde(default)]
+    pub lxm: Option<String>,
 }
 
 pub async fn create_service_auth_headers(params: ServiceJwtParams) -> Result<HeaderMap> {
@@ -114,6 +118,7 @@ where
                 iss: payload.iss,
                 aud: payload.aud,
                 exp: Some(Duration::from_micros(payload.exp)),
+                lxm: payload.lxm,
             })
         }
         _ => bail!("BadJwt: poorly formatted jwt"),